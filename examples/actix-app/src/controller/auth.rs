@@ -1,10 +1,13 @@
 use zino::{prelude::*, Request, Response, Result};
+use zino_core::auth::OidcProvider;
 use zino_model::user::{JwtAuthService, User};
 
 pub async fn login(mut req: Request) -> Result {
     let current_time = DateTime::now();
     let body: Map = req.parse_body().await?;
-    let (user_id, mut data) = User::generate_token(body).await.extract(&req)?;
+    let (user_id, mut data) = User::generate_token_with_ip(body, req.client_ip())
+        .await
+        .extract(&req)?;
 
     let user_updates = json!({
         "status": "Active",
@@ -37,23 +40,82 @@ pub async fn refresh(req: Request) -> Result {
     Ok(res.into())
 }
 
-pub async fn logout(req: Request) -> Result {
+pub async fn logout(mut req: Request) -> Result {
     let user_session = req
         .get_data::<UserSession<_>>()
         .ok_or_else(|| warn!("401 Unauthorized: the user session is invalid"))
         .extract(&req)?;
 
-    let mut mutations = Map::from_entry("status", "SignedOut");
-    let user_id = user_session.user_id();
-    let (validation, user) = User::update_by_id(user_id, &mut mutations, None)
+    let claims = req.parse_jwt_claims(JwtClaims::shared_key())?;
+    let body: Map = req.parse_body().await.unwrap_or_default();
+    let mut data = User::sign_out(&claims, &body).await.extract(&req)?;
+    if body.get_str("scope") == Some("all") {
+        let mut mutations = Map::from_entry("status", "SignedOut");
+        let user_id = user_session.user_id();
+        let (validation, user) = User::update_by_id(user_id, &mut mutations, None)
+            .await
+            .extract(&req)?;
+        if !validation.is_success() {
+            reject!(req, validation);
+        }
+        data.upsert("entry", user.snapshot());
+    }
+
+    let mut res = Response::default().context(&req);
+    res.set_json_data(data);
+    Ok(res.into())
+}
+
+pub async fn oidc_authorize(req: Request) -> Result {
+    let provider = req.parse_param::<String>("provider")?;
+    let redirect_to = req.get_query("redirect_to");
+    let (url, cookie) = User::oidc_authorize(&provider, redirect_to)
+        .await
+        .extract(&req)?;
+    let mut res = Response::new(StatusCode::FOUND).context(&req);
+    res.insert_header("location", url);
+    res.insert_header("set-cookie", cookie);
+    Ok(res.into())
+}
+
+pub async fn oidc_callback(req: Request) -> Result {
+    let current_time = DateTime::now();
+    let provider_name = req.parse_param::<String>("provider")?;
+    let provider = OidcProvider::get(&provider_name)
+        .ok_or_else(|| {
+            warn!(
+                "404 Not Found: the provider `{}` is unavailable",
+                provider_name
+            )
+        })
+        .extract(&req)?;
+    let params: Map = req.parse_query()?;
+    let state_cookie = req.get_cookie(provider.cookie_name());
+    let state = state_cookie.as_ref().map(|cookie| cookie.value());
+    let (user_id, mut data) = User::oidc_login(&provider_name, &params, state, None)
+        .await
+        .extract(&req)?;
+
+    let user_updates = json!({
+        "status": "Active",
+        "last_login_at": data.remove("current_login_at").and_then(|v| v.as_datetime()),
+        "last_login_ip": data.remove("current_login_ip"),
+        "current_login_at": current_time,
+        "current_login_ip": req.client_ip(),
+        "$inc": { "login_count": 1 },
+    });
+
+    let mut user_mutations = user_updates.into_map_opt().unwrap_or_default();
+    let (validation, user) = User::update_by_id(&user_id, &mut user_mutations, None)
         .await
         .extract(&req)?;
     if !validation.is_success() {
         reject!(req, validation);
     }
+    data.upsert("entry", user.snapshot());
 
-    let data = Map::data_entry(user.snapshot());
     let mut res = Response::default().context(&req);
+    res.set_cookie(&provider.expired_cookie());
     res.set_json_data(data);
     Ok(res.into())
 }
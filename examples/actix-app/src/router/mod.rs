@@ -26,6 +26,14 @@ pub fn debug_routes() -> Vec<RouterConfigure> {
 
 fn auth_router(cfg: &mut ServiceConfig) {
     cfg.route("/auth/login", post().to(auth::login));
+    cfg.route(
+        "/auth/oidc/{provider}/authorize",
+        get().to(auth::oidc_authorize),
+    );
+    cfg.route(
+        "/auth/oidc/{provider}/callback",
+        get().to(auth::oidc_callback),
+    );
     cfg.service(
         scope("/auth")
             .route("/refresh", get().to(auth::refresh))
@@ -8,9 +8,12 @@ mod router;
 mod schedule;
 mod service;
 
+use crate::model::User;
 use zino::prelude::*;
+use zino_model::user::JwtAuthService;
 
 fn main() {
+    zino::MessageChannel::set_jwt_verifier(|claims| Box::pin(User::verify_jwt_claims(claims)));
     zino::Cluster::boot()
         .register(router::routes())
         .register_debug(router::debug_routes())
@@ -1,6 +1,7 @@
 use super::Tag;
 use serde::{Deserialize, Serialize};
-use zino::prelude::*;
+use zino::{prelude::*, MessageChannel};
+use zino_core::channel::CloudEvent;
 use zino_derive::{DecodeRow, Model, ModelAccessor, ModelHooks, Schema};
 use zino_model::user::JwtAuthService;
 
@@ -35,6 +36,8 @@ pub struct User {
     description: String,
 
     // Info fields.
+    #[schema(unique, write_only, comment = "Union ID of the identity providers")]
+    union_id: String,
     #[schema(not_null, unique, write_only, constructor = "AccessKeyId::new")]
     access_key_id: String,
     #[schema(
@@ -94,4 +97,11 @@ pub struct User {
 impl JwtAuthService<i64> for User {
     const LOGIN_AT_FIELD: Option<&'static str> = Some("current_login_at");
     const LOGIN_IP_FIELD: Option<&'static str> = Some("current_login_ip");
+    const UNION_ID_FIELD: Option<&'static str> = Some("union_id");
+
+    fn emit_event(event: CloudEvent) {
+        if let Err(err) = MessageChannel::shared().try_send(event) {
+            tracing::error!("fail to send the cloud event: {err}");
+        }
+    }
 }
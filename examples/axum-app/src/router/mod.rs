@@ -9,17 +9,22 @@ use axum::{
     Router,
 };
 use zino::DefaultController;
+use zino_model::{Task, TaskRun};
 
 pub fn routes() -> Vec<Router> {
     let mut routes = Vec::new();
 
     // Auth controller.
-    let router = Router::new().route("/auth/login", post(auth::login)).merge(
-        Router::new()
-            .route("/auth/refresh", get(auth::refresh))
-            .route("/auth/logout", post(auth::logout))
-            .layer(from_fn(middleware::init_user_session)),
-    );
+    let router = Router::new()
+        .route("/auth/login", post(auth::login))
+        .route("/auth/oidc/:provider/authorize", get(auth::oidc_authorize))
+        .route("/auth/oidc/:provider/callback", get(auth::oidc_callback))
+        .merge(
+            Router::new()
+                .route("/auth/refresh", get(auth::refresh))
+                .route("/auth/logout", post(auth::logout))
+                .layer(from_fn(middleware::init_user_session)),
+        );
     routes.push(router);
 
     // File controller.
@@ -50,6 +55,15 @@ pub fn routes() -> Vec<Router> {
         .route("/tag/tree", get(Tag::tree));
     routes.push(router);
 
+    // Task controller.
+    let router = Router::new()
+        .route("/task/new", post(Task::new))
+        .route("/task/:id/update", post(Task::update))
+        .route("/task/:id/view", get(Task::view))
+        .route("/task/list", get(Task::list))
+        .route("/task/runs", get(TaskRun::list));
+    routes.push(router);
+
     routes
 }
 
@@ -33,6 +33,8 @@ pub(super) fn init<APP: Application + ?Sized>() {
         .cookie_store(true)
         .gzip(true);
     let mut max_retries = 3;
+    #[cfg(feature = "http-signing")]
+    let mut request_signing = None;
     if let Some(http_client) = APP::config().get_table("http-client") {
         if let Some(timeout) = http_client.get_duration("request-timeout") {
             client_builder = client_builder.timeout(timeout);
@@ -79,6 +81,13 @@ pub(super) fn init<APP: Application + ?Sized>() {
         if let Some(retries) = http_client.get_u32("max-retries") {
             max_retries = retries;
         }
+        #[cfg(feature = "http-signing")]
+        if let Some(endpoints) = http_client.get_array("signing") {
+            match super::request_signing::RequestSigning::try_from_config(endpoints) {
+                Ok(signing) => request_signing = Some(signing),
+                Err(err) => panic!("fail to configure the request signing: {err}"),
+            }
+        }
     }
 
     let reqwest_client = client_builder
@@ -89,10 +98,17 @@ pub(super) fn init<APP: Application + ?Sized>() {
         .expect("fail to set an HTTP client for the application");
 
     let retry_policy = ExponentialBackoff::builder().build_with_max_retries(max_retries);
-    let client = ClientBuilder::new(reqwest_client)
+    let client_builder = ClientBuilder::new(reqwest_client)
         .with(TracingMiddleware::<RequestTiming>::new())
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-        .build();
+        .with(RetryTransientMiddleware::new_with_policy(retry_policy));
+
+    // The signing runs after the retry so that each attempt is signed with a fresh date.
+    #[cfg(feature = "http-signing")]
+    let client_builder = match request_signing {
+        Some(signing) => client_builder.with(signing),
+        None => client_builder,
+    };
+    let client = client_builder.build();
     SHARED_HTTP_CLIENT_WITH_MIDDLEWARE
         .set(client)
         .expect("fail to set an HTTP client with middleware for the application");
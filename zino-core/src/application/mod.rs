@@ -22,6 +22,9 @@ mod static_record;
 mod system_monitor;
 mod tracing_subscriber;
 
+#[cfg(feature = "http-signing")]
+mod request_signing;
+
 #[cfg(feature = "metrics")]
 mod metrics_exporter;
 
@@ -63,6 +66,10 @@ pub trait Application {
         // HTTP client.
         http_client::init::<Self>();
 
+        // Identity providers.
+        #[cfg(any(feature = "auth-oauth2", feature = "auth-oidc"))]
+        crate::auth::init_oidc_providers();
+
         // View template.
         #[cfg(feature = "view")]
         crate::view::init::<Self>();
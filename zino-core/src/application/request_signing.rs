@@ -0,0 +1,374 @@
+use super::http_client::SHARED_HTTP_CLIENT;
+use crate::{
+    auth::{AccessKeyId, Authentication, ClientCredentials, SecretAccessKey},
+    crypto::Digest,
+    datetime::DateTime,
+    encoding::base64,
+    error::Error,
+    extension::{HeaderMapExt, JsonObjectExt, TomlTableExt},
+    warn, Map, TomlValue,
+};
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use hmac::Hmac;
+use md5::Md5;
+use reqwest::{
+    header::{self, HeaderValue},
+    Request, Response, Url,
+};
+use reqwest_middleware::{Middleware, Next};
+use std::{future::Future, time::Duration};
+use task_local_extensions::Extensions;
+use toml::Table;
+
+/// Credentials for signing the outbound requests to an endpoint.
+enum SigningCredentials {
+    /// An access key for the HMAC signature in the `authorization` header.
+    AccessKey {
+        /// Service name.
+        service_name: String,
+        /// Access key ID.
+        access_key_id: AccessKeyId,
+        /// Secret access key.
+        secret_access_key: SecretAccessKey,
+        /// Names of the canonicalized headers.
+        headers: Vec<String>,
+    },
+    /// Client credentials for a bearer token, which is refreshed when it has expired.
+    ClientCredentials {
+        /// Token endpoint.
+        token_url: Url,
+        /// Optional scope of the access token.
+        scope: Option<String>,
+        /// Client credentials.
+        credentials: ClientCredentials<RequestSigning>,
+        /// A lock to ensure that only one request refreshes the access token.
+        refresh_lock: Mutex<()>,
+    },
+}
+
+impl SigningCredentials {
+    /// Attempts to create a new instance with the configuration.
+    ///
+    /// The client credentials are used if the `token-url` field is specified.
+    /// Otherwise, the secret access key is a base64-encoded string,
+    /// and the key derived by [`SecretAccessKey::new()`] is used if it is absent.
+    fn try_from_config(config: &'static Table) -> Result<Self, Error> {
+        if let Some(token_url) = config.get_str("token-url") {
+            return Ok(Self::ClientCredentials {
+                token_url: token_url.parse()?,
+                scope: config.get_str("scope").map(|s| s.to_owned()),
+                credentials: ClientCredentials::try_from_config(config)?,
+                refresh_lock: Mutex::new(()),
+            });
+        }
+
+        let access_key_id = config
+            .get_str("access-key-id")
+            .ok_or_else(|| warn!("the `access-key-id` field should be specified"))?;
+        let access_key_id = AccessKeyId::from(access_key_id);
+        let secret_access_key = match config.get_str("secret-access-key") {
+            Some(secret_access_key) => SecretAccessKey::from_base64(secret_access_key)?,
+            None => SecretAccessKey::new(&access_key_id),
+        };
+        let headers = config
+            .get_str_array("headers")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|header| header.to_ascii_lowercase())
+            .collect();
+        Ok(Self::AccessKey {
+            service_name: config.get_str("service-name").unwrap_or("ZINO").to_owned(),
+            access_key_id,
+            secret_access_key,
+            headers,
+        })
+    }
+
+    /// Signs the request by setting the `authorization` header.
+    ///
+    /// For an access key, the `content-md5` header is set with the hash of the body
+    /// if it is absent, so that the body is covered by the signature.
+    async fn sign(&self, req: &mut Request) -> Result<(), Error> {
+        let authorization = match self {
+            Self::AccessKey {
+                service_name,
+                access_key_id,
+                secret_access_key,
+                headers: header_names,
+            } => {
+                let now = DateTime::now();
+                let date = HeaderValue::from_str(&now.to_utc_string())?;
+                req.headers_mut().insert(header::DATE, date);
+                if !req.headers().contains_key("content-md5") {
+                    if let Some(bytes) = req.body().and_then(|body| body.as_bytes()) {
+                        let content_md5 = HeaderValue::from_str(&content_md5(bytes))?;
+                        req.headers_mut().insert("content-md5", content_md5);
+                    }
+                }
+
+                let headers = req.headers();
+                let mut authentication = Authentication::new(req.method().as_str());
+                authentication.set_service_name(service_name);
+                authentication.set_access_key_id(access_key_id.clone());
+                if let Some(content_md5) = headers.get_str("content-md5") {
+                    authentication.set_content_md5(content_md5.to_owned());
+                }
+                authentication
+                    .set_content_type(headers.get_str("content-type").map(|s| s.to_owned()));
+                authentication.set_date_header("date", now);
+
+                let filter = header_names.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                let canonical_headers = filter.iter().filter_map(|&name| {
+                    headers
+                        .get_str(name)
+                        .map(|value| (name.to_owned(), value.to_owned()))
+                });
+                authentication.set_headers(canonical_headers, &filter);
+                authentication.set_resource(req.url().path().to_owned(), None);
+
+                let signature = authentication.sign_with::<Hmac<Digest>>(secret_access_key)?;
+                authentication.set_signature(signature);
+                authentication.authorization()
+            }
+            Self::ClientCredentials {
+                token_url,
+                scope,
+                credentials,
+                refresh_lock,
+            } => {
+                let access_token = refresh_access_token(credentials, refresh_lock, || async {
+                    let mut params = credentials.to_request_params();
+                    params.upsert("grant_type", "client_credentials");
+                    if let Some(scope) = scope {
+                        params.upsert("scope", scope.as_str());
+                    }
+
+                    let client = SHARED_HTTP_CLIENT
+                        .get()
+                        .ok_or_else(|| warn!("fail to get the global HTTP client"))?;
+                    let data = client
+                        .post(token_url.clone())
+                        .header(header::ACCEPT, "application/json")
+                        .form(&params)
+                        .send()
+                        .await?
+                        .error_for_status()?
+                        .json::<Map>()
+                        .await?;
+                    Ok(data)
+                })
+                .await?;
+                format!("Bearer {access_token}")
+            }
+        };
+        req.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&authorization)?,
+        );
+        Ok(())
+    }
+}
+
+/// A middleware which signs the outbound requests to the endpoints
+/// configured by the `[[http-client.signing]]` tables.
+///
+/// A request is signed by the first endpoint whose `base-url` has the same origin
+/// and is a path prefix of the request URL.
+pub(super) struct RequestSigning {
+    /// Base URLs of the endpoints with the signing credentials.
+    endpoints: Vec<(Url, SigningCredentials)>,
+}
+
+impl RequestSigning {
+    /// Attempts to create a new instance with the configuration.
+    pub(super) fn try_from_config(config: &'static [TomlValue]) -> Result<Self, Error> {
+        let mut endpoints = Vec::new();
+        for config in config.iter().filter_map(|v| v.as_table()) {
+            let base_url = config
+                .get_str("base-url")
+                .ok_or_else(|| warn!("the `base-url` field should be specified"))?
+                .parse::<Url>()?;
+            let credentials = SigningCredentials::try_from_config(config)?;
+            endpoints.push((base_url, credentials));
+        }
+        Ok(Self { endpoints })
+    }
+
+    /// Finds the signing credentials for the URL.
+    fn find_credentials(&self, url: &Url) -> Option<&SigningCredentials> {
+        self.endpoints
+            .iter()
+            .find(|(base_url, _)| {
+                base_url.origin() == url.origin() && url.path().starts_with(base_url.path())
+            })
+            .map(|(_, credentials)| credentials)
+    }
+}
+
+#[async_trait]
+impl Middleware for RequestSigning {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if let Some(credentials) = self.find_credentials(req.url()) {
+            if let Err(err) = credentials.sign(&mut req).await {
+                let err = anyhow::Error::msg(format!("fail to sign the request: {err}"));
+                return Err(reqwest_middleware::Error::Middleware(err));
+            }
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// Returns the access token of the client credentials, which is refreshed by the token response
+/// of `fetch` if it has expired.
+///
+/// The concurrent requests wait for the refresh in progress instead of fetching a token again.
+async fn refresh_access_token<S, F, Fut>(
+    credentials: &ClientCredentials<S>,
+    refresh_lock: &Mutex<()>,
+    fetch: F,
+) -> Result<String, Error>
+where
+    S: ?Sized,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Map, Error>>,
+{
+    if credentials.is_expired() {
+        let _guard = refresh_lock.lock().await;
+        if credentials.is_expired() {
+            let data = fetch().await?;
+            let access_token = data
+                .get_str("access_token")
+                .ok_or_else(|| warn!("the `access_token` is absent in the token response"))?;
+            let expires_in = data.get_u64("expires_in").unwrap_or(3600);
+            credentials.set_access_token(access_token);
+            credentials.set_expires(
+                Duration::from_secs(expires_in).saturating_sub(Duration::from_secs(30)),
+            );
+        }
+    }
+    Ok(credentials.access_token())
+}
+
+/// Returns the base64-encoded MD5 digest of the body.
+fn content_md5(bytes: &[u8]) -> String {
+    use md5::Digest;
+
+    base64::encode(Md5::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{refresh_access_token, RequestSigning, SigningCredentials};
+    use crate::{
+        auth::{AccessKeyId, Authentication, ClientCredentials, SecretAccessKey},
+        crypto::Digest,
+        datetime::DateTime,
+        extension::HeaderMapExt,
+        Map,
+    };
+    use futures::{executor::block_on, future, lock::Mutex};
+    use hmac::Hmac;
+    use reqwest::{Method, Request, Url};
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    fn access_key_credentials(access_key_id: &AccessKeyId) -> SigningCredentials {
+        SigningCredentials::AccessKey {
+            service_name: "ZINO".to_owned(),
+            access_key_id: access_key_id.clone(),
+            secret_access_key: SecretAccessKey::new(access_key_id),
+            headers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_finds_credentials_by_the_base_url() {
+        let access_key_id = AccessKeyId::new();
+        let base_url = "https://api.example.com/v1/".parse::<Url>().unwrap();
+        let signing = RequestSigning {
+            endpoints: vec![(base_url, access_key_credentials(&access_key_id))],
+        };
+        let url = "https://api.example.com/v1/user/list".parse().unwrap();
+        assert!(signing.find_credentials(&url).is_some());
+
+        let url = "https://api.example.com/v2/user/list".parse().unwrap();
+        assert!(signing.find_credentials(&url).is_none());
+
+        let url = "http://api.example.com/v1/user/list".parse().unwrap();
+        assert!(signing.find_credentials(&url).is_none());
+    }
+
+    #[test]
+    fn it_signs_the_hash_of_the_body() {
+        let access_key_id = AccessKeyId::new();
+        let credentials = access_key_credentials(&access_key_id);
+        let url = "https://api.example.com/v1/user/new".parse().unwrap();
+        let mut req = Request::new(Method::POST, url);
+        *req.body_mut() = Some(r#"{"name":"alice"}"#.into());
+        block_on(credentials.sign(&mut req)).unwrap();
+
+        let headers = req.headers();
+        let content_md5 = headers.get_str("content-md5").unwrap();
+        assert_eq!(content_md5, super::content_md5(br#"{"name":"alice"}"#));
+
+        let date = headers.get_str("date").unwrap();
+        let authorization = headers.get_str("authorization").unwrap();
+        let signature = authorization.rsplit_once(':').unwrap().1;
+        let mut authentication = Authentication::new("POST");
+        authentication.set_service_name("ZINO");
+        authentication.set_access_key_id(access_key_id.clone());
+        authentication.set_date_header("date", DateTime::parse_utc_str(date).unwrap());
+        authentication.set_resource("/v1/user/new".to_owned(), None);
+        authentication.set_signature(signature.to_owned());
+
+        let secret_access_key = SecretAccessKey::new(&access_key_id);
+        authentication.set_content_md5(content_md5.to_owned());
+        assert!(authentication
+            .validate_with::<Hmac<Digest>>(&secret_access_key)
+            .is_success());
+
+        authentication.set_content_md5(super::content_md5(br#"{"name":"bob"}"#));
+        assert!(!authentication
+            .validate_with::<Hmac<Digest>>(&secret_access_key)
+            .is_success());
+    }
+
+    #[test]
+    fn it_refreshes_the_access_token_once() {
+        let credentials = ClientCredentials::<()>::new("client-id", "client-secret");
+        let refresh_lock = Mutex::new(());
+        let fetches = AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, Relaxed);
+
+            // Yields once so that the other request waits for the refresh in progress.
+            let mut yielded = false;
+            future::poll_fn(|cx| {
+                if yielded {
+                    std::task::Poll::Ready(())
+                } else {
+                    yielded = true;
+                    cx.waker().wake_by_ref();
+                    std::task::Poll::Pending
+                }
+            })
+            .await;
+
+            let mut data = Map::new();
+            data.insert("access_token".to_owned(), "token".into());
+            Ok(data)
+        };
+        let (first, second) = block_on(future::join(
+            refresh_access_token(&credentials, &refresh_lock, fetch),
+            refresh_access_token(&credentials, &refresh_lock, fetch),
+        ));
+        assert_eq!(first.unwrap(), "token");
+        assert_eq!(second.unwrap(), "token");
+        assert_eq!(fetches.load(Relaxed), 1);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     crypto::{self, Digest},
     encoding::base64,
+    error::Error,
     extension::TomlTableExt,
     state::State,
     LazyLock,
@@ -16,7 +17,7 @@ use std::{borrow::Cow, fmt, iter};
 use totp_rs::{Algorithm, TOTP};
 
 /// Access key ID.
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct AccessKeyId(String);
 
 impl AccessKeyId {
@@ -94,6 +95,14 @@ impl SecretAccessKey {
         Self(mac.finalize().into_bytes().to_vec())
     }
 
+    /// Attempts to create a new instance from the base64-encoded string,
+    /// which is the same as the output of `Display`.
+    #[inline]
+    pub fn from_base64(encoded: &str) -> Result<Self, Error> {
+        let key = base64::decode(encoded)?;
+        Ok(Self(key))
+    }
+
     /// Returns a byte slice.
     #[inline]
     pub fn as_bytes(&self) -> &[u8] {
@@ -0,0 +1,209 @@
+use super::UserSession;
+use crate::{datetime::DateTime, error::Error, warn, SharedString};
+use std::{fmt, str::FromStr};
+
+/// Effect of an access policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PolicyEffect {
+    /// Allows the actions.
+    #[default]
+    Allow,
+    /// Denies the actions.
+    Deny,
+}
+
+impl fmt::Display for PolicyEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyEffect::Allow => write!(f, "Allow"),
+            PolicyEffect::Deny => write!(f, "Deny"),
+        }
+    }
+}
+
+impl FromStr for PolicyEffect {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("allow") {
+            Ok(PolicyEffect::Allow)
+        } else if s.eq_ignore_ascii_case("deny") {
+            Ok(PolicyEffect::Deny)
+        } else {
+            Err(warn!("invalid policy effect `{}`", s))
+        }
+    }
+}
+
+/// An access policy which allows or denies the actions on a resource.
+///
+/// The resource and actions are patterns in which `*` matches any sequence of characters.
+/// An empty list of actions matches any action, and an empty list of roles applies to anyone.
+#[derive(Debug, Clone)]
+pub struct AccessPolicy {
+    /// Policy name.
+    name: SharedString,
+    /// Resource pattern.
+    resource: String,
+    /// Action patterns.
+    actions: Vec<String>,
+    /// Policy effect.
+    effect: PolicyEffect,
+    /// Roles that the policy applies to.
+    roles: Vec<String>,
+    /// Tenant ID that the policy applies to.
+    tenant_id: Option<String>,
+    /// Start time of the valid period.
+    valid_from: Option<DateTime>,
+    /// End time of the valid period.
+    expires_at: Option<DateTime>,
+}
+
+impl AccessPolicy {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(
+        name: impl Into<SharedString>,
+        resource: impl ToString,
+        effect: PolicyEffect,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            resource: resource.to_string(),
+            actions: Vec::new(),
+            effect,
+            roles: Vec::new(),
+            tenant_id: None,
+            valid_from: None,
+            expires_at: None,
+        }
+    }
+
+    /// Sets the action patterns.
+    #[inline]
+    pub fn set_actions(&mut self, actions: Vec<String>) {
+        self.actions = actions;
+    }
+
+    /// Sets the roles that the policy applies to.
+    #[inline]
+    pub fn set_roles(&mut self, roles: Vec<String>) {
+        self.roles = roles;
+    }
+
+    /// Sets the tenant ID that the policy applies to.
+    #[inline]
+    pub fn set_tenant_id(&mut self, tenant_id: impl ToString) {
+        self.tenant_id = Some(tenant_id.to_string());
+    }
+
+    /// Sets the valid period.
+    #[inline]
+    pub fn set_valid_period(&mut self, valid_from: Option<DateTime>, expires_at: Option<DateTime>) {
+        self.valid_from = valid_from;
+        self.expires_at = expires_at;
+    }
+
+    /// Returns the policy name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    /// Returns the resource pattern.
+    #[inline]
+    pub fn resource(&self) -> &str {
+        &self.resource
+    }
+
+    /// Returns the action patterns.
+    #[inline]
+    pub fn actions(&self) -> &[String] {
+        &self.actions
+    }
+
+    /// Returns the policy effect.
+    #[inline]
+    pub fn effect(&self) -> PolicyEffect {
+        self.effect
+    }
+
+    /// Returns `true` if the policy is in the valid period at the time.
+    pub fn is_valid_at(&self, time: DateTime) -> bool {
+        self.valid_from
+            .map_or(true, |valid_from| valid_from <= time)
+            && self.expires_at.map_or(true, |expires_at| time < expires_at)
+    }
+
+    /// Returns `true` if the policy matches the resource and action.
+    pub fn matches(&self, resource: &str, action: &str) -> bool {
+        match_pattern(&self.resource, resource, false)
+            && (self.actions.is_empty()
+                || self
+                    .actions
+                    .iter()
+                    .any(|pattern| match_pattern(pattern, action, true)))
+    }
+
+    /// Returns `true` if the policy applies to the user session.
+    /// An anonymous user is represented by `None`.
+    pub fn applies_to<U, T: ToString>(&self, session: Option<&UserSession<U, String, T>>) -> bool {
+        if let Some(tenant_id) = self.tenant_id.as_deref() {
+            let matched = session
+                .and_then(|session| session.tenant_id())
+                .is_some_and(|id| id.to_string() == tenant_id);
+            if !matched {
+                return false;
+            }
+        }
+        self.roles.is_empty()
+            || session.is_some_and(|session| self.roles.iter().any(|role| session.has_role(role)))
+    }
+}
+
+/// Returns `true` if the value matches the pattern in which `*` matches any sequence of characters.
+pub(crate) fn match_pattern(pattern: &str, value: &str, ignore_case: bool) -> bool {
+    let eq = |a: u8, b: u8| {
+        if ignore_case {
+            a.eq_ignore_ascii_case(&b)
+        } else {
+            a == b
+        }
+    };
+    let pattern = pattern.as_bytes();
+    let value = value.as_bytes();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if p < pattern.len() && eq(pattern[p], value[v]) {
+            p += 1;
+            v += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::match_pattern;
+
+    #[test]
+    fn it_matches_patterns() {
+        assert!(match_pattern("*", "/user/:id/view", false));
+        assert!(match_pattern("/user/*", "/user/:id/view", false));
+        assert!(match_pattern("/user/*/view", "/user/:id/view", false));
+        assert!(!match_pattern("/user/*/update", "/user/:id/view", false));
+        assert!(!match_pattern("/user", "/user/list", false));
+        assert!(match_pattern("get", "GET", true));
+        assert!(!match_pattern("get", "GET", false));
+    }
+}
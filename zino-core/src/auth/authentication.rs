@@ -30,6 +30,8 @@ pub struct Authentication {
     headers: Vec<(String, String)>,
     /// Canonicalized resource.
     resource: String,
+    /// Max clock skew of the date header.
+    max_clock_skew: Duration,
 }
 
 impl Authentication {
@@ -48,6 +50,7 @@ impl Authentication {
             expires: None,
             headers: Vec::new(),
             resource: String::new(),
+            max_clock_skew: Duration::from_secs(900),
         }
     }
 
@@ -93,6 +96,12 @@ impl Authentication {
         self.date_header = (header_name, date);
     }
 
+    /// Sets the max clock skew of the date header. The default value is 15 minutes.
+    #[inline]
+    pub fn set_max_clock_skew(&mut self, max_clock_skew: Duration) {
+        self.max_clock_skew = max_clock_skew;
+    }
+
     /// Sets the expires timestamp.
     #[inline]
     pub fn set_expires(&mut self, expires: Option<DateTime>) {
@@ -105,7 +114,7 @@ impl Authentication {
     pub fn set_headers(
         &mut self,
         headers: impl Iterator<Item = (String, String)>,
-        filter: &[&'static str],
+        filter: &[&str],
     ) {
         let mut headers = headers
             .filter_map(|(name, values)| {
@@ -247,7 +256,7 @@ impl Authentication {
         let mut validation = Validation::new();
         let current = DateTime::now();
         let date = self.date_header.1;
-        let max_tolerance = Duration::from_secs(900);
+        let max_tolerance = self.max_clock_skew;
         if date < current && date < current - max_tolerance
             || date > current && date > current + max_tolerance
         {
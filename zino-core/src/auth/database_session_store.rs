@@ -0,0 +1,222 @@
+use super::SessionStore;
+use crate::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    model::DecodeRow,
+    orm::{self, ConnectionPool, Executor, GlobalPool},
+    BoxFuture, JsonValue, Map,
+};
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
+
+/// A store which keeps the sessions in the database,
+/// so that the sessions can be shared across the instances in a cluster.
+#[derive(Debug)]
+pub struct DatabaseSessionStore {
+    /// Connection pool.
+    pool: &'static ConnectionPool,
+    /// Table name.
+    table_name: String,
+    /// A flag to indicate whether the table has been created.
+    table_created: AtomicBool,
+    /// Number of saves.
+    saves: AtomicUsize,
+}
+
+impl DatabaseSessionStore {
+    /// Creates a new instance for the database service.
+    pub fn new(service: &str) -> Result<Self, Error> {
+        let Some(pool) = GlobalPool::get(service) else {
+            bail!(
+                "connection to the database service `{}` is unavailable",
+                service
+            );
+        };
+        Ok(Self::with_pool(pool))
+    }
+
+    /// Creates a new instance with the connection pool.
+    pub fn with_pool(pool: &'static ConnectionPool) -> Self {
+        Self {
+            pool,
+            table_name: "zino_sessions".to_owned(),
+            table_created: AtomicBool::new(false),
+            saves: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets the table name.
+    #[inline]
+    pub fn set_table_name(&mut self, table_name: impl ToString) {
+        self.table_name = table_name.to_string();
+    }
+
+    /// Creates the table if it does not exist.
+    async fn create_table(&self) -> Result<(), Error> {
+        if self.table_created.load(Relaxed) {
+            return Ok(());
+        }
+
+        let table_name = &self.table_name;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (\n  \
+                session_id VARCHAR(255) PRIMARY KEY,\n  \
+                data TEXT NOT NULL,\n  \
+                version BIGINT NOT NULL,\n  \
+                expires_at BIGINT NOT NULL\n\
+            );"
+        );
+        self.pool.pool().execute(&sql).await?;
+        self.table_created.store(true, Relaxed);
+        Ok(())
+    }
+}
+
+impl SessionStore for DatabaseSessionStore {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn load<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Option<Map>, Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let table_name = &self.table_name;
+            let now = DateTime::current_timestamp_millis();
+            let sql = format!(
+                "SELECT data FROM {table_name} \
+                    WHERE session_id = {} AND expires_at > {now};",
+                orm::placeholder(1),
+            );
+            let Some(row) = self
+                .pool
+                .pool()
+                .fetch_optional_with_values(&sql, &[session_id])
+                .await?
+            else {
+                return Ok(None);
+            };
+            let mut data = Map::decode_row(&row)?;
+            // The data may have been decoded as a JSON object.
+            let record = match data.remove("data") {
+                Some(JsonValue::String(data)) => serde_json::from_str::<Map>(&data).ok(),
+                Some(JsonValue::Object(record)) => Some(record),
+                _ => None,
+            };
+            Ok(record)
+        })
+    }
+
+    fn save<'a>(
+        &'a self,
+        session_id: &'a str,
+        record: Map,
+        version: u64,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let pool = self.pool.pool();
+            let table_name = &self.table_name;
+            let now = DateTime::current_timestamp_millis();
+            let expires_at = now + i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX / 2);
+            if self.saves.fetch_add(1, Relaxed) % 1000 == 0 {
+                let sql = format!("DELETE FROM {table_name} WHERE expires_at <= {now};");
+                pool.execute(&sql).await?;
+            }
+
+            // The session IDs are random, so a new record is inserted without an upsert,
+            // and a duplicate key is reported as an error instead of overwriting the record.
+            let next_version = version + 1;
+            let sql = if version == 0 {
+                format!(
+                    "INSERT INTO {table_name} (session_id, data, version, expires_at) \
+                        VALUES ({}, {}, {next_version}, {expires_at});",
+                    orm::placeholder(1),
+                    orm::placeholder(2),
+                )
+            } else {
+                format!(
+                    "UPDATE {table_name} \
+                        SET data = {}, version = {next_version}, expires_at = {expires_at} \
+                        WHERE session_id = {} AND version = {version} AND expires_at > {now};",
+                    orm::placeholder(1),
+                    orm::placeholder(2),
+                )
+            };
+            let data = serde_json::to_string(&record)?;
+            let arguments = if version == 0 {
+                [session_id, data.as_str()]
+            } else {
+                [data.as_str(), session_id]
+            };
+            let query_result = pool.execute_with_values(&sql, &arguments).await?;
+            Ok(query_result.rows_affected() == 1)
+        })
+    }
+
+    fn delete<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let table_name = &self.table_name;
+            let sql = format!(
+                "DELETE FROM {table_name} WHERE session_id = {};",
+                orm::placeholder(1),
+            );
+            self.pool
+                .pool()
+                .execute_with_values(&sql, &[session_id])
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+mod tests {
+    use super::DatabaseSessionStore;
+    use crate::{
+        auth::SessionStore,
+        extension::JsonObjectExt,
+        orm::fixture::{block_on, TEST_POOL},
+        Map,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn it_saves_the_records_with_the_version() {
+        block_on(async {
+            let mut store = DatabaseSessionStore::with_pool(&TEST_POOL);
+            store.set_table_name("test_sessions");
+
+            let ttl = Duration::from_secs(60);
+            let mut record = Map::new();
+            record.upsert("version", 1);
+            assert!(store.save("session", record.clone(), 0, ttl).await.unwrap());
+
+            record.upsert("version", 2);
+            assert!(store.save("session", record.clone(), 1, ttl).await.unwrap());
+            assert!(!store.save("session", record, 1, ttl).await.unwrap());
+
+            let record = store.load("session").await.unwrap().unwrap();
+            assert_eq!(record.get_u64("version"), Some(2));
+
+            store.delete("session").await.unwrap();
+            assert!(store.load("session").await.unwrap().is_none());
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use super::{JwtKey, JwtKeySet};
 use crate::{
     crypto,
     datetime::DateTime,
@@ -8,7 +9,7 @@ use crate::{
 };
 use jwt_simple::{
     algorithms::MACLike,
-    claims::{self, Claims, JWTClaims},
+    claims::{self, Audiences, Claims, JWTClaims},
     common::VerificationOptions,
 };
 use serde::{de::DeserializeOwned, Serialize};
@@ -35,23 +36,46 @@ impl<T: Default + Serialize + DeserializeOwned> JwtClaims<T> {
         Self(claims)
     }
 
-    /// Generates an access token signed with the shared secret access key.
+    /// Generates a refresh token signed with the shared signing key.
+    /// The asymmetric key in `[[jwt.keys]]` is preferred to the shared secret access key.
+    #[inline]
     pub fn refresh_token(&self) -> Result<String, Error> {
+        self.sign_refresh_token(None)
+    }
+
+    /// Generates a refresh token with the JWT ID, which can be used for the token rotation.
+    #[inline]
+    pub fn refresh_token_with_id(&self, jwt_id: impl ToString) -> Result<String, Error> {
+        self.sign_refresh_token(Some(jwt_id.to_string()))
+    }
+
+    /// Signs a refresh token with the optional JWT ID.
+    fn sign_refresh_token(&self, jwt_id: Option<String>) -> Result<String, Error> {
         let mut claims = Claims::create((*DEFAULT_REFRESH_INTERVAL).into());
         claims.invalid_before = self
             .0
             .expires_at
             .map(|max_age| max_age - (*DEFAULT_TIME_TOLERANCE).into());
         claims.subject = self.0.subject.as_ref().cloned();
-        JwtClaims::shared_key()
-            .authenticate(claims)
-            .map_err(|err| Error::new(err.to_string()))
+        claims.jwt_id = jwt_id;
+        if let Some(key) = JwtKeySet::shared().signing_key() {
+            key.sign(claims)
+        } else {
+            JwtClaims::shared_key()
+                .authenticate(claims)
+                .map_err(|err| Error::new(err.to_string()))
+        }
     }
 
-    /// Generates an access token signed with the shared secret access key.
+    /// Generates an access token signed with the shared signing key.
+    /// The asymmetric key in `[[jwt.keys]]` is preferred to the shared secret access key.
     #[inline]
     pub fn access_token(self) -> Result<String, Error> {
-        self.sign_with(JwtClaims::shared_key())
+        if let Some(key) = JwtKeySet::shared().signing_key() {
+            self.sign_with_key(key)
+        } else {
+            self.sign_with(JwtClaims::shared_key())
+        }
     }
 
     /// Generates a signature with the secret access key.
@@ -60,6 +84,12 @@ impl<T: Default + Serialize + DeserializeOwned> JwtClaims<T> {
         key.authenticate(self.0)
             .map_err(|err| Error::new(err.to_string()))
     }
+
+    /// Generates a signature with the asymmetric key.
+    #[inline]
+    pub fn sign_with_key(self, key: &JwtKey) -> Result<String, Error> {
+        key.sign(self.0)
+    }
 }
 
 impl<T> JwtClaims<T> {
@@ -69,6 +99,18 @@ impl<T> JwtClaims<T> {
         self.0.nonce = Some(nonce.to_string());
     }
 
+    /// Sets the JWT ID.
+    #[inline]
+    pub fn set_jwt_id(&mut self, jwt_id: impl ToString) {
+        self.0.jwt_id = Some(jwt_id.to_string());
+    }
+
+    /// Sets the audience.
+    #[inline]
+    pub fn set_audience(&mut self, audience: impl ToString) {
+        self.0.audiences = Some(Audiences::AsString(audience.to_string()));
+    }
+
     /// Returns the time the claims were created at.
     #[inline]
     pub fn issued_at(&self) -> DateTime {
@@ -114,6 +156,24 @@ impl<T> JwtClaims<T> {
         self.0.nonce.as_deref()
     }
 
+    /// Returns the JWT ID.
+    #[inline]
+    pub fn jwt_id(&self) -> Option<&str> {
+        self.0.jwt_id.as_deref()
+    }
+
+    /// Returns the audience if there is only one audience.
+    #[inline]
+    pub fn audience(&self) -> Option<&str> {
+        match self.0.audiences.as_ref()? {
+            Audiences::AsString(audience) => Some(audience),
+            Audiences::AsSet(audiences) if audiences.len() == 1 => {
+                audiences.iter().next().map(|s| s.as_str())
+            }
+            _ => None,
+        }
+    }
+
     /// Returns the custom data.
     #[inline]
     pub fn data(&self) -> &T {
@@ -135,6 +195,12 @@ impl JwtClaims<()> {
     pub fn shared_key() -> &'static JwtHmacKey {
         LazyLock::force(&SECRET_KEY)
     }
+
+    /// Returns the refresh interval for the refresh token.
+    #[inline]
+    pub fn refresh_interval() -> Duration {
+        *DEFAULT_REFRESH_INTERVAL
+    }
 }
 
 /// Returns the default time tolerance.
@@ -0,0 +1,429 @@
+use crate::{
+    application::PROJECT_DIR,
+    encoding::base64,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    warn, JsonValue, LazyLock, Map,
+};
+use jwt_simple::{
+    algorithms::{
+        ECDSAP256KeyPairLike, ECDSAP256PublicKeyLike, ES256KeyPair, ES256PublicKey, Ed25519KeyPair,
+        Ed25519PublicKey, EdDSAKeyPairLike, EdDSAPublicKeyLike, RS256KeyPair, RS256PublicKey,
+        RSAKeyPairLike, RSAPublicKeyLike,
+    },
+    claims::JWTClaims,
+    common::VerificationOptions,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, sync::Arc};
+use toml::Table;
+
+/// Private key for signing JWT tokens.
+/// The key pairs are shared since some of them can not be cloned.
+#[derive(Debug, Clone)]
+enum PrivateKey {
+    /// RSA key pair with SHA-256.
+    RS256(Arc<RS256KeyPair>),
+    /// ECDSA key pair with the P-256 curve and SHA-256.
+    ES256(Arc<ES256KeyPair>),
+    /// Ed25519 key pair.
+    EdDSA(Arc<Ed25519KeyPair>),
+}
+
+/// Public key for verifying JWT tokens.
+#[derive(Debug, Clone)]
+enum PublicKey {
+    /// RSA public key with SHA-256.
+    RS256(Box<RS256PublicKey>),
+    /// ECDSA public key with the P-256 curve and SHA-256.
+    ES256(ES256PublicKey),
+    /// Ed25519 public key.
+    EdDSA(Ed25519PublicKey),
+}
+
+/// An asymmetric key for signing and verifying JWT tokens.
+///
+/// A key without the private part can only be used for verification.
+#[derive(Debug, Clone)]
+pub struct JwtKey {
+    /// Key ID.
+    key_id: String,
+    /// Private key.
+    private_key: Option<PrivateKey>,
+    /// Public key.
+    public_key: PublicKey,
+}
+
+impl JwtKey {
+    /// Creates a new instance from the PEM-encoded keys.
+    /// Supported algorithms: `RS256`, `ES256` and `EdDSA`.
+    pub fn from_pem(
+        key_id: &str,
+        algorithm: &str,
+        private_key: Option<&str>,
+        public_key: Option<&str>,
+    ) -> Result<Self, Error> {
+        let convert_err = |err: jwt_simple::Error| Error::new(err.to_string());
+        let (private_key, public_key) = match algorithm {
+            "RS256" => {
+                let private_key = private_key
+                    .map(RS256KeyPair::from_pem)
+                    .transpose()
+                    .map_err(convert_err)?
+                    .map(|key_pair| key_pair.with_key_id(key_id));
+                let public_key = match (public_key, &private_key) {
+                    (Some(pem), _) => RS256PublicKey::from_pem(pem).map_err(convert_err)?,
+                    (None, Some(key_pair)) => key_pair.public_key(),
+                    _ => return Err(warn!("the public key of `{}` is absent", key_id)),
+                };
+                (
+                    private_key.map(|key_pair| PrivateKey::RS256(Arc::new(key_pair))),
+                    PublicKey::RS256(Box::new(public_key.with_key_id(key_id))),
+                )
+            }
+            "ES256" => {
+                let private_key = private_key
+                    .map(ES256KeyPair::from_pem)
+                    .transpose()
+                    .map_err(convert_err)?
+                    .map(|key_pair| key_pair.with_key_id(key_id));
+                let public_key = match (public_key, &private_key) {
+                    (Some(pem), _) => ES256PublicKey::from_pem(pem).map_err(convert_err)?,
+                    (None, Some(key_pair)) => key_pair.public_key(),
+                    _ => return Err(warn!("the public key of `{}` is absent", key_id)),
+                };
+                (
+                    private_key.map(|key_pair| PrivateKey::ES256(Arc::new(key_pair))),
+                    PublicKey::ES256(public_key.with_key_id(key_id)),
+                )
+            }
+            "EdDSA" => {
+                let private_key = private_key
+                    .map(Ed25519KeyPair::from_pem)
+                    .transpose()
+                    .map_err(convert_err)?
+                    .map(|key_pair| key_pair.with_key_id(key_id));
+                let public_key = match (public_key, &private_key) {
+                    (Some(pem), _) => Ed25519PublicKey::from_pem(pem).map_err(convert_err)?,
+                    (None, Some(key_pair)) => key_pair.public_key(),
+                    _ => return Err(warn!("the public key of `{}` is absent", key_id)),
+                };
+                (
+                    private_key.map(|key_pair| PrivateKey::EdDSA(Arc::new(key_pair))),
+                    PublicKey::EdDSA(public_key.with_key_id(key_id)),
+                )
+            }
+            _ => return Err(warn!("unsupported JWT algorithm `{}`", algorithm)),
+        };
+        Ok(Self {
+            key_id: key_id.to_owned(),
+            private_key,
+            public_key,
+        })
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    /// The paths of PEM files are relative to the project directory.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let key_id = config
+            .get_str("kid")
+            .ok_or_else(|| warn!("the `kid` field should be specified"))?;
+        let algorithm = config.get_str("algorithm").unwrap_or("RS256");
+        let read_pem = |key: &str| {
+            config
+                .get_str(key)
+                .map(|path| fs::read_to_string(PROJECT_DIR.join(path)))
+                .transpose()
+                .map_err(|err| warn!("fail to read the `{}` file: {}", key, err))
+        };
+        let private_key = read_pem("private-key")?;
+        let public_key = read_pem("public-key")?;
+        Self::from_pem(
+            key_id,
+            algorithm,
+            private_key.as_deref(),
+            public_key.as_deref(),
+        )
+    }
+
+    /// Returns the key ID.
+    #[inline]
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Returns the algorithm name.
+    #[inline]
+    pub fn algorithm(&self) -> &'static str {
+        match self.public_key {
+            PublicKey::RS256(_) => "RS256",
+            PublicKey::ES256(_) => "ES256",
+            PublicKey::EdDSA(_) => "EdDSA",
+        }
+    }
+
+    /// Returns `true` if the key can be used for signing.
+    #[inline]
+    pub fn can_sign(&self) -> bool {
+        self.private_key.is_some()
+    }
+
+    /// Returns the public key as a JSON Web Key.
+    pub fn to_jwk(&self) -> Map {
+        let mut jwk = Map::new();
+        match &self.public_key {
+            PublicKey::RS256(key) => {
+                let components = key.to_components();
+                jwk.upsert("kty", "RSA");
+                jwk.upsert("n", base64::encode_url_safe(components.n));
+                jwk.upsert("e", base64::encode_url_safe(components.e));
+            }
+            PublicKey::ES256(key) => {
+                // The uncompressed point is encoded as `0x04 || x || y`.
+                let point = key.public_key().to_bytes_uncompressed();
+                let (x, y) = point[1..].split_at(32);
+                jwk.upsert("kty", "EC");
+                jwk.upsert("crv", "P-256");
+                jwk.upsert("x", base64::encode_url_safe(x));
+                jwk.upsert("y", base64::encode_url_safe(y));
+            }
+            PublicKey::EdDSA(key) => {
+                jwk.upsert("kty", "OKP");
+                jwk.upsert("crv", "Ed25519");
+                jwk.upsert("x", base64::encode_url_safe(key.to_bytes()));
+            }
+        }
+        jwk.upsert("kid", self.key_id.as_str());
+        jwk.upsert("alg", self.algorithm());
+        jwk.upsert("use", "sig");
+        jwk
+    }
+
+    /// Signs the claims with the private key.
+    pub(crate) fn sign<T: Serialize + DeserializeOwned>(
+        &self,
+        claims: JWTClaims<T>,
+    ) -> Result<String, Error> {
+        let result = match &self.private_key {
+            Some(PrivateKey::RS256(key_pair)) => key_pair.sign(claims),
+            Some(PrivateKey::ES256(key_pair)) => key_pair.sign(claims),
+            Some(PrivateKey::EdDSA(key_pair)) => key_pair.sign(claims),
+            None => return Err(warn!("the private key of `{}` is absent", self.key_id)),
+        };
+        result.map_err(|err| Error::new(err.to_string()))
+    }
+
+    /// Verifies the token with the public key.
+    pub(crate) fn verify_token<T: Serialize + DeserializeOwned>(
+        &self,
+        token: &str,
+        options: Option<VerificationOptions>,
+    ) -> Result<JWTClaims<T>, Error> {
+        let result = match &self.public_key {
+            PublicKey::RS256(key) => key.verify_token(token, options),
+            PublicKey::ES256(key) => key.verify_token(token, options),
+            PublicKey::EdDSA(key) => key.verify_token(token, options),
+        };
+        result.map_err(|err| Error::new(err.to_string()))
+    }
+}
+
+/// A set of asymmetric keys for JWT tokens, configured by `[[jwt.keys]]`.
+///
+/// Multiple keys can be active at the same time for the key rotation.
+/// Tokens are signed with the key specified by `signing-kid` or the first key
+/// which has a private key, and they are verified by the key selected by the `kid` header.
+/// The tokens signed by the shared HMAC key are rejected if `hmac-enabled` is `false`.
+#[derive(Debug, Clone)]
+pub struct JwtKeySet {
+    /// Keys.
+    keys: Vec<JwtKey>,
+    /// Key ID for signing.
+    signing_key_id: Option<String>,
+    /// A flag to accept the tokens signed by the shared HMAC key.
+    hmac_enabled: bool,
+}
+
+impl JwtKeySet {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(keys: Vec<JwtKey>) -> Self {
+        Self {
+            keys,
+            signing_key_id: None,
+            hmac_enabled: true,
+        }
+    }
+
+    /// Sets the key ID for signing.
+    #[inline]
+    pub fn set_signing_key_id(&mut self, key_id: impl ToString) {
+        self.signing_key_id = Some(key_id.to_string());
+    }
+
+    /// Enables or disables the tokens signed by the shared HMAC key.
+    #[inline]
+    pub fn set_hmac_enabled(&mut self, enabled: bool) {
+        self.hmac_enabled = enabled;
+    }
+
+    /// Returns `true` if the tokens signed by the shared HMAC key are accepted.
+    #[inline]
+    pub fn hmac_enabled(&self) -> bool {
+        self.hmac_enabled
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the keys.
+    #[inline]
+    pub fn keys(&self) -> &[JwtKey] {
+        &self.keys
+    }
+
+    /// Returns the key with the key ID.
+    #[inline]
+    pub fn get(&self, key_id: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|key| key.key_id() == key_id)
+    }
+
+    /// Returns the key for signing.
+    pub fn signing_key(&self) -> Option<&JwtKey> {
+        if let Some(key_id) = self.signing_key_id.as_deref() {
+            self.get(key_id).filter(|key| key.can_sign())
+        } else {
+            self.keys.iter().find(|key| key.can_sign())
+        }
+    }
+
+    /// Returns the key to verify a token with the algorithm and optional key ID.
+    pub fn verification_key(&self, algorithm: &str, key_id: Option<&str>) -> Option<&JwtKey> {
+        if let Some(key_id) = key_id {
+            self.get(key_id)
+        } else {
+            self.signing_key()
+        }
+        .filter(|key| key.algorithm() == algorithm)
+    }
+
+    /// Returns the public keys as a JSON Web Key Set.
+    pub fn jwks(&self) -> Map {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| JsonValue::Object(key.to_jwk()))
+            .collect::<Vec<_>>();
+        Map::from_entry("keys", keys)
+    }
+
+    /// Returns the shared key set.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        LazyLock::force(&SHARED_KEY_SET)
+    }
+}
+
+impl Default for JwtKeySet {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+/// Shared key set for JWT tokens.
+static SHARED_KEY_SET: LazyLock<JwtKeySet> = LazyLock::new(|| {
+    let Some(config) = State::shared().get_config("jwt") else {
+        return JwtKeySet::default();
+    };
+    let keys = config
+        .get_array("keys")
+        .map(|keys| {
+            keys.iter()
+                .filter_map(|key| key.as_table())
+                .filter_map(|key| match JwtKey::try_from_config(key) {
+                    Ok(key) => Some(key),
+                    Err(err) => {
+                        tracing::error!("fail to load the JWT key: {err}");
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut key_set = JwtKeySet::new(keys);
+    if let Some(key_id) = config.get_str("signing-kid") {
+        key_set.set_signing_key_id(key_id);
+    }
+    if let Some(hmac_enabled) = config.get_bool("hmac-enabled") {
+        key_set.set_hmac_enabled(hmac_enabled);
+    }
+    if !key_set.is_empty() && key_set.signing_key().is_none() {
+        tracing::warn!("there is no JWT key available for signing tokens");
+    }
+    key_set
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{JwtKey, JwtKeySet};
+    use jwt_simple::algorithms::{ES256KeyPair, Ed25519KeyPair};
+
+    #[test]
+    fn it_converts_keys_to_jwks() {
+        let key_pair = ES256KeyPair::generate();
+        let private_key = key_pair.to_pem().unwrap();
+        let key = JwtKey::from_pem("es-key", "ES256", Some(&private_key), None).unwrap();
+        let jwk = key.to_jwk();
+        assert_eq!(jwk.get("kty").and_then(|v| v.as_str()), Some("EC"));
+        assert_eq!(jwk.get("crv").and_then(|v| v.as_str()), Some("P-256"));
+        assert_eq!(jwk.get("kid").and_then(|v| v.as_str()), Some("es-key"));
+        assert_eq!(jwk.get("alg").and_then(|v| v.as_str()), Some("ES256"));
+        assert!(jwk.contains_key("x") && jwk.contains_key("y"));
+
+        let key_pair = Ed25519KeyPair::generate();
+        let public_key = key_pair.public_key().to_pem();
+        let key = JwtKey::from_pem("ed-key", "EdDSA", None, Some(&public_key)).unwrap();
+        assert!(!key.can_sign());
+        let jwk = key.to_jwk();
+        assert_eq!(jwk.get("kty").and_then(|v| v.as_str()), Some("OKP"));
+        assert_eq!(jwk.get("crv").and_then(|v| v.as_str()), Some("Ed25519"));
+        assert!(jwk.get("d").is_none());
+    }
+
+    #[test]
+    fn it_selects_verification_keys() {
+        let es_key_pem = ES256KeyPair::generate().to_pem().unwrap();
+        let ed_key_pem = Ed25519KeyPair::generate().to_pem();
+        let es_key = JwtKey::from_pem("es-key", "ES256", Some(&es_key_pem), None).unwrap();
+        let ed_key = JwtKey::from_pem("ed-key", "EdDSA", Some(&ed_key_pem), None).unwrap();
+        let mut key_set = JwtKeySet::new(vec![es_key, ed_key]);
+        assert_eq!(
+            key_set.signing_key().map(|key| key.key_id()),
+            Some("es-key")
+        );
+
+        let key = key_set.verification_key("EdDSA", Some("ed-key"));
+        assert_eq!(key.map(|key| key.key_id()), Some("ed-key"));
+        assert!(key_set.verification_key("ES256", Some("ed-key")).is_none());
+        assert!(key_set.verification_key("ES256", Some("unknown")).is_none());
+
+        let key = key_set.verification_key("ES256", None);
+        assert_eq!(key.map(|key| key.key_id()), Some("es-key"));
+        key_set.set_signing_key_id("ed-key");
+        assert!(key_set.verification_key("ES256", None).is_none());
+        assert_eq!(
+            key_set
+                .jwks()
+                .get("keys")
+                .and_then(|v| v.as_array())
+                .map(|v| v.len()),
+            Some(2)
+        );
+        assert!(key_set.hmac_enabled());
+    }
+}
@@ -0,0 +1,139 @@
+use super::{AccessKeyId, SecretAccessKey, UserSession};
+use crate::{error::Error, extension::TomlTableExt, warn, BoxFuture};
+use std::collections::HashMap;
+use toml::Table;
+
+/// A secret access key with the user session of the principal.
+pub type ResolvedKey = (SecretAccessKey, UserSession<String>);
+
+/// A provider which resolves the secret access key and the principal for an access key ID.
+pub trait KeyProvider: Send + Sync {
+    /// Returns the provider name.
+    fn name(&self) -> &'static str;
+
+    /// Resolves the secret access key and the user session for the access key ID.
+    /// It returns `None` if the access key ID is unknown to the provider.
+    fn resolve<'a>(
+        &'a self,
+        access_key_id: &'a AccessKeyId,
+    ) -> BoxFuture<'a, Result<Option<ResolvedKey>, Error>>;
+}
+
+/// A provider which resolves the access keys in the configuration.
+///
+/// The secret access key is a base64-encoded string. If it is absent,
+/// the key derived by [`SecretAccessKey::new()`] is used.
+#[derive(Debug, Default)]
+pub struct ConfigKeyProvider {
+    /// Access keys.
+    keys: HashMap<AccessKeyId, ResolvedKey>,
+}
+
+impl ConfigKeyProvider {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attempts to create a new instance with the access keys
+    /// configured by the `keys` array of tables.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let mut provider = Self::new();
+        let keys = config
+            .get_array("keys")
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_table());
+        for config in keys {
+            let access_key_id = config
+                .get_str("access-key-id")
+                .ok_or_else(|| warn!("the `access-key-id` should be specified"))?;
+            let access_key_id = AccessKeyId::from(access_key_id);
+            let secret_access_key = match config.get_str("secret-access-key") {
+                Some(secret_access_key) => SecretAccessKey::from_base64(secret_access_key)?,
+                None => SecretAccessKey::new(&access_key_id),
+            };
+            let user_id = config
+                .get_str("subject")
+                .unwrap_or_else(|| access_key_id.as_str());
+            let mut session = UserSession::new(user_id.to_owned(), None);
+            if let Some(roles) = config.get_str_array("roles") {
+                session.set_roles(roles.into_iter().map(|s| s.to_owned()).collect::<Vec<_>>());
+            }
+            provider.add_key(access_key_id, secret_access_key, session);
+        }
+        Ok(provider)
+    }
+
+    /// Adds an access key with the user session.
+    #[inline]
+    pub fn add_key(
+        &mut self,
+        access_key_id: AccessKeyId,
+        secret_access_key: SecretAccessKey,
+        session: UserSession<String>,
+    ) {
+        self.keys
+            .insert(access_key_id, (secret_access_key, session));
+    }
+
+    /// Returns `true` if there are no access keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl KeyProvider for ConfigKeyProvider {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "config"
+    }
+
+    fn resolve<'a>(
+        &'a self,
+        access_key_id: &'a AccessKeyId,
+    ) -> BoxFuture<'a, Result<Option<ResolvedKey>, Error>> {
+        let key = self.keys.get(access_key_id).cloned();
+        Box::pin(async move { Ok(key) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigKeyProvider, KeyProvider};
+    use crate::{
+        auth::{AccessKeyId, SecretAccessKey},
+        crypto::Digest,
+    };
+    use futures::executor::block_on;
+    use hmac::Hmac;
+
+    #[test]
+    fn it_resolves_config_keys() {
+        let secret_access_key =
+            SecretAccessKey::with_key::<Hmac<Digest>>(&AccessKeyId::from("worker"), "secret");
+        let config = format!(
+            r#"
+            [[keys]]
+            access-key-id = "worker"
+            secret-access-key = "{secret_access_key}"
+            subject = "service-a"
+            roles = ["worker"]
+            "#
+        );
+        let config = config.parse().unwrap();
+        let provider = ConfigKeyProvider::try_from_config(&config).unwrap();
+
+        let (key, session) = block_on(provider.resolve(&"worker".into()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(key.as_bytes(), secret_access_key.as_bytes());
+        assert_eq!(session.user_id(), "service-a");
+        assert!(session.has_role("worker"));
+        assert!(block_on(provider.resolve(&"unknown".into()))
+            .unwrap()
+            .is_none());
+    }
+}
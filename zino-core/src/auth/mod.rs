@@ -15,34 +15,70 @@
 //! [`totp-rs`]: https://crates.io/crates/totp-rs
 
 mod access_key;
+mod access_policy;
 mod authentication;
 mod authorization_provider;
 mod client_credentials;
 mod jwt_claims;
+mod jwt_key;
+mod key_provider;
+mod nonce_store;
+mod policy_engine;
 mod security_token;
 mod session_id;
+mod session_store;
+mod signature_verifier;
 mod user_session;
 
+#[cfg(feature = "accessor")]
+mod operator_session_store;
+
 #[cfg(feature = "auth-oauth2")]
 mod oauth2_client;
 
 #[cfg(feature = "auth-oidc")]
 mod oidc_client;
 
+#[cfg(any(feature = "auth-oauth2", feature = "auth-oidc"))]
+mod oidc_provider;
+
+#[cfg(feature = "orm")]
+mod database_session_store;
+
+pub(crate) use access_policy::match_pattern;
 pub(crate) use jwt_claims::{default_time_tolerance, default_verification_options};
 pub(crate) use security_token::ParseSecurityTokenError;
 
+#[cfg(any(feature = "auth-oauth2", feature = "auth-oidc"))]
+pub(crate) use oidc_provider::init as init_oidc_providers;
+
 pub use access_key::{AccessKeyId, SecretAccessKey};
+pub use access_policy::{AccessPolicy, PolicyEffect};
 pub use authentication::Authentication;
 pub use authorization_provider::AuthorizationProvider;
 pub use client_credentials::ClientCredentials;
 pub use jwt_claims::{JwtClaims, JwtHmacKey};
+pub use jwt_key::{JwtKey, JwtKeySet};
+pub use key_provider::{ConfigKeyProvider, KeyProvider, ResolvedKey};
+pub use nonce_store::{MemoryNonceStore, NonceStore};
+pub use policy_engine::PolicyEngine;
 pub use security_token::SecurityToken;
 pub use session_id::SessionId;
+pub use session_store::{MemorySessionStore, Session, SessionManager, SessionStore};
+pub use signature_verifier::SignatureVerifier;
 pub use user_session::UserSession;
 
+#[cfg(feature = "accessor")]
+pub use operator_session_store::OperatorSessionStore;
+
 #[cfg(feature = "auth-oauth2")]
 pub use oauth2_client::OAuth2Client;
 
 #[cfg(feature = "auth-oidc")]
 pub use oidc_client::OidcClient;
+
+#[cfg(any(feature = "auth-oauth2", feature = "auth-oidc"))]
+pub use oidc_provider::{OidcIdentity, OidcProvider};
+
+#[cfg(feature = "orm")]
+pub use database_session_store::DatabaseSessionStore;
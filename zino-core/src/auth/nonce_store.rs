@@ -0,0 +1,68 @@
+use crate::{datetime::DateTime, error::Error, BoxFuture};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
+
+/// A store which records the used nonces to prevent the replay attacks.
+///
+/// A store shared by the instances, such as Redis, should be registered
+/// if the service is deployed with multiple instances.
+pub trait NonceStore: Send + Sync {
+    /// Returns the store name.
+    fn name(&self) -> &'static str;
+
+    /// Records the nonce which is used until the expiration time.
+    /// It returns `false` if the nonce has been recorded and not expired.
+    ///
+    /// The check and the insertion should be atomic.
+    fn insert<'a>(
+        &'a self,
+        nonce: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+}
+
+/// A store which keeps the nonces in the process memory.
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    /// Nonces with the expiration time.
+    nonces: Mutex<HashMap<String, DateTime>>,
+    /// Number of insertions.
+    insertions: AtomicUsize,
+}
+
+impl MemoryNonceStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn insert<'a>(
+        &'a self,
+        nonce: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        let now = DateTime::now();
+        let mut nonces = self.nonces.lock();
+        if self.insertions.fetch_add(1, Relaxed) % 1000 == 0 {
+            nonces.retain(|_, expires_at| *expires_at > now);
+        }
+        let is_new = !nonces
+            .get(nonce)
+            .is_some_and(|expires_at| *expires_at > now);
+        if is_new {
+            nonces.insert(nonce.to_owned(), expires_at);
+        }
+        Box::pin(async move { Ok(is_new) })
+    }
+}
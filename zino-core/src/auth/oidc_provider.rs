@@ -0,0 +1,475 @@
+use super::{session_store::new_session_store, MemorySessionStore, SessionStore};
+use crate::{
+    application::{http_client, StaticRecord},
+    bail,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    warn, LazyLock, Map, SharedString,
+};
+use cookie::{Cookie, SameSite};
+use reqwest::header::ACCEPT;
+use std::time::Duration;
+use toml::Table;
+use url::Url;
+
+#[cfg(feature = "auth-oauth2")]
+use super::OAuth2Client;
+
+#[cfg(feature = "auth-oidc")]
+use super::OidcClient;
+
+#[cfg(feature = "auth-oidc")]
+use crate::extension::JsonValueExt;
+
+/// Client of an identity provider.
+enum ProviderClient {
+    /// OAuth2 client.
+    #[cfg(feature = "auth-oauth2")]
+    OAuth2(Box<OAuth2Client>),
+    /// OpenID Connect client.
+    #[cfg(feature = "auth-oidc")]
+    Oidc(Box<OidcClient>),
+}
+
+/// An identity provider for the login with the authorization code flow.
+///
+/// The providers are configured by the `[[oidc]]` tables. The authorization state
+/// together with the PKCE verifier and the nonce is kept in a store until the callback,
+/// which can be configured by the `state-store` table with the same settings as `[session]`.
+/// The state is also bound to the user agent by a cookie, so that the callback
+/// is rejected if it is not initiated by the same user agent.
+pub struct OidcProvider {
+    /// Provider name.
+    name: String,
+    /// Provider client.
+    client: ProviderClient,
+    /// Scopes to request.
+    scopes: Vec<String>,
+    /// A flag to indicate whether the PKCE is enabled.
+    pkce: bool,
+    /// A flag to indicate whether a new user can be created for the login.
+    sign_up: bool,
+    /// Claim name of the subject identifier.
+    subject_claim: String,
+    /// URL of the userinfo endpoint.
+    userinfo_url: Option<Url>,
+    /// Time-to-live for the authorization state.
+    state_ttl: Duration,
+    /// Store for the authorization state.
+    state_store: Box<dyn SessionStore>,
+    /// Name of the cookie which binds the state to the user agent.
+    cookie_name: SharedString,
+    /// Path of the cookie.
+    cookie_path: SharedString,
+    /// A flag to indicate whether the cookie is only sent over HTTPS.
+    secure: bool,
+}
+
+impl OidcProvider {
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let name = config
+            .get_str("name")
+            .ok_or_else(|| warn!("the `name` field should be specified"))?;
+        let protocol = config.get_str("protocol").unwrap_or("oidc");
+        let client = match protocol {
+            #[cfg(feature = "auth-oauth2")]
+            "oauth2" => ProviderClient::OAuth2(Box::new(OAuth2Client::try_from_config(config)?)),
+            #[cfg(feature = "auth-oidc")]
+            "oidc" => ProviderClient::Oidc(Box::new(OidcClient::try_from_config(config)?)),
+            _ => bail!(
+                "unsupported protocol `{}` for the provider `{}`",
+                protocol,
+                name
+            ),
+        };
+        let scopes = if let Some(scopes) = config.get_str_array("scopes") {
+            scopes.into_iter().map(|s| s.to_owned()).collect()
+        } else if protocol == "oidc" {
+            vec!["profile".to_owned(), "email".to_owned()]
+        } else {
+            Vec::new()
+        };
+        let userinfo_url = config
+            .get_str("userinfo-url")
+            .map(|s| s.parse::<Url>())
+            .transpose()?;
+        if protocol == "oauth2" && userinfo_url.is_none() {
+            bail!(
+                "the `userinfo-url` field should be specified for the provider `{}`",
+                name
+            );
+        }
+        let state_store = match config.get_table("state-store") {
+            Some(config) => new_session_store(config)?,
+            None => Box::new(MemorySessionStore::new()),
+        };
+        Ok(Self {
+            name: name.to_owned(),
+            client,
+            scopes,
+            pkce: config.get_bool("pkce").unwrap_or(true),
+            sign_up: config.get_bool("sign-up").unwrap_or(false),
+            subject_claim: config.get_str("subject-claim").unwrap_or("sub").to_owned(),
+            userinfo_url,
+            state_ttl: config
+                .get_duration("state-ttl")
+                .unwrap_or(Duration::from_secs(10 * 60)),
+            state_store,
+            cookie_name: config
+                .get_str("cookie-name")
+                .unwrap_or("zino-oidc-state")
+                .to_owned()
+                .into(),
+            cookie_path: config
+                .get_str("cookie-path")
+                .unwrap_or("/")
+                .to_owned()
+                .into(),
+            secure: config.get_bool("secure").unwrap_or(true),
+        })
+    }
+
+    /// Returns the provider name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Returns `true` if a new user can be created for the login.
+    /// It is disabled by default.
+    #[inline]
+    pub fn sign_up_allowed(&self) -> bool {
+        self.sign_up
+    }
+
+    /// Returns the name of the cookie which binds the state to the user agent.
+    #[inline]
+    pub fn cookie_name(&self) -> &str {
+        self.cookie_name.as_ref()
+    }
+
+    /// Returns a cookie which removes the state from the user agent.
+    #[inline]
+    pub fn expired_cookie(&self) -> Cookie<'static> {
+        self.build_cookie(String::new(), Duration::ZERO)
+    }
+
+    /// Generates the URL of the authorization endpoint for redirecting the user agent,
+    /// and the cookie which should be set in the response to bind the state.
+    /// The `redirect_to` path will be returned after the callback.
+    pub async fn authorize(
+        &self,
+        redirect_to: Option<&str>,
+    ) -> Result<(Url, Cookie<'static>), Error> {
+        let mut record = Map::new();
+        record.upsert("provider", self.name.as_str());
+        if let Some(redirect_to) = redirect_to.filter(|s| !s.is_empty()) {
+            if !is_local_path(redirect_to) {
+                bail!(
+                    "400 Bad Request: the redirect path `{}` is not allowed",
+                    redirect_to
+                );
+            }
+            record.upsert("redirect_to", redirect_to);
+        }
+
+        let (url, state) = match &self.client {
+            #[cfg(feature = "auth-oauth2")]
+            ProviderClient::OAuth2(client) => {
+                let mut request = client.authorize_url(oauth2::CsrfToken::new_random);
+                for scope in &self.scopes {
+                    request = request.add_scope(oauth2::Scope::new(scope.to_owned()));
+                }
+                if self.pkce {
+                    let (challenge, verifier) = oauth2::PkceCodeChallenge::new_random_sha256();
+                    request = request.set_pkce_challenge(challenge);
+                    record.upsert("pkce_verifier", verifier.secret().as_str());
+                }
+                let (url, state) = request.url();
+                (url, state.secret().to_owned())
+            }
+            #[cfg(feature = "auth-oidc")]
+            ProviderClient::Oidc(client) => {
+                use openidconnect::core::CoreAuthenticationFlow;
+
+                let mut request = client.authorize_url(
+                    CoreAuthenticationFlow::AuthorizationCode,
+                    openidconnect::CsrfToken::new_random,
+                    openidconnect::Nonce::new_random,
+                );
+                for scope in &self.scopes {
+                    request = request.add_scope(openidconnect::Scope::new(scope.to_owned()));
+                }
+                if self.pkce {
+                    let (challenge, verifier) =
+                        openidconnect::PkceCodeChallenge::new_random_sha256();
+                    request = request.set_pkce_challenge(challenge);
+                    record.upsert("pkce_verifier", verifier.secret().as_str());
+                }
+                let (url, state, nonce) = request.url();
+                record.upsert("nonce", nonce.secret().as_str());
+                (url, state.secret().to_owned())
+            }
+        };
+        if !self
+            .state_store
+            .save(&state, record, 0, self.state_ttl)
+            .await?
+        {
+            bail!("the authorization state `{}` has been used", state);
+        }
+
+        let cookie = self.build_cookie(state, self.state_ttl);
+        Ok((url, cookie))
+    }
+
+    /// Handles the callback from the authorization endpoint with the query parameters
+    /// and the value of the cookie set by [`authorize`](Self::authorize).
+    /// The authorization code will be exchanged for the tokens, and the ID token
+    /// will be validated against the nonce.
+    pub async fn callback(
+        &self,
+        query: &Map,
+        state_cookie: Option<&str>,
+    ) -> Result<OidcIdentity, Error> {
+        if let Some(error) = query.get_str("error") {
+            let description = query.get_str("error_description").unwrap_or(error);
+            bail!(
+                "401 Unauthorized: the authorization is denied by the provider `{}`: {}",
+                self.name,
+                description
+            );
+        }
+
+        let state = query
+            .get_str("state")
+            .ok_or_else(|| warn!("400 Bad Request: the `state` parameter should be specified"))?;
+        let code = query
+            .get_str("code")
+            .ok_or_else(|| warn!("400 Bad Request: the `code` parameter should be specified"))?;
+        let bound = state_cookie.is_some_and(|value| {
+            value.len() == state.len()
+                && value
+                    .bytes()
+                    .zip(state.bytes())
+                    .fold(0, |acc, (a, b)| acc | (a ^ b))
+                    == 0
+        });
+        if !bound {
+            bail!("401 Unauthorized: the authorization state is not bound to the user agent");
+        }
+        let Some(record) = self.state_store.load(state).await? else {
+            bail!("401 Unauthorized: the authorization state is invalid or expired");
+        };
+        self.state_store.delete(state).await?;
+        if record.get_str("provider") != Some(self.name.as_str()) {
+            bail!("401 Unauthorized: the authorization state is invalid");
+        }
+
+        let pkce_verifier = record.get_str("pkce_verifier").map(|s| s.to_owned());
+        let claims = match &self.client {
+            #[cfg(feature = "auth-oauth2")]
+            ProviderClient::OAuth2(client) => {
+                use oauth2::TokenResponse;
+
+                let code = oauth2::AuthorizationCode::new(code.to_owned());
+                let mut request = client.exchange_code(code);
+                if let Some(verifier) = pkce_verifier {
+                    request = request.set_pkce_verifier(oauth2::PkceCodeVerifier::new(verifier));
+                }
+                let token_response = request
+                    .request_async(oauth2::reqwest::async_http_client)
+                    .await?;
+                self.fetch_userinfo(token_response.access_token().secret())
+                    .await?
+            }
+            #[cfg(feature = "auth-oidc")]
+            ProviderClient::Oidc(client) => {
+                use openidconnect::{OAuth2TokenResponse, TokenResponse};
+
+                let nonce = record
+                    .get_str("nonce")
+                    .ok_or_else(|| warn!("401 Unauthorized: the nonce is absent"))?;
+                let code = openidconnect::AuthorizationCode::new(code.to_owned());
+                let mut request = client.exchange_code(code);
+                if let Some(verifier) = pkce_verifier {
+                    request =
+                        request.set_pkce_verifier(openidconnect::PkceCodeVerifier::new(verifier));
+                }
+                let token_response = request
+                    .request_async(openidconnect::reqwest::async_http_client)
+                    .await?;
+                let id_token = token_response
+                    .id_token()
+                    .ok_or_else(|| warn!("401 Unauthorized: the ID token is absent"))?;
+                let verifier = client.id_token_verifier();
+                let nonce = openidconnect::Nonce::new(nonce.to_owned());
+                let id_token_claims = id_token.claims(&verifier, &nonce)?;
+                if let Some(expected_hash) = id_token_claims.access_token_hash() {
+                    let access_token_hash = openidconnect::AccessTokenHash::from_token(
+                        token_response.access_token(),
+                        &id_token.signing_alg()?,
+                    )?;
+                    if access_token_hash != *expected_hash {
+                        bail!("401 Unauthorized: the access token hash is invalid");
+                    }
+                }
+
+                let mut claims = serde_json::to_value(id_token_claims)?
+                    .into_map_opt()
+                    .unwrap_or_default();
+                if self.userinfo_url.is_some() {
+                    let access_token = token_response.access_token().secret();
+                    for (key, value) in self.fetch_userinfo(access_token).await? {
+                        claims.entry(key).or_insert(value);
+                    }
+                }
+                claims
+            }
+        };
+
+        let subject = claims
+            .parse_string(&self.subject_claim)
+            .ok_or_else(|| {
+                warn!(
+                    "401 Unauthorized: the `{}` claim is absent",
+                    self.subject_claim
+                )
+            })?
+            .into_owned();
+        Ok(OidcIdentity {
+            provider: self.name.clone(),
+            subject,
+            claims,
+            redirect_to: record.get_str("redirect_to").map(|s| s.to_owned()),
+        })
+    }
+
+    /// Builds a cookie for the state.
+    fn build_cookie(&self, state: String, max_age: Duration) -> Cookie<'static> {
+        let mut cookie_builder = Cookie::build((self.cookie_name.clone(), state))
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax)
+            .path(self.cookie_path.clone());
+        if let Ok(max_age) = max_age.try_into() {
+            cookie_builder = cookie_builder.max_age(max_age);
+        }
+        cookie_builder.build()
+    }
+
+    /// Fetches the claims from the userinfo endpoint.
+    async fn fetch_userinfo(&self, access_token: &str) -> Result<Map, Error> {
+        let Some(userinfo_url) = self.userinfo_url.as_ref() else {
+            bail!(
+                "the `userinfo-url` field should be specified for the provider `{}`",
+                self.name
+            );
+        };
+        let client = http_client::SHARED_HTTP_CLIENT
+            .get()
+            .ok_or_else(|| warn!("fail to get the global HTTP client"))?;
+        let userinfo = client
+            .get(userinfo_url.clone())
+            .bearer_auth(access_token)
+            .header(ACCEPT, "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Map>()
+            .await?;
+        Ok(userinfo)
+    }
+
+    /// Gets the provider configured by `[[oidc]]` with the name.
+    #[inline]
+    pub fn get(name: &str) -> Option<&'static Self> {
+        SHARED_OIDC_PROVIDERS.find(name)
+    }
+}
+
+/// An identity authenticated by the provider.
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    /// Provider name.
+    provider: String,
+    /// Subject identifier.
+    subject: String,
+    /// Claims about the user.
+    claims: Map,
+    /// Path to redirect after the login.
+    redirect_to: Option<String>,
+}
+
+impl OidcIdentity {
+    /// Returns the provider name.
+    #[inline]
+    pub fn provider(&self) -> &str {
+        self.provider.as_str()
+    }
+
+    /// Returns the subject identifier.
+    #[inline]
+    pub fn subject(&self) -> &str {
+        self.subject.as_str()
+    }
+
+    /// Returns the union ID which is unique across the providers.
+    #[inline]
+    pub fn union_id(&self) -> String {
+        format!("{}:{}", self.provider, self.subject)
+    }
+
+    /// Returns a reference to the claims.
+    #[inline]
+    pub fn claims(&self) -> &Map {
+        &self.claims
+    }
+
+    /// Returns the path to redirect after the login.
+    #[inline]
+    pub fn redirect_to(&self) -> Option<&str> {
+        self.redirect_to.as_deref()
+    }
+}
+
+/// Returns `true` if the path is local to the application.
+fn is_local_path(path: &str) -> bool {
+    path.starts_with('/') && !path.starts_with("//") && !path.contains(['\\', '\r', '\n'])
+}
+
+/// Initializes the identity providers.
+pub(crate) fn init() {
+    LazyLock::force(&SHARED_OIDC_PROVIDERS);
+}
+
+/// Shared identity providers.
+static SHARED_OIDC_PROVIDERS: LazyLock<StaticRecord<OidcProvider>> = LazyLock::new(|| {
+    let mut providers = StaticRecord::new();
+    if let Some(configs) = State::shared().config().get_array("oidc") {
+        for config in configs.iter().filter_map(|v| v.as_table()) {
+            let name = config.get_str("name").unwrap_or("oidc");
+            let provider = OidcProvider::try_from_config(config)
+                .unwrap_or_else(|err| panic!("fail to create the provider `{name}`: {err}"));
+            providers.add(name, provider);
+        }
+    }
+    providers
+});
+
+#[cfg(test)]
+mod tests {
+    use super::is_local_path;
+
+    #[test]
+    fn it_checks_local_paths() {
+        assert!(is_local_path("/"));
+        assert!(is_local_path("/dashboard?tab=1"));
+        assert!(!is_local_path("//evil.example.com"));
+        assert!(!is_local_path("/\\evil.example.com"));
+        assert!(!is_local_path("https://evil.example.com"));
+        assert!(!is_local_path("dashboard"));
+    }
+}
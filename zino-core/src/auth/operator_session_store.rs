@@ -0,0 +1,107 @@
+use super::SessionStore;
+use crate::{
+    accessor::GlobalAccessor, bail, datetime::DateTime, error::Error, extension::JsonObjectExt,
+    BoxFuture, Map,
+};
+use opendal::{ErrorKind, Operator};
+use std::time::Duration;
+
+/// A store which keeps the sessions in a storage service of the `opendal` operator,
+/// such as Redis or Memcached configured by `[[accessor]]`.
+///
+/// The version of a record is checked before saving it, but the check is not atomic
+/// since the storage services have no conditional writes in common.
+#[derive(Debug)]
+pub struct OperatorSessionStore {
+    /// Storage operator.
+    operator: &'static Operator,
+    /// Path prefix for the session records.
+    prefix: String,
+}
+
+impl OperatorSessionStore {
+    /// Creates a new instance for the storage accessor.
+    pub fn new(name: &str) -> Result<Self, Error> {
+        let Some(operator) = GlobalAccessor::get(name) else {
+            bail!("the storage accessor `{}` is unavailable", name);
+        };
+        Ok(Self {
+            operator,
+            prefix: "sessions/".to_owned(),
+        })
+    }
+
+    /// Sets the path prefix for the session records.
+    #[inline]
+    pub fn set_prefix(&mut self, prefix: impl ToString) {
+        self.prefix = prefix.to_string();
+    }
+
+    /// Returns the path of the session record.
+    #[inline]
+    fn record_path(&self, session_id: &str) -> String {
+        format!("{}{}", self.prefix, session_id)
+    }
+}
+
+impl SessionStore for OperatorSessionStore {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "accessor"
+    }
+
+    fn load<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Option<Map>, Error>> {
+        Box::pin(async move {
+            let path = self.record_path(session_id);
+            let bytes = match self.operator.read(&path).await {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+            let mut record = serde_json::from_slice::<Map>(&bytes)?;
+            let is_expired = record
+                .parse_datetime("expires_at")
+                .and_then(|result| result.ok())
+                .is_some_and(|expires_at| expires_at <= DateTime::now());
+            if is_expired {
+                self.operator.delete(&path).await?;
+                return Ok(None);
+            }
+            record.remove("expires_at");
+            Ok(Some(record))
+        })
+    }
+
+    fn save<'a>(
+        &'a self,
+        session_id: &'a str,
+        mut record: Map,
+        version: u64,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let current_version = self
+                .load(session_id)
+                .await?
+                .and_then(|record| record.get_u64("version"))
+                .unwrap_or_default();
+            if current_version != version {
+                return Ok(false);
+            }
+
+            let path = self.record_path(session_id);
+            record.upsert("expires_at", DateTime::now() + ttl);
+            let bytes = serde_json::to_vec(&record)?;
+            self.operator.write(&path, bytes).await?;
+            Ok(true)
+        })
+    }
+
+    fn delete<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let path = self.record_path(session_id);
+            self.operator.delete(&path).await?;
+            Ok(())
+        })
+    }
+}
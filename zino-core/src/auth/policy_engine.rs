@@ -0,0 +1,128 @@
+use super::{AccessPolicy, PolicyEffect, UserSession};
+use crate::{bail, datetime::DateTime, error::Error, LazyLock};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// An engine which evaluates the access policies.
+///
+/// A request is denied if any applicable policy denies it, otherwise it is allowed
+/// if any applicable policy allows it. When no policy applies, the default effect is used.
+#[derive(Debug, Clone)]
+pub struct PolicyEngine {
+    /// Access policies.
+    policies: Vec<AccessPolicy>,
+    /// Effect used when no policy applies.
+    default_effect: PolicyEffect,
+}
+
+impl PolicyEngine {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_policies(Vec::new())
+    }
+
+    /// Creates a new instance with the policies.
+    #[inline]
+    pub fn with_policies(policies: Vec<AccessPolicy>) -> Self {
+        Self {
+            policies,
+            default_effect: PolicyEffect::Deny,
+        }
+    }
+
+    /// Adds an access policy.
+    #[inline]
+    pub fn add_policy(&mut self, policy: AccessPolicy) {
+        self.policies.push(policy);
+    }
+
+    /// Sets the effect used when no policy applies. The default is `Deny`.
+    #[inline]
+    pub fn set_default_effect(&mut self, effect: PolicyEffect) {
+        self.default_effect = effect;
+    }
+
+    /// Returns the access policies.
+    #[inline]
+    pub fn policies(&self) -> &[AccessPolicy] {
+        &self.policies
+    }
+
+    /// Evaluates the effect of the action on the resource for the user session.
+    pub fn evaluate<U, T: ToString>(
+        &self,
+        session: Option<&UserSession<U, String, T>>,
+        resource: &str,
+        action: &str,
+    ) -> (PolicyEffect, Option<&AccessPolicy>) {
+        let now = DateTime::now();
+        let mut allowed_policy = None;
+        for policy in self.policies.iter().filter(|policy| {
+            policy.is_valid_at(now)
+                && policy.matches(resource, action)
+                && policy.applies_to(session)
+        }) {
+            match policy.effect() {
+                PolicyEffect::Deny => return (PolicyEffect::Deny, Some(policy)),
+                PolicyEffect::Allow => {
+                    allowed_policy.get_or_insert(policy);
+                }
+            }
+        }
+        match allowed_policy {
+            Some(policy) => (PolicyEffect::Allow, Some(policy)),
+            None => (self.default_effect, None),
+        }
+    }
+
+    /// Authorizes the action on the resource for the user session.
+    pub fn authorize<U, T: ToString>(
+        &self,
+        session: Option<&UserSession<U, String, T>>,
+        resource: &str,
+        action: &str,
+    ) -> Result<(), Error> {
+        match self.evaluate(session, resource, action) {
+            (PolicyEffect::Allow, _) => Ok(()),
+            (PolicyEffect::Deny, Some(policy)) => {
+                bail!(
+                    "action `{}` on the resource `{}` is denied by the policy `{}`",
+                    action,
+                    resource,
+                    policy.name()
+                );
+            }
+            (PolicyEffect::Deny, None) => {
+                bail!(
+                    "action `{}` on the resource `{}` is not allowed by any policy",
+                    action,
+                    resource
+                );
+            }
+        }
+    }
+
+    /// Returns the shared policy engine.
+    #[inline]
+    pub fn shared() -> Arc<Self> {
+        SHARED_POLICY_ENGINE.read().clone()
+    }
+
+    /// Replaces the shared policy engine.
+    #[inline]
+    pub fn set_shared(engine: Self) {
+        *SHARED_POLICY_ENGINE.write() = Arc::new(engine);
+    }
+}
+
+impl Default for PolicyEngine {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shared policy engine.
+static SHARED_POLICY_ENGINE: LazyLock<RwLock<Arc<PolicyEngine>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(PolicyEngine::new())));
@@ -0,0 +1,657 @@
+use crate::{
+    bail,
+    datetime::DateTime,
+    encoding::base64,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    warn, BoxFuture, JsonValue, Map, SharedString,
+};
+use cookie::{Cookie, SameSite};
+use parking_lot::{Mutex, RwLock};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    },
+    time::Duration,
+};
+use toml::Table;
+
+/// Status of a session in the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionStatus {
+    /// The session is unchanged.
+    Unchanged,
+    /// The session data has been changed.
+    Changed,
+    /// The session ID has been renewed.
+    Renewed,
+    /// The session has been destroyed.
+    Destroyed,
+}
+
+/// State of a session.
+#[derive(Debug)]
+struct SessionState {
+    /// Session ID.
+    id: String,
+    /// Previous session ID before the renewal.
+    previous_id: Option<String>,
+    /// Session data.
+    data: Map,
+    /// Time when the session was created at.
+    created_at: DateTime,
+    /// Time when the session was accessed at.
+    accessed_at: DateTime,
+    /// Status.
+    status: SessionStatus,
+    /// A flag to indicate whether the session is new.
+    is_new: bool,
+    /// Version of the record in the store, which is `0` if it has not been saved.
+    version: u64,
+}
+
+/// A server-side session which is shared by the clones in a request.
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Shared state.
+    state: Arc<RwLock<SessionState>>,
+}
+
+impl Session {
+    /// Creates a new instance with a random session ID.
+    pub fn new() -> Self {
+        let now = DateTime::now();
+        let state = SessionState {
+            id: generate_session_id(),
+            previous_id: None,
+            data: Map::new(),
+            created_at: now,
+            accessed_at: now,
+            status: SessionStatus::Unchanged,
+            is_new: true,
+            version: 0,
+        };
+        Self {
+            state: Arc::new(RwLock::new(state)),
+        }
+    }
+
+    /// Restores a session from the record in a store.
+    fn from_record(id: &str, record: &Map) -> Option<Self> {
+        let created_at = record.parse_datetime("created_at")?.ok()?;
+        let accessed_at = record.parse_datetime("accessed_at")?.ok()?;
+        let data = record.get_object("data").cloned().unwrap_or_default();
+        let state = SessionState {
+            id: id.to_owned(),
+            previous_id: None,
+            data,
+            created_at,
+            accessed_at,
+            status: SessionStatus::Unchanged,
+            is_new: false,
+            version: record.get_u64("version").unwrap_or_default(),
+        };
+        Some(Self {
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    /// Returns the record to be saved in a store with the next version.
+    fn to_record(&self) -> Map {
+        let state = self.state.read();
+        let mut record = Map::new();
+        record.upsert("data", state.data.clone());
+        record.upsert("created_at", state.created_at);
+        record.upsert("accessed_at", state.accessed_at);
+        record.upsert("version", state.version + 1);
+        record
+    }
+
+    /// Returns the version of the record in the store.
+    #[inline]
+    fn version(&self) -> u64 {
+        self.state.read().version
+    }
+
+    /// Returns the session ID.
+    #[inline]
+    pub fn id(&self) -> String {
+        self.state.read().id.clone()
+    }
+
+    /// Returns the time when the session was created at.
+    #[inline]
+    pub fn created_at(&self) -> DateTime {
+        self.state.read().created_at
+    }
+
+    /// Returns the time when the session was last accessed at.
+    #[inline]
+    pub fn accessed_at(&self) -> DateTime {
+        self.state.read().accessed_at
+    }
+
+    /// Returns `true` if the session is created in the current request.
+    #[inline]
+    pub fn is_new(&self) -> bool {
+        self.state.read().is_new
+    }
+
+    /// Returns `true` if the session data has been changed or the session has been renewed.
+    #[inline]
+    pub fn is_modified(&self) -> bool {
+        matches!(
+            self.state.read().status,
+            SessionStatus::Changed | SessionStatus::Renewed
+        )
+    }
+
+    /// Returns `true` if the session has been destroyed.
+    #[inline]
+    pub fn is_destroyed(&self) -> bool {
+        self.state.read().status == SessionStatus::Destroyed
+    }
+
+    /// Returns a copy of the session data.
+    #[inline]
+    pub fn data(&self) -> Map {
+        self.state.read().data.clone()
+    }
+
+    /// Gets the value corresponding to the key.
+    #[inline]
+    pub fn get_value(&self, key: &str) -> Option<JsonValue> {
+        self.state.read().data.get(key).cloned()
+    }
+
+    /// Gets the value corresponding to the key and deserializes it as `T`.
+    #[inline]
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.get_value(key)
+            .and_then(|value| serde_json::from_value(value).ok())
+    }
+
+    /// Inserts a key-value pair into the session data.
+    pub fn insert(&self, key: impl Into<String>, value: impl Into<JsonValue>) {
+        let mut state = self.state.write();
+        state.data.upsert(key.into(), value.into());
+        state.mark_changed();
+    }
+
+    /// Removes the value corresponding to the key.
+    pub fn remove(&self, key: &str) -> Option<JsonValue> {
+        let mut state = self.state.write();
+        let value = state.data.remove(key);
+        if value.is_some() {
+            state.mark_changed();
+        }
+        value
+    }
+
+    /// Clears the session data.
+    pub fn clear(&self) {
+        let mut state = self.state.write();
+        if !state.data.is_empty() {
+            state.data.clear();
+            state.mark_changed();
+        }
+    }
+
+    /// Renews the session ID while keeping the data.
+    /// It should be called after the privilege level changes, such as a login.
+    pub fn renew(&self) {
+        let mut state = self.state.write();
+        if state.status != SessionStatus::Destroyed {
+            let previous_id = std::mem::replace(&mut state.id, generate_session_id());
+            if !state.is_new && state.previous_id.is_none() {
+                state.previous_id = Some(previous_id);
+            }
+            state.status = SessionStatus::Renewed;
+            state.version = 0;
+        }
+    }
+
+    /// Destroys the session.
+    pub fn destroy(&self) {
+        let mut state = self.state.write();
+        state.data.clear();
+        state.status = SessionStatus::Destroyed;
+    }
+}
+
+impl Default for Session {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionState {
+    /// Marks the session as changed.
+    #[inline]
+    fn mark_changed(&mut self) {
+        if self.status == SessionStatus::Unchanged {
+            self.status = SessionStatus::Changed;
+        }
+    }
+}
+
+/// A store which keeps the server-side sessions.
+pub trait SessionStore: Send + Sync {
+    /// Returns the store name.
+    fn name(&self) -> &'static str;
+
+    /// Loads the session record with the ID.
+    fn load<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Option<Map>, Error>>;
+
+    /// Saves the session record with the ID, which will expire after the TTL.
+    ///
+    /// The record is saved only if the stored record has the `version`,
+    /// where `0` means that there is no such record or it has expired.
+    /// It returns `false` if the record has been saved by others.
+    fn save<'a>(
+        &'a self,
+        session_id: &'a str,
+        record: Map,
+        version: u64,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Deletes the session record with the ID.
+    fn delete<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// A store which keeps the sessions in the process memory.
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    /// Session records with the expiration time.
+    records: Mutex<HashMap<String, (Map, DateTime)>>,
+    /// Number of saves.
+    saves: AtomicUsize,
+}
+
+impl MemorySessionStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn load<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Option<Map>, Error>> {
+        let record = self
+            .records
+            .lock()
+            .get(session_id)
+            .filter(|(_, expires_at)| *expires_at > DateTime::now())
+            .map(|(record, _)| record.clone());
+        Box::pin(async move { Ok(record) })
+    }
+
+    fn save<'a>(
+        &'a self,
+        session_id: &'a str,
+        record: Map,
+        version: u64,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        let now = DateTime::now();
+        let mut records = self.records.lock();
+        if self.saves.fetch_add(1, Relaxed) % 1000 == 0 {
+            records.retain(|_, (_, expires_at)| *expires_at > now);
+        }
+        let current_version = records
+            .get(session_id)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .and_then(|(record, _)| record.get_u64("version"))
+            .unwrap_or_default();
+        let is_saved = current_version == version;
+        if is_saved {
+            records.insert(session_id.to_owned(), (record, now + ttl));
+        }
+        Box::pin(async move { Ok(is_saved) })
+    }
+
+    fn delete<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        self.records.lock().remove(session_id);
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+/// A manager which loads and saves the sessions identified by a cookie.
+///
+/// A session expires if it has been idle for the `idle-timeout`,
+/// or it has been created for the `max-age` regardless of the activity.
+pub struct SessionManager {
+    /// Store.
+    store: Box<dyn SessionStore>,
+    /// Cookie name.
+    cookie_name: SharedString,
+    /// Cookie path.
+    cookie_path: SharedString,
+    /// A flag to indicate whether the cookie is only sent over HTTPS.
+    secure: bool,
+    /// Idle timeout.
+    idle_timeout: Duration,
+    /// Absolute timeout.
+    max_age: Duration,
+}
+
+impl SessionManager {
+    /// Creates a new instance with the store.
+    #[inline]
+    pub fn new(store: Box<dyn SessionStore>) -> Self {
+        Self {
+            store,
+            cookie_name: "zino-session".into(),
+            cookie_path: "/".into(),
+            secure: true,
+            idle_timeout: Duration::from_secs(30 * 60),
+            max_age: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let store = new_session_store(config)?;
+        let mut manager = Self::new(store);
+        if let Some(cookie_name) = config.get_str("cookie-name") {
+            manager.cookie_name = cookie_name.to_owned().into();
+        }
+        if let Some(cookie_path) = config.get_str("cookie-path") {
+            manager.cookie_path = cookie_path.to_owned().into();
+        }
+        if let Some(secure) = config.get_bool("secure") {
+            manager.secure = secure;
+        }
+        if let Some(idle_timeout) = config.get_duration("idle-timeout") {
+            manager.idle_timeout = idle_timeout;
+        }
+        if let Some(max_age) = config.get_duration("max-age") {
+            manager.max_age = max_age;
+        }
+        Ok(manager)
+    }
+
+    /// Sets the idle timeout.
+    #[inline]
+    pub fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    /// Sets the absolute timeout.
+    #[inline]
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.max_age = max_age;
+    }
+
+    /// Returns the cookie name.
+    #[inline]
+    pub fn cookie_name(&self) -> &str {
+        self.cookie_name.as_ref()
+    }
+
+    /// Returns the store name.
+    #[inline]
+    pub fn store_name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    /// Loads the session with the ID, or creates a new session if it is absent or expired.
+    /// A new session is also created if the store fails.
+    pub async fn load_session(&self, session_id: Option<&str>) -> Session {
+        let Some(session_id) = session_id.filter(|id| !id.is_empty()) else {
+            return Session::new();
+        };
+        match self.store.load(session_id).await {
+            Ok(Some(record)) => {
+                if let Some(session) = Session::from_record(session_id, &record) {
+                    let now = DateTime::now();
+                    let is_expired = session.accessed_at() + self.idle_timeout <= now
+                        || session.created_at() + self.max_age <= now;
+                    if !is_expired {
+                        return session;
+                    }
+                }
+                if let Err(err) = self.store.delete(session_id).await {
+                    tracing::error!(store = self.store.name(), "fail to delete a session: {err}");
+                }
+            }
+            Ok(None) => (),
+            Err(err) => {
+                tracing::error!(store = self.store.name(), "fail to load a session: {err}");
+            }
+        }
+        Session::new()
+    }
+
+    /// Commits the changes of the session to the store,
+    /// and returns a cookie if it should be set in the response.
+    ///
+    /// Unchanged sessions are saved only if they have been idle for a while,
+    /// so that the idle timeout can be extended without saving on every request.
+    /// It is an error if the changes conflict with the ones committed by a concurrent request.
+    pub async fn commit_session(
+        &self,
+        session: &Session,
+    ) -> Result<Option<Cookie<'static>>, Error> {
+        let (session_id, previous_id, status, is_new) = {
+            let state = session.state.read();
+            (
+                state.id.clone(),
+                state.previous_id.clone(),
+                state.status,
+                state.is_new,
+            )
+        };
+        if let Some(previous_id) = previous_id {
+            self.store.delete(&previous_id).await?;
+        }
+        match status {
+            SessionStatus::Destroyed => {
+                if is_new {
+                    return Ok(None);
+                }
+                self.store.delete(&session_id).await?;
+                let cookie = self.build_cookie(String::new(), Some(Duration::ZERO));
+                Ok(Some(cookie))
+            }
+            SessionStatus::Unchanged => {
+                let elapsed = session.accessed_at().span_before_now().unwrap_or_default();
+                if !is_new && elapsed >= self.idle_timeout / 4 {
+                    // The session has been saved by a concurrent request if there is a conflict.
+                    session.state.write().accessed_at = DateTime::now();
+                    self.save_session(&session_id, session).await?;
+                }
+                Ok(None)
+            }
+            SessionStatus::Changed | SessionStatus::Renewed => {
+                session.state.write().accessed_at = DateTime::now();
+                if !self.save_session(&session_id, session).await? {
+                    bail!("the session has been modified by a concurrent request");
+                }
+                if is_new || status == SessionStatus::Renewed {
+                    let cookie = self.build_cookie(session_id, None);
+                    Ok(Some(cookie))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Saves the session to the store, and returns `false` if there is a conflict.
+    async fn save_session(&self, session_id: &str, session: &Session) -> Result<bool, Error> {
+        let remaining = (session.created_at() + self.max_age)
+            .span_after_now()
+            .unwrap_or_default();
+        let ttl = self.idle_timeout.min(remaining);
+        if ttl.is_zero() {
+            return Ok(true);
+        }
+
+        let version = session.version();
+        let is_saved = self
+            .store
+            .save(session_id, session.to_record(), version, ttl)
+            .await?;
+        if is_saved {
+            session.state.write().version = version + 1;
+        }
+        Ok(is_saved)
+    }
+
+    /// Builds a session cookie.
+    fn build_cookie(&self, session_id: String, max_age: Option<Duration>) -> Cookie<'static> {
+        let mut cookie_builder = Cookie::build((self.cookie_name.clone(), session_id))
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax)
+            .path(self.cookie_path.clone());
+        if let Some(max_age) = max_age.and_then(|d| d.try_into().ok()) {
+            cookie_builder = cookie_builder.max_age(max_age);
+        }
+        cookie_builder.build()
+    }
+}
+
+/// Creates a session store with the configuration.
+pub(crate) fn new_session_store(config: &Table) -> Result<Box<dyn SessionStore>, Error> {
+    let store: Box<dyn SessionStore> = match config.get_str("store").unwrap_or("memory") {
+        "memory" => Box::new(MemorySessionStore::new()),
+        #[cfg(feature = "orm")]
+        "database" => {
+            let service = config.get_str("database").unwrap_or("main");
+            let mut store = super::DatabaseSessionStore::new(service)?;
+            if let Some(table_name) = config.get_str("table") {
+                store.set_table_name(table_name);
+            }
+            Box::new(store)
+        }
+        #[cfg(feature = "accessor")]
+        "accessor" => {
+            let name = config
+                .get_str("accessor")
+                .ok_or_else(|| warn!("the `accessor` field should be specified"))?;
+            let mut store = super::OperatorSessionStore::new(name)?;
+            if let Some(prefix) = config.get_str("prefix") {
+                store.set_prefix(prefix);
+            }
+            Box::new(store)
+        }
+        store => return Err(warn!("unsupported session store `{}`", store)),
+    };
+    Ok(store)
+}
+
+/// Generates a random session ID.
+fn generate_session_id() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    base64::encode_url_safe(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemorySessionStore, SessionManager, SessionStore};
+    use crate::{extension::JsonObjectExt, Map};
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn it_saves_the_records_with_the_version() {
+        let store = MemorySessionStore::new();
+        let ttl = Duration::from_secs(60);
+        let mut record = Map::new();
+        record.upsert("version", 1);
+        assert!(block_on(store.save("session", record.clone(), 0, ttl)).unwrap());
+        assert!(!block_on(store.save("session", record.clone(), 0, ttl)).unwrap());
+
+        record.upsert("version", 2);
+        assert!(block_on(store.save("session", record.clone(), 1, ttl)).unwrap());
+        assert!(!block_on(store.save("session", record, 1, ttl)).unwrap());
+
+        let record = block_on(store.load("session")).unwrap().unwrap();
+        assert_eq!(record.get_u64("version"), Some(2));
+
+        block_on(store.delete("session")).unwrap();
+        assert!(block_on(store.load("session")).unwrap().is_none());
+    }
+
+    #[test]
+    fn it_commits_the_session_changes() {
+        let manager = SessionManager::new(Box::new(MemorySessionStore::new()));
+        let session = block_on(manager.load_session(None));
+        assert!(session.is_new());
+        assert!(block_on(manager.commit_session(&session))
+            .unwrap()
+            .is_none());
+
+        session.insert("user_id", "alice");
+        let cookie = block_on(manager.commit_session(&session)).unwrap().unwrap();
+        assert_eq!(cookie.value(), session.id());
+
+        let session = block_on(manager.load_session(Some(cookie.value())));
+        assert!(!session.is_new());
+        assert_eq!(session.get::<String>("user_id").as_deref(), Some("alice"));
+
+        session.insert("role", "admin");
+        assert!(block_on(manager.commit_session(&session))
+            .unwrap()
+            .is_none());
+
+        let session = block_on(manager.load_session(Some(cookie.value())));
+        assert_eq!(session.get::<String>("role").as_deref(), Some("admin"));
+
+        session.destroy();
+        let cookie = block_on(manager.commit_session(&session)).unwrap().unwrap();
+        assert!(cookie.value().is_empty());
+        assert!(block_on(manager.load_session(Some(&session.id()))).is_new());
+    }
+
+    #[test]
+    fn it_rejects_the_concurrent_changes() {
+        let manager = SessionManager::new(Box::new(MemorySessionStore::new()));
+        let session = block_on(manager.load_session(None));
+        session.insert("counter", 0);
+        block_on(manager.commit_session(&session)).unwrap();
+
+        let session_id = session.id();
+        let first = block_on(manager.load_session(Some(&session_id)));
+        let second = block_on(manager.load_session(Some(&session_id)));
+        first.insert("counter", 1);
+        second.insert("counter", 2);
+        assert!(block_on(manager.commit_session(&first)).is_ok());
+        assert!(block_on(manager.commit_session(&second)).is_err());
+
+        let session = block_on(manager.load_session(Some(&session_id)));
+        assert_eq!(session.get::<u64>("counter"), Some(1));
+    }
+
+    #[test]
+    fn it_renews_the_session_id() {
+        let manager = SessionManager::new(Box::new(MemorySessionStore::new()));
+        let session = block_on(manager.load_session(None));
+        session.insert("user_id", "alice");
+        block_on(manager.commit_session(&session)).unwrap();
+
+        let previous_id = session.id();
+        let session = block_on(manager.load_session(Some(&previous_id)));
+        session.renew();
+        let cookie = block_on(manager.commit_session(&session)).unwrap().unwrap();
+        assert_ne!(cookie.value(), previous_id);
+        assert!(block_on(manager.load_session(Some(&previous_id))).is_new());
+
+        let session = block_on(manager.load_session(Some(cookie.value())));
+        assert_eq!(session.get::<String>("user_id").as_deref(), Some("alice"));
+    }
+}
@@ -0,0 +1,361 @@
+use super::{
+    AccessKeyId, ConfigKeyProvider, KeyProvider, MemoryNonceStore, NonceStore, ResolvedKey,
+    UserSession,
+};
+use crate::{
+    crypto::Digest, datetime::DateTime, error::Error, extension::TomlTableExt,
+    request::RequestContext, response::Rejection, warn, LazyLock,
+};
+use hmac::Hmac;
+use parking_lot::RwLock;
+use std::{sync::Arc, time::Duration};
+use toml::Table;
+
+/// A verifier for the HMAC signatures of the requests parsed by
+/// [`parse_authentication()`](RequestContext::parse_authentication).
+///
+/// The secret access keys are resolved by the access keys in the configuration
+/// and the registered key providers in order. A signature in the `authorization` header
+/// should be sent with a `date` header within the max clock skew, and it can not be replayed
+/// as long as the nonce is recorded by the shared nonce store.
+/// A signature in the query is valid until the `expires` timestamp.
+pub struct SignatureVerifier {
+    /// Key provider for the access keys in the configuration.
+    key_provider: ConfigKeyProvider,
+    /// Expected service name.
+    service_name: Option<String>,
+    /// Names of the canonicalized headers.
+    headers: Vec<String>,
+    /// Max clock skew.
+    max_clock_skew: Duration,
+}
+
+impl SignatureVerifier {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            key_provider: ConfigKeyProvider::new(),
+            service_name: None,
+            headers: Vec::new(),
+            max_clock_skew: Duration::from_secs(900),
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let mut verifier = Self::new();
+        verifier.key_provider = ConfigKeyProvider::try_from_config(config)?;
+        if let Some(service_name) = config.get_str("service-name") {
+            verifier.set_service_name(service_name);
+        }
+        if let Some(headers) = config.get_str_array("headers") {
+            verifier.set_headers(headers.into_iter().map(|s| s.to_owned()).collect());
+        }
+        if let Some(max_clock_skew) = config.get_duration("max-clock-skew") {
+            verifier.set_max_clock_skew(max_clock_skew);
+        }
+        Ok(verifier)
+    }
+
+    /// Sets the expected service name.
+    #[inline]
+    pub fn set_service_name(&mut self, service_name: impl ToString) {
+        self.service_name = Some(service_name.to_string());
+    }
+
+    /// Sets the names of the canonicalized headers.
+    #[inline]
+    pub fn set_headers(&mut self, headers: Vec<String>) {
+        self.headers = headers
+            .into_iter()
+            .map(|header| header.to_ascii_lowercase())
+            .collect();
+    }
+
+    /// Sets the max clock skew of the `date` header. The default value is 15 minutes.
+    ///
+    /// It takes precedence over the time tolerance in parsing the authentication.
+    #[inline]
+    pub fn set_max_clock_skew(&mut self, max_clock_skew: Duration) {
+        self.max_clock_skew = max_clock_skew;
+    }
+
+    /// Registers a key provider which is shared by all the verifiers.
+    #[inline]
+    pub fn register_key_provider(provider: impl KeyProvider + 'static) {
+        SHARED_KEY_PROVIDERS.write().push(Arc::new(provider));
+    }
+
+    /// Registers a nonce store which is shared by all the verifiers.
+    /// The default store keeps the nonces in the process memory.
+    #[inline]
+    pub fn register_nonce_store(store: impl NonceStore + 'static) {
+        *SHARED_NONCE_STORE.write() = Arc::new(store);
+    }
+
+    /// Verifies the signature of the request and returns the user session of the principal.
+    /// It returns `None` if the request is not signed by an access key.
+    pub async fn verify<Ctx: RequestContext>(
+        &self,
+        ctx: &Ctx,
+    ) -> Result<Option<UserSession<String>>, Rejection> {
+        let is_presigned = ctx.get_query("signature").is_some();
+        let is_signed = is_presigned
+            || ctx
+                .get_header("authorization")
+                .is_some_and(|authorization| {
+                    authorization
+                        .split_once(' ')
+                        .is_some_and(|(service_name, token)| {
+                            !service_name.eq_ignore_ascii_case("Bearer") && token.contains(':')
+                        })
+                });
+        if !is_signed {
+            return Ok(None);
+        }
+
+        let mut authentication = ctx.parse_authentication()?;
+        if !is_presigned {
+            if let Some(service_name) = self.service_name.as_deref() {
+                if !authentication
+                    .service_name()
+                    .eq_ignore_ascii_case(service_name)
+                {
+                    let err = warn!("invalid service name");
+                    return Err(Rejection::unauthorized(err).context(ctx));
+                }
+            }
+        }
+        let headers = self.headers.iter().filter_map(|name| {
+            ctx.get_header(name)
+                .map(|value| (name.to_owned(), value.to_owned()))
+        });
+        let filter = self.headers.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        authentication.set_headers(headers, &filter);
+
+        // Signatures in the query are valid until the `expires`,
+        // which has been checked in parsing the authentication.
+        authentication.set_max_clock_skew(self.max_clock_skew);
+        let nonce_expires_at = if is_presigned {
+            None
+        } else if let Some(date) = ctx.get_header("date") {
+            match DateTime::parse_utc_str(date) {
+                Ok(date) if date.span_between_now() <= self.max_clock_skew => {
+                    authentication.set_date_header("date", date);
+                    Some(date + self.max_clock_skew)
+                }
+                _ => {
+                    let err = warn!("untrusted date");
+                    return Err(Rejection::unauthorized(err).context(ctx));
+                }
+            }
+        } else {
+            let err = warn!("the `date` header should be specified");
+            return Err(Rejection::unauthorized(err).context(ctx));
+        };
+
+        let access_key_id = AccessKeyId::from(authentication.access_key_id());
+        let Some((secret_access_key, mut session)) = self
+            .resolve_key(&access_key_id)
+            .await
+            .map_err(|err| Rejection::internal_server_error(err).context(ctx))?
+        else {
+            let err = warn!("invalid access key ID `{}`", access_key_id);
+            return Err(Rejection::unauthorized(err).context(ctx));
+        };
+        let validation = authentication.validate_with::<Hmac<Digest>>(&secret_access_key);
+        if !validation.is_success() {
+            let err = warn!("invalid signature");
+            return Err(Rejection::unauthorized(err).context(ctx));
+        }
+
+        if let Some(expires_at) = nonce_expires_at {
+            let nonce = format!("{}:{}", access_key_id, authentication.signature());
+            let store = SHARED_NONCE_STORE.read().clone();
+            let is_new = store
+                .insert(&nonce, expires_at)
+                .await
+                .map_err(|err| Rejection::internal_server_error(err).context(ctx))?;
+            if !is_new {
+                let err = warn!("the signature has been used");
+                return Err(Rejection::unauthorized(err).context(ctx));
+            }
+        }
+        session.set_access_key_id(access_key_id);
+        Ok(Some(session))
+    }
+
+    /// Resolves the secret access key and the user session for the access key ID.
+    async fn resolve_key(&self, access_key_id: &AccessKeyId) -> Result<Option<ResolvedKey>, Error> {
+        if let Some(key) = self.key_provider.resolve(access_key_id).await? {
+            return Ok(Some(key));
+        }
+
+        let providers = SHARED_KEY_PROVIDERS.read().clone();
+        for provider in providers {
+            if let Some(key) = provider.resolve(access_key_id).await? {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Default for SignatureVerifier {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shared key providers.
+static SHARED_KEY_PROVIDERS: LazyLock<RwLock<Vec<Arc<dyn KeyProvider>>>> =
+    LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Shared nonce store.
+static SHARED_NONCE_STORE: LazyLock<RwLock<Arc<dyn NonceStore>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(MemoryNonceStore::new())));
+
+#[cfg(test)]
+mod tests {
+    use super::SignatureVerifier;
+    use crate::{
+        auth::{AccessKeyId, Authentication, SecretAccessKey, UserSession},
+        crypto::Digest,
+        datetime::DateTime,
+        error::Error,
+        request::{Context, RequestContext, Uri},
+    };
+    use futures::executor::block_on;
+    use hmac::Hmac;
+    use std::{borrow::Cow, collections::HashMap, net::IpAddr, time::Duration};
+
+    /// A request with the headers only.
+    struct TestRequest {
+        method: String,
+        uri: Uri,
+        headers: HashMap<String, String>,
+    }
+
+    impl RequestContext for TestRequest {
+        type Method = String;
+        type Headers = HashMap<String, String>;
+
+        fn request_method(&self) -> &Self::Method {
+            &self.method
+        }
+
+        fn original_uri(&self) -> &Uri {
+            &self.uri
+        }
+
+        fn matched_route(&self) -> Cow<'_, str> {
+            self.uri.path().into()
+        }
+
+        fn header_map(&self) -> &Self::Headers {
+            &self.headers
+        }
+
+        fn get_header(&self, name: &str) -> Option<&str> {
+            self.headers.get(name).map(|s| s.as_str())
+        }
+
+        fn get_context(&self) -> Option<Context> {
+            None
+        }
+
+        fn get_data<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+            None
+        }
+
+        fn set_data<T: Clone + Send + Sync + 'static>(&mut self, _value: T) -> Option<T> {
+            None
+        }
+
+        fn client_ip(&self) -> Option<IpAddr> {
+            None
+        }
+
+        async fn read_body_bytes(&mut self) -> Result<Vec<u8>, Error> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn new_verifier(access_key_id: &AccessKeyId) -> SignatureVerifier {
+        let mut verifier = SignatureVerifier::new();
+        verifier.key_provider.add_key(
+            access_key_id.clone(),
+            SecretAccessKey::new(access_key_id),
+            UserSession::new("service-a".to_owned(), None),
+        );
+        verifier
+    }
+
+    fn signed_request(access_key_id: &AccessKeyId, date: DateTime) -> TestRequest {
+        let mut authentication = Authentication::new("POST");
+        authentication.set_service_name("ZINO");
+        authentication.set_access_key_id(access_key_id.clone());
+        authentication.set_date_header("date", date);
+        authentication.set_resource("/user/new".to_owned(), None);
+
+        let secret_access_key = SecretAccessKey::new(access_key_id);
+        let signature = authentication
+            .sign_with::<Hmac<Digest>>(&secret_access_key)
+            .unwrap();
+        authentication.set_signature(signature);
+
+        let mut headers = HashMap::new();
+        headers.insert("authorization".to_owned(), authentication.authorization());
+        headers.insert("date".to_owned(), date.to_utc_string());
+        TestRequest {
+            method: "POST".to_owned(),
+            uri: Uri::from_static("/user/new"),
+            headers,
+        }
+    }
+
+    #[test]
+    fn it_rejects_a_replayed_signature() {
+        let access_key_id = AccessKeyId::new();
+        let verifier = new_verifier(&access_key_id);
+        let req = signed_request(&access_key_id, DateTime::now());
+        let session = block_on(verifier.verify(&req)).ok().flatten().unwrap();
+        assert_eq!(session.user_id(), "service-a");
+        assert!(block_on(verifier.verify(&req)).is_err());
+
+        let mut req = signed_request(&access_key_id, DateTime::now());
+        req.headers.insert(
+            "authorization".to_owned(),
+            "ZINO unknown:signature".to_owned(),
+        );
+        assert!(block_on(verifier.verify(&req)).is_err());
+    }
+
+    #[test]
+    fn it_checks_the_date_with_the_max_clock_skew() {
+        let access_key_id = AccessKeyId::new();
+        let mut verifier = new_verifier(&access_key_id);
+        let date = DateTime::now() - Duration::from_secs(20 * 60);
+        let req = signed_request(&access_key_id, date);
+        assert!(block_on(verifier.verify(&req)).is_err());
+
+        verifier.set_max_clock_skew(Duration::from_secs(30 * 60));
+        assert!(block_on(verifier.verify(&req)).ok().flatten().is_some());
+
+        verifier.set_max_clock_skew(Duration::from_secs(60));
+        let date = DateTime::now() - Duration::from_secs(5 * 60);
+        let req = signed_request(&access_key_id, date);
+        assert!(block_on(verifier.verify(&req)).is_err());
+    }
+
+    #[test]
+    fn it_skips_the_unsigned_requests() {
+        let verifier = SignatureVerifier::new();
+        let mut req = signed_request(&AccessKeyId::new(), DateTime::now());
+        req.headers
+            .insert("authorization".to_owned(), "Bearer token".to_owned());
+        assert!(block_on(verifier.verify(&req)).ok().flatten().is_none());
+    }
+}
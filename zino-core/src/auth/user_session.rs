@@ -99,6 +99,7 @@ where
     <U as FromStr>::Err: std::error::Error,
 {
     /// Attempts to construct an instance from a `JwtClaims`.
+    /// The session ID is derived from the `sid` claim if it exists.
     pub fn try_from_jwt_claims(claims: JwtClaims) -> Result<Self, Error> {
         let data = claims.data();
         let user_id = claims
@@ -107,7 +108,10 @@ where
             .or_else(|| data.parse_string("uid"))
             .ok_or_else(|| warn!("the subject of a JWT token should be specified"))?
             .parse()?;
-        let mut user_session = Self::new(user_id, None);
+        let session_id = data
+            .get_str("sid")
+            .map(|sid| SessionId::new::<Digest>(*APP_DOMAIN, sid));
+        let mut user_session = Self::new(user_id, session_id);
         if let Some(Ok(roles)) = data
             .parse_array("roles")
             .or_else(|| data.parse_array("role"))
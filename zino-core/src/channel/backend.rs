@@ -0,0 +1,38 @@
+use super::CloudEvent;
+use crate::{error::Error, BoxFuture};
+
+/// A backend for delivering cloud events across the instances in a cluster.
+///
+/// The events are always delivered to the subscribers in the current process,
+/// and the backend is responsible for relaying them to the other instances.
+pub trait ChannelBackend: Send + Sync {
+    /// Returns the backend name.
+    fn name(&self) -> &'static str;
+
+    /// Publishes an event to the other instances.
+    fn publish(&self, event: CloudEvent) -> BoxFuture<'_, Result<(), Error>>;
+
+    /// Receives the events published by the other instances since the last call.
+    fn receive(&self) -> BoxFuture<'_, Result<Vec<CloudEvent>, Error>>;
+}
+
+/// A process-local backend which does not relay events across instances.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryBackend;
+
+impl ChannelBackend for MemoryBackend {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    #[inline]
+    fn publish(&self, _event: CloudEvent) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    #[inline]
+    fn receive(&self) -> BoxFuture<'_, Result<Vec<CloudEvent>, Error>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+}
@@ -0,0 +1,138 @@
+use super::{CloudEvent, Subscription};
+use crate::{
+    auth::{PolicyEngine, UserSession},
+    error::Error,
+    BoxFuture,
+};
+use std::sync::Arc;
+
+/// An authorizer which decides the sources and topics that a principal
+/// may subscribe or publish to. An anonymous client is represented by `None`.
+pub trait ChannelAuthorizer: Send + Sync {
+    /// Returns the authorizer name.
+    fn name(&self) -> &'static str;
+
+    /// Authorizes the principal to receive the events matching the subscription.
+    fn authorize_subscription<'a>(
+        &'a self,
+        session: Option<&'a UserSession<String>>,
+        subscription: &'a Subscription,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Authorizes the principal to publish the event.
+    fn authorize_publication<'a>(
+        &'a self,
+        session: Option<&'a UserSession<String>>,
+        event: &'a CloudEvent,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// An authorizer which evaluates the access policies by a [`PolicyEngine`].
+///
+/// The resource is `channel:{source}:{topic}` and the action is `subscribe` or `publish`.
+/// An unspecified source or topic of a subscription is represented by `*`,
+/// so that it is only allowed by a policy matching any source or topic.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyAuthorizer {
+    /// Policy engine. The shared one is used if it is `None`.
+    engine: Option<Arc<PolicyEngine>>,
+}
+
+impl PolicyAuthorizer {
+    /// Creates a new instance which uses the shared policy engine.
+    #[inline]
+    pub fn new() -> Self {
+        Self { engine: None }
+    }
+
+    /// Creates a new instance with the policy engine.
+    #[inline]
+    pub fn with_engine(engine: PolicyEngine) -> Self {
+        Self {
+            engine: Some(Arc::new(engine)),
+        }
+    }
+
+    /// Authorizes the action on the source and topic.
+    fn authorize(
+        &self,
+        session: Option<&UserSession<String>>,
+        source: Option<&str>,
+        topic: Option<&str>,
+        action: &str,
+    ) -> Result<(), Error> {
+        let resource = format!("channel:{}:{}", source.unwrap_or("*"), topic.unwrap_or("*"));
+        match self.engine.as_ref() {
+            Some(engine) => engine.authorize(session, &resource, action),
+            None => PolicyEngine::shared().authorize(session, &resource, action),
+        }
+    }
+}
+
+impl ChannelAuthorizer for PolicyAuthorizer {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "policy"
+    }
+
+    fn authorize_subscription<'a>(
+        &'a self,
+        session: Option<&'a UserSession<String>>,
+        subscription: &'a Subscription,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        let result = self.authorize(
+            session,
+            subscription.source(),
+            subscription.topic(),
+            "subscribe",
+        );
+        Box::pin(async move { result })
+    }
+
+    fn authorize_publication<'a>(
+        &'a self,
+        session: Option<&'a UserSession<String>>,
+        event: &'a CloudEvent,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        let result = self.authorize(
+            session,
+            Some(event.source()),
+            Some(event.event_type()),
+            "publish",
+        );
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelAuthorizer, PolicyAuthorizer};
+    use crate::{
+        auth::{AccessPolicy, PolicyEffect, PolicyEngine, UserSession},
+        channel::{CloudEvent, Subscription},
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn it_authorizes_subscriptions() {
+        let mut policy = AccessPolicy::new("orders", "channel:*:order.*", PolicyEffect::Allow);
+        policy.set_actions(vec!["subscribe".to_owned()]);
+        policy.set_roles(vec!["user".to_owned()]);
+        let authorizer = PolicyAuthorizer::with_engine(PolicyEngine::with_policies(vec![policy]));
+
+        let mut session = UserSession::<String>::new("alice".to_owned(), None);
+        session.set_roles(vec!["user".to_owned()]);
+
+        let subscription = Subscription::new(None, Some("order.created".to_owned()));
+        assert!(block_on(authorizer.authorize_subscription(Some(&session), &subscription)).is_ok());
+        assert!(block_on(authorizer.authorize_subscription(None, &subscription)).is_err());
+
+        let subscription = Subscription::new(None, None);
+        assert!(
+            block_on(authorizer.authorize_subscription(Some(&session), &subscription)).is_err()
+        );
+
+        let event = CloudEvent::new("1".to_owned(), "app".to_owned(), "order.created");
+        assert!(block_on(authorizer.authorize_publication(Some(&session), &event)).is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use super::{ChannelBackend, CloudEvent};
+use crate::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::DecodeRow,
+    orm::{self, ConnectionPool, Executor, GlobalPool},
+    BoxFuture, JsonValue, Map, Uuid,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
+
+/// A backend which relays events through an append-only table in the database.
+///
+/// Each instance appends the published events to the table,
+/// and polls the events appended by the other instances in the order of sequence numbers.
+/// Since the sequence numbers may be committed out of order by concurrent transactions,
+/// the missing ones are polled again until the lookback period elapses.
+/// Events older than the retention period are deleted periodically.
+#[derive(Debug)]
+pub struct DatabaseBackend {
+    /// Connection pool.
+    pool: &'static ConnectionPool,
+    /// Table name.
+    table_name: String,
+    /// Instance ID.
+    instance_id: Uuid,
+    /// Retention period.
+    retention: Duration,
+    /// Period to wait for the missing sequence numbers.
+    lookback: Duration,
+    /// Max number of events received at once.
+    batch_size: usize,
+    /// Time when the backend is created.
+    started_at: i64,
+    /// Cursor of the received events.
+    cursor: Mutex<PollCursor>,
+    /// A flag to indicate whether the table has been created.
+    table_created: AtomicBool,
+    /// Number of polls.
+    poll_count: AtomicUsize,
+}
+
+impl DatabaseBackend {
+    /// Creates a new instance for the database service.
+    pub fn new(service: &str) -> Result<Self, Error> {
+        let Some(pool) = GlobalPool::get(service) else {
+            bail!(
+                "connection to the database service `{}` is unavailable",
+                service
+            );
+        };
+        Ok(Self::with_pool(pool))
+    }
+
+    /// Creates a new instance with the connection pool.
+    pub fn with_pool(pool: &'static ConnectionPool) -> Self {
+        Self {
+            pool,
+            table_name: "zino_channel_events".to_owned(),
+            instance_id: Uuid::now_v7(),
+            retention: Duration::from_secs(3600),
+            lookback: Duration::from_secs(10),
+            batch_size: 1000,
+            started_at: DateTime::current_timestamp_millis(),
+            cursor: Mutex::new(PollCursor::default()),
+            table_created: AtomicBool::new(false),
+            poll_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets the table name.
+    #[inline]
+    pub fn set_table_name(&mut self, table_name: impl ToString) {
+        self.table_name = table_name.to_string();
+    }
+
+    /// Sets the retention period of events.
+    #[inline]
+    pub fn set_retention(&mut self, retention: Duration) {
+        self.retention = retention;
+    }
+
+    /// Sets the period to wait for the missing sequence numbers.
+    #[inline]
+    pub fn set_lookback(&mut self, lookback: Duration) {
+        self.lookback = lookback;
+    }
+
+    /// Sets the max number of events received at once.
+    #[inline]
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size;
+    }
+
+    /// Creates the table if it does not exist.
+    async fn create_table(&self) -> Result<(), Error> {
+        if self.table_created.load(Relaxed) {
+            return Ok(());
+        }
+
+        let table_name = &self.table_name;
+        let seq_definition = if cfg!(any(
+            feature = "orm-mariadb",
+            feature = "orm-mysql",
+            feature = "orm-tidb"
+        )) {
+            "seq BIGINT AUTO_INCREMENT PRIMARY KEY"
+        } else if cfg!(feature = "orm-postgres") {
+            "seq BIGSERIAL PRIMARY KEY"
+        } else {
+            "seq INTEGER PRIMARY KEY AUTOINCREMENT"
+        };
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (\n  \
+                {seq_definition},\n  \
+                instance_id VARCHAR(255) NOT NULL,\n  \
+                payload TEXT NOT NULL,\n  \
+                created_at BIGINT NOT NULL\n\
+            );"
+        );
+        self.pool.pool().execute(&sql).await?;
+        self.table_created.store(true, Relaxed);
+        Ok(())
+    }
+}
+
+impl ChannelBackend for DatabaseBackend {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn publish(&self, event: CloudEvent) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let table_name = &self.table_name;
+            let instance_id = self.instance_id.to_string();
+            let payload = serde_json::to_string(&event)?;
+            let created_at = DateTime::current_timestamp_millis();
+            let sql = format!(
+                "INSERT INTO {table_name} (instance_id, payload, created_at) \
+                    VALUES ({}, {}, {created_at});",
+                orm::placeholder(1),
+                orm::placeholder(2),
+            );
+            self.pool
+                .pool()
+                .execute_with_values(&sql, &[instance_id, payload])
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn receive(&self) -> BoxFuture<'_, Result<Vec<CloudEvent>, Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let pool = self.pool.pool();
+            let table_name = &self.table_name;
+            let now = DateTime::current_timestamp_millis();
+            let (last_seq, gaps) = {
+                let mut cursor = self.cursor.lock();
+                let lookback = i64::try_from(self.lookback.as_millis()).unwrap_or(i64::MAX);
+                let expired_at = now.saturating_sub(lookback);
+                cursor
+                    .gaps
+                    .retain(|_, detected_at| *detected_at >= expired_at);
+                (
+                    cursor.last_seq,
+                    cursor.gaps.keys().copied().collect::<Vec<_>>(),
+                )
+            };
+            let last_seq = match last_seq {
+                Some(last_seq) => last_seq,
+                None => {
+                    // Events published by the other instances since the startup are delivered.
+                    let started_at = self.started_at;
+                    let sql = format!(
+                        "SELECT max(seq) AS seq FROM {table_name} \
+                            WHERE created_at < {started_at};"
+                    );
+                    let row = pool.fetch_one(&sql).await?;
+                    Map::decode_row(&row)?
+                        .parse_i64("seq")
+                        .and_then(|result| result.ok())
+                        .unwrap_or_default()
+                }
+            };
+
+            let batch_size = self.batch_size;
+            let mut sql = format!(
+                "SELECT seq, instance_id, payload FROM {table_name} WHERE seq > {last_seq}"
+            );
+            if !gaps.is_empty() {
+                let gaps = gaps
+                    .iter()
+                    .map(|seq| seq.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                sql += &format!(" OR seq IN ({gaps})");
+            }
+            sql += &format!(" ORDER BY seq LIMIT {batch_size};");
+
+            let rows = pool.fetch(&sql).await?;
+            let instance_id = self.instance_id.to_string();
+            let mut events = Vec::with_capacity(rows.len());
+            {
+                let mut cursor = self.cursor.lock();
+                let mut max_seq = cursor.last_seq.unwrap_or(last_seq).max(last_seq);
+                for row in rows {
+                    let mut data = Map::decode_row(&row)?;
+                    let Some(Ok(seq)) = data.parse_i64("seq") else {
+                        continue;
+                    };
+                    if seq > max_seq {
+                        cursor.add_gaps(max_seq + 1, seq, now, batch_size);
+                        max_seq = seq;
+                    } else if cursor.gaps.remove(&seq).is_none() {
+                        // The event has been received.
+                        continue;
+                    }
+                    if data.get_str("instance_id") != Some(instance_id.as_str()) {
+                        // The payload may have been decoded as a JSON object.
+                        let result = match data.remove("payload") {
+                            Some(JsonValue::String(payload)) => serde_json::from_str(&payload),
+                            Some(payload) => serde_json::from_value(payload),
+                            None => continue,
+                        };
+                        match result {
+                            Ok(event) => events.push(event),
+                            Err(err) => tracing::error!("fail to parse the cloud event: {err}"),
+                        }
+                    }
+                }
+                cursor.last_seq = Some(max_seq);
+            }
+
+            if self.poll_count.fetch_add(1, Relaxed) % 100 == 0 {
+                let retention = i64::try_from(self.retention.as_millis()).unwrap_or(i64::MAX);
+                let expired_at = DateTime::current_timestamp_millis().saturating_sub(retention);
+                let sql = format!("DELETE FROM {table_name} WHERE created_at < {expired_at};");
+                pool.execute(&sql).await?;
+            }
+            Ok(events)
+        })
+    }
+}
+
+/// Cursor of the received events.
+#[derive(Debug, Default)]
+struct PollCursor {
+    /// Sequence number of the last received event.
+    last_seq: Option<i64>,
+    /// Missing sequence numbers with the time when they are detected.
+    gaps: BTreeMap<i64, i64>,
+}
+
+impl PollCursor {
+    /// Adds the missing sequence numbers in the range `[start, end)`.
+    /// The number of gaps is limited to `max_gaps`.
+    fn add_gaps(&mut self, start: i64, end: i64, detected_at: i64, max_gaps: usize) {
+        let start = start.max(end.saturating_sub(i64::try_from(max_gaps).unwrap_or(i64::MAX)));
+        for seq in start..end {
+            self.gaps.insert(seq, detected_at);
+        }
+        while self.gaps.len() > max_gaps {
+            self.gaps.pop_first();
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+mod tests {
+    use super::DatabaseBackend;
+    use crate::{
+        channel::{ChannelBackend, CloudEvent},
+        datetime::DateTime,
+        orm::{
+            fixture::{block_on, TEST_POOL},
+            Executor,
+        },
+        JsonValue,
+    };
+
+    #[test]
+    fn it_receives_events_committed_out_of_order() {
+        block_on(async {
+            let mut publisher = DatabaseBackend::with_pool(&TEST_POOL);
+            publisher.set_table_name("test_channel_events");
+
+            let event: CloudEvent = CloudEvent::new("1", "test", "test.published");
+            publisher.publish(event).await.unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+
+            // Events published after the startup are received in the first poll.
+            let mut subscriber = DatabaseBackend::with_pool(&TEST_POOL);
+            subscriber.set_table_name("test_channel_events");
+            publisher
+                .publish(CloudEvent::new("2", "test", "test.published"))
+                .await
+                .unwrap();
+            let events = subscriber.receive().await.unwrap();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].id(), "2");
+
+            // The row with the sequence number `100` is committed before `99`.
+            let payload = |id: &str| {
+                let event: CloudEvent = CloudEvent::new(id.to_owned(), "test", "test.published");
+                serde_json::to_string(&event).unwrap()
+            };
+            let created_at = DateTime::current_timestamp_millis();
+            let pool = TEST_POOL.pool();
+            let sql = "INSERT INTO test_channel_events (seq, instance_id, payload, created_at) \
+                VALUES (100, 'other', ?, ?);";
+            pool.execute_with_values(sql, &[JsonValue::from(payload("100")), created_at.into()])
+                .await
+                .unwrap();
+            let events = subscriber.receive().await.unwrap();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].id(), "100");
+
+            let sql = "INSERT INTO test_channel_events (seq, instance_id, payload, created_at) \
+                VALUES (99, 'other', ?, ?);";
+            pool.execute_with_values(sql, &[JsonValue::from(payload("99")), created_at.into()])
+                .await
+                .unwrap();
+            let events = subscriber.receive().await.unwrap();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].id(), "99");
+            assert!(subscriber.receive().await.unwrap().is_empty());
+        });
+    }
+}
@@ -0,0 +1,229 @@
+use super::{CloudEvent, EventStore};
+use crate::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    model::DecodeRow,
+    orm::{self, ConnectionPool, Executor, GlobalPool},
+    BoxFuture, JsonValue, Map,
+};
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
+
+/// A store which persists the history of cloud events in an append-only table.
+/// Events older than the retention period are deleted periodically.
+#[derive(Debug)]
+pub struct DatabaseEventStore {
+    /// Connection pool.
+    pool: &'static ConnectionPool,
+    /// Table name.
+    table_name: String,
+    /// Retention period.
+    retention: Duration,
+    /// A flag to indicate whether the table has been created.
+    table_created: AtomicBool,
+    /// Number of appends.
+    appends: AtomicUsize,
+}
+
+impl DatabaseEventStore {
+    /// Creates a new instance for the database service.
+    pub fn new(service: &str) -> Result<Self, Error> {
+        let Some(pool) = GlobalPool::get(service) else {
+            bail!(
+                "connection to the database service `{}` is unavailable",
+                service
+            );
+        };
+        Ok(Self::with_pool(pool))
+    }
+
+    /// Creates a new instance with the connection pool.
+    pub fn with_pool(pool: &'static ConnectionPool) -> Self {
+        Self {
+            pool,
+            table_name: "zino_event_history".to_owned(),
+            retention: Duration::from_secs(24 * 3600),
+            table_created: AtomicBool::new(false),
+            appends: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets the table name.
+    #[inline]
+    pub fn set_table_name(&mut self, table_name: impl ToString) {
+        self.table_name = table_name.to_string();
+    }
+
+    /// Sets the retention period of events.
+    #[inline]
+    pub fn set_retention(&mut self, retention: Duration) {
+        self.retention = retention;
+    }
+
+    /// Creates the table if it does not exist.
+    async fn create_table(&self) -> Result<(), Error> {
+        if self.table_created.load(Relaxed) {
+            return Ok(());
+        }
+
+        let table_name = &self.table_name;
+        let seq_definition = if cfg!(any(
+            feature = "orm-mariadb",
+            feature = "orm-mysql",
+            feature = "orm-tidb"
+        )) {
+            "seq BIGINT AUTO_INCREMENT PRIMARY KEY"
+        } else if cfg!(feature = "orm-postgres") {
+            "seq BIGSERIAL PRIMARY KEY"
+        } else {
+            "seq INTEGER PRIMARY KEY AUTOINCREMENT"
+        };
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (\n  \
+                {seq_definition},\n  \
+                event_id VARCHAR(255) NOT NULL,\n  \
+                topic VARCHAR(255) NOT NULL,\n  \
+                payload TEXT NOT NULL,\n  \
+                created_at BIGINT NOT NULL\n\
+            );"
+        );
+        self.pool.pool().execute(&sql).await?;
+        self.table_created.store(true, Relaxed);
+        Ok(())
+    }
+}
+
+impl EventStore for DatabaseEventStore {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn append(&self, event: CloudEvent) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let pool = self.pool.pool();
+            let table_name = &self.table_name;
+            let event_id = event.id().to_owned();
+            let topic = event.event_type().to_owned();
+            let payload = serde_json::to_string(&event)?;
+            let created_at = DateTime::current_timestamp_millis();
+            let sql = format!(
+                "INSERT INTO {table_name} (event_id, topic, payload, created_at) \
+                    VALUES ({}, {}, {}, {created_at});",
+                orm::placeholder(1),
+                orm::placeholder(2),
+                orm::placeholder(3),
+            );
+            pool.execute_with_values(&sql, &[event_id, topic, payload])
+                .await?;
+
+            if self.appends.fetch_add(1, Relaxed) % 1000 == 0 {
+                let retention = i64::try_from(self.retention.as_millis()).unwrap_or(i64::MAX);
+                let expired_at = created_at.saturating_sub(retention);
+                let sql = format!("DELETE FROM {table_name} WHERE created_at < {expired_at};");
+                pool.execute(&sql).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn load_after<'a>(
+        &'a self,
+        last_event_id: &'a str,
+        topic: Option<&'a str>,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<CloudEvent>, Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let table_name = &self.table_name;
+            let last_seq_query = format!(
+                "SELECT max(seq) FROM {table_name} WHERE event_id = {}",
+                orm::placeholder(1),
+            );
+            let rows = if let Some(topic) = topic {
+                let sql = format!(
+                    "SELECT payload FROM {table_name} \
+                        WHERE seq > ({last_seq_query}) AND topic = {} \
+                        ORDER BY seq LIMIT {limit};",
+                    orm::placeholder(2),
+                );
+                self.pool
+                    .pool()
+                    .fetch_with_values(&sql, &[last_event_id, topic])
+                    .await?
+            } else {
+                let sql = format!(
+                    "SELECT payload FROM {table_name} \
+                        WHERE seq > ({last_seq_query}) ORDER BY seq LIMIT {limit};"
+                );
+                self.pool
+                    .pool()
+                    .fetch_with_values(&sql, &[last_event_id])
+                    .await?
+            };
+
+            let mut events = Vec::with_capacity(rows.len());
+            for row in rows {
+                let mut data = Map::decode_row(&row)?;
+                // The payload may have been decoded as a JSON object.
+                let result = match data.remove("payload") {
+                    Some(JsonValue::String(payload)) => serde_json::from_str(&payload),
+                    Some(payload) => serde_json::from_value(payload),
+                    None => continue,
+                };
+                match result {
+                    Ok(event) => events.push(event),
+                    Err(err) => tracing::error!("fail to parse the cloud event: {err}"),
+                }
+            }
+            Ok(events)
+        })
+    }
+}
+
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+mod tests {
+    use super::DatabaseEventStore;
+    use crate::{
+        channel::{CloudEvent, EventStore},
+        orm::fixture::{block_on, TEST_POOL},
+    };
+
+    #[test]
+    fn it_loads_the_events_after_the_last_one() {
+        block_on(async {
+            let mut store = DatabaseEventStore::with_pool(&TEST_POOL);
+            store.set_table_name("test_event_history");
+            for (id, event_type) in [("1", "a"), ("2", "b"), ("3", "a"), ("4", "a")] {
+                let mut event: CloudEvent = CloudEvent::new(id, "test", event_type);
+                event.set_data(serde_json::json!({ "id": id }));
+                store.append(event).await.unwrap();
+            }
+
+            let events = store.load_after("1", None, 10).await.unwrap();
+            let ids = events.iter().map(|event| event.id()).collect::<Vec<_>>();
+            assert_eq!(ids, ["2", "3", "4"]);
+
+            let events = store.load_after("1", Some("a"), 1).await.unwrap();
+            let ids = events.iter().map(|event| event.id()).collect::<Vec<_>>();
+            assert_eq!(ids, ["3"]);
+
+            let events = store.load_after("unknown", None, 10).await.unwrap();
+            assert!(events.is_empty());
+        });
+    }
+}
@@ -0,0 +1,239 @@
+use super::{CloudEvent, Subscription};
+use crate::{error::Error, extension::TomlTableExt, warn, BoxFuture};
+use parking_lot::RwLock;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+use toml::Table;
+
+/// A store for persisting the history of cloud events,
+/// so that the events can be replayed after a restart of the instance.
+pub trait EventStore: Send + Sync {
+    /// Returns the store name.
+    fn name(&self) -> &'static str;
+
+    /// Appends an event to the history.
+    fn append(&self, event: CloudEvent) -> BoxFuture<'_, Result<(), Error>>;
+
+    /// Loads at most `limit` events appended after the event with the ID,
+    /// in the order of appending. The events can be filtered by the topic.
+    fn load_after<'a>(
+        &'a self,
+        last_event_id: &'a str,
+        topic: Option<&'a str>,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<CloudEvent>, Error>>;
+}
+
+/// History of recent cloud events, which is kept in a bounded ring buffer per topic.
+///
+/// The topic of an event is its event type. If an event store is configured,
+/// the events missing in the ring buffers will be loaded from the store.
+pub struct EventHistory {
+    /// Max number of events kept for each topic.
+    capacity: usize,
+    /// Sequence number of the last event.
+    seq: AtomicU64,
+    /// Ring buffers for the topics.
+    buffers: RwLock<HashMap<String, VecDeque<(u64, CloudEvent)>>>,
+    /// Optional event store.
+    store: Option<Box<dyn EventStore>>,
+}
+
+impl EventHistory {
+    /// Creates a new instance with the capacity for each topic.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seq: AtomicU64::new(0),
+            buffers: RwLock::new(HashMap::new()),
+            store: None,
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let capacity = config.get_usize("capacity").unwrap_or(100);
+        let mut history = Self::new(capacity);
+        if let Some(store) = config.get_str("store") {
+            match store {
+                "memory" => (),
+                #[cfg(feature = "orm")]
+                "database" => {
+                    let service = config.get_str("database").unwrap_or("main");
+                    let mut store = super::DatabaseEventStore::new(service)?;
+                    if let Some(table_name) = config.get_str("table") {
+                        store.set_table_name(table_name);
+                    }
+                    if let Some(retention) = config.get_duration("retention") {
+                        store.set_retention(retention);
+                    }
+                    history.set_store(store);
+                }
+                _ => return Err(warn!("unsupported event store `{}`", store)),
+            }
+        }
+        Ok(history)
+    }
+
+    /// Sets the event store.
+    #[inline]
+    pub fn set_store(&mut self, store: impl EventStore + 'static) {
+        self.store = Some(Box::new(store));
+    }
+
+    /// Returns the max number of events kept for each topic.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns `true` if an event store is configured.
+    #[inline]
+    pub fn has_store(&self) -> bool {
+        self.store.is_some()
+    }
+
+    /// Returns `true` if the history is disabled.
+    #[inline]
+    pub fn is_disabled(&self) -> bool {
+        self.capacity == 0
+    }
+
+    /// Pushes an event into the ring buffer of its topic.
+    pub fn push(&self, event: CloudEvent) {
+        if self.is_disabled() {
+            return;
+        }
+
+        let seq = self.seq.fetch_add(1, Relaxed) + 1;
+        let mut buffers = self.buffers.write();
+        let buffer = buffers
+            .entry(event.event_type().to_owned())
+            .or_insert_with(|| VecDeque::with_capacity(self.capacity.min(16)));
+        if buffer.len() >= self.capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back((seq, event));
+    }
+
+    /// Persists an event in the store if it is configured.
+    pub async fn persist(&self, event: CloudEvent) -> Result<(), Error> {
+        if let Some(store) = self.store.as_ref() {
+            store.append(event).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the events after the event with the ID which match the subscription.
+    ///
+    /// If the event is not in the ring buffers, the events will be loaded from the store.
+    /// Without a store, it returns `None` to indicate a gap in the history,
+    /// since the event has been evicted and the missed events can not be determined.
+    pub async fn replay(
+        &self,
+        subscription: &Subscription,
+        last_event_id: &str,
+    ) -> Result<Option<Vec<CloudEvent>>, Error> {
+        if self.is_disabled() {
+            return Ok(None);
+        }
+
+        let topic = subscription.topic();
+        let mut last_seq = None;
+        let mut events = Vec::new();
+        {
+            let buffers = self.buffers.read();
+            let buffers = buffers
+                .iter()
+                .filter(|(key, _)| topic.is_none() || topic == Some(key.as_str()));
+            for (_, buffer) in buffers {
+                for (seq, event) in buffer {
+                    if event.id() == last_event_id {
+                        last_seq = Some(*seq);
+                    }
+                    if subscription.matches(event) {
+                        events.push((*seq, event.clone()));
+                    }
+                }
+            }
+        }
+        if let Some(last_seq) = last_seq {
+            events.retain(|(seq, _)| *seq > last_seq);
+        } else if let Some(store) = self.store.as_ref() {
+            let events = store
+                .load_after(last_event_id, topic, self.capacity)
+                .await?
+                .into_iter()
+                .filter(|event| subscription.matches(event))
+                .collect();
+            return Ok(Some(events));
+        } else {
+            return Ok(None);
+        }
+        events.sort_by_key(|(seq, _)| *seq);
+        Ok(Some(events.into_iter().map(|(_, event)| event).collect()))
+    }
+}
+
+impl Default for EventHistory {
+    #[inline]
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventHistory;
+    use crate::channel::{CloudEvent, Subscription};
+    use futures::executor::block_on;
+
+    #[test]
+    fn it_replays_events() {
+        let history = EventHistory::new(2);
+        for (id, event_type) in [("1", "a"), ("2", "b"), ("3", "a"), ("4", "a")] {
+            history.push(CloudEvent::new(
+                id.to_owned(),
+                "test".to_owned(),
+                event_type,
+            ));
+        }
+
+        let subscription = Subscription::default();
+        let events = block_on(history.replay(&subscription, "3"))
+            .unwrap()
+            .unwrap();
+        let ids = events.iter().map(|event| event.id()).collect::<Vec<_>>();
+        assert_eq!(ids, ["4"]);
+
+        let events = block_on(history.replay(&subscription, "2"))
+            .unwrap()
+            .unwrap();
+        let ids = events.iter().map(|event| event.id()).collect::<Vec<_>>();
+        assert_eq!(ids, ["3", "4"]);
+
+        let subscription = Subscription::new(None, Some("b".to_owned()));
+        let events = block_on(history.replay(&subscription, "2"))
+            .unwrap()
+            .unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn it_signals_a_gap_for_an_evicted_event() {
+        let history = EventHistory::new(2);
+        for id in ["1", "2", "3"] {
+            history.push(CloudEvent::new(id.to_owned(), "test".to_owned(), "a"));
+        }
+
+        let subscription = Subscription::default();
+        let events = block_on(history.replay(&subscription, "1")).unwrap();
+        assert!(events.is_none());
+
+        let events = block_on(history.replay(&subscription, "unknown")).unwrap();
+        assert!(events.is_none());
+    }
+}
@@ -1,7 +1,23 @@
-//! Cloud events and subscriptions.
+//! Cloud events, subscriptions and channel backends.
 
+mod backend;
+mod channel_authorizer;
 mod cloud_event;
+mod event_history;
 mod subscription;
 
+pub use backend::{ChannelBackend, MemoryBackend};
+pub use channel_authorizer::{ChannelAuthorizer, PolicyAuthorizer};
 pub use cloud_event::CloudEvent;
+pub use event_history::{EventHistory, EventStore};
 pub use subscription::Subscription;
+
+#[cfg(feature = "orm")]
+mod database_backend;
+#[cfg(feature = "orm")]
+mod database_event_store;
+
+#[cfg(feature = "orm")]
+pub use database_backend::DatabaseBackend;
+#[cfg(feature = "orm")]
+pub use database_event_store::DatabaseEventStore;
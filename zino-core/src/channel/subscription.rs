@@ -1,3 +1,4 @@
+use super::CloudEvent;
 use serde::{Deserialize, Serialize};
 
 /// Subscription.
@@ -59,4 +60,13 @@ impl Subscription {
     pub fn topic(&self) -> Option<&str> {
         self.topic.as_deref()
     }
+
+    /// Returns `true` if the event matches the source and topic of the subscription,
+    /// and it is not emitted by the same session.
+    pub fn matches<T>(&self, event: &CloudEvent<T>) -> bool {
+        let session_id = self.session_id();
+        (session_id.is_none() || session_id != event.session_id())
+            && self.source().filter(|&s| event.source() != s).is_none()
+            && self.topic().filter(|&t| event.event_type() != t).is_none()
+    }
 }
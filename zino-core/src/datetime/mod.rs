@@ -5,7 +5,7 @@ use chrono::{
     format::ParseError, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime,
     SecondsFormat, TimeZone, Timelike, Utc, Weekday,
 };
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     fmt,
     ops::{Add, AddAssign, Sub, SubAssign},
@@ -25,7 +25,7 @@ pub use time::Time;
 type LocalDateTime = chrono::DateTime<Local>;
 
 /// A wrapper type for [`chrono::DateTime<Local>`](chrono::DateTime).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DateTime(LocalDateTime);
 
 impl DateTime {
@@ -564,6 +564,15 @@ impl Serialize for DateTime {
     }
 }
 
+impl<'de> Deserialize<'de> for DateTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .or_else(|_| LocalDateTime::from_str(&s).map(Self))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 impl From<Date> for DateTime {
     fn from(d: Date) -> Self {
         let dt = NaiveDateTime::new(d.into(), NaiveTime::default());
@@ -699,5 +708,9 @@ mod tests {
         assert!(datetime.day_of_week() == 4);
         assert_eq!("2023-11-30", datetime.format_date());
         assert_eq!("00:00:00", datetime.format_time());
+
+        let json = serde_json::to_string(&datetime).unwrap();
+        assert_eq!(serde_json::from_str::<DateTime>(&json).unwrap(), datetime);
+        assert!(serde_json::from_str::<DateTime>("\"2023-11-30T16:24:30-05:00\"").is_ok());
     }
 }
@@ -0,0 +1,77 @@
+use crate::{extension::JsonValueExt, JsonValue};
+
+/// Typed arguments bound to the placeholders of a SQL query.
+///
+/// The arguments are collected in the order of the placeholders when the SQL is formatted
+/// from a [`Query`](super::Query) or a [`Mutation`](super::Mutation).
+/// In the inline mode, the values are formatted as escaped literals instead,
+/// which is used for the statements without parameters such as the table definitions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryArguments {
+    /// Values.
+    values: Vec<JsonValue>,
+    /// A flag to inline the values as literals.
+    inline: bool,
+}
+
+impl QueryArguments {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new instance in the inline mode.
+    #[inline]
+    pub fn inline() -> Self {
+        Self {
+            values: Vec::new(),
+            inline: true,
+        }
+    }
+
+    /// Returns `true` if the values should be inlined as literals.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.inline
+    }
+
+    /// Pushes a value and returns the position of its placeholder starting from 1.
+    #[inline]
+    pub fn push(&mut self, value: impl Into<JsonValue>) -> usize {
+        self.values.push(value.into());
+        self.values.len()
+    }
+
+    /// Returns a reference to the values.
+    #[inline]
+    pub fn values(&self) -> &[JsonValue] {
+        &self.values
+    }
+
+    /// Consumes `self` and returns the values.
+    #[inline]
+    pub fn into_values(self) -> Vec<JsonValue> {
+        self.values
+    }
+
+    /// Returns the number of values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if there are no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Formats the values for logging.
+    pub fn format_values(&self) -> Vec<String> {
+        self.values
+            .iter()
+            .map(|value| value.to_string_unquoted())
+            .collect()
+    }
+}
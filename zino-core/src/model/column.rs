@@ -1,4 +1,4 @@
-use super::Reference;
+use super::{QueryArguments, Reference};
 use crate::{
     datetime::{Date, DateTime, Time},
     extension::{JsonObjectExt, JsonValueExt},
@@ -716,11 +716,18 @@ pub trait EncodeColumn<DB> {
     fn column_type(&self) -> &str;
 
     /// Encodes a json value as a column value represented by a str.
-    fn encode_value<'a>(&self, value: Option<&'a JsonValue>) -> Cow<'a, str>;
+    /// The value is bound as an argument unless it is a SQL keyword or function.
+    fn encode_value<'a>(
+        &self,
+        value: Option<&'a JsonValue>,
+        arguments: &mut QueryArguments,
+    ) -> Cow<'a, str>;
 
     /// Formats a string value for the column.
-    fn format_value<'a>(&self, value: &'a str) -> Cow<'a, str>;
+    /// The value is bound as an argument unless it is a SQL keyword or function.
+    fn format_value<'a>(&self, value: &'a str, arguments: &mut QueryArguments) -> Cow<'a, str>;
 
-    /// Formats a column filter.
-    fn format_filter(&self, key: &str, value: &JsonValue) -> String;
+    /// Formats a column filter, and the values are bound as arguments.
+    fn format_filter(&self, key: &str, value: &JsonValue, arguments: &mut QueryArguments)
+        -> String;
 }
@@ -4,7 +4,7 @@ use crate::{
     model::{Model, Mutation, Query},
     Map,
 };
-use std::borrow::Cow;
+use std::{borrow::Cow, future::Future};
 
 /// Hooks for the model.
 ///
@@ -275,18 +275,29 @@ pub trait ModelHooks: Model {
     }
 
     /// A hook running after decoding the model as a `Map`.
+    /// The future should be `Send` since the models can be exported lazily in a stream.
     #[inline]
-    async fn after_decode(_model: &mut Map) -> Result<(), Error> {
-        Ok(())
+    fn after_decode(_model: &mut Map) -> impl Future<Output = Result<(), Error>> + Send {
+        async { Ok(()) }
     }
 
     /// A hook running before returning the model data as a HTTP response.
+    /// The future should be `Send` since the models can be exported lazily in a stream.
     #[inline]
-    async fn before_respond(
+    fn before_respond(
         _model: &mut Map,
         _extension: Option<&Self::Extension>,
-    ) -> Result<(), Error> {
-        Ok(())
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        async { Ok(()) }
+    }
+
+    /// A hook running before exporting the model data in a stream.
+    #[inline]
+    fn before_export(
+        _model: &mut Map,
+        _extension: Option<&Self::Extension>,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        async { Ok(()) }
     }
 
     /// A hook running before mocking the model data.
@@ -1,4 +1,6 @@
 use crate::{
+    encoding::base64,
+    error::Error,
     extension::{JsonObjectExt, JsonValueExt},
     validation::Validation,
     JsonValue, Map, SharedString,
@@ -18,6 +20,8 @@ pub struct Query {
     offset: usize,
     // Limit.
     limit: usize,
+    // Cursor for keyset pagination: sort key values and `true` for paging backward.
+    cursor: Option<(Map, bool)>,
     // Extra attributes.
     extra: Map,
 }
@@ -32,6 +36,7 @@ impl Query {
             sort_order: SmallVec::new(),
             offset: 0,
             limit: 0,
+            cursor: None,
             extra: Map::new(),
         }
     }
@@ -89,6 +94,14 @@ impl Query {
                         }
                     }
                 }
+                "after" | "before" => {
+                    if let Some(cursor) = value.as_str() {
+                        match Self::decode_cursor(cursor) {
+                            Ok(cursor) => self.cursor = Some((cursor, key == "before")),
+                            Err(err) => validation.record_fail(key.to_owned(), err),
+                        }
+                    }
+                }
                 "current_page" => {
                     if let Some(result) = value.parse_usize() {
                         match result {
@@ -234,6 +247,45 @@ impl Query {
         self.limit = limit;
     }
 
+    /// Seeks the rows after the cursor in the sort order for keyset pagination.
+    #[inline]
+    pub fn seek_after(&mut self, cursor: Map) {
+        self.cursor = Some((cursor, false));
+    }
+
+    /// Seeks the rows before the cursor in the sort order for keyset pagination.
+    #[inline]
+    pub fn seek_before(&mut self, cursor: Map) {
+        self.cursor = Some((cursor, true));
+    }
+
+    /// Clears the cursor for keyset pagination.
+    #[inline]
+    pub fn clear_cursor(&mut self) {
+        self.cursor = None;
+    }
+
+    /// Encodes the values of sort keys in the entry as an opaque cursor.
+    ///
+    /// The entry should be a raw row, and it is an error if any sort key is missing.
+    pub fn encode_cursor(&self, entry: &Map) -> Result<String, Error> {
+        let mut cursor = Map::new();
+        for (field, _) in self.sort_order.iter() {
+            let field = field.as_ref();
+            let value = entry
+                .get(field)
+                .ok_or_else(|| Error::new(format!("the sort key `{field}` is missing")))?;
+            cursor.upsert(field, value.clone());
+        }
+        Ok(base64::encode_url_safe(JsonValue::from(cursor).to_string()))
+    }
+
+    /// Decodes an opaque cursor as the values of sort keys.
+    pub fn decode_cursor(cursor: &str) -> Result<Map, Error> {
+        let data = base64::decode_url_safe(cursor)?;
+        serde_json::from_slice(&data).map_err(Error::from)
+    }
+
     /// Returns a reference to the projection fields.
     #[inline]
     pub fn fields(&self) -> &[String] {
@@ -265,6 +317,15 @@ impl Query {
         self.limit
     }
 
+    /// Returns the cursor for keyset pagination.
+    /// A `true` boolean value represents paging backward.
+    #[inline]
+    pub fn cursor(&self) -> Option<(&Map, bool)> {
+        self.cursor
+            .as_ref()
+            .map(|(cursor, backward)| (cursor, *backward))
+    }
+
     /// Returns `true` if the `flag` has been enabled.
     #[inline]
     pub fn enabled(&self, flag: &str) -> bool {
@@ -311,6 +372,7 @@ impl Default for Query {
             sort_order: SmallVec::new(),
             offset: 0,
             limit: 10,
+            cursor: None,
             extra: Map::new(),
         }
     }
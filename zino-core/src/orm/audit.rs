@@ -0,0 +1,656 @@
+use super::{query::QueryExt, DatabaseRow, Executor, ReborrowedExecutor, Schema};
+use crate::{
+    crypto,
+    datetime::DateTime,
+    encoding::hex,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{DecodeRow, Query, QueryArguments},
+    warn, BoxFuture, JsonValue, LazyLock, Map,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, future::Future, sync::Arc};
+
+/// An entry of the audit trail which records a change of the model data.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditEntry {
+    /// Model name.
+    model_name: String,
+    /// Primary key of the entity.
+    entity_id: String,
+    /// Action of the change.
+    action: String,
+    /// ID of the user who made the change.
+    user_id: Option<String>,
+    /// Request ID.
+    request_id: Option<String>,
+    /// Snapshot before the change.
+    before: Option<Map>,
+    /// Snapshot after the change.
+    after: Option<Map>,
+    /// Field-level diff in the form of `{ field: { "before": old, "after": new } }`.
+    diff: Map,
+    /// Recorded time.
+    recorded_at: DateTime,
+}
+
+impl AuditEntry {
+    /// Creates a new instance with the snapshots. The user and request ID are
+    /// obtained from the current [`AuditContext`].
+    pub fn new(
+        model_name: impl ToString,
+        entity_id: impl ToString,
+        action: impl ToString,
+        before: Option<Map>,
+        after: Option<Map>,
+    ) -> Self {
+        let diff = diff_snapshots(before.as_ref(), after.as_ref());
+        let ctx = AuditContext::current();
+        Self {
+            model_name: model_name.to_string(),
+            entity_id: entity_id.to_string(),
+            action: action.to_string(),
+            user_id: ctx.user_id,
+            request_id: ctx.request_id,
+            before,
+            after,
+            diff,
+            recorded_at: DateTime::now(),
+        }
+    }
+
+    /// Returns the model name.
+    #[inline]
+    pub fn model_name(&self) -> &str {
+        &self.model_name
+    }
+
+    /// Returns the primary key of the entity.
+    #[inline]
+    pub fn entity_id(&self) -> &str {
+        &self.entity_id
+    }
+
+    /// Returns the action of the change.
+    #[inline]
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Returns the ID of the user who made the change.
+    #[inline]
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    /// Returns the request ID.
+    #[inline]
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Returns the snapshot before the change.
+    #[inline]
+    pub fn before(&self) -> Option<&Map> {
+        self.before.as_ref()
+    }
+
+    /// Returns the snapshot after the change. It is `None` for a deletion.
+    #[inline]
+    pub fn after(&self) -> Option<&Map> {
+        self.after.as_ref()
+    }
+
+    /// Returns the field-level diff.
+    #[inline]
+    pub fn diff(&self) -> &Map {
+        &self.diff
+    }
+
+    /// Returns the recorded time.
+    #[inline]
+    pub fn recorded_at(&self) -> DateTime {
+        self.recorded_at
+    }
+
+    /// Attempts to create a new instance from the JSON object.
+    #[inline]
+    pub fn try_from_map(map: Map) -> Result<Self, Error> {
+        serde_json::from_value(JsonValue::Object(map)).map_err(Error::from)
+    }
+
+    /// Converts `self` into a JSON object.
+    pub fn into_map(self) -> Map {
+        match serde_json::to_value(self) {
+            Ok(JsonValue::Object(map)) => map,
+            _ => Map::new(),
+        }
+    }
+
+    /// Returns the hex-encoded checksum of the entry,
+    /// which can be used to verify the integrity of a stored entry.
+    pub fn checksum(&self) -> String {
+        let data = serde_json::to_vec(self).unwrap_or_default();
+        hex::encode(crypto::digest(&data))
+    }
+}
+
+/// A recorder which stores the audit entries and queries the history of an entity.
+pub trait AuditRecorder: Send + Sync {
+    /// Returns the recorder name.
+    fn name(&self) -> &'static str;
+
+    /// Records an audit entry.
+    fn record<'a>(&'a self, entry: &'a AuditEntry) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Records an audit entry using the executor of the change, so that the entry
+    /// is committed or rolled back together with the change in a transaction.
+    /// The default implementation ignores the executor and calls [`record()`](Self::record).
+    fn record_in<'a>(
+        &'a self,
+        entry: &'a AuditEntry,
+        executor: ReborrowedExecutor<'a>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        let _ = executor;
+        self.record(entry)
+    }
+
+    /// Returns the audit entries of an entity in the order of the recorded time.
+    fn history<'a>(
+        &'a self,
+        model_name: &'a str,
+        entity_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<AuditEntry>, Error>>;
+}
+
+/// Audit trail for the models with the `#[schema(audit)]` attribute.
+///
+/// The changes made by the [`Schema`] methods are recorded by the registered [`AuditRecorder`].
+/// A model can not be modified if the audit is enabled while there is no recorder.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AuditTrail;
+
+impl AuditTrail {
+    /// Registers the shared audit recorder.
+    #[inline]
+    pub fn register_recorder(recorder: impl AuditRecorder + 'static) {
+        *SHARED_AUDIT_RECORDER.write() = Some(Arc::new(recorder));
+    }
+
+    /// Returns the shared audit recorder.
+    pub fn recorder() -> Result<Arc<dyn AuditRecorder>, Error> {
+        SHARED_AUDIT_RECORDER
+            .read()
+            .clone()
+            .ok_or_else(|| warn!("the audit recorder should be registered"))
+    }
+
+    /// Returns the audit entries of an entity in the order of the recorded time.
+    pub async fn history(model_name: &str, entity_id: &str) -> Result<Vec<AuditEntry>, Error> {
+        let recorder = Self::recorder()?;
+        let mut entries = recorder.history(model_name, entity_id).await?;
+        entries.sort_by_key(|entry| entry.recorded_at);
+        Ok(entries)
+    }
+
+    /// Reconstructs the state of an entity at the time from the audit entries.
+    /// It returns `None` if the entity does not exist at that time.
+    pub fn state_at(entries: &[AuditEntry], time: DateTime) -> Option<Map> {
+        entries
+            .iter()
+            .filter(|entry| entry.recorded_at <= time)
+            .max_by_key(|entry| entry.recorded_at)
+            .and_then(|entry| entry.after.clone())
+    }
+}
+
+/// A request-scoped context of the audit trail.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    /// User ID.
+    user_id: Option<String>,
+    /// Request ID.
+    request_id: Option<String>,
+}
+
+impl AuditContext {
+    /// Runs the future in a new audit context. It is used to wrap the handling of a request.
+    #[inline]
+    pub async fn scope<F: Future>(future: F) -> F::Output {
+        AUDIT_CONTEXT
+            .scope(RefCell::new(Self::default()), future)
+            .await
+    }
+
+    /// Sets the user ID for the rest of the current scope.
+    #[inline]
+    pub fn set_user_id(user_id: impl ToString) {
+        let user_id = user_id.to_string();
+        let _ = AUDIT_CONTEXT.try_with(|ctx| ctx.borrow_mut().user_id = Some(user_id));
+    }
+
+    /// Sets the request ID for the rest of the current scope.
+    #[inline]
+    pub fn set_request_id(request_id: impl ToString) {
+        let request_id = request_id.to_string();
+        let _ = AUDIT_CONTEXT.try_with(|ctx| ctx.borrow_mut().request_id = Some(request_id));
+    }
+
+    /// Returns the audit context of the current scope.
+    #[inline]
+    pub fn current() -> Self {
+        AUDIT_CONTEXT
+            .try_with(|ctx| ctx.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the user ID.
+    #[inline]
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    /// Returns the request ID.
+    #[inline]
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+}
+
+/// Returns the audit recorder if the audit is enabled for the model.
+pub(super) fn audit_recorder<M: Schema>() -> Result<Option<Arc<dyn AuditRecorder>>, Error> {
+    if M::AUDIT_ENABLED {
+        AuditTrail::recorder().map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Creates a snapshot of the model data without the write-only fields.
+pub(super) fn snapshot<M: Schema>(model: &Map) -> Map {
+    let write_only_fields = M::write_only_fields();
+    model
+        .iter()
+        .filter(|(key, _)| !write_only_fields.contains(&key.as_str()))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// Fetches the snapshots of the models selected by the query using the executor.
+pub(super) async fn fetch_snapshots<M, E>(
+    query: &Query,
+    limit: Option<usize>,
+    executor: E,
+) -> Result<Vec<Map>, Error>
+where
+    M: Schema,
+    E: Executor<Row = DatabaseRow>,
+{
+    let table_name = query.format_table_name::<M>();
+    let mut arguments = QueryArguments::new();
+    let filters = query.format_filters::<M>(&mut arguments);
+    let sql = if let Some(limit) = limit {
+        let sort = query.format_sort();
+        format!("SELECT * FROM {table_name} {filters} {sort} LIMIT {limit};")
+    } else {
+        format!("SELECT * FROM {table_name} {filters};")
+    };
+    let rows = executor.fetch_with_values(&sql, arguments.values()).await?;
+    rows.iter()
+        .map(|row| Map::decode_row(row).map(|model| snapshot::<M>(&model)))
+        .collect()
+}
+
+/// Fetches the snapshots of the models with the primary keys using the executor.
+async fn fetch_snapshots_by_ids<M, E>(ids: Vec<JsonValue>, executor: E) -> Result<Vec<Map>, Error>
+where
+    M: Schema,
+    E: Executor<Row = DatabaseRow>,
+{
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let filter = Map::from_entry("$in", ids);
+    let query = Query::new(Map::from_entry(M::PRIMARY_KEY_NAME, filter));
+    fetch_snapshots::<M, E>(&query, None, executor).await
+}
+
+/// Fetches the snapshot of the model with the primary key using the executor.
+pub(super) async fn fetch_snapshot<M, E>(
+    primary_key: &M::PrimaryKey,
+    executor: E,
+) -> Result<Option<Map>, Error>
+where
+    M: Schema,
+    E: Executor<Row = DatabaseRow>,
+{
+    let ids = vec![primary_key.to_string().into()];
+    fetch_snapshots_by_ids::<M, E>(ids, executor)
+        .await
+        .map(|mut models| models.pop())
+}
+
+/// Returns the primary keys of the snapshots.
+fn snapshot_ids<M: Schema>(models: &[Map]) -> Vec<JsonValue> {
+    models
+        .iter()
+        .filter_map(|model| model.get(M::PRIMARY_KEY_NAME).cloned())
+        .collect()
+}
+
+/// Applies the writable fields of the model data to the snapshot before the change.
+pub(super) fn apply_snapshot<M: Schema>(before: Option<&Map>, model: &Map) -> Map {
+    let Some(before) = before else {
+        return snapshot::<M>(model);
+    };
+
+    let read_only_fields = M::read_only_fields();
+    let write_only_fields = M::write_only_fields();
+    let mut after = before.clone();
+    for (key, value) in model.iter() {
+        let field = key.as_str();
+        if !read_only_fields.contains(&field)
+            && !write_only_fields.contains(&field)
+            && Some(field) != M::TENANT_KEY
+        {
+            after.upsert(key, value.clone());
+        }
+    }
+    after
+}
+
+/// Records the change of a model using the executor of the change.
+pub(super) async fn record_change<M: Schema>(
+    recorder: &dyn AuditRecorder,
+    entity_id: impl ToString,
+    action: &str,
+    before: Option<Map>,
+    after: Option<Map>,
+    executor: ReborrowedExecutor<'_>,
+) -> Result<(), Error> {
+    let entry = AuditEntry::new(M::MODEL_NAME, entity_id, action, before, after);
+    record_entry(recorder, &entry, executor).await
+}
+
+/// Fetches the snapshots of the updated models using the executor,
+/// and records the changes by comparing them with the snapshots before the update.
+pub(super) async fn record_updates<M: Schema>(
+    recorder: &dyn AuditRecorder,
+    before: Vec<Map>,
+    mut executor: ReborrowedExecutor<'_>,
+) -> Result<(), Error> {
+    let ids = snapshot_ids::<M>(&before);
+    let after = fetch_snapshots_by_ids::<M, _>(ids, executor.reborrow()).await?;
+    record_changes::<M>(recorder, "update", before, after, executor).await
+}
+
+/// Records the changes of the models by comparing the snapshots before and after
+/// using the executor of the changes. The unchanged models are skipped.
+pub(super) async fn record_changes<M: Schema>(
+    recorder: &dyn AuditRecorder,
+    action: &str,
+    before: Vec<Map>,
+    after: Vec<Map>,
+    mut executor: ReborrowedExecutor<'_>,
+) -> Result<(), Error> {
+    let primary_key_name = M::PRIMARY_KEY_NAME;
+    for model in before {
+        let Some(id) = model.get(primary_key_name).cloned() else {
+            continue;
+        };
+        let model_after = after
+            .iter()
+            .find(|model| model.get(primary_key_name) == Some(&id))
+            .cloned();
+        let action = if model_after.is_some() {
+            action
+        } else {
+            "delete"
+        };
+        let entity_id = id.as_str().map(|s| s.to_owned()).unwrap_or(id.to_string());
+        let entry = AuditEntry::new(M::MODEL_NAME, entity_id, action, Some(model), model_after);
+        if !entry.diff.is_empty() {
+            record_entry(recorder, &entry, executor.reborrow()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Records the audit entry using the executor and logs the failure.
+async fn record_entry(
+    recorder: &dyn AuditRecorder,
+    entry: &AuditEntry,
+    executor: ReborrowedExecutor<'_>,
+) -> Result<(), Error> {
+    recorder.record_in(entry, executor).await.map_err(|err| {
+        tracing::error!(
+            model_name = entry.model_name(),
+            entity_id = entry.entity_id(),
+            action = entry.action(),
+            "fail to record the audit entry: {err}"
+        );
+        err
+    })
+}
+
+/// Computes the field-level diff of the snapshots.
+fn diff_snapshots(before: Option<&Map>, after: Option<&Map>) -> Map {
+    let empty = Map::new();
+    let before = before.unwrap_or(&empty);
+    let after = after.unwrap_or(&empty);
+    let mut diff = Map::new();
+    for (key, value) in before.iter() {
+        let new_value = after.get(key).unwrap_or(&JsonValue::Null);
+        if value != new_value {
+            let mut change = Map::new();
+            change.upsert("before", value.clone());
+            change.upsert("after", new_value.clone());
+            diff.upsert(key, change);
+        }
+    }
+    for (key, value) in after.iter() {
+        if !before.contains_key(key) && !value.is_null() {
+            let mut change = Map::new();
+            change.upsert("before", JsonValue::Null);
+            change.upsert("after", value.clone());
+            diff.upsert(key, change);
+        }
+    }
+    diff
+}
+
+/// Shared audit recorder.
+static SHARED_AUDIT_RECORDER: LazyLock<RwLock<Option<Arc<dyn AuditRecorder>>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+tokio::task_local! {
+    /// Audit context of the current scope.
+    static AUDIT_CONTEXT: RefCell<AuditContext>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditEntry, AuditTrail};
+    use crate::{datetime::DateTime, extension::JsonObjectExt, Map};
+    use std::time::Duration;
+
+    #[test]
+    fn it_reconstructs_history() {
+        let mut model = Map::from_entry("id", "1");
+        model.upsert("name", "alice");
+        let created = AuditEntry::new("user", "1", "insert", None, Some(model.clone()));
+        assert_eq!(created.diff().len(), 2);
+
+        let mut updated_model = model.clone();
+        updated_model.upsert("name", "bob");
+        let mut updated = AuditEntry::new(
+            "user",
+            "1",
+            "update",
+            Some(model.clone()),
+            Some(updated_model.clone()),
+        );
+        updated.recorded_at = created.recorded_at() + Duration::from_secs(60);
+        assert_eq!(updated.diff().len(), 1);
+        assert!(updated.diff().contains_key("name"));
+
+        let entries = vec![created.clone(), updated.clone()];
+        let time = created.recorded_at() + Duration::from_secs(30);
+        assert_eq!(AuditTrail::state_at(&entries, time), Some(model));
+        let time = updated.recorded_at() + Duration::from_secs(30);
+        assert_eq!(AuditTrail::state_at(&entries, time), Some(updated_model));
+        let time = created.recorded_at() - Duration::from_secs(30);
+        assert_eq!(AuditTrail::state_at(&entries, time), None);
+        assert_ne!(created.checksum(), updated.checksum());
+        assert!(DateTime::now() >= created.recorded_at());
+    }
+
+    #[cfg(not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    )))]
+    #[test]
+    fn it_records_changes_in_transactions() {
+        use super::{AuditRecorder, ReborrowedExecutor};
+        use crate::{
+            error::Error,
+            model::{Mutation, Query},
+            orm::{
+                fixture::{block_on, create_table, test_model, TEST_POOL},
+                Executor, Schema, TransactionHandle,
+            },
+            warn, BoxFuture, JsonValue,
+        };
+
+        test_model!(
+            AuditDocument,
+            "audit_documents",
+            auto_increment = true,
+            const AUDIT_ENABLED: bool = true;
+        );
+
+        /// A recorder which writes the entries into the `audit_entries` table
+        /// and fails to record the deletions.
+        struct TableRecorder;
+
+        impl AuditRecorder for TableRecorder {
+            fn name(&self) -> &'static str {
+                "table"
+            }
+
+            fn record<'a>(&'a self, entry: &'a AuditEntry) -> BoxFuture<'a, Result<(), Error>> {
+                self.record_in(entry, ReborrowedExecutor::Pool(TEST_POOL.pool()))
+            }
+
+            fn record_in<'a>(
+                &'a self,
+                entry: &'a AuditEntry,
+                executor: ReborrowedExecutor<'a>,
+            ) -> BoxFuture<'a, Result<(), Error>> {
+                Box::pin(async move {
+                    if entry.action() == "delete" {
+                        return Err(warn!("the deletions can not be recorded"));
+                    }
+
+                    let sql = "INSERT INTO audit_entries (entity_id, data) VALUES (?, ?);";
+                    let data = serde_json::to_string(entry)?;
+                    let arguments: [JsonValue; 2] = [entry.entity_id().into(), data.into()];
+                    executor.execute_with_values(sql, &arguments).await?;
+                    Ok(())
+                })
+            }
+
+            fn history<'a>(
+                &'a self,
+                _model_name: &'a str,
+                entity_id: &'a str,
+            ) -> BoxFuture<'a, Result<Vec<AuditEntry>, Error>> {
+                Box::pin(async move {
+                    let sql = "SELECT data FROM audit_entries WHERE entity_id = ?;";
+                    let rows = sqlx::query_as::<_, (String,)>(sql)
+                        .bind(entity_id)
+                        .fetch_all(TEST_POOL.pool())
+                        .await?;
+                    rows.into_iter()
+                        .map(|(data,)| serde_json::from_str(&data).map_err(Error::from))
+                        .collect()
+                })
+            }
+        }
+
+        AuditTrail::register_recorder(TableRecorder);
+        block_on(async {
+            create_table("audit_documents", true).await;
+            sqlx::query(
+                "CREATE TABLE audit_entries (entity_id TEXT NOT NULL, data TEXT NOT NULL);",
+            )
+            .execute(TEST_POOL.pool())
+            .await
+            .unwrap();
+
+            let docs = ["alpha", "beta"]
+                .into_iter()
+                .map(|name| AuditDocument {
+                    name: name.to_owned(),
+                    ..Default::default()
+                })
+                .collect();
+            AuditDocument::insert_many(docs).await.unwrap();
+            for entity_id in ["1", "2"] {
+                let history = AuditTrail::history("audit_documents", entity_id)
+                    .await
+                    .unwrap();
+                assert_eq!(history.len(), 1);
+                assert_eq!(history[0].action(), "insert");
+                assert_eq!(
+                    history[0].after().and_then(|model| model.get("id")),
+                    Some(&JsonValue::from(entity_id.parse::<i64>().unwrap()))
+                );
+            }
+
+            // The snapshots are fetched in the transaction holding the only connection.
+            let mut tx = TransactionHandle::begin(&TEST_POOL).await.unwrap();
+            let query = Query::from_entry("name", "alpha");
+            let mut mutation = Mutation::from_entry("name", "gamma");
+            AuditDocument::update_many_in(&query, &mut mutation, &mut tx)
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+
+            let history = AuditTrail::history("audit_documents", "1").await.unwrap();
+            assert_eq!(history.len(), 2);
+            assert_eq!(history[1].action(), "update");
+            assert!(history[1].diff().contains_key("name"));
+            assert_eq!(
+                history[1].after().and_then(|model| model.get_str("name")),
+                Some("gamma")
+            );
+
+            // The entries are rolled back with the transaction.
+            let mut tx = TransactionHandle::begin(&TEST_POOL).await.unwrap();
+            let query = Query::from_entry("name", "beta");
+            let mut mutation = Mutation::from_entry("name", "delta");
+            AuditDocument::update_many_in(&query, &mut mutation, &mut tx)
+                .await
+                .unwrap();
+            tx.rollback().await.unwrap();
+
+            let history = AuditTrail::history("audit_documents", "2").await.unwrap();
+            assert_eq!(history.len(), 1);
+
+            // The failure of recording is returned so that the deletion can be rolled back.
+            let mut tx = TransactionHandle::begin(&TEST_POOL).await.unwrap();
+            let result = AuditDocument::delete_many_in(&query, &mut tx).await;
+            assert!(result.is_err());
+            tx.rollback().await.unwrap();
+
+            let count = AuditDocument::count(&query).await.unwrap();
+            assert_eq!(count, 1);
+        });
+    }
+}
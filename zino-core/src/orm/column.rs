@@ -1,6 +1,6 @@
 use crate::{
     extension::JsonObjectExt,
-    model::{Column, EncodeColumn},
+    model::{Column, EncodeColumn, QueryArguments},
 };
 use convert_case::{Case, Casing};
 
@@ -61,8 +61,15 @@ impl<'a> ColumnExt for Column<'a> {
                     ""
                 };
             } else {
-                let value = self.format_value(value);
-                if cfg!(feature = "orm-sqlite") && value.contains('(') {
+                // The default value can not be bound in the table definition.
+                let value = self.format_value(value, &mut QueryArguments::inline());
+                let is_sqlite = cfg!(not(any(
+                    feature = "orm-mariadb",
+                    feature = "orm-mysql",
+                    feature = "orm-postgres",
+                    feature = "orm-tidb"
+                )));
+                if is_sqlite && value.contains('(') {
                     definition = format!("{definition} DEFAULT ({value})");
                 } else {
                     definition = format!("{definition} DEFAULT {value}");
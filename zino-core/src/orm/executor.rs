@@ -1,4 +1,9 @@
-use crate::error::Error;
+use crate::{error::Error, JsonValue};
+
+#[cfg(feature = "orm-sqlx")]
+use crate::model::{DecodeRow, QueryArguments, QueryContext};
+#[cfg(feature = "orm-sqlx")]
+use futures::stream::BoxStream;
 
 /// Executing queries against the database.
 pub trait Executor {
@@ -8,6 +13,10 @@ pub trait Executor {
     /// A type for the query result.
     type QueryResult;
 
+    /// Reborrows the executor so that it can be used for more than one query.
+    #[cfg(feature = "orm-sqlx")]
+    fn reborrow(&mut self) -> ReborrowedExecutor<'_>;
+
     /// Executes the query and return the total number of rows affected.
     async fn execute(self, sql: &str) -> Result<Self::QueryResult, Error>;
 
@@ -18,6 +27,21 @@ pub trait Executor {
         arguments: &[T],
     ) -> Result<Self::QueryResult, Error>;
 
+    /// Executes the query with typed arguments and return the total number of rows affected.
+    ///
+    /// The default implementation binds the arguments as strings.
+    async fn execute_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Self::QueryResult, Error>
+    where
+        Self: Sized,
+    {
+        self.execute_with(sql, &stringify_arguments(arguments))
+            .await
+    }
+
     /// Executes the query and return all the generated results.
     async fn fetch(self, sql: &str) -> Result<Vec<Self::Row>, Error>;
 
@@ -28,9 +52,41 @@ pub trait Executor {
         arguments: &[T],
     ) -> Result<Vec<Self::Row>, Error>;
 
+    /// Executes the query with typed arguments and return all the generated results.
+    ///
+    /// The default implementation binds the arguments as strings.
+    async fn fetch_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Vec<Self::Row>, Error>
+    where
+        Self: Sized,
+    {
+        self.fetch_with(sql, &stringify_arguments(arguments)).await
+    }
+
     /// Executes the query and returns exactly one row.
     async fn fetch_one(self, sql: &str) -> Result<Self::Row, Error>;
 
+    /// Executes the query with typed arguments and returns exactly one row.
+    ///
+    /// The default implementation binds the arguments as strings.
+    async fn fetch_one_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Self::Row, Error>
+    where
+        Self: Sized,
+    {
+        self.fetch_optional_with(sql, &stringify_arguments(arguments))
+            .await?
+            .ok_or_else(|| {
+                Error::new("no rows returned by a query that expected to return one row")
+            })
+    }
+
     /// Executes the query and returns at most one row.
     async fn fetch_optional(self, sql: &str) -> Result<Option<Self::Row>, Error>;
 
@@ -40,17 +96,46 @@ pub trait Executor {
         sql: &str,
         arguments: &[T],
     ) -> Result<Option<Self::Row>, Error>;
+
+    /// Executes the query with typed arguments and returns at most one row.
+    ///
+    /// The default implementation binds the arguments as strings.
+    async fn fetch_optional_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Option<Self::Row>, Error>
+    where
+        Self: Sized,
+    {
+        self.fetch_optional_with(sql, &stringify_arguments(arguments))
+            .await
+    }
+}
+
+/// Converts the typed arguments to strings.
+fn stringify_arguments<T: Clone + Into<JsonValue>>(arguments: &[T]) -> Vec<String> {
+    arguments
+        .iter()
+        .map(|arg| match arg.clone().into() {
+            JsonValue::String(value) => value,
+            value => value.to_string(),
+        })
+        .collect()
 }
 
 #[cfg(feature = "orm-sqlx")]
 macro_rules! impl_sqlx_executor {
-    () => {
+    ($($on_success:expr)?) => {
         type Row = super::DatabaseRow;
         type QueryResult = <super::DatabaseDriver as sqlx::Database>::QueryResult;
 
         async fn execute(self, sql: &str) -> Result<Self::QueryResult, Error> {
             match sqlx::query(sql).execute(self).await {
-                Ok(result) => Ok(result),
+                Ok(result) => {
+                    $($on_success;)?
+                    Ok(result)
+                }
                 Err(err) => {
                     if matches!(err, sqlx::error::Error::PoolTimedOut) {
                         super::GlobalPool::connect_all().await;
@@ -70,7 +155,33 @@ macro_rules! impl_sqlx_executor {
                 query = query.bind(arg.to_string());
             }
             match query.execute(self).await {
-                Ok(result) => Ok(result),
+                Ok(result) => {
+                    $($on_success;)?
+                    Ok(result)
+                }
+                Err(err) => {
+                    if matches!(err, sqlx::error::Error::PoolTimedOut) {
+                        super::GlobalPool::connect_all().await;
+                    }
+                    Err(err.into())
+                }
+            }
+        }
+
+        async fn execute_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Self::QueryResult, Error> {
+            let mut query = sqlx::query(sql);
+            for arg in arguments {
+                query = bind_argument(query, arg.clone().into());
+            }
+            match query.execute(self).await {
+                Ok(result) => {
+                    $($on_success;)?
+                    Ok(result)
+                }
                 Err(err) => {
                     if matches!(err, sqlx::error::Error::PoolTimedOut) {
                         super::GlobalPool::connect_all().await;
@@ -139,6 +250,40 @@ macro_rules! impl_sqlx_executor {
             Ok(rows)
         }
 
+        async fn fetch_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Vec<Self::Row>, Error> {
+            use futures::StreamExt;
+            use std::sync::atomic::Ordering::Relaxed;
+
+            let mut query = sqlx::query(sql);
+            for arg in arguments {
+                query = bind_argument(query, arg.clone().into());
+            }
+
+            let mut stream = query.fetch(self);
+            let mut max_rows = super::MAX_ROWS.load(Relaxed);
+            let mut rows = Vec::with_capacity(stream.size_hint().0.min(max_rows));
+            while let Some(result) = stream.next().await {
+                match result {
+                    Ok(row) if max_rows > 0 => {
+                        rows.push(row);
+                        max_rows -= 1;
+                    }
+                    Err(err) => {
+                        if matches!(err, sqlx::error::Error::PoolTimedOut) {
+                            super::GlobalPool::connect_all().await;
+                        }
+                        return Err(err.into());
+                    }
+                    _ => break,
+                }
+            }
+            Ok(rows)
+        }
+
         async fn fetch_one(self, sql: &str) -> Result<Self::Row, Error> {
             match sqlx::query(sql).fetch_one(self).await {
                 Ok(row) => Ok(row),
@@ -151,6 +296,26 @@ macro_rules! impl_sqlx_executor {
             }
         }
 
+        async fn fetch_one_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Self::Row, Error> {
+            let mut query = sqlx::query(sql);
+            for arg in arguments {
+                query = bind_argument(query, arg.clone().into());
+            }
+            match query.fetch_one(self).await {
+                Ok(row) => Ok(row),
+                Err(err) => {
+                    if matches!(err, sqlx::error::Error::PoolTimedOut) {
+                        super::GlobalPool::connect_all().await;
+                    }
+                    Err(err.into())
+                }
+            }
+        }
+
         async fn fetch_optional(self, sql: &str) -> Result<Option<Self::Row>, Error> {
             match sqlx::query(sql).fetch_optional(self).await {
                 Ok(row) => Ok(row),
@@ -182,15 +347,380 @@ macro_rules! impl_sqlx_executor {
                 }
             }
         }
+
+        async fn fetch_optional_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Option<Self::Row>, Error> {
+            let mut query = sqlx::query(sql);
+            for arg in arguments {
+                query = bind_argument(query, arg.clone().into());
+            }
+            match query.fetch_optional(self).await {
+                Ok(row) => Ok(row),
+                Err(err) => {
+                    if matches!(err, sqlx::error::Error::PoolTimedOut) {
+                        super::GlobalPool::connect_all().await;
+                    }
+                    Err(err.into())
+                }
+            }
+        }
     };
 }
 
+/// A SQL query with the arguments of the database driver.
+#[cfg(feature = "orm-sqlx")]
+pub(super) type SqlxQuery<'q> = sqlx::query::Query<
+    'q,
+    super::DatabaseDriver,
+    <super::DatabaseDriver as sqlx::database::HasArguments<'q>>::Arguments,
+>;
+
+/// Binds the JSON value to the query as an argument of the corresponding type.
+#[cfg(feature = "orm-sqlx")]
+pub(super) fn bind_argument(query: SqlxQuery<'_>, value: JsonValue) -> SqlxQuery<'_> {
+    match value {
+        JsonValue::Null => query.bind(None::<String>),
+        JsonValue::Bool(value) => query.bind(value),
+        JsonValue::Number(value) => {
+            if let Some(value) = value.as_i64() {
+                query.bind(value)
+            } else if let Some(value) = value.as_f64().filter(|_| value.is_f64()) {
+                query.bind(value)
+            } else {
+                query.bind(value.to_string())
+            }
+        }
+        JsonValue::String(value) => query.bind(value),
+        _ => query.bind(value.to_string()),
+    }
+}
+
 #[cfg(feature = "orm-sqlx")]
 impl<'c> Executor for &'c sqlx::Pool<super::DatabaseDriver> {
-    impl_sqlx_executor!();
+    #[inline]
+    fn reborrow(&mut self) -> ReborrowedExecutor<'_> {
+        ReborrowedExecutor::Pool(self)
+    }
+
+    // Auto-committed writes pin the reads to the writer for the rest of the request.
+    impl_sqlx_executor!(super::GlobalPool::pin_writer());
 }
 
 #[cfg(feature = "orm-sqlx")]
 impl<'c> Executor for &'c mut super::DatabaseConnection {
+    #[inline]
+    fn reborrow(&mut self) -> ReborrowedExecutor<'_> {
+        ReborrowedExecutor::Connection(self)
+    }
+
     impl_sqlx_executor!();
 }
+
+#[cfg(feature = "orm-sqlx")]
+macro_rules! impl_transaction_executor {
+    () => {
+        type Row = super::DatabaseRow;
+        type QueryResult = <super::DatabaseDriver as sqlx::Database>::QueryResult;
+
+        #[inline]
+        async fn execute(self, sql: &str) -> Result<Self::QueryResult, Error> {
+            (&mut **self).execute(sql).await
+        }
+
+        #[inline]
+        async fn execute_with<T: ToString>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Self::QueryResult, Error> {
+            (&mut **self).execute_with(sql, arguments).await
+        }
+
+        #[inline]
+        async fn execute_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Self::QueryResult, Error> {
+            (&mut **self).execute_with_values(sql, arguments).await
+        }
+
+        #[inline]
+        async fn fetch(self, sql: &str) -> Result<Vec<Self::Row>, Error> {
+            (&mut **self).fetch(sql).await
+        }
+
+        #[inline]
+        async fn fetch_with<T: ToString>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Vec<Self::Row>, Error> {
+            (&mut **self).fetch_with(sql, arguments).await
+        }
+
+        #[inline]
+        async fn fetch_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Vec<Self::Row>, Error> {
+            (&mut **self).fetch_with_values(sql, arguments).await
+        }
+
+        #[inline]
+        async fn fetch_one(self, sql: &str) -> Result<Self::Row, Error> {
+            (&mut **self).fetch_one(sql).await
+        }
+
+        #[inline]
+        async fn fetch_one_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Self::Row, Error> {
+            (&mut **self).fetch_one_with_values(sql, arguments).await
+        }
+
+        #[inline]
+        async fn fetch_optional(self, sql: &str) -> Result<Option<Self::Row>, Error> {
+            (&mut **self).fetch_optional(sql).await
+        }
+
+        #[inline]
+        async fn fetch_optional_with<T: ToString>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Option<Self::Row>, Error> {
+            (&mut **self).fetch_optional_with(sql, arguments).await
+        }
+
+        #[inline]
+        async fn fetch_optional_with_values<T: Clone + Into<JsonValue>>(
+            self,
+            sql: &str,
+            arguments: &[T],
+        ) -> Result<Option<Self::Row>, Error> {
+            (&mut **self)
+                .fetch_optional_with_values(sql, arguments)
+                .await
+        }
+    };
+}
+
+#[cfg(feature = "orm-sqlx")]
+impl<'t, 'c> Executor for &'t mut sqlx::Transaction<'c, super::DatabaseDriver> {
+    #[inline]
+    fn reborrow(&mut self) -> ReborrowedExecutor<'_> {
+        ReborrowedExecutor::Connection(self)
+    }
+
+    impl_transaction_executor!();
+}
+
+#[cfg(feature = "orm-sqlx")]
+impl<'t, 'c> Executor for &'t mut super::TransactionHandle<'c> {
+    #[inline]
+    fn reborrow(&mut self) -> ReborrowedExecutor<'_> {
+        ReborrowedExecutor::Connection(self)
+    }
+
+    impl_transaction_executor!();
+}
+
+/// An executor reborrowed from a connection pool or a connection,
+/// which is returned by [`Executor::reborrow()`].
+#[cfg(feature = "orm-sqlx")]
+pub enum ReborrowedExecutor<'a> {
+    /// A connection pool.
+    Pool(&'a sqlx::Pool<super::DatabaseDriver>),
+    /// A connection, which may be a transaction.
+    Connection(&'a mut super::DatabaseConnection),
+}
+
+#[cfg(feature = "orm-sqlx")]
+macro_rules! delegate_executor {
+    ($executor:expr, $method:ident($($arg:expr),*)) => {
+        match $executor {
+            ReborrowedExecutor::Pool(pool) => pool.$method($($arg),*).await,
+            ReborrowedExecutor::Connection(conn) => conn.$method($($arg),*).await,
+        }
+    };
+}
+
+#[cfg(feature = "orm-sqlx")]
+impl<'a> Executor for ReborrowedExecutor<'a> {
+    type Row = super::DatabaseRow;
+    type QueryResult = <super::DatabaseDriver as sqlx::Database>::QueryResult;
+
+    #[inline]
+    fn reborrow(&mut self) -> ReborrowedExecutor<'_> {
+        match self {
+            Self::Pool(pool) => ReborrowedExecutor::Pool(pool),
+            Self::Connection(conn) => ReborrowedExecutor::Connection(conn),
+        }
+    }
+
+    #[inline]
+    async fn execute(self, sql: &str) -> Result<Self::QueryResult, Error> {
+        delegate_executor!(self, execute(sql))
+    }
+
+    #[inline]
+    async fn execute_with<T: ToString>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Self::QueryResult, Error> {
+        delegate_executor!(self, execute_with(sql, arguments))
+    }
+
+    #[inline]
+    async fn execute_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Self::QueryResult, Error> {
+        delegate_executor!(self, execute_with_values(sql, arguments))
+    }
+
+    #[inline]
+    async fn fetch(self, sql: &str) -> Result<Vec<Self::Row>, Error> {
+        delegate_executor!(self, fetch(sql))
+    }
+
+    #[inline]
+    async fn fetch_with<T: ToString>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Vec<Self::Row>, Error> {
+        delegate_executor!(self, fetch_with(sql, arguments))
+    }
+
+    #[inline]
+    async fn fetch_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Vec<Self::Row>, Error> {
+        delegate_executor!(self, fetch_with_values(sql, arguments))
+    }
+
+    #[inline]
+    async fn fetch_one(self, sql: &str) -> Result<Self::Row, Error> {
+        delegate_executor!(self, fetch_one(sql))
+    }
+
+    #[inline]
+    async fn fetch_one_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Self::Row, Error> {
+        delegate_executor!(self, fetch_one_with_values(sql, arguments))
+    }
+
+    #[inline]
+    async fn fetch_optional(self, sql: &str) -> Result<Option<Self::Row>, Error> {
+        delegate_executor!(self, fetch_optional(sql))
+    }
+
+    #[inline]
+    async fn fetch_optional_with<T: ToString>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Option<Self::Row>, Error> {
+        delegate_executor!(self, fetch_optional_with(sql, arguments))
+    }
+
+    #[inline]
+    async fn fetch_optional_with_values<T: Clone + Into<JsonValue>>(
+        self,
+        sql: &str,
+        arguments: &[T],
+    ) -> Result<Option<Self::Row>, Error> {
+        delegate_executor!(self, fetch_optional_with_values(sql, arguments))
+    }
+}
+
+/// Fetches the rows lazily and returns a stream of the decoded values.
+/// Unlike [`Executor::fetch()`], the number of rows is not limited by the `max-rows`.
+///
+/// The query is logged with the number of rows when the stream is finished.
+#[cfg(feature = "orm-sqlx")]
+pub(super) fn fetch_stream<T>(
+    pool: &'static super::DatabasePool,
+    sql: String,
+    arguments: QueryArguments,
+    mut ctx: QueryContext,
+) -> BoxStream<'static, Result<T, Error>>
+where
+    T: DecodeRow<super::DatabaseRow, Error = Error> + Send + 'static,
+{
+    use futures::{channel::mpsc, future, stream, FutureExt, SinkExt, StreamExt};
+
+    let (mut sender, receiver) = mpsc::channel(STREAM_BUFFER_SIZE);
+    let producer = async move {
+        let mut num_rows = 0;
+        let mut success = true;
+        {
+            let mut query = sqlx::query(&sql);
+            for arg in arguments.values() {
+                query = bind_argument(query, arg.clone());
+            }
+
+            let mut rows = query.fetch(pool);
+            while let Some(result) = rows.next().await {
+                let item = match result {
+                    Ok(row) => T::decode_row(&row),
+                    Err(err) => {
+                        if matches!(err, sqlx::error::Error::PoolTimedOut) {
+                            super::GlobalPool::connect_all().await;
+                        }
+                        Err(err.into())
+                    }
+                };
+                success = item.is_ok();
+                if sender.send(item).await.is_err() || !success {
+                    break;
+                }
+                num_rows += 1;
+            }
+        }
+        ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
+        ctx.set_query_result(Some(num_rows), success);
+
+        let query_id = ctx.query_id().to_string();
+        let query = ctx.query();
+        let arguments = ctx.format_arguments();
+        let execution_time_millis = ctx.start_time().elapsed().as_millis();
+        tracing::info!(
+            query_id,
+            query,
+            arguments,
+            execution_time_millis,
+            "{num_rows} rows fetched in the stream"
+        );
+        if !success {
+            ctx.record_error("fail to fetch the rows in the stream");
+        }
+        #[cfg(feature = "metrics")]
+        ctx.emit_metrics("query");
+    };
+
+    // The producer is polled together with the receiver, so that it stops
+    // fetching the rows once the stream is dropped.
+    let producer = producer.into_stream().filter_map(|_| future::ready(None));
+    stream::select(receiver, producer).boxed()
+}
+
+/// Max number of rows buffered in a stream.
+#[cfg(feature = "orm-sqlx")]
+const STREAM_BUFFER_SIZE: usize = 64;
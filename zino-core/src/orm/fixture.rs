@@ -0,0 +1,137 @@
+//! Test fixtures backed by an in-memory SQLite database.
+
+use super::ConnectionPool;
+use crate::LazyLock;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use std::{future::Future, str::FromStr};
+use tokio::runtime::{Builder, Runtime};
+
+/// Shared runtime for the tests.
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("fail to build the runtime for tests")
+});
+
+/// Shared connection pool for the tests.
+///
+/// There is only one connection so that the in-memory database is shared.
+pub(crate) static TEST_POOL: LazyLock<ConnectionPool> = LazyLock::new(|| {
+    let options = SqliteConnectOptions::from_str("sqlite::memory:")
+        .expect("fail to parse the SQLite connect options");
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_lazy_with(options);
+    ConnectionPool::new("test", "test", pool)
+});
+
+/// Runs a future to completion on the shared runtime.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    RUNTIME.block_on(future)
+}
+
+/// Creates a table with the columns `id`, `tenant_id`, `name` and `version`.
+pub(crate) async fn create_table(table_name: &str, auto_increment: bool) {
+    let id = if auto_increment {
+        "id INTEGER PRIMARY KEY AUTOINCREMENT"
+    } else {
+        "id INTEGER PRIMARY KEY"
+    };
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {table_name} ({id}, \
+            tenant_id TEXT NOT NULL DEFAULT '', \
+            name TEXT NOT NULL DEFAULT '', \
+            version INTEGER NOT NULL DEFAULT 0);"
+    );
+    sqlx::query(&sql)
+        .execute(TEST_POOL.pool())
+        .await
+        .expect("fail to create the table");
+}
+
+/// Defines a model for the tests with the columns created by [`create_table`].
+///
+/// The optional items are the associated constants overriding the defaults in `Schema`.
+macro_rules! test_model {
+    ($model:ident, $table_name:literal, auto_increment = $auto_increment:literal $(, $item:item)* $(,)?) => {
+        #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
+        struct $model {
+            id: i64,
+            tenant_id: String,
+            name: String,
+            version: i64,
+        }
+
+        impl $crate::model::Model for $model {}
+
+        impl $crate::model::ModelHooks for $model {
+            type Data = ();
+            type Extension = ();
+        }
+
+        impl $crate::orm::Schema for $model {
+            type PrimaryKey = i64;
+
+            const MODEL_NAME: &'static str = $table_name;
+            const TABLE_NAME: Option<&'static str> = Some($table_name);
+
+            $($item)*
+
+            fn primary_key(&self) -> &Self::PrimaryKey {
+                &self.id
+            }
+
+            fn schema() -> &'static apache_avro::Schema {
+                static SCHEMA: $crate::LazyLock<apache_avro::Schema> =
+                    $crate::LazyLock::new(|| apache_avro::Schema::Null);
+                &SCHEMA
+            }
+
+            fn columns() -> &'static [$crate::model::Column<'static>] {
+                static COLUMNS: $crate::LazyLock<Vec<$crate::model::Column<'static>>> =
+                    $crate::LazyLock::new(|| {
+                        let mut id = $crate::model::Column::new("id", "i64", true);
+                        id.set_extra_attribute("primary_key", true);
+                        if $auto_increment {
+                            id.set_default_value("auto_increment");
+                        }
+                        vec![
+                            id,
+                            $crate::model::Column::new("tenant_id", "String", true),
+                            $crate::model::Column::new("name", "String", true),
+                            $crate::model::Column::new("version", "i64", true),
+                        ]
+                    });
+                &COLUMNS
+            }
+
+            fn fields() -> &'static [&'static str] {
+                &["id", "tenant_id", "name", "version"]
+            }
+
+            fn read_only_fields() -> &'static [&'static str] {
+                &[]
+            }
+
+            fn write_only_fields() -> &'static [&'static str] {
+                &[]
+            }
+
+            async fn acquire_reader(
+            ) -> Result<&'static $crate::orm::ConnectionPool, $crate::error::Error> {
+                Ok(&$crate::orm::fixture::TEST_POOL)
+            }
+
+            async fn acquire_writer(
+            ) -> Result<&'static $crate::orm::ConnectionPool, $crate::error::Error> {
+                Ok(&$crate::orm::fixture::TEST_POOL)
+            }
+        }
+    };
+}
+
+pub(crate) use test_model;
@@ -48,6 +48,24 @@ where
         }
     }
 
+    /// Encrypts the secret for the model, which can be decrypted by
+    /// [`decrypt_secret()`](Self::decrypt_secret).
+    fn encrypt_secret(secret: &str) -> Result<String, Error> {
+        let key = Self::secret_key();
+        crypto::encrypt(secret.as_bytes(), key)
+            .map(base64::encode)
+            .map_err(|err| warn!("fail to encrypt the secret: {}", err.message()))
+    }
+
+    /// Decrypts the secret for the model.
+    fn decrypt_secret(encrypted_secret: &str) -> Result<String, Error> {
+        let key = Self::secret_key();
+        let data = base64::decode(encrypted_secret)?;
+        let secret = crypto::decrypt(&data, key)
+            .map_err(|err| warn!("fail to decrypt the secret: {}", err.message()))?;
+        String::from_utf8(secret).map_err(Error::from)
+    }
+
     /// Translates the model data.
     #[inline]
     fn translate_model(model: &mut Map) {
@@ -1,5 +1,5 @@
 use super::{pool::ConnectionPool, DatabasePool};
-use crate::extension::TomlTableExt;
+use crate::{error::Error, extension::TomlTableExt};
 use std::time::Duration;
 use toml::value::Table;
 
@@ -11,6 +11,9 @@ pub trait PoolManager {
     /// Checks the availability of the connection pool.
     async fn check_availability(&self) -> bool;
 
+    /// Checks the health of the connection pool by pinging the database within the timeout.
+    async fn check_health(&self, timeout: Duration) -> Result<(), Error>;
+
     /// Shuts down the connection pool.
     async fn close(&self);
 }
@@ -86,6 +89,21 @@ impl PoolManager for ConnectionPool<DatabasePool> {
         }
     }
 
+    async fn check_health(&self, timeout: Duration) -> Result<(), Error> {
+        use sqlx::Connection;
+
+        let ping = async {
+            let mut conn = self.pool().acquire().await?;
+            conn.ping().await
+        };
+        match tokio::time::timeout(timeout, ping).await {
+            Ok(result) => result.map_err(Error::from),
+            Err(_) => Err(Error::new(format!(
+                "fail to ping the database within {timeout:?}"
+            ))),
+        }
+    }
+
     async fn close(&self) {
         let name = self.name();
         tracing::warn!("closing the connection pool for the `{name}` service");
@@ -0,0 +1,691 @@
+use super::{
+    column::ColumnExt, query::QueryExt, ConnectionPool, DatabaseConnection, Executor, GlobalPool,
+    Schema,
+};
+use crate::{
+    application::PROJECT_DIR,
+    bail, crypto,
+    datetime::DateTime,
+    encoding::hex,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    model::{DecodeRow, EncodeColumn, Query},
+    state::State,
+    LazyLock, Map,
+};
+use sqlx::Acquire;
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+/// A versioned schema migration.
+///
+/// Migrations are loaded from the files `{version}_{name}.up.sql` and `{version}_{name}.down.sql`
+/// in the migrations directory, and they are applied in the order of versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// Version.
+    version: String,
+    /// Name.
+    name: String,
+    /// SQL statements to apply the migration.
+    up: String,
+    /// SQL statements to revert the migration.
+    down: String,
+}
+
+impl Migration {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(
+        version: impl ToString,
+        name: impl ToString,
+        up: impl ToString,
+        down: impl ToString,
+    ) -> Self {
+        Self {
+            version: version.to_string(),
+            name: name.to_string(),
+            up: up.to_string(),
+            down: down.to_string(),
+        }
+    }
+
+    /// Returns the version.
+    #[inline]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the SQL statements to apply the migration.
+    #[inline]
+    pub fn up_sql(&self) -> &str {
+        &self.up
+    }
+
+    /// Returns the SQL statements to revert the migration.
+    #[inline]
+    pub fn down_sql(&self) -> &str {
+        &self.down
+    }
+
+    /// Returns the checksum of the SQL statements to apply the migration.
+    #[inline]
+    pub fn checksum(&self) -> String {
+        hex::encode(crypto::digest(self.up.trim().as_bytes()))
+    }
+}
+
+/// Versioned schema migrations for a database service.
+///
+/// The applied migrations are recorded in the bookkeeping table `zino_migrations`.
+/// For PostgreSQL and SQLite, each migration is applied or reverted inside of a transaction,
+/// so a failed migration is rolled back completely.
+/// MySQL, MariaDB and TiDB commit DDL statements implicitly, so the statements
+/// executed before a failure are kept while the migration is not recorded.
+/// In this case, the remaining statements should be completed manually.
+///
+/// ```rust,ignore
+/// use zino_core::orm::Migrator;
+///
+/// let migrator = Migrator::new("main")?;
+/// if let Some(migration) = migrator.generate::<User>("sync_user").await? {
+///     migrator.write_migration(&migration)?;
+/// }
+/// migrator.migrate().await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Migrator {
+    /// Connection pool.
+    pool: &'static ConnectionPool,
+    /// Migrations sorted by versions.
+    migrations: Vec<Migration>,
+}
+
+impl Migrator {
+    /// Creates a new instance for the database service,
+    /// and loads the migrations from the migrations directory.
+    pub fn new(name: &str) -> Result<Self, Error> {
+        let Some(pool) = GlobalPool::get(name) else {
+            bail!(
+                "connection to the database service `{}` is unavailable",
+                name
+            );
+        };
+
+        let mut migrator = Self {
+            pool,
+            migrations: Vec::new(),
+        };
+        let entries = match fs::read_dir(MIGRATIONS_DIR.as_path()) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(migrator),
+            Err(err) => return Err(err.into()),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let file = entry?.path();
+            if let Some(file_name) = file.file_name().and_then(|s| s.to_str()) {
+                if let Some((version, name)) = parse_file_name(file_name)? {
+                    let file_stem = file_name.trim_end_matches(".up.sql");
+                    let up = fs::read_to_string(&file)?;
+                    let down_file = file.with_file_name(format!("{file_stem}.down.sql"));
+                    let down = match fs::read_to_string(down_file) {
+                        Ok(down) => down,
+                        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+                        Err(err) => return Err(err.into()),
+                    };
+                    files.push(Migration::new(version, name, up, down));
+                }
+            }
+        }
+        for migration in files {
+            migrator.add_migration(migration)?;
+        }
+        Ok(migrator)
+    }
+
+    /// Adds a migration.
+    pub fn add_migration(&mut self, migration: Migration) -> Result<(), Error> {
+        let version = migration.version();
+        match self
+            .migrations
+            .binary_search_by(|m| m.version().cmp(version))
+        {
+            Ok(_) => bail!("the migration version `{}` is duplicated", version),
+            Err(index) => self.migrations.insert(index, migration),
+        }
+        Ok(())
+    }
+
+    /// Returns a reference to the migrations sorted by versions.
+    #[inline]
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Returns the records of applied migrations sorted by versions.
+    pub async fn applied_migrations(&self) -> Result<Vec<Map>, Error> {
+        let pool = self.pool.pool();
+        let table_name = &*MIGRATIONS_TABLE;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (\n  \
+                version VARCHAR(255) PRIMARY KEY,\n  \
+                name VARCHAR(255) NOT NULL,\n  \
+                checksum VARCHAR(255) NOT NULL,\n  \
+                applied_at VARCHAR(255) NOT NULL\n\
+            );"
+        );
+        pool.execute(&sql).await?;
+
+        let sql = format!(
+            "SELECT version, name, checksum, applied_at FROM {table_name} ORDER BY version;"
+        );
+        let rows = pool.fetch(&sql).await?;
+        let mut data = Vec::with_capacity(rows.len());
+        for row in rows {
+            data.push(Map::decode_row(&row)?);
+        }
+        Ok(data)
+    }
+
+    /// Returns the migrations which have not been applied.
+    pub async fn pending_migrations(&self) -> Result<Vec<&Migration>, Error> {
+        let applied_migrations = self.applied_migrations().await?;
+        let mut migrations = Vec::new();
+        for migration in &self.migrations {
+            let version = migration.version();
+            let record = applied_migrations
+                .iter()
+                .find(|d| d.get_str("version") == Some(version));
+            if let Some(record) = record {
+                if record.get_str("checksum") != Some(migration.checksum().as_str()) {
+                    tracing::warn!(
+                        version,
+                        name = migration.name(),
+                        "the migration `{version}` has been modified after it was applied",
+                    );
+                }
+            } else {
+                migrations.push(migration);
+            }
+        }
+        for record in applied_migrations {
+            if let Some(version) = record.get_str("version") {
+                if !self.migrations.iter().any(|m| m.version() == version) {
+                    tracing::warn!(version, "the applied migration `{version}` is not found");
+                }
+            }
+        }
+        Ok(migrations)
+    }
+
+    /// Applies the pending migrations in the order of versions,
+    /// and returns the number of applied migrations.
+    pub async fn migrate(&self) -> Result<usize, Error> {
+        let table_name = &*MIGRATIONS_TABLE;
+        let migrations = self.pending_migrations().await?;
+        for migration in &migrations {
+            let version = migration.version();
+            let name = migration.name();
+            let mut transaction = self.pool.pool().begin().await?;
+            let connection = transaction.acquire().await?;
+            execute_statements(&mut *connection, migration.up_sql()).await?;
+
+            let placeholders = (1..=4).map(Query::placeholder).collect::<Vec<_>>();
+            let sql = format!(
+                "INSERT INTO {table_name} (version, name, checksum, applied_at) \
+                    VALUES ({});",
+                placeholders.join(", ")
+            );
+            let arguments = [
+                version.to_owned(),
+                name.to_owned(),
+                migration.checksum(),
+                DateTime::now().to_string(),
+            ];
+            connection.execute_with_values(&sql, &arguments).await?;
+            transaction.commit().await?;
+            tracing::warn!(version, name, "the migration `{version}` has been applied");
+        }
+        Ok(migrations.len())
+    }
+
+    /// Reverts the last applied migrations with the number of steps,
+    /// and returns the number of reverted migrations.
+    pub async fn rollback(&self, steps: usize) -> Result<usize, Error> {
+        let table_name = &*MIGRATIONS_TABLE;
+        let applied_migrations = self.applied_migrations().await?;
+        let mut reverted = 0;
+        for record in applied_migrations.iter().rev().take(steps) {
+            let Some(version) = record.get_str("version") else {
+                continue;
+            };
+            let Some(migration) = self.migrations.iter().find(|m| m.version() == version) else {
+                bail!("the migration `{}` to revert is not found", version);
+            };
+            let name = migration.name();
+            let mut transaction = self.pool.pool().begin().await?;
+            let connection = transaction.acquire().await?;
+            execute_statements(&mut *connection, migration.down_sql()).await?;
+
+            let placeholder = Query::placeholder(1);
+            let sql = format!("DELETE FROM {table_name} WHERE version = {placeholder};");
+            connection.execute_with_values(&sql, &[version]).await?;
+            transaction.commit().await?;
+            tracing::warn!(version, name, "the migration `{version}` has been reverted");
+            reverted += 1;
+        }
+        Ok(reverted)
+    }
+
+    /// Generates a migration by comparing the columns of the model with the table schema.
+    /// It returns `None` if the table schema is up to date.
+    ///
+    /// A column can be renamed with the attribute `#[schema(renamed_from = "old_name")]`.
+    /// It returns an error for the changes which can not be expressed for the driver,
+    /// such as altering a column type in SQLite, which requires recreating the table.
+    pub async fn generate<M: Schema>(&self, name: &str) -> Result<Option<Migration>, Error> {
+        let pool = M::init_writer()?;
+        if pool.name() != self.pool.name() {
+            bail!(
+                "the model `{}` belongs to the database service `{}` instead of `{}`",
+                M::model_name(),
+                pool.name(),
+                self.pool.name()
+            );
+        }
+
+        let table_name = M::table_name();
+        let data = fetch_table_columns(self.pool, table_name).await?;
+        let primary_key_name = M::PRIMARY_KEY_NAME;
+        let mut up_statements = Vec::new();
+        let mut down_statements = Vec::new();
+        if data.is_empty() {
+            bail!("the table `{}` does not exist", table_name);
+        }
+
+        let is_mysql = cfg!(any(
+            feature = "orm-mariadb",
+            feature = "orm-mysql",
+            feature = "orm-tidb"
+        ));
+        let is_postgres = cfg!(feature = "orm-postgres");
+        let mut matched_columns = Vec::new();
+        for col in M::columns() {
+            let extra = col.extra();
+            let column_name = extra.get_str("column_name").unwrap_or_else(|| col.name());
+            let column_definition = col.field_definition(primary_key_name);
+            let column_opt = data
+                .iter()
+                .find(|d| get_column_name(d) == Some(column_name))
+                .or_else(|| {
+                    let old_name = extra.get_str("renamed_from")?;
+                    data.iter().find(|d| get_column_name(d) == Some(old_name))
+                });
+            let Some(d) = column_opt else {
+                up_statements.push(format!(
+                    "ALTER TABLE {table_name} ADD COLUMN {column_definition};"
+                ));
+                down_statements.push(format!(
+                    "ALTER TABLE {table_name} DROP COLUMN {column_name};"
+                ));
+                continue;
+            };
+
+            let current_name = get_column_name(d).unwrap_or(column_name);
+            matched_columns.push(current_name);
+            if current_name != column_name {
+                up_statements.push(format!(
+                    "ALTER TABLE {table_name} RENAME COLUMN {current_name} TO {column_name};"
+                ));
+                down_statements.push(format!(
+                    "ALTER TABLE {table_name} RENAME COLUMN {column_name} TO {current_name};"
+                ));
+            }
+
+            let column_type = col.column_type();
+            let data_type = get_data_type(d).unwrap_or_default();
+            let type_changed = !is_compatible_type(column_type, data_type);
+            let nullability_changed =
+                col.is_not_null() != is_not_null(d) && column_name != primary_key_name;
+            if !type_changed && !nullability_changed {
+                continue;
+            }
+            if is_mysql {
+                let current_definition = format!(
+                    "{column_name} {data_type}{}{}",
+                    not_null_suffix(is_not_null(d)),
+                    default_suffix(d)
+                );
+                up_statements.push(format!(
+                    "ALTER TABLE {table_name} MODIFY COLUMN {column_definition};"
+                ));
+                down_statements.push(format!(
+                    "ALTER TABLE {table_name} MODIFY COLUMN {current_definition};"
+                ));
+            } else if is_postgres {
+                if type_changed {
+                    up_statements.push(format!(
+                        "ALTER TABLE {table_name} ALTER COLUMN {column_name} \
+                            TYPE {column_type} USING {column_name}::{column_type};"
+                    ));
+                    down_statements.push(format!(
+                        "ALTER TABLE {table_name} ALTER COLUMN {column_name} \
+                            TYPE {data_type} USING {column_name}::{data_type};"
+                    ));
+                }
+                if nullability_changed {
+                    let (set, unset) = if col.is_not_null() {
+                        ("SET", "DROP")
+                    } else {
+                        ("DROP", "SET")
+                    };
+                    up_statements.push(format!(
+                        "ALTER TABLE {table_name} ALTER COLUMN {column_name} {set} NOT NULL;"
+                    ));
+                    down_statements.push(format!(
+                        "ALTER TABLE {table_name} ALTER COLUMN {column_name} {unset} NOT NULL;"
+                    ));
+                }
+            } else {
+                bail!(
+                    "SQLite can not alter the column `{}` to `{}`, and the table `{}` should be recreated",
+                    column_name,
+                    column_definition,
+                    table_name
+                );
+            }
+        }
+        for d in data.iter() {
+            let Some(column_name) = get_column_name(d) else {
+                continue;
+            };
+            if !matched_columns.contains(&column_name) {
+                let data_type = get_data_type(d).unwrap_or_default();
+                let not_null = not_null_suffix(is_not_null(d));
+                let default = default_suffix(d);
+                up_statements.push(format!(
+                    "ALTER TABLE {table_name} DROP COLUMN {column_name};"
+                ));
+                down_statements.push(format!(
+                    "ALTER TABLE {table_name} ADD COLUMN {column_name} {data_type}{not_null}{default};"
+                ));
+            }
+        }
+        if up_statements.is_empty() {
+            return Ok(None);
+        }
+
+        down_statements.reverse();
+        let version = DateTime::now().format("%Y%m%d%H%M%S");
+        let up = up_statements.join("\n") + "\n";
+        let down = down_statements.join("\n") + "\n";
+        Ok(Some(Migration::new(version, name, up, down)))
+    }
+
+    /// Writes the migration files into the migrations directory.
+    pub fn write_migration(&self, migration: &Migration) -> Result<(), Error> {
+        let dir = MIGRATIONS_DIR.as_path();
+        fs::create_dir_all(dir)?;
+
+        let version = migration.version();
+        let name = migration.name();
+        fs::write(
+            dir.join(format!("{version}_{name}.up.sql")),
+            migration.up_sql(),
+        )?;
+        fs::write(
+            dir.join(format!("{version}_{name}.down.sql")),
+            migration.down_sql(),
+        )?;
+        Ok(())
+    }
+}
+
+/// Parses the version and name from the file name `{version}_{name}.up.sql`.
+/// It returns `None` if the file does not apply a migration.
+fn parse_file_name(file_name: &str) -> Result<Option<(&str, &str)>, Error> {
+    let Some(file_stem) = file_name.strip_suffix(".up.sql") else {
+        return Ok(None);
+    };
+    match file_stem.split_once('_') {
+        Some((version, name)) if !version.is_empty() && !name.is_empty() => {
+            Ok(Some((version, name)))
+        }
+        _ => bail!("invalid migration file name `{}`", file_name),
+    }
+}
+
+/// Fetches the column definitions of a table from `information_schema` or `pragma_table_info`.
+pub(super) async fn fetch_table_columns(
+    connection_pool: &ConnectionPool,
+    table_name: &str,
+) -> Result<Vec<Map>, Error> {
+    let (sql, arguments) = if cfg!(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-tidb"
+    )) {
+        let sql = format!(
+            "SELECT column_name, data_type, column_default, is_nullable \
+                FROM information_schema.columns \
+                    WHERE table_schema = {} AND table_name = {};",
+            Query::placeholder(1),
+            Query::placeholder(2),
+        );
+        (sql, vec![connection_pool.database(), table_name])
+    } else if cfg!(feature = "orm-postgres") {
+        let sql = format!(
+            "SELECT column_name, data_type, column_default, is_nullable \
+                FROM information_schema.columns \
+                    WHERE table_schema = 'public' AND table_name = {};",
+            Query::placeholder(1),
+        );
+        (sql, vec![table_name])
+    } else {
+        let sql = format!(
+            "SELECT p.name AS column_name, p.type AS data_type, \
+                    p.dflt_value AS column_default, p.[notnull] AS is_not_null \
+                FROM sqlite_master m LEFT OUTER JOIN pragma_table_info((m.name)) p
+                    ON m.name <> p.name WHERE m.name = {};",
+            Query::placeholder(1),
+        );
+        (sql, vec![table_name])
+    };
+    let rows = connection_pool
+        .pool()
+        .fetch_with_values(&sql, &arguments)
+        .await?;
+    let mut data = Vec::with_capacity(rows.len());
+    for row in rows {
+        data.push(Map::decode_row(&row)?);
+    }
+    Ok(data)
+}
+
+/// Returns the column name of the column definition.
+#[inline]
+pub(super) fn get_column_name(data: &Map) -> Option<&str> {
+    data.get_str("column_name")
+        .or_else(|| data.get_str("COLUMN_NAME"))
+}
+
+/// Returns the data type of the column definition.
+#[inline]
+pub(super) fn get_data_type(data: &Map) -> Option<&str> {
+    data.get_str("data_type")
+        .or_else(|| data.get_str("DATA_TYPE"))
+}
+
+/// Returns `true` if the column definition has a `NOT NULL` constraint.
+pub(super) fn is_not_null(data: &Map) -> bool {
+    if cfg!(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    )) {
+        data.get_str("is_nullable")
+            .or_else(|| data.get_str("IS_NULLABLE"))
+            .unwrap_or("YES")
+            .eq_ignore_ascii_case("NO")
+    } else {
+        data.get_i64("is_not_null") == Some(1) || data.get_str("is_not_null") == Some("1")
+    }
+}
+
+/// Returns the suffix for a `NOT NULL` constraint.
+#[inline]
+fn not_null_suffix(not_null: bool) -> &'static str {
+    if not_null {
+        " NOT NULL"
+    } else {
+        ""
+    }
+}
+
+/// Returns the suffix for the `DEFAULT` value of the column definition.
+///
+/// MySQL reports the string literals without quotes while MariaDB and PostgreSQL
+/// report the default values as SQL expressions.
+fn default_suffix(data: &Map) -> String {
+    let Some(value) = data
+        .get_str("column_default")
+        .or_else(|| data.get_str("COLUMN_DEFAULT"))
+    else {
+        return String::new();
+    };
+    let is_mysql = cfg!(any(feature = "orm-mysql", feature = "orm-tidb"));
+    let is_expression = value.starts_with('\'')
+        || value.contains('(')
+        || value.parse::<f64>().is_ok()
+        || value.eq_ignore_ascii_case("NULL")
+        || value.to_ascii_uppercase().starts_with("CURRENT_");
+    if is_mysql && !is_expression {
+        format!(" DEFAULT '{}'", value.replace('\'', "''"))
+    } else {
+        format!(" DEFAULT {value}")
+    }
+}
+
+/// Returns `true` if the column type is compatible with the data type reported by the database.
+fn is_compatible_type(column_type: &str, data_type: &str) -> bool {
+    let column_type = canonical_type(column_type);
+    let data_type = canonical_type(data_type);
+    if column_type == "array" || data_type == "array" || data_type == "user-defined" {
+        // The element types of arrays and user-defined types are not reported.
+        return true;
+    }
+    column_type == data_type
+}
+
+/// Returns the canonical name of a data type to compare the aliases.
+fn canonical_type(data_type: &str) -> String {
+    let data_type = data_type.trim().to_ascii_lowercase();
+    if data_type.ends_with("[]") || data_type == "array" {
+        return "array".to_owned();
+    }
+
+    let base_type = data_type
+        .split_once('(')
+        .map(|(s, _)| s)
+        .unwrap_or(&data_type)
+        .trim_end_matches(" unsigned")
+        .trim();
+    let canonical_type = match base_type {
+        "int" | "int4" | "integer" | "serial" | "serial4" => "integer",
+        "int8" | "bigint" | "bigserial" | "serial8" => "bigint",
+        "int2" | "smallint" | "smallserial" | "serial2" => "smallint",
+        "bool" | "boolean"
+            if cfg!(any(
+                feature = "orm-mariadb",
+                feature = "orm-mysql",
+                feature = "orm-tidb"
+            )) =>
+        {
+            "tinyint"
+        }
+        "bool" | "boolean" => "boolean",
+        "float4" | "real" => "real",
+        "float8" | "double" | "double precision" => "double",
+        "decimal" | "numeric" => "numeric",
+        "character varying" | "varchar" => "varchar",
+        "character" | "char" => "char",
+        "timestamp with time zone" | "timestamptz" => "timestamptz",
+        "timestamp without time zone" | "timestamp" => "timestamp",
+        "time with time zone" | "timetz" => "timetz",
+        "time without time zone" | "time" => "time",
+        _ => base_type,
+    };
+    canonical_type.to_owned()
+}
+
+/// Executes the SQL statements of a migration.
+async fn execute_statements(connection: &mut DatabaseConnection, sql: &str) -> Result<(), Error> {
+    if sql.trim().is_empty() {
+        return Ok(());
+    }
+    // A query without arguments is executed by the simple query protocol,
+    // which allows multiple statements.
+    sqlx::Executor::execute(connection, sql).await?;
+    Ok(())
+}
+
+/// Migrations directory.
+static MIGRATIONS_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    let dir = State::shared()
+        .get_config("database")
+        .and_then(|config| config.get_str("migrations-dir"))
+        .unwrap_or("./migrations");
+    PROJECT_DIR.join(dir)
+});
+
+/// Migrations table.
+static MIGRATIONS_TABLE: LazyLock<String> =
+    LazyLock::new(|| [*super::TABLE_PREFIX, "zino_migrations"].concat());
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_type, is_compatible_type, parse_file_name};
+
+    #[test]
+    fn it_canonicalizes_data_types() {
+        assert_eq!(canonical_type("INT4"), "integer");
+        assert_eq!(canonical_type("bigserial"), "bigint");
+        assert_eq!(canonical_type("character varying(255)"), "varchar");
+        assert_eq!(canonical_type("int(10) unsigned"), "integer");
+        assert_eq!(canonical_type("timestamp with time zone"), "timestamptz");
+        assert_eq!(canonical_type("TEXT[]"), "array");
+        assert_eq!(canonical_type("jsonb"), "jsonb");
+    }
+
+    #[test]
+    fn it_checks_compatible_types() {
+        assert!(is_compatible_type("BIGINT", "int8"));
+        assert!(is_compatible_type("VARCHAR(255)", "character varying"));
+        assert!(is_compatible_type("TEXT[]", "ARRAY"));
+        assert!(is_compatible_type("UUID", "USER-DEFINED"));
+        assert!(!is_compatible_type("BIGINT", "integer"));
+        assert!(!is_compatible_type("TEXT", "varchar"));
+    }
+
+    #[test]
+    fn it_parses_migration_file_names() {
+        assert_eq!(
+            parse_file_name("20240101120000_create_user.up.sql").unwrap(),
+            Some(("20240101120000", "create_user"))
+        );
+        assert_eq!(
+            parse_file_name("20240101120000_create_user.down.sql").unwrap(),
+            None
+        );
+        assert_eq!(parse_file_name("README.md").unwrap(), None);
+        assert!(parse_file_name("20240101120000.up.sql").is_err());
+        assert!(parse_file_name("_create_user.up.sql").is_err());
+    }
+}
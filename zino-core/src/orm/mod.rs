@@ -12,6 +12,64 @@
 //! | `orm-sqlite`   | Enables the SQLite database driver.                  | No       |
 //! | `orm-tidb`     | Enables the TiDB database driver.                    | No       |
 //!
+//! # Schema migrations
+//!
+//! Versioned migrations are loaded from the files `{version}_{name}.up.sql`
+//! and `{version}_{name}.down.sql` in the `migrations` directory,
+//! which can be configured by `migrations-dir` in the `[database]` table.
+//! [`Migrator`] applies or reverts them inside of transactions for PostgreSQL and SQLite,
+//! while MySQL, MariaDB and TiDB commit DDL statements implicitly.
+//! It also generates new migrations by comparing [`Schema::columns()`] with the table schema.
+//!
+//! # Transactions
+//!
+//! The CRUD methods of [`Schema`] have the `*_in` variants which accept an [`Executor`],
+//! such as a [`TransactionHandle`] returned by [`Transaction::begin()`].
+//! A transaction handle supports nested savepoints and an explicit rollback.
+//!
+//! # Read replicas
+//!
+//! Database services configured with the same `name` form a [`PoolGroup`],
+//! such as several replicas behind the `READER_NAME` of a model.
+//! One of the available pools is selected by the `load-balancing` strategy,
+//! which can be `round-robin` or `least-connections`. The health of the pools is checked
+//! periodically by [`GlobalPool::run_health_checks()`] according to the `[database.health-check]`
+//! table, and a pool is ejected after `max-failures` consecutive failures
+//! until it passes a health check again. If `read-your-writes` is enabled,
+//! the reads are pinned to the model writer for the rest of a request
+//! once a write has been committed successfully. With the `metrics` feature,
+//! the pool stats, selections, misses, ejections and writer pins are exported.
+//!
+//! ```toml
+//! [database]
+//! load-balancing = "least-connections"
+//! read-your-writes = true
+//!
+//! [database.health-check]
+//! interval = "30s"
+//! timeout = "5s"
+//! max-failures = 3
+//! ```
+//!
+//! # Multi-tenancy
+//!
+//! A model with a `#[schema(tenant_key)]` field is isolated by the tenant
+//! of the current [`TenantContext`]. The query filters of [`Schema`] methods are restricted
+//! to the tenant, and the inserted models are stamped with it. Accessing the model
+//! without a tenant is an error unless it runs in [`TenantContext::scope_superuser()`].
+//! The raw SQL executed by [`Schema::query()`] and [`Schema::execute()`] is not rewritten.
+//!
+//! # Audit trail
+//!
+//! The changes of a model with the `#[schema(audit)]` attribute are recorded
+//! by the [`AuditRecorder`] registered with [`AuditTrail::register_recorder()`].
+//! Each [`AuditEntry`] contains the user and request ID of the current [`AuditContext`],
+//! the snapshots before and after the change, and a field-level diff.
+//! The snapshots are read and the entries are written with the same executor as the change,
+//! so that they are committed or rolled back together in a transaction,
+//! and the history of a model can be queried by [`Schema::audit_history()`].
+//! A failure of the recorder is returned, which should be used to roll back the transaction.
+//!
 //! # Design references
 //!
 //! The design of our ORM is inspired by [`Mongoose`], [`Prisma`], [`TypeORM`] and [`PostgREST`].
@@ -108,37 +166,68 @@
 //! [`TypeORM`]: https://typeorm.io/
 //! [`PostgREST`]: https://postgrest.org/
 
-use crate::{extension::TomlTableExt, state::State, LazyLock};
+use crate::{extension::TomlTableExt, model::Query, state::State, LazyLock, SharedString};
+use query::QueryExt;
 use smallvec::SmallVec;
-use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::{
+    cell::Cell,
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
 
 mod accessor;
+mod audit;
 mod column;
 mod executor;
 mod helper;
 mod manager;
 mod mutation;
 mod pool;
+mod pool_group;
 mod query;
 mod schema;
+mod tenant;
 mod transaction;
 
 pub use accessor::ModelAccessor;
+pub use audit::{AuditContext, AuditEntry, AuditRecorder, AuditTrail};
 pub use executor::Executor;
 pub use helper::ModelHelper;
 pub use manager::PoolManager;
 pub use pool::ConnectionPool;
+pub use pool_group::{LoadBalancing, PoolGroup};
 pub use schema::Schema;
+pub use tenant::{TenantContext, TenantScope};
 pub use transaction::Transaction;
 
+#[cfg(feature = "orm-sqlx")]
+pub use executor::ReborrowedExecutor;
+#[cfg(feature = "orm-sqlx")]
+pub use transaction::TransactionHandle;
+
 #[cfg(feature = "orm-sqlx")]
 mod decode;
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+pub(crate) mod fixture;
+#[cfg(feature = "orm-sqlx")]
+mod migration;
 #[cfg(feature = "orm-sqlx")]
 mod scalar;
 
 #[cfg(feature = "orm-sqlx")]
 pub use decode::{decode, decode_array, decode_decimal, decode_uuid};
 #[cfg(feature = "orm-sqlx")]
+pub use migration::{Migration, Migrator};
+#[cfg(feature = "orm-sqlx")]
 pub use scalar::ScalarQuery;
 
 cfg_if::cfg_if! {
@@ -163,6 +252,9 @@ cfg_if::cfg_if! {
         /// MySQL database connection.
         pub type DatabaseConnection = sqlx::MySqlConnection;
 
+        /// MySQL query result.
+        pub type DatabaseQueryResult = sqlx::mysql::MySqlQueryResult;
+
         /// A single row from the MySQL database.
         pub type DatabaseRow = sqlx::mysql::MySqlRow;
     } else if #[cfg(feature = "orm-postgres")] {
@@ -180,6 +272,9 @@ cfg_if::cfg_if! {
         /// PostgreSQL database connection.
         pub type DatabaseConnection = sqlx::PgConnection;
 
+        /// PostgreSQL query result.
+        pub type DatabaseQueryResult = sqlx::postgres::PgQueryResult;
+
         /// A single row from the PostgreSQL database.
         pub type DatabaseRow = sqlx::postgres::PgRow;
     } else {
@@ -197,27 +292,35 @@ cfg_if::cfg_if! {
         /// SQLite database connection.
         pub type DatabaseConnection = sqlx::SqliteConnection;
 
+        /// SQLite query result.
+        pub type DatabaseQueryResult = sqlx::sqlite::SqliteQueryResult;
+
         /// A single row from the SQLite database.
         pub type DatabaseRow = sqlx::sqlite::SqliteRow;
     }
 }
 
-/// A list of database connection pools.
+/// A list of database connection pool groups.
 #[derive(Debug)]
-struct ConnectionPools(SmallVec<[ConnectionPool; 4]>);
+struct ConnectionPools(SmallVec<[PoolGroup; 4]>);
 
 impl ConnectionPools {
+    /// Returns a connection pool group with the specific name.
+    #[inline]
+    pub(crate) fn get_group(&self, name: &str) -> Option<&PoolGroup> {
+        self.0.iter().find(|group| group.name() == name)
+    }
+
     /// Returns a connection pool with the specific name.
+    #[inline]
     pub(crate) fn get_pool(&self, name: &str) -> Option<&ConnectionPool> {
-        let mut pool = None;
-        for cp in self.0.iter().filter(|cp| cp.name() == name) {
-            if cp.is_available() {
-                return Some(cp);
-            } else {
-                pool = Some(cp);
-            }
-        }
-        pool
+        self.get_group(name).and_then(|group| group.select())
+    }
+
+    /// Returns an iterator over all the connection pools.
+    #[inline]
+    pub(crate) fn iter_pools(&self) -> impl Iterator<Item = &ConnectionPool> {
+        self.0.iter().flat_map(|group| group.pools())
     }
 }
 
@@ -227,16 +330,24 @@ pub struct GlobalPool;
 
 impl GlobalPool {
     /// Gets the connection pool for the specific service.
+    /// If there are several pools with the same name, one of the available pools
+    /// is selected by the load balancing strategy.
     #[inline]
     pub fn get(name: &str) -> Option<&'static ConnectionPool> {
         SHARED_CONNECTION_POOLS.get_pool(name)
     }
 
+    /// Gets the connection pool group for the specific service.
+    #[inline]
+    pub fn get_group(name: &str) -> Option<&'static PoolGroup> {
+        SHARED_CONNECTION_POOLS.get_group(name)
+    }
+
     /// Iterates over the shared connection pools and
     /// attempts to establish a database connection for each of them.
     #[inline]
     pub async fn connect_all() {
-        for cp in SHARED_CONNECTION_POOLS.0.iter() {
+        for cp in SHARED_CONNECTION_POOLS.iter_pools() {
             cp.check_availability().await;
         }
     }
@@ -244,10 +355,74 @@ impl GlobalPool {
     /// Shuts down the shared connection pools to ensure all connections are gracefully closed.
     #[inline]
     pub async fn close_all() {
-        for cp in SHARED_CONNECTION_POOLS.0.iter() {
+        for cp in SHARED_CONNECTION_POOLS.iter_pools() {
             cp.close().await;
         }
     }
+
+    /// Checks the health of the shared connection pools periodically.
+    /// It is configured by the `[database.health-check]` table
+    /// and should be spawned as a background task.
+    pub async fn run_health_checks() {
+        let groups = &SHARED_CONNECTION_POOLS.0;
+        if groups.is_empty() {
+            return;
+        }
+
+        let config = State::shared()
+            .get_config("database")
+            .and_then(|config| config.get_table("health-check"));
+        let interval = config
+            .and_then(|config| config.get_duration("interval"))
+            .unwrap_or_else(|| Duration::from_secs(30));
+        let timeout = config
+            .and_then(|config| config.get_duration("timeout"))
+            .unwrap_or_else(|| Duration::from_secs(5));
+        let max_failures = config
+            .and_then(|config| config.get_usize("max-failures"))
+            .unwrap_or(3);
+        loop {
+            tokio::time::sleep(interval).await;
+            for group in groups.iter() {
+                group.check_health(timeout, max_failures).await;
+            }
+        }
+    }
+
+    /// Runs the future in a scope where the reads are pinned to the model writer
+    /// after a write has been performed, if the `read-your-writes` is enabled
+    /// in the `[database]` table. It is used to wrap the handling of a request.
+    #[inline]
+    pub async fn scope_read_your_writes<F: Future>(future: F) -> F::Output {
+        if *READ_YOUR_WRITES {
+            WRITER_PINNED.scope(Cell::new(false), future).await
+        } else {
+            future.await
+        }
+    }
+
+    /// Pins the reads to the model writer for the rest of the current scope.
+    /// It should be called after a write has been committed successfully.
+    pub fn pin_writer() {
+        if let Ok(false) = WRITER_PINNED.try_with(|pinned| pinned.replace(true)) {
+            #[cfg(feature = "metrics")]
+            metrics::counter!("zino_db_writer_pins_total").increment(1);
+        }
+    }
+
+    /// Returns `true` if the reads are pinned to the model writer in the current scope.
+    #[inline]
+    pub fn is_writer_pinned() -> bool {
+        WRITER_PINNED
+            .try_with(|pinned| pinned.get())
+            .unwrap_or(false)
+    }
+}
+
+/// Returns a placeholder for the n-th parameter of a SQL query.
+#[inline]
+pub(crate) fn placeholder(n: usize) -> SharedString {
+    <Query as QueryExt<DatabaseDriver>>::placeholder(n)
 }
 
 /// Shared connection pools.
@@ -266,11 +441,26 @@ static SHARED_CONNECTION_POOLS: LazyLock<ConnectionPools> = LazyLock::new(|| {
                 please use `[[{database_type}]]` to configure a list of database services"
         )
     });
-    let pools = databases
-        .iter()
-        .filter_map(|v| v.as_table())
-        .map(ConnectionPool::with_config)
-        .collect();
+    let default_load_balancing = database_config
+        .get_str("load-balancing")
+        .and_then(LoadBalancing::parse)
+        .unwrap_or_default();
+    let mut groups = SmallVec::<[PoolGroup; 4]>::new();
+    for config in databases.iter().filter_map(|v| v.as_table()) {
+        let cp = ConnectionPool::with_config(config);
+        let name = cp.name();
+        if let Some(group) = groups.iter_mut().find(|group| group.name() == name) {
+            group.add_pool(cp);
+        } else {
+            let load_balancing = config
+                .get_str("load-balancing")
+                .and_then(LoadBalancing::parse)
+                .unwrap_or(default_load_balancing);
+            let mut group = PoolGroup::new(name, load_balancing);
+            group.add_pool(cp);
+            groups.push(group);
+        }
+    }
     if database_type == driver {
         tracing::warn!(driver, "connect to database services lazily");
     } else {
@@ -279,9 +469,22 @@ static SHARED_CONNECTION_POOLS: LazyLock<ConnectionPools> = LazyLock::new(|| {
             "invalid database type `{database_type}` for the driver `{driver}`"
         );
     }
-    ConnectionPools(pools)
+    ConnectionPools(groups)
 });
 
+/// A flag to indicate whether the reads are pinned to the model writer after a write.
+static READ_YOUR_WRITES: LazyLock<bool> = LazyLock::new(|| {
+    State::shared()
+        .get_config("database")
+        .and_then(|config| config.get_bool("read-your-writes"))
+        .unwrap_or(false)
+});
+
+tokio::task_local! {
+    /// A flag to indicate whether the reads are pinned to the model writer in the current scope.
+    static WRITER_PINNED: Cell<bool>;
+}
+
 /// Database namespace prefix.
 static NAMESPACE_PREFIX: LazyLock<&'static str> = LazyLock::new(|| {
     State::shared()
@@ -1,15 +1,16 @@
 /// Generates SQL `SET` expressions.
 use super::{query::QueryExt, DatabaseDriver, Schema};
-use crate::model::{EncodeColumn, Mutation, Query};
+use crate::model::{EncodeColumn, Mutation, Query, QueryArguments};
 
 /// Extension trait for [`Mutation`](crate::model::Mutation).
 pub(super) trait MutationExt<DB> {
-    /// Formats the updates to generate SQL `SET` expression.
-    fn format_updates<M: Schema>(&self) -> String;
+    /// Formats the updates to generate SQL `SET` expression,
+    /// and the values are bound as arguments.
+    fn format_updates<M: Schema>(&self, arguments: &mut QueryArguments) -> String;
 }
 
 impl MutationExt<DatabaseDriver> for Mutation {
-    fn format_updates<M: Schema>(&self) -> String {
+    fn format_updates<M: Schema>(&self, arguments: &mut QueryArguments) -> String {
         let updates = self.updates();
         if updates.is_empty() {
             return String::new();
@@ -26,7 +27,7 @@ impl MutationExt<DatabaseDriver> for Mutation {
                             if permissive || fields.contains(key) {
                                 if let Some(col) = M::get_writable_column(key) {
                                     let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
+                                    let value = col.encode_value(Some(value), arguments);
                                     let mutation = format!(r#"{key} = {value} + {key}"#);
                                     mutations.push(mutation);
                                 }
@@ -40,7 +41,7 @@ impl MutationExt<DatabaseDriver> for Mutation {
                             if permissive || fields.contains(key) {
                                 if let Some(col) = M::get_writable_column(key) {
                                     let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
+                                    let value = col.encode_value(Some(value), arguments);
                                     let mutation = format!(r#"{key} = {value} * {key}"#);
                                     mutations.push(mutation);
                                 }
@@ -54,7 +55,7 @@ impl MutationExt<DatabaseDriver> for Mutation {
                             if permissive || fields.contains(key) {
                                 if let Some(col) = M::get_writable_column(key) {
                                     let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
+                                    let value = col.encode_value(Some(value), arguments);
                                     let mutation = if cfg!(feature = "orm-sqlite") {
                                         format!(r#"{key} = MIN({value}, {key})"#)
                                     } else {
@@ -72,7 +73,7 @@ impl MutationExt<DatabaseDriver> for Mutation {
                             if permissive || fields.contains(key) {
                                 if let Some(col) = M::get_writable_column(key) {
                                     let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
+                                    let value = col.encode_value(Some(value), arguments);
                                     let mutation = if cfg!(feature = "orm-sqlite") {
                                         format!(r#"{key} = MAX({value}, {key})"#)
                                     } else {
@@ -88,7 +89,7 @@ impl MutationExt<DatabaseDriver> for Mutation {
                     if permissive || fields.contains(key) {
                         if let Some(col) = M::get_writable_column(key) {
                             let key = Query::format_field(key);
-                            let value = col.encode_value(Some(value));
+                            let value = col.encode_value(Some(value), arguments);
                             let mutation = format!(r#"{key} = {value}"#);
                             mutations.push(mutation);
                         }
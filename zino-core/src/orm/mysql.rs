@@ -3,7 +3,7 @@ use crate::{
     datetime::{Date, DateTime, Time},
     error::Error,
     extension::{JsonObjectExt, JsonValueExt},
-    model::{Column, DecodeRow, EncodeColumn, Query},
+    model::{Column, DecodeRow, EncodeColumn, Query, QueryArguments},
     AvroValue, JsonValue, Map, Record, SharedString, Uuid,
 };
 use chrono::NaiveDateTime;
@@ -49,7 +49,11 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
         }
     }
 
-    fn encode_value<'a>(&self, value: Option<&'a JsonValue>) -> Cow<'a, str> {
+    fn encode_value<'a>(
+        &self,
+        value: Option<&'a JsonValue>,
+        arguments: &mut QueryArguments,
+    ) -> Cow<'a, str> {
         if let Some(value) = value {
             match value {
                 JsonValue::Null => "NULL".into(),
@@ -57,11 +61,11 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                     let value = if *value { "TRUE" } else { "FALSE" };
                     value.into()
                 }
-                JsonValue::Number(value) => value.to_string().into(),
+                JsonValue::Number(value) => Query::bind_value(value.clone(), arguments).into(),
                 JsonValue::String(value) => {
                     if value.is_empty() {
                         if let Some(value) = self.default_value() {
-                            self.format_value(value).into_owned().into()
+                            self.format_value(value, arguments).into_owned().into()
                         } else {
                             "''".into()
                         }
@@ -70,20 +74,20 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                     } else if value == "not_null" {
                         "NOT NULL".into()
                     } else {
-                        self.format_value(value)
+                        self.format_value(value, arguments)
                     }
                 }
                 JsonValue::Array(value) => {
                     let values = value
                         .iter()
                         .map(|v| match v {
-                            JsonValue::String(v) => Query::escape_string(v),
-                            _ => self.encode_value(Some(v)).into_owned(),
+                            JsonValue::String(v) => Query::bind_string(v, arguments),
+                            _ => self.encode_value(Some(v), arguments).into_owned(),
                         })
                         .collect::<Vec<_>>();
                     format!(r#"json_array({})"#, values.join(",")).into()
                 }
-                JsonValue::Object(_) => Query::escape_string(value).into(),
+                JsonValue::Object(_) => Query::bind_string(value, arguments).into(),
             }
         } else if self.default_value().is_some() {
             "DEFAULT".into()
@@ -92,29 +96,36 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
         }
     }
 
-    fn format_value<'a>(&self, value: &'a str) -> Cow<'a, str> {
+    fn format_value<'a>(&self, value: &'a str, arguments: &mut QueryArguments) -> Cow<'a, str> {
         match self.type_name() {
             "bool" => {
                 let value = if value == "true" { "TRUE" } else { "FALSE" };
                 value.into()
             }
             "u64" | "u32" | "u16" | "u8" | "usize" | "Option<u64>" | "Option<u32>" => {
-                if value.parse::<u64>().is_ok() {
-                    value.into()
+                if let Ok(value) = value.parse::<u64>() {
+                    Query::bind_value(value, arguments).into()
                 } else {
                     "NULL".into()
                 }
             }
             "i64" | "i32" | "i16" | "i8" | "isize" | "Option<i64>" | "Option<i32>" => {
-                if value.parse::<i64>().is_ok() {
-                    value.into()
+                if let Ok(value) = value.parse::<i64>() {
+                    Query::bind_value(value, arguments).into()
                 } else {
                     "NULL".into()
                 }
             }
-            "f64" | "f32" | "Decimal" => {
+            "f64" | "f32" => {
+                if let Ok(value) = value.parse::<f64>() {
+                    Query::bind_value(value, arguments).into()
+                } else {
+                    "NULL".into()
+                }
+            }
+            "Decimal" => {
                 if value.parse::<f64>().is_ok() {
-                    value.into()
+                    Query::bind_string(value, arguments).into()
                 } else {
                     "NULL".into()
                 }
@@ -125,43 +136,48 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                 "today" => "curdate()".into(),
                 "tomorrow" => "curdate() + INTERVAL 1 DAY".into(),
                 "yesterday" => "curdate() - INTERVAL 1 DAY".into(),
-                _ => Query::escape_string(value).into(),
+                _ => Query::bind_string(value, arguments).into(),
             },
             "Date" | "NaiveDate" => match value {
                 "epoch" => "'1970-01-01'".into(),
                 "today" => "curdate()".into(),
                 "tomorrow" => "curdate() + INTERVAL 1 DAY".into(),
                 "yesterday" => "curdate() - INTERVAL 1 DAY".into(),
-                _ => Query::escape_string(value).into(),
+                _ => Query::bind_string(value, arguments).into(),
             },
             "Time" | "NaiveTime" => match value {
                 "now" => "curtime()".into(),
                 "midnight" => "'00:00:00'".into(),
-                _ => Query::escape_string(value).into(),
+                _ => Query::bind_string(value, arguments).into(),
             },
-            "Vec<u8>" => format!("'{value}'").into(),
+            "Vec<u8>" => Query::bind_string(value, arguments).into(),
             "Vec<String>" | "Vec<Uuid>" | "Vec<u64>" | "Vec<i64>" | "Vec<u32>" | "Vec<i32>" => {
                 if value.contains(',') {
                     let values = value
                         .split(',')
-                        .map(Query::escape_string)
+                        .map(|s| Query::bind_string(s, arguments))
                         .collect::<Vec<_>>();
                     format!(r#"json_array({})"#, values.join(",")).into()
                 } else {
-                    let value = Query::escape_string(value);
+                    let value = Query::bind_string(value, arguments);
                     format!(r#"json_array({value})"#).into()
                 }
             }
-            _ => Query::escape_string(value).into(),
+            _ => Query::bind_string(value, arguments).into(),
         }
     }
 
-    fn format_filter(&self, field: &str, value: &JsonValue) -> String {
+    fn format_filter(
+        &self,
+        field: &str,
+        value: &JsonValue,
+        arguments: &mut QueryArguments,
+    ) -> String {
         let type_name = self.type_name();
         let field = Query::format_field(field);
         if let Some(filter) = value.as_object() {
             if type_name == "Map" {
-                let value = self.encode_value(Some(value));
+                let value = self.encode_value(Some(value), arguments);
                 return format!(r#"json_contains({field}, {value})"#);
             } else {
                 let mut conditions = Vec::with_capacity(filter.len());
@@ -197,7 +213,7 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                             } else {
                                 let value = values
                                     .iter()
-                                    .map(|v| self.encode_value(Some(v)))
+                                    .map(|v| self.encode_value(Some(v), arguments))
                                     .collect::<Vec<_>>()
                                     .join(", ");
                                 let condition = format!(r#"{field} {operator} ({value})"#);
@@ -207,8 +223,8 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                     } else if operator == "BETWEEN" {
                         if let Some(values) = value.parse_str_array() {
                             if let [min_value, max_value] = values.as_slice() {
-                                let min_value = self.format_value(min_value);
-                                let max_value = self.format_value(max_value);
+                                let min_value = self.format_value(min_value, arguments);
+                                let max_value = self.format_value(max_value, arguments);
                                 let condition =
                                     format!(r#"({field} BETWEEN {min_value} AND {max_value})"#);
                                 conditions.push(condition);
@@ -220,7 +236,7 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                             conditions.push(condition);
                         }
                     } else {
-                        let value = self.encode_value(Some(value));
+                        let value = self.encode_value(Some(value), arguments);
                         let condition = format!(r#"{field} {operator} {value}"#);
                         conditions.push(condition);
                     }
@@ -232,16 +248,16 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                 }
             }
         } else if let Some([min_value, max_value]) = value.as_array().map(|v| v.as_slice()) {
-            let min_value = self.encode_value(Some(min_value));
-            let max_value = self.encode_value(Some(max_value));
+            let min_value = self.encode_value(Some(min_value), arguments);
+            let max_value = self.encode_value(Some(max_value), arguments);
             return format!(r#"{field} >= {min_value} AND {field} < {max_value}"#);
         } else if let Some((min_value, max_value)) = value
             .as_str()
             .and_then(|value| value.split_once(','))
             .filter(|_| self.is_datetime_type())
         {
-            let min_value = self.format_value(min_value);
-            let max_value = self.format_value(max_value);
+            let min_value = self.format_value(min_value, arguments);
+            let max_value = self.format_value(max_value, arguments);
             return format!(r#"{field} >= {min_value} AND {field} < {max_value}"#);
         } else if value.is_null() {
             return format!(r#"{field} IS NULL"#);
@@ -249,7 +265,7 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
 
         match type_name {
             "bool" => {
-                let value = self.encode_value(Some(value));
+                let value = self.encode_value(Some(value), arguments);
                 if value == "TRUE" {
                     format!(r#"{field} IS TRUE"#)
                 } else {
@@ -266,14 +282,18 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                     } else if value == "nonzero" {
                         format!(r#"{field} <> 0"#)
                     } else if value.contains(',') {
-                        let value = value.split(',').collect::<Vec<_>>().join(",");
+                        let value = value
+                            .split(',')
+                            .map(|s| self.format_value(s, arguments))
+                            .collect::<Vec<_>>()
+                            .join(", ");
                         format!(r#"{field} IN ({value})"#)
                     } else {
-                        let value = self.format_value(value);
+                        let value = self.format_value(value, arguments);
                         format!(r#"{field} = {value}"#)
                     }
                 } else {
-                    let value = self.encode_value(Some(value));
+                    let value = self.encode_value(Some(value), arguments);
                     format!(r#"{field} = {value}"#)
                 }
             }
@@ -289,35 +309,35 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                             value
                                 .split(',')
                                 .map(|s| {
-                                    let value = Query::escape_string(s);
+                                    let value = Query::bind_string(s, arguments);
                                     format!(r#"{field} RLIKE {value}"#)
                                 })
                                 .collect::<Vec<_>>()
                                 .join(" OR ")
                         } else {
-                            let value = Query::escape_string(value);
+                            let value = Query::bind_string(value, arguments);
                             format!(r#"{field} RLIKE {value}"#)
                         }
                     } else if value.contains(',') {
                         let value = value
                             .split(',')
-                            .map(Query::escape_string)
+                            .map(|s| Query::bind_string(s, arguments))
                             .collect::<Vec<_>>()
                             .join(", ");
                         format!(r#"{field} IN ({value})"#)
                     } else {
-                        let value = Query::escape_string(value);
+                        let value = Query::bind_string(value, arguments);
                         format!(r#"{field} = {value}"#)
                     }
                 } else {
-                    let value = self.encode_value(Some(value));
+                    let value = self.encode_value(Some(value), arguments);
                     format!(r#"{field} = {value}"#)
                 }
             }
             "DateTime" | "NaiveDateTime" => {
                 if let Some(value) = value.as_str() {
                     let length = value.len();
-                    let value = self.format_value(value);
+                    let value = self.format_value(value, arguments);
                     match length {
                         4 => format!(r#"date_format({field}, '%Y') = {value}"#),
                         7 => format!(r#"date_format({field}, '%Y-%m') = {value}"#),
@@ -325,28 +345,28 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                         _ => format!(r#"{field} = {value}"#),
                     }
                 } else {
-                    let value = self.encode_value(Some(value));
+                    let value = self.encode_value(Some(value), arguments);
                     format!(r#"{field} = {value}"#)
                 }
             }
             "Date" | "NaiveDate" => {
                 if let Some(value) = value.as_str() {
                     let length = value.len();
-                    let value = self.format_value(value);
+                    let value = self.format_value(value, arguments);
                     match length {
                         4 => format!(r#"date_format({field}, '%Y') = {value}"#),
                         7 => format!(r#"date_format({field}, '%Y-%m') = {value}"#),
                         _ => format!(r#"{field} = {value}"#),
                     }
                 } else {
-                    let value = self.encode_value(Some(value));
+                    let value = self.encode_value(Some(value), arguments);
                     format!(r#"{field} = {value}"#)
                 }
             }
             "Time" | "NaiveTime" => {
                 if let Some(value) = value.as_str() {
                     let length = value.len();
-                    let value = self.format_value(value);
+                    let value = self.format_value(value, arguments);
                     match length {
                         2 => format!(r#"date_format({field}, '%H') = {value}"#),
                         5 => format!(r#"date_format({field}, '%H:%i') = {value}"#),
@@ -354,7 +374,7 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                         _ => format!(r#"{field} = {value}"#),
                     }
                 } else {
-                    let value = self.encode_value(Some(value));
+                    let value = self.encode_value(Some(value), arguments);
                     format!(r#"{field} = {value}"#)
                 }
             }
@@ -367,16 +387,16 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                     } else if value.contains(',') {
                         let value = value
                             .split(',')
-                            .map(Query::escape_string)
+                            .map(|s| Query::bind_string(s, arguments))
                             .collect::<Vec<_>>()
                             .join(", ");
                         format!(r#"{field} IN ({value})"#)
                     } else {
-                        let value = Query::escape_string(value);
+                        let value = Query::bind_string(value, arguments);
                         format!(r#"{field} = {value}"#)
                     }
                 } else {
-                    let value = self.encode_value(Some(value));
+                    let value = self.encode_value(Some(value), arguments);
                     format!(r#"{field} = {value}"#)
                 }
             }
@@ -389,26 +409,26 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                             .split(',')
                             .map(|v| {
                                 let s = v.replace(';', ",");
-                                let value = self.format_value(&s);
+                                let value = self.format_value(&s, arguments);
                                 format!(r#"json_contains({field}, {value})"#)
                             })
                             .collect::<Vec<_>>()
                             .join(" OR ")
                     } else {
-                        let value = self.format_value(value);
+                        let value = self.format_value(value, arguments);
                         format!(r#"json_overlaps({field}, {value})"#)
                     }
                 } else {
-                    let value = self.encode_value(Some(value));
+                    let value = self.encode_value(Some(value), arguments);
                     format!(r#"json_overlaps({field}, {value})"#)
                 }
             }
             "Map" => {
-                let value = self.encode_value(Some(value));
+                let value = self.encode_value(Some(value), arguments);
                 format!(r#"json_contains({field}, {value})"#)
             }
             _ => {
-                let value = self.encode_value(Some(value));
+                let value = self.encode_value(Some(value), arguments);
                 format!(r#"{field} = {value}"#)
             }
         }
@@ -583,6 +603,11 @@ impl QueryExt<DatabaseDriver> for Query {
         self.sort_order()
     }
 
+    #[inline]
+    fn query_cursor(&self) -> Option<(&Map, bool)> {
+        self.cursor()
+    }
+
     #[inline]
     fn query_offset(&self) -> usize {
         self.offset()
@@ -654,11 +679,11 @@ impl QueryExt<DatabaseDriver> for Query {
         format!(r#"`{table_name}` AS `{model_name}`"#)
     }
 
-    fn parse_text_search(filter: &Map) -> Option<String> {
+    fn parse_text_search(filter: &Map, arguments: &mut QueryArguments) -> Option<String> {
         let fields = filter.parse_str_array("$fields")?;
         filter.parse_string("$search").map(|search| {
             let fields = fields.join(",");
-            let search = Query::escape_string(search.as_ref());
+            let search = Query::bind_string(search, arguments);
             format!("match({fields}) against({search})")
         })
     }
@@ -55,6 +55,14 @@ impl<P> ConnectionPool<P> {
     #[inline]
     pub fn increment_missed_count(&self) {
         self.missed_count.fetch_add(1, Relaxed);
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!(
+            "zino_db_pool_misses_total",
+            "service" => self.name,
+            "database" => self.database,
+        )
+        .increment(1);
     }
 
     /// Resets the number of missed count.
@@ -63,13 +71,6 @@ impl<P> ConnectionPool<P> {
         self.missed_count.store(0, Relaxed);
     }
 
-    /// Returns `true` if the connection pool is retryable to connect.
-    #[inline]
-    pub fn is_retryable(&self) -> bool {
-        let missed_count = self.missed_count();
-        missed_count > 2 && missed_count.is_power_of_two()
-    }
-
     /// Returns the name.
     #[inline]
     pub fn name(&self) -> &'static str {
@@ -88,3 +89,23 @@ impl<P> ConnectionPool<P> {
         &self.pool
     }
 }
+
+impl ConnectionPool<DatabasePool> {
+    /// Returns the number of connections currently active, including the idle ones.
+    #[inline]
+    pub fn num_connections(&self) -> u32 {
+        self.pool.size()
+    }
+
+    /// Returns the number of idle connections.
+    #[inline]
+    pub fn num_idle_connections(&self) -> usize {
+        self.pool.num_idle()
+    }
+
+    /// Returns the number of connections which are in use.
+    #[inline]
+    pub fn num_active_connections(&self) -> usize {
+        (self.num_connections() as usize).saturating_sub(self.num_idle_connections())
+    }
+}
@@ -0,0 +1,286 @@
+use super::{pool::ConnectionPool, PoolManager};
+use smallvec::SmallVec;
+use std::{
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
+
+/// Load balancing strategies for selecting a connection pool in a group.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// Selects the available pools in turn.
+    #[default]
+    RoundRobin,
+    /// Selects the available pool with the fewest active connections.
+    LeastConnections,
+}
+
+impl LoadBalancing {
+    /// Parses the strategy from a string.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "round-robin" => Some(Self::RoundRobin),
+            "least-connections" => Some(Self::LeastConnections),
+            _ => None,
+        }
+    }
+}
+
+/// A group of connection pools for the database service with the same name,
+/// such as a set of read replicas.
+#[derive(Debug)]
+pub struct PoolGroup {
+    /// Name.
+    name: &'static str,
+    /// Connection pools.
+    pools: SmallVec<[ConnectionPool; 2]>,
+    /// Load balancing strategy.
+    load_balancing: LoadBalancing,
+    /// Counter for the round-robin selection.
+    counter: AtomicUsize,
+}
+
+impl PoolGroup {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(name: &'static str, load_balancing: LoadBalancing) -> Self {
+        Self {
+            name,
+            pools: SmallVec::new(),
+            load_balancing,
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds a connection pool to the group.
+    #[inline]
+    pub fn add_pool(&mut self, pool: ConnectionPool) {
+        self.pools.push(pool);
+    }
+
+    /// Returns the name.
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the load balancing strategy.
+    #[inline]
+    pub fn load_balancing(&self) -> LoadBalancing {
+        self.load_balancing
+    }
+
+    /// Returns the connection pools.
+    #[inline]
+    pub fn pools(&self) -> &[ConnectionPool] {
+        &self.pools
+    }
+
+    /// Selects a connection pool by the load balancing strategy.
+    /// If none of the pools is available, the one with the fewest missed count is returned.
+    pub fn select(&self) -> Option<&ConnectionPool> {
+        let mut available_pools = self.pools.iter().filter(|cp| cp.is_available());
+        let pool = match self.load_balancing {
+            LoadBalancing::RoundRobin => {
+                let num_available = self.pools.iter().filter(|cp| cp.is_available()).count();
+                if num_available > 0 {
+                    let index = self.counter.fetch_add(1, Relaxed) % num_available;
+                    available_pools.nth(index)
+                } else {
+                    None
+                }
+            }
+            LoadBalancing::LeastConnections => {
+                available_pools.min_by_key(|cp| cp.num_active_connections())
+            }
+        };
+        let pool = pool.or_else(|| self.pools.iter().min_by_key(|cp| cp.missed_count()));
+        #[cfg(feature = "metrics")]
+        if let Some(cp) = pool {
+            metrics::counter!(
+                "zino_db_pool_selections_total",
+                "service" => cp.name(),
+                "database" => cp.database(),
+            )
+            .increment(1);
+        }
+        pool
+    }
+
+    /// Checks the health of the connection pools.
+    /// A pool is ejected after `max_failures` consecutive failures,
+    /// and it is re-admitted once a health check succeeds.
+    pub async fn check_health(&self, timeout: Duration, max_failures: usize) {
+        for cp in self.pools.iter() {
+            let name = cp.name();
+            let database = cp.database();
+            let result = cp.check_health(timeout).await;
+            #[cfg(feature = "metrics")]
+            let status = if result.is_ok() {
+                "healthy"
+            } else {
+                "unhealthy"
+            };
+            match result {
+                Ok(()) => {
+                    if !cp.is_available() {
+                        tracing::warn!(database, "the `{name}` service is re-admitted");
+                        #[cfg(feature = "metrics")]
+                        metrics::counter!(
+                            "zino_db_pool_readmissions_total",
+                            "service" => name,
+                            "database" => database,
+                        )
+                        .increment(1);
+                    }
+                    cp.store_availability(true);
+                }
+                Err(err) => {
+                    if cp.is_available() && cp.missed_count() + 1 >= max_failures {
+                        cp.store_availability(false);
+                        tracing::error!(database, "the `{name}` service is ejected: {err}");
+                        #[cfg(feature = "metrics")]
+                        metrics::counter!(
+                            "zino_db_pool_ejections_total",
+                            "service" => name,
+                            "database" => database,
+                        )
+                        .increment(1);
+                    } else {
+                        cp.increment_missed_count();
+                        tracing::warn!(database, "fail to check the `{name}` service: {err}");
+                    }
+                }
+            }
+            #[cfg(feature = "metrics")]
+            {
+                let labels = [("service", name), ("database", database)];
+                metrics::counter!(
+                    "zino_db_pool_health_checks_total",
+                    "service" => name,
+                    "database" => database,
+                    "status" => status,
+                )
+                .increment(1);
+                metrics::gauge!("zino_db_pool_available", &labels).set(if cp.is_available() {
+                    1.0
+                } else {
+                    0.0
+                });
+                metrics::gauge!("zino_db_pool_connections", &labels)
+                    .set(cp.num_connections() as f64);
+                metrics::gauge!("zino_db_pool_idle_connections", &labels)
+                    .set(cp.num_idle_connections() as f64);
+                metrics::gauge!("zino_db_pool_missed_count", &labels).set(cp.missed_count() as f64);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoadBalancing;
+
+    #[cfg(not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    )))]
+    use crate::orm::{fixture::block_on, PoolGroup};
+
+    #[cfg(not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    )))]
+    fn new_group(load_balancing: LoadBalancing, databases: &[&'static str]) -> PoolGroup {
+        use crate::orm::ConnectionPool;
+        use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+        let mut group = PoolGroup::new("replica", load_balancing);
+        for &database in databases {
+            let pool = SqlitePoolOptions::new().connect_lazy_with(SqliteConnectOptions::new());
+            group.add_pool(ConnectionPool::new("replica", database, pool));
+        }
+        group
+    }
+
+    #[test]
+    fn it_parses_load_balancing() {
+        assert_eq!(
+            LoadBalancing::parse("round-robin"),
+            Some(LoadBalancing::RoundRobin)
+        );
+        assert_eq!(
+            LoadBalancing::parse("least-connections"),
+            Some(LoadBalancing::LeastConnections)
+        );
+        assert_eq!(LoadBalancing::parse("random"), None);
+    }
+
+    #[cfg(not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    )))]
+    #[test]
+    fn it_selects_the_available_pools_in_turn() {
+        block_on(async {
+            let group = new_group(LoadBalancing::RoundRobin, &["a", "b", "c"]);
+            let databases = (0..4)
+                .filter_map(|_| group.select())
+                .map(|cp| cp.database())
+                .collect::<Vec<_>>();
+            assert_eq!(databases, ["a", "b", "c", "a"]);
+
+            group.pools()[1].store_availability(false);
+            let databases = (0..4)
+                .filter_map(|_| group.select())
+                .map(|cp| cp.database())
+                .collect::<Vec<_>>();
+            assert!(databases.iter().all(|&database| database != "b"));
+            assert!(databases.contains(&"a") && databases.contains(&"c"));
+        });
+    }
+
+    #[cfg(not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    )))]
+    #[test]
+    fn it_selects_the_pool_with_the_least_connections() {
+        block_on(async {
+            let group = new_group(LoadBalancing::LeastConnections, &["a", "b"]);
+            assert_eq!(group.select().map(|cp| cp.database()), Some("a"));
+
+            group.pools()[0].store_availability(false);
+            assert_eq!(group.select().map(|cp| cp.database()), Some("b"));
+        });
+    }
+
+    #[cfg(not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    )))]
+    #[test]
+    fn it_falls_back_to_the_pool_with_the_fewest_misses() {
+        block_on(async {
+            let group = new_group(LoadBalancing::RoundRobin, &["a", "b"]);
+            for cp in group.pools() {
+                cp.store_availability(false);
+            }
+            group.pools()[0].increment_missed_count();
+            assert_eq!(group.select().map(|cp| cp.database()), Some("b"));
+
+            group.pools()[0].store_availability(true);
+            assert_eq!(group.select().map(|cp| cp.database()), Some("a"));
+        });
+    }
+}
@@ -1,7 +1,7 @@
 use super::Schema;
 use crate::{
     extension::{JsonObjectExt, JsonValueExt},
-    model::EncodeColumn,
+    model::{EncodeColumn, QueryArguments},
     JsonValue, Map, SharedString,
 };
 use std::{borrow::Cow, fmt::Display};
@@ -23,6 +23,9 @@ pub(super) trait QueryExt<DB> {
     /// Returns the sort order.
     fn query_order(&self) -> &[(SharedString, bool)];
 
+    /// Returns the cursor for keyset pagination.
+    fn query_cursor(&self) -> Option<(&Map, bool)>;
+
     /// Returns the query offset.
     fn query_offset(&self) -> usize;
 
@@ -48,7 +51,7 @@ pub(super) trait QueryExt<DB> {
     fn format_table_name<M: Schema>(&self) -> String;
 
     /// Parses text search filter.
-    fn parse_text_search(filter: &Map) -> Option<String>;
+    fn parse_text_search(filter: &Map, arguments: &mut QueryArguments) -> Option<String>;
 
     /// Escapes a string.
     #[inline]
@@ -56,6 +59,30 @@ pub(super) trait QueryExt<DB> {
         format!("'{}'", value.to_string().replace('\'', "''"))
     }
 
+    /// Binds a value as an argument and returns the placeholder.
+    /// The value is formatted as a literal if the arguments are inlined.
+    fn bind_value(value: impl Into<JsonValue>, arguments: &mut QueryArguments) -> String {
+        let value = value.into();
+        if arguments.is_inline() {
+            match value {
+                JsonValue::Null => "NULL".to_owned(),
+                JsonValue::Bool(value) => if value { "TRUE" } else { "FALSE" }.to_owned(),
+                JsonValue::Number(value) => value.to_string(),
+                JsonValue::String(value) => Self::escape_string(value),
+                _ => Self::escape_string(value),
+            }
+        } else {
+            let n = arguments.push(value);
+            Self::placeholder(n).into_owned()
+        }
+    }
+
+    /// Binds a string as an argument and returns the placeholder.
+    #[inline]
+    fn bind_string(value: impl Display, arguments: &mut QueryArguments) -> String {
+        Self::bind_value(value.to_string(), arguments)
+    }
+
     /// Formats projection fields.
     fn format_projection(&self) -> Cow<'_, str> {
         let fields = self.query_fields();
@@ -79,9 +106,10 @@ pub(super) trait QueryExt<DB> {
     }
 
     /// Formats the query filters to generate SQL `WHERE` expression.
-    fn format_filters<M: Schema>(&self) -> String {
+    fn format_filters<M: Schema>(&self, arguments: &mut QueryArguments) -> String {
         let filters = self.query_filters();
-        if filters.is_empty() {
+        let cursor = self.query_cursor();
+        if filters.is_empty() && cursor.is_none() && M::TENANT_KEY.is_none() {
             return String::new();
         }
 
@@ -91,19 +119,22 @@ pub(super) trait QueryExt<DB> {
             match key.as_str() {
                 "$and" => {
                     if let Some(filters) = value.as_array() {
-                        let condition = Self::format_logical_filters::<M>(filters, " AND ");
+                        let condition =
+                            Self::format_logical_filters::<M>(filters, " AND ", arguments);
                         conditions.push(condition);
                     }
                 }
                 "$not" => {
                     if let Some(filters) = value.as_array() {
-                        let condition = Self::format_logical_filters::<M>(filters, " AND ");
+                        let condition =
+                            Self::format_logical_filters::<M>(filters, " AND ", arguments);
                         conditions.push(format!("(NOT {condition})"));
                     }
                 }
                 "$or" => {
                     if let Some(filters) = value.as_array() {
-                        let condition = Self::format_logical_filters::<M>(filters, " OR ");
+                        let condition =
+                            Self::format_logical_filters::<M>(filters, " OR ", arguments);
                         conditions.push(condition);
                     }
                 }
@@ -125,47 +156,26 @@ pub(super) trait QueryExt<DB> {
                     }
                 }
                 "$text" => {
-                    if let Some(condition) = value.as_object().and_then(Self::parse_text_search) {
+                    if let Some(condition) = value
+                        .as_object()
+                        .and_then(|filter| Self::parse_text_search(filter, arguments))
+                    {
                         conditions.push(condition);
                     }
                 }
                 "$ovlp" => {
-                    if let Some(values) = value.parse_str_array() {
-                        if let [start_field, end_field, start_value, end_value] = values.as_slice()
-                        {
-                            let start_field = Self::format_field(start_field);
-                            let end_field = Self::format_field(end_field);
-                            let start_value = Self::escape_string(start_value);
-                            let end_value = Self::escape_string(end_value);
-                            let condition = if cfg!(any(
-                                feature = "orm-mariadb",
-                                feature = "orm-mysql",
-                                feature = "orm-tidb"
-                            )) {
-                                format!(
-                                    r#"overlaps({start_field}, {end_field}, {start_value}, {end_value})"#
-                                )
-                            } else if cfg!(feature = "orm-postgres") {
-                                format!(
-                                    r#"({start_field}, {end_field}) OVERLAPS ({start_value}, {end_value})"#
-                                )
-                            } else {
-                                format!(
-                                    r#"({start_field} <= {end_value} AND {end_field} >= {start_value})"#
-                                )
-                            };
-                            conditions.push(condition);
-                        }
+                    if let Some(condition) = Self::parse_overlaps(value, arguments) {
+                        conditions.push(condition);
                     }
                 }
                 _ => {
                     if let Some(col) = M::get_column(key) {
-                        let condition = col.format_filter(key, value);
+                        let condition = col.format_filter(key, value, arguments);
                         if !condition.is_empty() {
                             conditions.push(condition);
                         }
                     } else if key.contains('.') {
-                        let condition = Self::format_filter(key, value);
+                        let condition = Self::format_filter(key, value, arguments);
                         if !condition.is_empty() {
                             conditions.push(condition);
                         }
@@ -173,6 +183,15 @@ pub(super) trait QueryExt<DB> {
                 }
             }
         }
+        if let Some((cursor, backward)) = cursor {
+            let condition = self.format_keyset_filters::<M>(cursor, backward, arguments);
+            if !condition.is_empty() {
+                conditions.push(condition);
+            }
+        }
+        if let Some(condition) = super::tenant::format_tenant_filter::<M>(arguments) {
+            conditions.push(condition);
+        }
         if !conditions.is_empty() {
             expression += &format!("WHERE {}", conditions.join(" AND "));
         };
@@ -184,7 +203,7 @@ pub(super) trait QueryExt<DB> {
                 .join(", ");
             expression += &format!(" GROUP BY {groups}");
             if let Some(filters) = filters.get_array("$having") {
-                let condition = Self::format_logical_filters::<M>(filters, " AND ");
+                let condition = Self::format_logical_filters::<M>(filters, " AND ", arguments);
                 expression += &format!(" HAVING {condition}");
             }
         }
@@ -192,7 +211,11 @@ pub(super) trait QueryExt<DB> {
     }
 
     // Formats the filters with a logic operator.
-    fn format_logical_filters<M: Schema>(filters: &[JsonValue], operator: &str) -> String {
+    fn format_logical_filters<M: Schema>(
+        filters: &[JsonValue],
+        operator: &str,
+        arguments: &mut QueryArguments,
+    ) -> String {
         let mut conditions = Vec::with_capacity(filters.len());
         for filter in filters {
             if let JsonValue::Object(filter) = filter {
@@ -200,66 +223,45 @@ pub(super) trait QueryExt<DB> {
                     match key.as_str() {
                         "$and" => {
                             if let Some(filters) = value.as_array() {
-                                let condition = Self::format_logical_filters::<M>(filters, " AND ");
+                                let condition =
+                                    Self::format_logical_filters::<M>(filters, " AND ", arguments);
                                 conditions.push(condition);
                             }
                         }
                         "$not" => {
                             if let Some(filters) = value.as_array() {
-                                let condition = Self::format_logical_filters::<M>(filters, " AND ");
+                                let condition =
+                                    Self::format_logical_filters::<M>(filters, " AND ", arguments);
                                 conditions.push(format!("(NOT {condition})"));
                             }
                         }
                         "$nor" => {
                             if let Some(filters) = value.as_array() {
-                                let condition = Self::format_logical_filters::<M>(filters, " OR ");
+                                let condition =
+                                    Self::format_logical_filters::<M>(filters, " OR ", arguments);
                                 conditions.push(format!("(NOT {condition})"));
                             }
                         }
                         "$or" => {
                             if let Some(filters) = value.as_array() {
-                                let condition = Self::format_logical_filters::<M>(filters, " OR ");
+                                let condition =
+                                    Self::format_logical_filters::<M>(filters, " OR ", arguments);
                                 conditions.push(condition);
                             }
                         }
                         "$ovlp" => {
-                            if let Some(values) = value.parse_str_array() {
-                                if let [start_field, end_field, start_value, end_value] =
-                                    values.as_slice()
-                                {
-                                    let start_field = Self::format_field(start_field);
-                                    let end_field = Self::format_field(end_field);
-                                    let start_value = Self::escape_string(start_value);
-                                    let end_value = Self::escape_string(end_value);
-                                    let condition = if cfg!(any(
-                                        feature = "orm-mariadb",
-                                        feature = "orm-mysql",
-                                        feature = "orm-tidb"
-                                    )) {
-                                        format!(
-                                            r#"overlaps({start_field}, {end_field}, {start_value}, {end_value})"#
-                                        )
-                                    } else if cfg!(feature = "orm-postgres") {
-                                        format!(
-                                            r#"({start_field}, {end_field}) OVERLAPS ({start_value}, {end_value})"#
-                                        )
-                                    } else {
-                                        format!(
-                                            r#"({start_field} <= {end_value} AND {end_field} >= {start_value})"#
-                                        )
-                                    };
-                                    conditions.push(condition);
-                                }
+                            if let Some(condition) = Self::parse_overlaps(value, arguments) {
+                                conditions.push(condition);
                             }
                         }
                         _ => {
                             if let Some(col) = M::get_column(key) {
-                                let condition = col.format_filter(key, value);
+                                let condition = col.format_filter(key, value, arguments);
                                 if !condition.is_empty() {
                                     conditions.push(condition);
                                 }
                             } else if key.contains('.') {
-                                let condition = Self::format_filter(key, value);
+                                let condition = Self::format_filter(key, value, arguments);
                                 if !condition.is_empty() {
                                     conditions.push(condition);
                                 }
@@ -276,8 +278,123 @@ pub(super) trait QueryExt<DB> {
         }
     }
 
-    /// Formats a query filter.
-    fn format_filter(key: &str, value: &JsonValue) -> String {
+    /// Formats the filters to seek the rows after or before the cursor in the sort order.
+    /// The sort keys should be unique as a whole.
+    ///
+    /// The `NULL` values are sorted as the smallest values in MySQL and SQLite,
+    /// and as the largest values in PostgreSQL.
+    fn format_keyset_filters<M: Schema>(
+        &self,
+        cursor: &Map,
+        backward: bool,
+        arguments: &mut QueryArguments,
+    ) -> String {
+        let sort_order = self
+            .query_order()
+            .iter()
+            .map_while(|(field, descending)| {
+                Some((field.as_ref(), *descending, cursor.get(field.as_ref())?))
+            })
+            .collect::<Vec<_>>();
+        if sort_order.is_empty() {
+            return String::new();
+        }
+
+        let nulls_largest = cfg!(feature = "orm-postgres");
+        let mut conditions = Vec::with_capacity(sort_order.len());
+        for (index, &(key, descending, value)) in sort_order.iter().enumerate() {
+            let seek_larger = descending == backward;
+            if value.is_null() && seek_larger == nulls_largest {
+                // There are no rows beyond the `NULL` values.
+                continue;
+            }
+
+            // The values should be formatted in the same order as the placeholders.
+            let mut expressions = sort_order[..index]
+                .iter()
+                .map(|&(key, _, value)| {
+                    let field = Self::format_field(key);
+                    if value.is_null() {
+                        format!("{field} IS NULL")
+                    } else {
+                        let value = Self::format_keyset_value::<M>(key, value, arguments);
+                        format!("{field} = {value}")
+                    }
+                })
+                .collect::<Vec<_>>();
+            let field = Self::format_field(key);
+            let condition = if value.is_null() {
+                format!("{field} IS NOT NULL")
+            } else {
+                let operator = if seek_larger { ">" } else { "<" };
+                let value = Self::format_keyset_value::<M>(key, value, arguments);
+                if seek_larger == nulls_largest {
+                    format!("({field} {operator} {value} OR {field} IS NULL)")
+                } else {
+                    format!("{field} {operator} {value}")
+                }
+            };
+            expressions.push(condition);
+            conditions.push(format!("({})", expressions.join(" AND ")));
+        }
+        if conditions.is_empty() {
+            "FALSE".to_owned()
+        } else {
+            format!("({})", conditions.join(" OR "))
+        }
+    }
+
+    /// Binds a value of the sort key for keyset pagination.
+    fn format_keyset_value<M: Schema>(
+        key: &str,
+        value: &JsonValue,
+        arguments: &mut QueryArguments,
+    ) -> String {
+        match (M::get_column(key), value) {
+            (Some(col), JsonValue::String(value)) => {
+                col.format_value(value, arguments).into_owned()
+            }
+            _ => Self::bind_value(value.clone(), arguments),
+        }
+    }
+
+    /// Parses the overlaps filter.
+    fn parse_overlaps(value: &JsonValue, arguments: &mut QueryArguments) -> Option<String> {
+        let values = value.parse_str_array()?;
+        let [start_field, end_field, start_value, end_value] = values.as_slice() else {
+            return None;
+        };
+        let start_field = Self::format_field(start_field);
+        let end_field = Self::format_field(end_field);
+        let condition = if cfg!(any(
+            feature = "orm-mariadb",
+            feature = "orm-mysql",
+            feature = "orm-tidb"
+        )) {
+            let start_value = Self::bind_string(start_value, arguments);
+            let end_value = Self::bind_string(end_value, arguments);
+            format!(r#"overlaps({start_field}, {end_field}, {start_value}, {end_value})"#)
+        } else if cfg!(feature = "orm-postgres") {
+            // The types of the arguments should be annotated for PostgreSQL.
+            let start_value = Self::bind_string(start_value, arguments);
+            let end_value = Self::bind_string(end_value, arguments);
+            format!(
+                r#"({start_field}, {end_field}) OVERLAPS ({start_value}::timestamptz, {end_value}::timestamptz)"#
+            )
+        } else {
+            // The values should be bound in the same order as the placeholders.
+            let end_value = Self::bind_string(end_value, arguments);
+            let start_value = Self::bind_string(start_value, arguments);
+            format!(r#"({start_field} <= {end_value} AND {end_field} >= {start_value})"#)
+        };
+        Some(condition)
+    }
+
+    /// Formats a query filter for a field without the column definition.
+    ///
+    /// PostgreSQL can not compare a field with a text argument of the unknown type,
+    /// so the field is converted to text unless the value is compared as a number.
+    fn format_filter(key: &str, value: &JsonValue, arguments: &mut QueryArguments) -> String {
         if let Some(filter) = value.as_object() {
             let mut conditions = Vec::with_capacity(filter.len());
             for (name, value) in filter {
@@ -291,9 +408,7 @@ pub(super) trait QueryExt<DB> {
                         "$ge" => ">=",
                         _ => "=",
                     };
-                    let field = Self::format_field(key);
-                    let value = Self::escape_string(value);
-                    let condition = format!(r#"{field} {operator} {value}"#);
+                    let condition = Self::format_untyped_filter(key, operator, &value, arguments);
                     conditions.push(condition);
                 }
             }
@@ -303,24 +418,50 @@ pub(super) trait QueryExt<DB> {
                 format!("({})", conditions.join(" AND "))
             }
         } else if let Some(value) = value.parse_string() {
-            let key = Self::format_field(key);
-            let value = Self::escape_string(value);
-            format!(r#"{key} = {value}"#)
+            Self::format_untyped_filter(key, "=", &value, arguments)
         } else {
             String::new()
         }
     }
 
+    /// Formats a condition for a field without the column definition.
+    fn format_untyped_filter(
+        key: &str,
+        operator: &str,
+        value: &str,
+        arguments: &mut QueryArguments,
+    ) -> String {
+        let field = Self::format_field(key);
+        if cfg!(feature = "orm-postgres") {
+            let is_ordering = matches!(operator, "<" | "<=" | ">" | ">=");
+            if let Some(number) = value.parse::<i64>().ok().filter(|_| is_ordering) {
+                let value = Self::bind_value(number, arguments);
+                format!(r#"{field} {operator} {value}"#)
+            } else if let Some(number) = value.parse::<f64>().ok().filter(|_| is_ordering) {
+                let value = Self::bind_value(number, arguments);
+                format!(r#"{field} {operator} {value}"#)
+            } else {
+                let value = Self::bind_string(value, arguments);
+                format!(r#"CAST({field} AS TEXT) {operator} {value}"#)
+            }
+        } else {
+            let value = Self::bind_string(value, arguments);
+            format!(r#"{field} {operator} {value}"#)
+        }
+    }
+
     /// Formats the query sort to generate SQL `ORDER BY` expression.
     fn format_sort(&self) -> String {
         let sort_order = self.query_order();
         if sort_order.is_empty() {
             String::new()
         } else {
+            // The sort order is reversed for paging backward, and so are the results.
+            let backward = self.query_cursor().is_some_and(|(_, backward)| backward);
             let sort_order = sort_order
                 .iter()
                 .map(|(sort, descending)| {
-                    if *descending {
+                    if *descending != backward {
                         format!("{sort} DESC")
                     } else {
                         format!("{sort} ASC")
@@ -338,7 +479,195 @@ pub(super) trait QueryExt<DB> {
             return String::new();
         }
 
+        if self.query_cursor().is_some() {
+            return format!("LIMIT {limit}");
+        }
+
         let offset = self.query_offset();
         format!("LIMIT {limit} OFFSET {offset}")
     }
 }
+
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+mod tests {
+    use super::QueryExt;
+    use crate::{
+        extension::JsonObjectExt,
+        model::{Mutation, Query, QueryArguments},
+        orm::{
+            fixture::{block_on, create_table, test_model},
+            mutation::MutationExt,
+            Schema,
+        },
+        JsonValue, Map,
+    };
+
+    test_model!(Account, "test_query_account", auto_increment = false);
+    test_model!(Member, "test_keyset_member", auto_increment = false);
+
+    #[test]
+    fn it_binds_the_filter_values_as_arguments() {
+        let mut filters = Map::new();
+        filters.upsert("name", "bob's");
+        filters.upsert("version", Map::from_entry("$gt", 3));
+        filters.upsert("profile.age", Map::from_entry("$ge", "18"));
+        let query = Query::new(filters);
+
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Account>(&mut arguments);
+        assert_eq!(
+            filters,
+            "WHERE `name` = ? AND (`profile`.`age` >= ?) AND `version` > ?"
+        );
+        assert_eq!(
+            arguments.values(),
+            [
+                JsonValue::from("bob's"),
+                JsonValue::from("18"),
+                JsonValue::from(3),
+            ]
+        );
+
+        let mut arguments = QueryArguments::inline();
+        let filters = query.format_filters::<Account>(&mut arguments);
+        assert_eq!(
+            filters,
+            "WHERE `name` = 'bob''s' AND (`profile`.`age` >= '18') AND `version` > 3"
+        );
+        assert!(arguments.is_empty());
+    }
+
+    #[test]
+    fn it_binds_the_updates_before_the_filters() {
+        let mut updates = Map::new();
+        updates.upsert("name", "alice");
+        updates.upsert("$inc", Map::from_entry("version", 1));
+        let mutation = Mutation::new(updates);
+        let query = Query::new(Map::from_entry("id", 1));
+
+        let mut arguments = QueryArguments::new();
+        let updates = mutation.format_updates::<Account>(&mut arguments);
+        let filters = query.format_filters::<Account>(&mut arguments);
+        assert_eq!(updates, "`version` = ? + `version`, `name` = ?");
+        assert_eq!(filters, "WHERE `id` = ?");
+        assert_eq!(
+            arguments.values(),
+            [
+                JsonValue::from(1),
+                JsonValue::from("alice"),
+                JsonValue::from(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_queries_with_the_bound_arguments() {
+        block_on(async {
+            create_table("test_query_account", false).await;
+
+            let account = Account {
+                id: 1,
+                name: "bob's".to_owned(),
+                version: 2,
+                ..Default::default()
+            };
+            account.insert().await.unwrap();
+
+            let mut filters = Map::new();
+            filters.upsert("name", "bob's");
+            filters.upsert("version", Map::from_entry("$ge", 2));
+            let models = Account::find_as::<Account>(&Query::new(filters))
+                .await
+                .unwrap();
+            assert_eq!(models.len(), 1);
+            assert_eq!(models[0].name, "bob's");
+
+            let filters = Map::from_entry("name", "' OR '1' = '1");
+            let models = Account::find_as::<Account>(&Query::new(filters))
+                .await
+                .unwrap();
+            assert!(models.is_empty());
+        });
+    }
+
+    #[test]
+    fn it_seeks_the_rows_after_null_sort_keys() {
+        let mut query = Query::default();
+        query.order_by("name", false);
+        query.order_by("id", false);
+
+        let mut cursor = Map::new();
+        cursor.upsert("name", JsonValue::Null);
+        cursor.upsert("id", 3);
+        query.seek_after(cursor.clone());
+
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Account>(&mut arguments);
+        assert_eq!(
+            filters,
+            "WHERE ((`name` IS NOT NULL) OR (`name` IS NULL AND `id` > ?))"
+        );
+        assert_eq!(arguments.values(), [JsonValue::from(3)]);
+
+        query.seek_before(cursor);
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Account>(&mut arguments);
+        assert_eq!(
+            filters,
+            "WHERE ((`name` IS NULL AND (`id` < ? OR `id` IS NULL)))"
+        );
+        assert_eq!(arguments.values(), [JsonValue::from(3)]);
+    }
+
+    #[test]
+    fn it_pages_forward_and_backward_by_the_cursors() {
+        block_on(async {
+            create_table("test_keyset_member", false).await;
+
+            for (id, name) in [(1, "carol"), (2, "alice"), (3, "bob"), (4, "alice")] {
+                let member = Member {
+                    id,
+                    name: name.to_owned(),
+                    ..Default::default()
+                };
+                member.insert().await.unwrap();
+            }
+
+            let mut query = Query::default();
+            query.allow_fields(&["id", "name"]);
+            query.order_by("name", false);
+            query.order_by("id", false);
+            query.set_limit(2);
+
+            let ids = |models: &[Map]| {
+                models
+                    .iter()
+                    .filter_map(|model| model.get_i64("id"))
+                    .collect::<Vec<_>>()
+            };
+            let first_page = Member::find::<Map>(&query).await.unwrap();
+            assert_eq!(ids(&first_page), [2, 4]);
+
+            let cursor = query.encode_cursor(&first_page[1]).unwrap();
+            query.seek_after(Query::decode_cursor(&cursor).unwrap());
+            let second_page = Member::find::<Map>(&query).await.unwrap();
+            assert_eq!(ids(&second_page), [3, 1]);
+
+            let cursor = query.encode_cursor(&second_page[0]).unwrap();
+            query.seek_before(Query::decode_cursor(&cursor).unwrap());
+            let previous_page = Member::find::<Map>(&query).await.unwrap();
+            assert_eq!(ids(&previous_page), [2, 4]);
+
+            query.order_by("version", false);
+            assert!(query.encode_cursor(&first_page[0]).is_err());
+        });
+    }
+}
@@ -1,5 +1,10 @@
-use super::{column::ColumnExt, query::QueryExt, schema::Schema, DatabaseDriver};
-use crate::{error::Error, extension::JsonValueExt, model::Query, Map};
+use super::{column::ColumnExt, executor, query::QueryExt, schema::Schema, DatabaseDriver};
+use crate::{
+    error::Error,
+    extension::JsonValueExt,
+    model::{Query, QueryArguments},
+    Map,
+};
 use futures::TryStreamExt;
 use sqlx::{Decode, Row, Type};
 use std::{fmt::Display, sync::atomic::Ordering::Relaxed};
@@ -20,13 +25,19 @@ where
 
         let table_name = Self::table_name();
         let projection = query.format_projection();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sort = query.format_sort();
         let sql = format!("SELECT {projection} FROM {table_name} {filters} {sort} LIMIT 1;");
 
         let mut ctx = Self::before_scan(&sql).await?;
-        let scalar = sqlx::query_scalar(&sql).fetch_one(pool).await?;
+        let mut query = sqlx::query(&sql);
+        for arg in arguments.values() {
+            query = executor::bind_argument(query, arg.clone());
+        }
+        let scalar = query.fetch_one(pool).await?.try_get(0)?;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(1), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -44,13 +55,19 @@ where
 
         let table_name = query.format_table_name::<Self>();
         let projection = query.format_table_fields::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sort = query.format_sort();
         let pagination = query.format_pagination();
         let sql = format!("SELECT {projection} FROM {table_name} {filters} {sort} {pagination};");
 
         let mut ctx = Self::before_scan(&sql).await?;
-        let mut rows = sqlx::query(&sql).fetch(pool);
+        let mut query = sqlx::query(&sql);
+        for arg in arguments.values() {
+            query = executor::bind_argument(query, arg.clone());
+        }
+
+        let mut rows = query.fetch(pool);
         let mut data = Vec::new();
         let mut max_rows = super::MAX_ROWS.load(Relaxed);
         while let Some(row) = rows.try_next().await? {
@@ -62,6 +79,7 @@ where
             }
         }
         ctx.set_query(&sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(u64::try_from(data.len())?), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -152,6 +170,7 @@ where
         let query = sqlx::query_scalar(&sql).bind(primary_key.to_string());
         let scalar = query.fetch_one(pool).await?;
         ctx.set_query(sql);
+        ctx.add_argument(primary_key);
         ctx.set_query_result(Some(1), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -1,14 +1,22 @@
 use super::{
-    column::ColumnExt, mutation::MutationExt, query::QueryExt, ConnectionPool, DatabaseRow,
-    Executor, GlobalPool, ModelHelper,
+    audit::{self, AuditEntry, AuditTrail},
+    column::ColumnExt,
+    executor, migration,
+    mutation::MutationExt,
+    query::QueryExt,
+    tenant, ConnectionPool, DatabaseQueryResult, DatabaseRow, Executor, GlobalPool, ModelHelper,
 };
 use crate::{
     bail,
+    datetime::DateTime,
     error::Error,
     extension::{JsonObjectExt, JsonValueExt},
-    model::{Column, DecodeRow, EncodeColumn, ModelHooks, Mutation, Query, QueryContext},
+    model::{
+        Column, DecodeRow, EncodeColumn, ModelHooks, Mutation, Query, QueryArguments, QueryContext,
+    },
     warn, JsonValue, Map,
 };
+use futures::stream::BoxStream;
 use serde::de::DeserializeOwned;
 use std::fmt::Display;
 
@@ -29,6 +37,10 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     const WRITER_NAME: &'static str = "main";
     /// Optional custom table name.
     const TABLE_NAME: Option<&'static str> = None;
+    /// Optional tenant key for the row-level multi-tenancy.
+    const TENANT_KEY: Option<&'static str> = None;
+    /// A flag to record an audit trail of the changes.
+    const AUDIT_ENABLED: bool = false;
 
     /// Returns the primary key.
     fn primary_key(&self) -> &Self::PrimaryKey;
@@ -109,6 +121,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     }
 
     /// Gets a column for the field if it is writable.
+    /// The tenant key is not writable since it is stamped with the tenant in the scope.
     #[inline]
     fn get_writable_column(key: &str) -> Option<&Column<'static>> {
         let key = if let Some((name, field)) = key.split_once('.') {
@@ -120,6 +133,9 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         } else {
             key
         };
+        if Self::TENANT_KEY == Some(key) {
+            return None;
+        }
         Self::columns()
             .iter()
             .find(|col| col.name() == key && !col.is_read_only())
@@ -204,58 +220,19 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let pool = connection_pool.pool();
 
         let table_name = Self::table_name();
-        let sql = if cfg!(any(
-            feature = "orm-mariadb",
-            feature = "orm-mysql",
-            feature = "orm-tidb"
-        )) {
-            let table_schema = connection_pool.database();
-            format!(
-                "SELECT column_name, data_type, column_default, is_nullable \
-                    FROM information_schema.columns \
-                        WHERE table_schema = '{table_schema}' AND table_name = '{table_name}';"
-            )
-        } else if cfg!(feature = "orm-postgres") {
-            format!(
-                "SELECT column_name, data_type, column_default, is_nullable \
-                    FROM information_schema.columns \
-                        WHERE table_schema = 'public' AND table_name = '{table_name}';"
-            )
-        } else {
-            format!(
-                "SELECT p.name AS column_name, p.type AS data_type, \
-                        p.dflt_value AS column_default, p.[notnull] AS is_not_null \
-                    FROM sqlite_master m LEFT OUTER JOIN pragma_table_info((m.name)) p
-                        ON m.name <> p.name WHERE m.name = '{table_name}';"
-            )
-        };
-        let rows = pool.fetch(&sql).await?;
-        let mut data = Vec::with_capacity(rows.len());
-        for row in rows {
-            data.push(Map::decode_row(&row)?);
-        }
-
+        let data = migration::fetch_table_columns(connection_pool, table_name).await?;
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         for col in Self::columns() {
             let column_name = col.name();
-            let column_opt = data.iter().find(|d| {
-                d.get_str("column_name")
-                    .or_else(|| d.get_str("COLUMN_NAME"))
-                    == Some(column_name)
-            });
+            let column_opt = data
+                .iter()
+                .find(|d| migration::get_column_name(d) == Some(column_name));
             if let Some(d) = column_opt {
-                let data_type = d.get_str("data_type").or_else(|| d.get_str("DATA_TYPE"));
+                let data_type = migration::get_data_type(d);
                 let column_default = d
                     .get_str("column_default")
                     .or_else(|| d.get_str("COLUMN_DEFAULT"));
-                let is_not_null = if cfg!(any(feature = "orm-mysql", feature = "orm-postgres")) {
-                    d.get_str("is_nullable")
-                        .or_else(|| d.get_str("IS_NULLABLE"))
-                        .unwrap_or("YES")
-                        .eq_ignore_ascii_case("NO")
-                } else {
-                    d.get_str("is_not_null") == Some("1")
-                };
+                let is_not_null = migration::is_not_null(d);
                 if col.is_not_null() != is_not_null && column_name != primary_key_name {
                     tracing::warn!(
                         model_name = Self::model_name(),
@@ -387,15 +364,29 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     }
 
     /// Inserts the model into the table.
-    async fn insert(mut self) -> Result<QueryContext, Error> {
+    async fn insert(self) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        self.insert_in(pool).await
+    }
+
+    /// Inserts the model into the table using the executor.
+    async fn insert_in<E>(mut self, mut executor: E) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         let model_data = self.before_insert().await?;
 
-        let map = self.into_map();
+        let primary_key = self.primary_key().to_string();
+        let mut map = self.into_map();
+        tenant::stamp_tenant::<Self>(&mut map, tenant_id.as_deref());
+
         let table_name = Self::table_name();
         let columns = Self::columns();
 
         let mut fields = Vec::with_capacity(columns.len());
+        let mut arguments = QueryArguments::new();
         let values = columns
             .iter()
             .filter_map(|col| {
@@ -404,7 +395,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
                 } else {
                     let name = col.name();
                     fields.push(name);
-                    Some(col.encode_value(map.get(name)))
+                    Some(col.encode_value(map.get(name), &mut arguments))
                 }
             })
             .collect::<Vec<_>>()
@@ -413,17 +404,41 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let sql = format!("INSERT INTO {table_name} ({fields}) VALUES ({values});");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let query_result = pool.execute(&sql).await?;
+        let query_result = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?;
         let (last_insert_id, rows_affected) = Query::parse_query_result(query_result);
         let success = rows_affected == 1;
         if let Some(last_insert_id) = last_insert_id {
             ctx.set_last_insert_id(last_insert_id);
         }
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         Self::after_insert(&ctx, model_data).await?;
         if success {
+            if let Some(recorder) = recorder {
+                // The last insert ID is only meaningful for an auto-increment primary key.
+                let mut after = audit::snapshot::<Self>(&map);
+                let entity_id = match last_insert_id {
+                    Some(id) if Self::primary_key_column().auto_increment() => {
+                        after.upsert(Self::PRIMARY_KEY_NAME, id);
+                        id.to_string()
+                    }
+                    _ => primary_key,
+                };
+                audit::record_change::<Self>(
+                    &*recorder,
+                    entity_id,
+                    "insert",
+                    None,
+                    Some(after),
+                    executor.reborrow(),
+                )
+                .await?;
+            }
             Ok(ctx)
         } else {
             bail!(
@@ -435,20 +450,53 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
 
     /// Inserts many models into the table.
     async fn insert_many(models: Vec<Self>) -> Result<QueryContext, Error> {
+        let pool = Self::acquire_writer().await?.pool();
+        Self::insert_many_in(models, pool).await
+    }
+
+    /// Inserts many models into the table using the executor.
+    async fn insert_many_in<E>(models: Vec<Self>, mut executor: E) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         if models.is_empty() {
             bail!("the list of models to be inserted should be nonempty");
         }
 
-        let pool = Self::acquire_writer().await?.pool();
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
+        if recorder.is_some() && Self::primary_key_column().auto_increment() {
+            // The primary keys are generated by the database, so the models are inserted
+            // one by one to record the audit entries with the last insert IDs.
+            let mut rows_affected = 0;
+            let mut last_ctx = None;
+            for model in models.into_iter() {
+                let ctx = model.insert_in(executor.reborrow()).await?;
+                rows_affected += ctx.rows_affected().unwrap_or_default();
+                last_ctx = Some(ctx);
+            }
+            let mut ctx = last_ctx.unwrap_or_default();
+            ctx.set_query_result(Some(rows_affected), true);
+            return Ok(ctx);
+        }
+
         let columns = Self::columns();
         let mut values = Vec::with_capacity(models.len());
+        let mut arguments = QueryArguments::new();
+        let mut snapshots = Vec::new();
         for mut model in models.into_iter() {
             let _model_data = model.before_insert().await?;
 
-            let map = model.into_map();
+            let primary_key = model.primary_key().to_string();
+            let mut map = model.into_map();
+            tenant::stamp_tenant::<Self>(&mut map, tenant_id.as_deref());
+            if recorder.is_some() {
+                snapshots.push((primary_key, audit::snapshot::<Self>(&map)));
+            }
+
             let entries = columns
                 .iter()
-                .map(|col| col.encode_value(map.get(col.name())))
+                .map(|col| col.encode_value(map.get(col.name()), &mut arguments))
                 .collect::<Vec<_>>()
                 .join(", ");
             values.push(format!("({entries})"));
@@ -460,47 +508,106 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let sql = format!("INSERT INTO {table_name} ({fields}) VALUES {values};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool.execute(&sql).await?.rows_affected();
+        let rows_affected = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         Self::after_scan(&ctx).await?;
+        if let Some(recorder) = recorder {
+            for (entity_id, after) in snapshots {
+                audit::record_change::<Self>(
+                    &*recorder,
+                    entity_id,
+                    "insert",
+                    None,
+                    Some(after),
+                    executor.reborrow(),
+                )
+                .await?;
+            }
+        }
         Ok(ctx)
     }
 
     /// Updates the model in the table.
-    async fn update(mut self) -> Result<QueryContext, Error> {
+    async fn update(self) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        self.update_in(pool).await
+    }
+
+    /// Updates the model in the table using the executor.
+    async fn update_in<E>(mut self, mut executor: E) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         let model_data = self.before_update().await?;
+        let before = if recorder.is_some() {
+            audit::fetch_snapshot::<Self, _>(self.primary_key(), executor.reborrow()).await?
+        } else {
+            None
+        };
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Self::table_name();
-        let primary_key = Query::escape_string(self.primary_key());
-        let map = self.into_map();
+        let entity_id = self.primary_key().to_string();
+        let mut map = self.into_map();
+        tenant::stamp_tenant::<Self>(&mut map, tenant_id.as_deref());
+
         let read_only_fields = Self::read_only_fields();
         let num_writable_fields = Self::fields().len() - read_only_fields.len();
         let mut mutations = Vec::with_capacity(num_writable_fields);
+        let mut arguments = QueryArguments::new();
         for col in Self::columns() {
             let field = col.name();
             if !read_only_fields.contains(&field) {
-                let value = col.encode_value(map.get(field));
+                let value = col.encode_value(map.get(field), &mut arguments);
                 let field = Query::format_field(field);
                 mutations.push(format!("{field} = {value}"));
             }
         }
 
         let mutations = mutations.join(", ");
+        let primary_key = Self::primary_key_column()
+            .encode_value(map.get(primary_key_name), &mut arguments)
+            .into_owned();
+        let tenant_filter =
+            tenant::format_tenant_condition::<Self>(tenant_id.as_deref(), &mut arguments);
         let sql = format!(
-            "UPDATE {table_name} SET {mutations} WHERE {primary_key_name} = {primary_key};"
+            "UPDATE {table_name} SET {mutations} \
+                WHERE {primary_key_name} = {primary_key}{tenant_filter};"
         );
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool.execute(&sql).await?.rows_affected();
+        let rows_affected = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         let success = rows_affected == 1;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         Self::after_update(&ctx, model_data).await?;
         if success {
+            if let Some(recorder) = recorder {
+                let after = audit::apply_snapshot::<Self>(before.as_ref(), &map);
+                audit::record_change::<Self>(
+                    &*recorder,
+                    entity_id,
+                    "update",
+                    before,
+                    Some(after),
+                    executor.reborrow(),
+                )
+                .await?;
+            }
             Ok(ctx)
         } else {
             bail!(
@@ -513,12 +620,32 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Updates at most one model selected by the query in the table.
     async fn update_one(query: &Query, mutation: &mut Mutation) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        Self::update_one_in(query, mutation, pool).await
+    }
+
+    /// Updates at most one model selected by the query in the table using the executor.
+    async fn update_one_in<E>(
+        query: &Query,
+        mutation: &mut Mutation,
+        mut executor: E,
+    ) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         Self::before_mutation(query, mutation).await?;
+        let snapshots = if recorder.is_some() {
+            audit::fetch_snapshots::<Self, _>(query, Some(1), executor.reborrow()).await?
+        } else {
+            Vec::new()
+        };
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Self::table_name();
-        let filters = query.format_filters::<Self>();
-        let updates = mutation.format_updates::<Self>();
+        let mut arguments = QueryArguments::new();
+        let updates = mutation.format_updates::<Self>(&mut arguments);
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = if cfg!(any(
             feature = "orm-mariadb",
             feature = "orm-mysql",
@@ -540,13 +667,21 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         };
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool.execute(&sql).await?.rows_affected();
+        let rows_affected = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         let success = rows_affected <= 1;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         Self::after_mutation(&ctx).await?;
         if success {
+            if let Some(recorder) = recorder {
+                audit::record_updates::<Self>(&*recorder, snapshots, executor.reborrow()).await?;
+            }
             Ok(ctx)
         } else {
             bail!(
@@ -559,43 +694,115 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Updates many models selected by the query in the table.
     async fn update_many(query: &Query, mutation: &mut Mutation) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        Self::update_many_in(query, mutation, pool).await
+    }
+
+    /// Updates many models selected by the query in the table using the executor.
+    async fn update_many_in<E>(
+        query: &Query,
+        mutation: &mut Mutation,
+        mut executor: E,
+    ) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         Self::before_mutation(query, mutation).await?;
+        let snapshots = if recorder.is_some() {
+            audit::fetch_snapshots::<Self, _>(query, None, executor.reborrow()).await?
+        } else {
+            Vec::new()
+        };
 
         let table_name = Self::table_name();
-        let filters = query.format_filters::<Self>();
-        let updates = mutation.format_updates::<Self>();
+        let mut arguments = QueryArguments::new();
+        let updates = mutation.format_updates::<Self>(&mut arguments);
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("UPDATE {table_name} SET {updates} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool.execute(&sql).await?.rows_affected();
+        let rows_affected = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         Self::after_scan(&ctx).await?;
         Self::after_mutation(&ctx).await?;
+        if let Some(recorder) = recorder {
+            audit::record_updates::<Self>(&*recorder, snapshots, executor.reborrow()).await?;
+        }
         Ok(ctx)
     }
 
     /// Updates or inserts the model into the table.
-    async fn upsert(mut self) -> Result<QueryContext, Error> {
+    async fn upsert(self) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        self.upsert_in(pool).await
+    }
+
+    /// Updates or inserts the model into the table using the executor.
+    async fn upsert_in<E>(mut self, mut executor: E) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         let model_data = self.before_upsert().await?;
+        let before = if recorder.is_some() {
+            audit::fetch_snapshot::<Self, _>(self.primary_key(), executor.reborrow()).await?
+        } else {
+            None
+        };
+
+        let entity_id = self.primary_key().to_string();
+        let mut map = self.into_map();
+        tenant::stamp_tenant::<Self>(&mut map, tenant_id.as_deref());
 
-        let map = self.into_map();
         let table_name = Self::table_name();
         let fields = Self::fields();
         let num_fields = fields.len();
         let read_only_fields = Self::read_only_fields();
         let num_writable_fields = num_fields - read_only_fields.len();
         let mut values = Vec::with_capacity(num_fields);
+        let mut arguments = QueryArguments::new();
+        for col in Self::columns() {
+            values.push(col.encode_value(map.get(col.name()), &mut arguments));
+        }
+
+        // The values are bound again for the mutations since the placeholders
+        // of MySQL and SQLite are positional.
         let mut mutations = Vec::with_capacity(num_writable_fields);
         for col in Self::columns() {
             let field = col.name();
-            let value = col.encode_value(map.get(field));
-            if !read_only_fields.contains(&field) {
+            if !read_only_fields.contains(&field) && Some(field) != Self::TENANT_KEY {
+                let value = map.get(field);
                 let field = Query::format_field(field);
-                mutations.push(format!("{field} = {value}"));
+                if tenant_id.is_none()
+                    || Self::TENANT_KEY.is_none()
+                    || !cfg!(any(
+                        feature = "orm-mariadb",
+                        feature = "orm-mysql",
+                        feature = "orm-tidb"
+                    ))
+                {
+                    let value = col.encode_value(value, &mut arguments);
+                    mutations.push(format!("{field} = {value}"));
+                } else {
+                    // MySQL does not support a `WHERE` clause in `ON DUPLICATE KEY UPDATE`,
+                    // so the existing rows of the other tenants are kept unchanged.
+                    let condition = tenant::format_tenant_condition::<Self>(
+                        tenant_id.as_deref(),
+                        &mut arguments,
+                    );
+                    let condition = condition.trim_start_matches(" AND ");
+                    let value = col.encode_value(value, &mut arguments);
+                    mutations.push(format!("{field} = IF({condition}, {value}, {field})"));
+                }
             }
-            values.push(value);
         }
 
         let fields = fields.join(", ");
@@ -614,24 +821,47 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
             let primary_key_name = Self::PRIMARY_KEY_NAME;
 
             // Both PostgreQL and SQLite (3.24+) support this syntax.
+            let tenant_filter =
+                tenant::format_tenant_condition::<Self>(tenant_id.as_deref(), &mut arguments);
+            let tenant_filter = tenant_filter
+                .strip_prefix(" AND ")
+                .map(|condition| format!(" WHERE {table_name}.{condition}"))
+                .unwrap_or_default();
             format!(
                 "INSERT INTO {table_name} ({fields}) VALUES ({values}) \
-                    ON CONFLICT ({primary_key_name}) DO UPDATE SET {mutations};"
+                    ON CONFLICT ({primary_key_name}) DO UPDATE SET {mutations}{tenant_filter};"
             )
         };
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let query_result = pool.execute(&sql).await?;
+        let query_result = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?;
         let (last_insert_id, rows_affected) = Query::parse_query_result(query_result);
         let success = rows_affected == 1;
         if let Some(last_insert_id) = last_insert_id {
             ctx.set_last_insert_id(last_insert_id);
         }
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         Self::after_upsert(&ctx, model_data).await?;
         if success {
+            if let Some(recorder) = recorder {
+                let action = if before.is_some() { "update" } else { "insert" };
+                let after = audit::apply_snapshot::<Self>(before.as_ref(), &map);
+                audit::record_change::<Self>(
+                    &*recorder,
+                    entity_id,
+                    action,
+                    before,
+                    Some(after),
+                    executor.reborrow(),
+                )
+                .await?;
+            }
             Ok(ctx)
         } else {
             bail!(
@@ -642,36 +872,69 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     }
 
     /// Deletes the model in the table.
-    async fn delete(mut self) -> Result<QueryContext, Error> {
+    async fn delete(self) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        self.delete_in(pool).await
+    }
+
+    /// Deletes the model in the table using the executor.
+    async fn delete_in<E>(mut self, mut executor: E) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         let model_data = self.before_delete().await?;
+        let before = if recorder.is_some() {
+            audit::fetch_snapshot::<Self, _>(self.primary_key(), executor.reborrow()).await?
+        } else {
+            None
+        };
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Self::table_name();
-        let primary_key = self.primary_key();
-        let placeholder = Query::placeholder(1);
+        let entity_id = self.primary_key().to_string();
+        let mut arguments = QueryArguments::new();
+        let placeholder = Query::bind_string(self.primary_key(), &mut arguments);
+        let tenant_filter =
+            tenant::format_tenant_condition::<Self>(tenant_id.as_deref(), &mut arguments);
         let sql = if cfg!(feature = "orm-postgres") {
             let type_annotation = Self::primary_key_column().type_annotation();
             format!(
                 "DELETE FROM {table_name} \
-                    WHERE {primary_key_name} = ({placeholder}){type_annotation};"
+                    WHERE {primary_key_name} = ({placeholder}){type_annotation}{tenant_filter};"
             )
         } else {
-            format!("DELETE FROM {table_name} WHERE {primary_key_name} = {placeholder};")
+            format!(
+                "DELETE FROM {table_name} \
+                    WHERE {primary_key_name} = {placeholder}{tenant_filter};"
+            )
         };
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool
-            .execute_with(&sql, &[primary_key])
+        let rows_affected = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
             .await?
             .rows_affected();
         let success = rows_affected == 1;
         ctx.set_query(sql);
-        ctx.add_argument(primary_key);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         self.after_delete(&ctx, model_data).await?;
         if success {
+            if let Some(recorder) = recorder {
+                audit::record_change::<Self>(
+                    &*recorder,
+                    entity_id,
+                    "delete",
+                    before,
+                    None,
+                    executor.reborrow(),
+                )
+                .await?;
+            }
             Ok(ctx)
         } else {
             bail!(
@@ -684,11 +947,27 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Deletes at most one model selected by the query in the table.
     async fn delete_one(query: &Query) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        Self::delete_one_in(query, pool).await
+    }
+
+    /// Deletes at most one model selected by the query in the table using the executor.
+    async fn delete_one_in<E>(query: &Query, mut executor: E) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         Self::before_query(query).await?;
+        let snapshots = if recorder.is_some() {
+            audit::fetch_snapshots::<Self, _>(query, Some(1), executor.reborrow()).await?
+        } else {
+            Vec::new()
+        };
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = query.format_table_name::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sort = query.format_sort();
         let sql = format!(
             "DELETE FROM {table_name} WHERE {primary_key_name} IN \
@@ -696,13 +975,28 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         );
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool.execute(&sql).await?.rows_affected();
+        let rows_affected = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         let success = rows_affected <= 1;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
         if success {
+            if let Some(recorder) = recorder {
+                audit::record_changes::<Self>(
+                    &*recorder,
+                    "delete",
+                    snapshots,
+                    Vec::new(),
+                    executor.reborrow(),
+                )
+                .await?;
+            }
             Ok(ctx)
         } else {
             bail!(
@@ -715,18 +1009,49 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Deletes many models selected by the query in the table.
     async fn delete_many(query: &Query) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        Self::delete_many_in(query, pool).await
+    }
+
+    /// Deletes many models selected by the query in the table using the executor.
+    async fn delete_many_in<E>(query: &Query, mut executor: E) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
+        let recorder = audit::audit_recorder::<Self>()?;
         Self::before_query(query).await?;
+        let snapshots = if recorder.is_some() {
+            audit::fetch_snapshots::<Self, _>(query, None, executor.reborrow()).await?
+        } else {
+            Vec::new()
+        };
 
         let table_name = query.format_table_name::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("DELETE FROM {table_name} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool.execute(&sql).await?.rows_affected();
+        let rows_affected = executor
+            .reborrow()
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
+        if let Some(recorder) = recorder {
+            audit::record_changes::<Self>(
+                &*recorder,
+                "delete",
+                snapshots,
+                Vec::new(),
+                executor.reborrow(),
+            )
+            .await?;
+        }
         Ok(ctx)
     }
 
@@ -737,22 +1062,38 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         let pool = Self::acquire_reader().await?.pool();
+        Self::find_in(query, pool).await
+    }
+
+    /// Finds a list of models selected by the query in the table using the executor,
+    /// and decodes it as `Vec<T>`.
+    async fn find_in<T, E>(query: &Query, executor: E) -> Result<Vec<T>, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error>,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
         Self::before_query(query).await?;
 
         let table_name = query.format_table_name::<Self>();
         let projection = query.format_table_fields::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sort = query.format_sort();
         let pagination = query.format_pagination();
         let sql = format!("SELECT {projection} FROM {table_name} {filters} {sort} {pagination};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows = pool.fetch(&sql).await?;
+        let rows = executor.fetch_with_values(&sql, arguments.values()).await?;
         let mut data = Vec::with_capacity(rows.len());
         for row in rows {
             data.push(T::decode_row(&row)?);
         }
+        if query.cursor().is_some_and(|(_, backward)| backward) {
+            data.reverse();
+        }
         ctx.set_query(&sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(u64::try_from(data.len())?), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -771,6 +1112,51 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         serde_json::from_value(data.into()).map_err(Error::from)
     }
 
+    /// Finds a list of models selected by the query in the table using the executor,
+    /// and parses it as `Vec<T>`.
+    async fn find_as_in<T, E>(query: &Query, executor: E) -> Result<Vec<T>, Error>
+    where
+        T: DeserializeOwned,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        let mut data = Self::find_in::<Map, E>(query, executor).await?;
+        let translate_enabled = query.translate_enabled();
+        for model in data.iter_mut() {
+            Self::after_decode(model).await?;
+            translate_enabled.then(|| Self::translate_model(model));
+        }
+        serde_json::from_value(data.into()).map_err(Error::from)
+    }
+
+    /// Finds a list of models selected by the query in the table,
+    /// and returns a stream of the rows decoded as `T`.
+    ///
+    /// Unlike [`find()`](Schema::find), the rows are fetched lazily and the number of rows
+    /// is not limited by the `max-rows`. The `after_scan` and `after_query` hooks are not called
+    /// since the stream is consumed after returning, and the query will be logged
+    /// when the stream is finished. Paging backward by a cursor is not supported.
+    async fn find_stream<T>(query: &Query) -> Result<BoxStream<'static, Result<T, Error>>, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error> + Send + 'static,
+    {
+        if query.cursor().is_some_and(|(_, backward)| backward) {
+            bail!("paging backward is not supported for the stream");
+        }
+        tenant::current_tenant_id::<Self>()?;
+        let pool = Self::acquire_reader().await?.pool();
+        Self::before_query(query).await?;
+
+        let table_name = query.format_table_name::<Self>();
+        let projection = query.format_table_fields::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
+        let sort = query.format_sort();
+        let pagination = query.format_pagination();
+        let sql = format!("SELECT {projection} FROM {table_name} {filters} {sort} {pagination};");
+        let ctx = Self::before_scan(&sql).await?;
+        Ok(executor::fetch_stream(pool, sql, arguments, ctx))
+    }
+
     /// Finds one model selected by the query in the table,
     /// and decodes it as an instance of type `T`.
     async fn find_one<T>(query: &Query) -> Result<Option<T>, Error>
@@ -778,21 +1164,37 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         let pool = Self::acquire_reader().await?.pool();
+        Self::find_one_in(query, pool).await
+    }
+
+    /// Finds one model selected by the query in the table using the executor,
+    /// and decodes it as an instance of type `T`.
+    async fn find_one_in<T, E>(query: &Query, executor: E) -> Result<Option<T>, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error>,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
         Self::before_query(query).await?;
 
         let table_name = query.format_table_name::<Self>();
         let projection = query.format_table_fields::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sort = query.format_sort();
         let sql = format!("SELECT {projection} FROM {table_name} {filters} {sort} LIMIT 1;");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let (num_rows, data) = if let Some(row) = pool.fetch_optional(&sql).await? {
+        let (num_rows, data) = if let Some(row) = executor
+            .fetch_optional_with_values(&sql, arguments.values())
+            .await?
+        {
             (1, Some(T::decode_row(&row)?))
         } else {
             (0, None)
         };
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(num_rows), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -814,6 +1216,25 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         }
     }
 
+    /// Finds one model selected by the query in the table using the executor,
+    /// and parses it as an instance of type `T`.
+    async fn find_one_as_in<T, E>(query: &Query, executor: E) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        match Self::find_one_in::<Map, E>(query, executor).await? {
+            Some(mut data) => {
+                Self::after_decode(&mut data).await?;
+                query
+                    .translate_enabled()
+                    .then(|| Self::translate_model(&mut data));
+                serde_json::from_value(data.into()).map_err(Error::from)
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Populates the related data in the corresponding `columns` for `Vec<Map>` using
     /// a merged select on the primary key, which solves the `N+1` problem.
     async fn populate(
@@ -822,6 +1243,20 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         columns: &[&str],
     ) -> Result<u64, Error> {
         let pool = Self::acquire_reader().await?.pool();
+        Self::populate_in(query, data, columns, pool).await
+    }
+
+    /// Populates the related data in the corresponding `columns` for `Vec<Map>` using
+    /// the executor, which solves the `N+1` problem with a merged select on the primary key.
+    async fn populate_in<E>(
+        query: &mut Query,
+        data: &mut Vec<Map>,
+        columns: &[&str],
+        executor: E,
+    ) -> Result<u64, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         Self::before_query(query).await?;
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
@@ -852,11 +1287,12 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
 
         let table_name = query.format_table_name::<Self>();
         let projection = query.format_table_fields::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("SELECT {projection} FROM {table_name} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows = pool.fetch(&sql).await?;
+        let rows = executor.fetch_with_values(&sql, arguments.values()).await?;
         let translate_enabled = query.translate_enabled();
         let mut associations = Vec::with_capacity(num_values);
         for row in rows {
@@ -871,6 +1307,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
 
         let associations_len = u64::try_from(associations.len())?;
         ctx.set_query(&sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(associations_len), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -915,6 +1352,20 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         columns: &[&str],
     ) -> Result<(), Error> {
         let pool = Self::acquire_reader().await?.pool();
+        Self::populate_one_in(query, data, columns, pool).await
+    }
+
+    /// Populates the related data in the corresponding `columns` for `Map` using
+    /// the executor, which solves the `N+1` problem with a merged select on the primary key.
+    async fn populate_one_in<E>(
+        query: &mut Query,
+        data: &mut Map,
+        columns: &[&str],
+        executor: E,
+    ) -> Result<(), Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         Self::before_query(query).await?;
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
@@ -943,11 +1394,12 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
 
         let table_name = Self::table_name();
         let projection = query.format_projection();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("SELECT {projection} FROM {table_name} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows = pool.fetch(&sql).await?;
+        let rows = executor.fetch_with_values(&sql, arguments.values()).await?;
         let translate_enabled = query.translate_enabled();
         let mut associations = Vec::with_capacity(num_values);
         for row in rows {
@@ -960,6 +1412,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
             }
         }
         ctx.set_query(&sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(u64::try_from(associations.len())?), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -1002,6 +1455,21 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         let pool = Self::acquire_reader().await?.pool();
+        Self::lookup_in::<M, T, _>(query, columns, pool).await
+    }
+
+    /// Performs a left outer join to another table to filter rows in the joined table
+    /// using the executor, and decodes it as `Vec<T>`.
+    async fn lookup_in<M, T, E>(
+        query: &Query,
+        columns: &[(&str, &str)],
+        executor: E,
+    ) -> Result<Vec<T>, Error>
+    where
+        M: Schema,
+        T: DecodeRow<DatabaseRow, Error = Error>,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         Self::before_query(query).await?;
 
         let model_name = Self::model_name();
@@ -1009,7 +1477,8 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let table_name = query.format_table_name::<Self>();
         let other_table_name = query.format_table_name::<M>();
         let projection = query.format_table_fields::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sort = query.format_sort();
         let pagination = query.format_pagination();
         let on_expressions = columns
@@ -1030,12 +1499,16 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         );
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows = pool.fetch(&sql).await?;
+        let rows = executor.fetch_with_values(&sql, arguments.values()).await?;
         let mut data = Vec::with_capacity(rows.len());
         for row in rows {
             data.push(T::decode_row(&row)?);
         }
+        if query.cursor().is_some_and(|(_, backward)| backward) {
+            data.reverse();
+        }
         ctx.set_query(&sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(u64::try_from(data.len())?), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -1061,16 +1534,29 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Checks whether there is a model selected by the query in the table.
     async fn exists(query: &Query) -> Result<bool, Error> {
         let pool = Self::acquire_reader().await?.pool();
+        Self::exists_in(query, pool).await
+    }
+
+    /// Checks whether there is a model selected by the query in the table using the executor.
+    async fn exists_in<E>(query: &Query, executor: E) -> Result<bool, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
         Self::before_query(query).await?;
 
         let table_name = Self::table_name();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("SELECT 1 FROM {table_name} {filters} LIMIT 1;");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let row = pool.fetch_optional(&sql).await?;
+        let row = executor
+            .fetch_optional_with_values(&sql, arguments.values())
+            .await?;
         let num_rows = if row.is_some() { 1 } else { 0 };
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(num_rows), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -1080,19 +1566,32 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Counts the number of rows selected by the query in the table.
     async fn count(query: &Query) -> Result<u64, Error> {
         let pool = Self::acquire_reader().await?.pool();
+        Self::count_in(query, pool).await
+    }
+
+    /// Counts the number of rows selected by the query in the table using the executor.
+    async fn count_in<E>(query: &Query, executor: E) -> Result<u64, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
+        tenant::current_tenant_id::<Self>()?;
         Self::before_count(query).await?;
 
         let table_name = Self::table_name();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("SELECT count(*) AS count FROM {table_name} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let row = pool.fetch_one(&sql).await?;
+        let row = executor
+            .fetch_one_with_values(&sql, arguments.values())
+            .await?;
         let map = Map::decode_row(&row)?;
 
         // SQLite may return a string value for the count value.
         let count = map.parse_u64("count").transpose()?.unwrap_or_default();
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(count), true);
         Self::after_scan(&ctx).await?;
         Self::after_count(&ctx).await?;
@@ -1106,10 +1605,25 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         let pool = Self::acquire_reader().await?.pool();
+        Self::count_many_in::<T, _>(query, columns, pool).await
+    }
+
+    /// Counts the number of rows selected by the query in the table using the executor.
+    /// The boolean value determines whether it only counts distinct values or not.
+    async fn count_many_in<T, E>(
+        query: &Query,
+        columns: &[(&str, bool)],
+        executor: E,
+    ) -> Result<T, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error>,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         Self::before_count(query).await?;
 
         let table_name = query.format_table_name::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let projection = columns
             .iter()
             .map(|&(key, distinct)| {
@@ -1129,8 +1643,11 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let sql = format!("SELECT {projection} FROM {table_name} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let row = pool.fetch_one(&sql).await?;
+        let row = executor
+            .fetch_one_with_values(&sql, arguments.values())
+            .await?;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(1), true);
         Self::after_scan(&ctx).await?;
         Self::after_count(&ctx).await?;
@@ -1150,6 +1667,19 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Executes the query in the table, and returns the total number of rows affected.
     async fn execute(query: &str, params: Option<&Map>) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        Self::execute_in(query, params, pool).await
+    }
+
+    /// Executes the query in the table using the executor,
+    /// and returns the total number of rows affected.
+    async fn execute_in<E>(
+        query: &str,
+        params: Option<&Map>,
+        executor: E,
+    ) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         let (sql, values) = Query::prepare_query(query, params);
 
         let mut ctx = Self::before_scan(&sql).await?;
@@ -1158,7 +1688,10 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
             .map(|v| v.to_string_unquoted())
             .collect::<Vec<_>>();
 
-        let rows_affected = pool.execute_with(&sql, &arguments).await?.rows_affected();
+        let rows_affected = executor
+            .execute_with_values(&sql, &arguments)
+            .await?
+            .rows_affected();
         ctx.set_query(sql);
         ctx.append_arguments(&mut arguments);
         ctx.set_query_result(Some(rows_affected), true);
@@ -1172,6 +1705,15 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         let pool = Self::acquire_reader().await?.pool();
+        Self::query_in::<T, _>(query, params, pool).await
+    }
+
+    /// Executes the query in the table using the executor, and decodes it as `Vec<T>`.
+    async fn query_in<T, E>(query: &str, params: Option<&Map>, executor: E) -> Result<Vec<T>, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error>,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         let (sql, values) = Query::prepare_query(query, params);
 
         let mut ctx = Self::before_scan(&sql).await?;
@@ -1180,7 +1722,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
             .map(|v| v.to_string_unquoted())
             .collect::<Vec<_>>();
 
-        let rows = pool.fetch_with(&sql, &arguments).await?;
+        let rows = executor.fetch_with_values(&sql, &arguments).await?;
         let mut data = Vec::with_capacity(rows.len());
         for row in rows {
             data.push(T::decode_row(&row)?);
@@ -1204,12 +1746,54 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         serde_json::from_value(data.into()).map_err(Error::from)
     }
 
+    /// Executes the query in the table, and returns a stream of the rows decoded as `T`.
+    ///
+    /// Unlike [`query()`](Schema::query), the rows are fetched lazily and the number of rows
+    /// is not limited by the `max-rows`. The `after_scan` hook is not called,
+    /// and the query will be logged when the stream is finished.
+    async fn query_stream<T>(
+        query: &str,
+        params: Option<&Map>,
+    ) -> Result<BoxStream<'static, Result<T, Error>>, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error> + Send + 'static,
+    {
+        let pool = Self::acquire_reader().await?.pool();
+        let (sql, values) = Query::prepare_query(query, params);
+
+        let ctx = Self::before_scan(&sql).await?;
+        let mut arguments = QueryArguments::new();
+        for value in values {
+            arguments.push(value.to_string_unquoted());
+        }
+        Ok(executor::fetch_stream(
+            pool,
+            sql.into_owned(),
+            arguments,
+            ctx,
+        ))
+    }
+
     /// Executes the query in the table, and decodes it as an instance of type `T`.
     async fn query_one<T>(query: &str, params: Option<&Map>) -> Result<Option<T>, Error>
     where
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         let pool = Self::acquire_reader().await?.pool();
+        Self::query_one_in::<T, _>(query, params, pool).await
+    }
+
+    /// Executes the query in the table using the executor,
+    /// and decodes it as an instance of type `T`.
+    async fn query_one_in<T, E>(
+        query: &str,
+        params: Option<&Map>,
+        executor: E,
+    ) -> Result<Option<T>, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error>,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         let (sql, values) = Query::prepare_query(query, params);
 
         let mut ctx = Self::before_scan(&sql).await?;
@@ -1218,7 +1802,9 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
             .map(|v| v.to_string_unquoted())
             .collect::<Vec<_>>();
 
-        let (num_rows, data) = if let Some(row) = pool.fetch_optional_with(&sql, &arguments).await?
+        let (num_rows, data) = if let Some(row) = executor
+            .fetch_optional_with_values(&sql, &arguments)
+            .await?
         {
             (1, Some(T::decode_row(&row)?))
         } else {
@@ -1248,28 +1834,45 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Deletes a model selected by the primary key in the table.
     async fn delete_by_id(primary_key: &Self::PrimaryKey) -> Result<QueryContext, Error> {
         let pool = Self::acquire_writer().await?.pool();
+        Self::delete_by_id_in(primary_key, pool).await
+    }
 
+    /// Deletes a model selected by the primary key in the table using the executor.
+    async fn delete_by_id_in<E>(
+        primary_key: &Self::PrimaryKey,
+        executor: E,
+    ) -> Result<QueryContext, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Self::table_name();
-        let placeholder = Query::placeholder(1);
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let mut arguments = QueryArguments::new();
+        let placeholder = Query::bind_string(primary_key, &mut arguments);
+        let tenant_filter =
+            tenant::format_tenant_condition::<Self>(tenant_id.as_deref(), &mut arguments);
         let sql = if cfg!(feature = "orm-postgres") {
             let type_annotation = Self::primary_key_column().type_annotation();
             format!(
                 "DELETE FROM {table_name} \
-                    WHERE {primary_key_name} = ({placeholder}){type_annotation};"
+                    WHERE {primary_key_name} = ({placeholder}){type_annotation}{tenant_filter};"
             )
         } else {
-            format!("DELETE FROM {table_name} WHERE {primary_key_name} = {placeholder};")
+            format!(
+                "DELETE FROM {table_name} \
+                    WHERE {primary_key_name} = {placeholder}{tenant_filter};"
+            )
         };
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let rows_affected = pool
-            .execute_with(&sql, &[primary_key])
+        let rows_affected = executor
+            .execute_with_values(&sql, arguments.values())
             .await?
             .rows_affected();
         let success = rows_affected == 1;
         ctx.set_query(sql);
-        ctx.add_argument(primary_key);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         if success {
@@ -1289,33 +1892,52 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         let pool = Self::acquire_reader().await?.pool();
+        Self::find_by_id_in::<T, _>(primary_key, pool).await
+    }
 
+    /// Finds a model selected by the primary key in the table using the executor,
+    /// and decodes it as an instance of type `T`.
+    async fn find_by_id_in<T, E>(
+        primary_key: &Self::PrimaryKey,
+        executor: E,
+    ) -> Result<Option<T>, Error>
+    where
+        T: DecodeRow<DatabaseRow, Error = Error>,
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Self::table_name();
         let query = Self::default_query();
         let projection = query.format_projection();
-        let placeholder = Query::placeholder(1);
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let mut arguments = QueryArguments::new();
+        let placeholder = Query::bind_string(primary_key, &mut arguments);
+        let tenant_filter =
+            tenant::format_tenant_condition::<Self>(tenant_id.as_deref(), &mut arguments);
         let sql = if cfg!(feature = "orm-postgres") {
             let type_annotation = Self::primary_key_column().type_annotation();
             format!(
                 "SELECT {projection} FROM {table_name} \
-                    WHERE {primary_key_name} = ({placeholder}){type_annotation};"
+                    WHERE {primary_key_name} = ({placeholder}){type_annotation}{tenant_filter};"
             )
         } else {
             format!(
-                "SELECT {projection} FROM {table_name} WHERE {primary_key_name} = {placeholder};"
+                "SELECT {projection} FROM {table_name} \
+                    WHERE {primary_key_name} = {placeholder}{tenant_filter};"
             )
         };
         let mut ctx = Self::before_scan(&sql).await?;
 
-        let (num_rows, data) =
-            if let Some(row) = pool.fetch_optional_with(&sql, &[primary_key]).await? {
-                (1, Some(T::decode_row(&row)?))
-            } else {
-                (0, None)
-            };
+        let (num_rows, data) = if let Some(row) = executor
+            .fetch_optional_with_values(&sql, arguments.values())
+            .await?
+        {
+            (1, Some(T::decode_row(&row)?))
+        } else {
+            (0, None)
+        };
         ctx.set_query(sql);
-        ctx.add_argument(primary_key);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(num_rows), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -1325,27 +1947,43 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Finds a model selected by the primary key in the table, and parses it as `Self`.
     async fn try_get_model(primary_key: &Self::PrimaryKey) -> Result<Self, Error> {
         let pool = Self::acquire_reader().await?.pool();
+        Self::try_get_model_in(primary_key, pool).await
+    }
 
+    /// Finds a model selected by the primary key in the table using the executor,
+    /// and parses it as `Self`.
+    async fn try_get_model_in<E>(primary_key: &Self::PrimaryKey, executor: E) -> Result<Self, Error>
+    where
+        E: Executor<Row = DatabaseRow, QueryResult = DatabaseQueryResult>,
+    {
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Self::table_name();
         let query = Self::default_query();
         let projection = query.format_projection();
-        let placeholder = Query::placeholder(1);
+        let tenant_id = tenant::current_tenant_id::<Self>()?;
+        let mut arguments = QueryArguments::new();
+        let placeholder = Query::bind_string(primary_key, &mut arguments);
+        let tenant_filter =
+            tenant::format_tenant_condition::<Self>(tenant_id.as_deref(), &mut arguments);
         let sql = if cfg!(feature = "orm-postgres") {
             let type_annotation = Self::primary_key_column().type_annotation();
             format!(
                 "SELECT {projection} FROM {table_name} \
-                    WHERE {primary_key_name} = ({placeholder}){type_annotation};"
+                    WHERE {primary_key_name} = ({placeholder}){type_annotation}{tenant_filter};"
             )
         } else {
             format!(
-                "SELECT {projection} FROM {table_name} WHERE {primary_key_name} = {placeholder};"
+                "SELECT {projection} FROM {table_name} \
+                    WHERE {primary_key_name} = {placeholder}{tenant_filter};"
             )
         };
         let mut ctx = Self::before_scan(&sql).await?;
 
-        ctx.add_argument(primary_key);
-        if let Some(row) = pool.fetch_optional_with(&sql, &[primary_key]).await? {
+        ctx.append_arguments(&mut arguments.format_values());
+        if let Some(row) = executor
+            .fetch_optional_with_values(&sql, arguments.values())
+            .await?
+        {
             ctx.set_query(sql);
             ctx.set_query_result(Some(1), true);
             Self::after_scan(&ctx).await?;
@@ -1367,6 +2005,22 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         }
     }
 
+    /// Returns the audit trail of the model with the primary key
+    /// in the order of the recorded time.
+    async fn audit_history(primary_key: &Self::PrimaryKey) -> Result<Vec<AuditEntry>, Error> {
+        AuditTrail::history(Self::MODEL_NAME, &primary_key.to_string()).await
+    }
+
+    /// Reconstructs the state of the model with the primary key at the time
+    /// from the audit trail. It returns `None` if the model does not exist at that time.
+    async fn audit_state_at(
+        primary_key: &Self::PrimaryKey,
+        time: DateTime,
+    ) -> Result<Option<Map>, Error> {
+        let entries = Self::audit_history(primary_key).await?;
+        Ok(AuditTrail::state_at(&entries, time))
+    }
+
     /// Randomly selects the specified number of models from the table
     /// and returns a list of the primary key values.
     async fn sample(size: usize) -> Result<Vec<JsonValue>, Error> {
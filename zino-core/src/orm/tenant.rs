@@ -0,0 +1,272 @@
+use super::{query::QueryExt, Schema};
+use crate::{
+    error::Error,
+    extension::JsonObjectExt,
+    model::{EncodeColumn, Query, QueryArguments},
+    warn, JsonValue, Map,
+};
+use std::{cell::RefCell, future::Future};
+
+/// Tenant of the current scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TenantScope {
+    /// The tenant is unspecified, and the multi-tenant models can not be accessed.
+    #[default]
+    Unspecified,
+    /// A specific tenant.
+    Tenant(String),
+    /// A superuser who can access the data of all tenants.
+    Superuser,
+}
+
+/// A request-scoped context of the tenant for the row-level multi-tenancy.
+///
+/// The rows of a model with a `#[schema(tenant_key)]` field are filtered by
+/// the tenant in the current scope, and the new rows are stamped with it.
+/// Accessing the model outside of a tenant scope is an error,
+/// unless it runs in the explicit [`scope_superuser()`](Self::scope_superuser).
+///
+/// The scope is a task-local value which is not inherited by a spawned task,
+/// so the future should be wrapped with [`propagate()`](Self::propagate) before spawning.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TenantContext;
+
+impl TenantContext {
+    /// Runs the future in a scope where the tenant is unspecified until it is set.
+    /// It is used to wrap the handling of a request.
+    #[inline]
+    pub async fn scope<F: Future>(future: F) -> F::Output {
+        TENANT_SCOPE
+            .scope(RefCell::new(TenantScope::Unspecified), future)
+            .await
+    }
+
+    /// Runs the future in the scope of a specific tenant.
+    #[inline]
+    pub async fn scope_tenant<F: Future>(tenant_id: impl ToString, future: F) -> F::Output {
+        let tenant = TenantScope::Tenant(tenant_id.to_string());
+        TENANT_SCOPE.scope(RefCell::new(tenant), future).await
+    }
+
+    /// Runs the future in a superuser scope which bypasses the tenant isolation.
+    /// It is the explicit escape hatch for the administrative tasks.
+    #[inline]
+    pub async fn scope_superuser<F: Future>(future: F) -> F::Output {
+        TENANT_SCOPE
+            .scope(RefCell::new(TenantScope::Superuser), future)
+            .await
+    }
+
+    /// Wraps the future in a copy of the current scope,
+    /// which can be spawned as a task accessing the same tenant.
+    #[inline]
+    pub fn propagate<F: Future>(future: F) -> impl Future<Output = F::Output> {
+        TENANT_SCOPE.scope(RefCell::new(Self::current()), future)
+    }
+
+    /// Sets the tenant for the rest of the current scope.
+    /// It is an error to set the tenant outside of a scope or to switch to another tenant.
+    pub fn set_tenant_id(tenant_id: impl ToString) -> Result<(), Error> {
+        let tenant_id = tenant_id.to_string();
+        TENANT_SCOPE
+            .try_with(|scope| {
+                let mut scope = scope.borrow_mut();
+                match &*scope {
+                    TenantScope::Tenant(current) if *current != tenant_id => Err(warn!(
+                        "the tenant `{}` can not be switched to `{}`",
+                        current, tenant_id
+                    )),
+                    _ => {
+                        *scope = TenantScope::Tenant(tenant_id);
+                        Ok(())
+                    }
+                }
+            })
+            .map_err(|_| warn!("the tenant can only be set in a tenant scope"))?
+    }
+
+    /// Returns the tenant of the current scope.
+    #[inline]
+    pub fn current() -> TenantScope {
+        TENANT_SCOPE
+            .try_with(|scope| scope.borrow().clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the tenant ID of the current scope for the model.
+/// It returns `None` if the model has no tenant key or the scope is a superuser.
+pub(super) fn current_tenant_id<M: Schema>() -> Result<Option<String>, Error> {
+    if M::TENANT_KEY.is_none() {
+        return Ok(None);
+    }
+    match TenantContext::current() {
+        TenantScope::Tenant(tenant_id) => Ok(Some(tenant_id)),
+        TenantScope::Superuser => Ok(None),
+        TenantScope::Unspecified => Err(warn!(
+            "the tenant should be specified to access the model `{}`",
+            M::MODEL_NAME
+        )),
+    }
+}
+
+/// Formats the tenant filter for the model in the current scope.
+/// Nothing is matched if the tenant is unspecified.
+pub(super) fn format_tenant_filter<M: Schema>(arguments: &mut QueryArguments) -> Option<String> {
+    let tenant_key = M::TENANT_KEY?;
+    match TenantContext::current() {
+        TenantScope::Tenant(tenant_id) => {
+            let col = M::get_column(tenant_key)?;
+            Some(col.format_filter(tenant_key, &JsonValue::String(tenant_id), arguments))
+        }
+        TenantScope::Superuser => None,
+        TenantScope::Unspecified => Some("1 = 0".to_owned()),
+    }
+}
+
+/// Stamps the tenant ID on the model data.
+pub(super) fn stamp_tenant<M: Schema>(model: &mut Map, tenant_id: Option<&str>) {
+    if let (Some(tenant_key), Some(tenant_id)) = (M::TENANT_KEY, tenant_id) {
+        model.upsert(tenant_key, tenant_id);
+    }
+}
+
+/// Formats the tenant condition with an `AND` prefix, and the value is bound as an argument.
+/// It is empty if there is no tenant ID.
+pub(super) fn format_tenant_condition<M: Schema>(
+    tenant_id: Option<&str>,
+    arguments: &mut QueryArguments,
+) -> String {
+    if let (Some(tenant_key), Some(tenant_id)) = (M::TENANT_KEY, tenant_id) {
+        let tenant_key = Query::format_field(tenant_key);
+        let tenant_id = Query::bind_string(tenant_id, arguments);
+        format!(" AND {tenant_key} = {tenant_id}")
+    } else {
+        String::new()
+    }
+}
+
+tokio::task_local! {
+    /// Tenant of the current scope.
+    static TENANT_SCOPE: RefCell<TenantScope>;
+}
+
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+mod tests {
+    use super::{TenantContext, TenantScope};
+    use crate::{
+        extension::JsonObjectExt,
+        model::{Mutation, Query},
+        orm::{
+            fixture::{block_on, create_table, test_model},
+            Schema,
+        },
+        Map,
+    };
+
+    test_model!(
+        Document,
+        "test_tenant_document",
+        auto_increment = false,
+        const TENANT_KEY: Option<&'static str> = Some("tenant_id");
+    );
+
+    #[test]
+    fn it_scopes_tenants() {
+        assert_eq!(TenantContext::current(), TenantScope::Unspecified);
+        assert!(TenantContext::set_tenant_id("a").is_err());
+
+        block_on(TenantContext::scope(async {
+            assert!(TenantContext::set_tenant_id("a").is_ok());
+            assert!(TenantContext::set_tenant_id("a").is_ok());
+            assert!(TenantContext::set_tenant_id("b").is_err());
+            assert_eq!(
+                TenantContext::current(),
+                TenantScope::Tenant("a".to_owned())
+            );
+
+            TenantContext::scope_superuser(async {
+                assert_eq!(TenantContext::current(), TenantScope::Superuser);
+            })
+            .await;
+            assert_eq!(
+                TenantContext::current(),
+                TenantScope::Tenant("a".to_owned())
+            );
+
+            let task = tokio::spawn(TenantContext::propagate(async { TenantContext::current() }));
+            assert_eq!(task.await.unwrap(), TenantScope::Tenant("a".to_owned()));
+            let task = tokio::spawn(async { TenantContext::current() });
+            assert_eq!(task.await.unwrap(), TenantScope::Unspecified);
+        }));
+    }
+
+    #[test]
+    fn it_isolates_the_rows_of_other_tenants() {
+        block_on(async {
+            create_table("test_tenant_document", false).await;
+            for (id, tenant_id) in [(1, "a"), (2, "b")] {
+                let document = Document {
+                    id,
+                    name: format!("{tenant_id}{id}"),
+                    ..Default::default()
+                };
+                TenantContext::scope_tenant(tenant_id, document.insert())
+                    .await
+                    .unwrap();
+            }
+
+            TenantContext::scope_tenant("b", async {
+                assert!(Document::find_by_id::<Map>(&1).await.unwrap().is_none());
+                assert!(Document::try_get_model(&1).await.is_err());
+                assert!(Document::delete_by_id(&1).await.is_err());
+
+                let query = Query::new(Map::from_entry("id", 1));
+                let mut mutation = Mutation::new(Map::from_entry("name", "b1"));
+                let ctx = Document::update_one(&query, &mut mutation).await.unwrap();
+                assert_eq!(ctx.rows_affected(), Some(0));
+
+                let document = Document {
+                    id: 1,
+                    name: "b1".to_owned(),
+                    ..Default::default()
+                };
+                assert!(document.update().await.is_err());
+
+                let mut updates = Map::new();
+                updates.upsert("tenant_id", "a");
+                updates.upsert("name", "b3");
+                let mut mutation = Mutation::new(updates);
+                let query = Query::new(Map::new());
+                Document::update_many(&query, &mut mutation).await.unwrap();
+
+                let documents = Document::find_as::<Document>(&query).await.unwrap();
+                assert_eq!(documents.len(), 1);
+                assert_eq!(documents[0].tenant_id, "b");
+                assert_eq!(documents[0].name, "b3");
+            })
+            .await;
+
+            TenantContext::scope_tenant("a", async {
+                let document = Document::try_get_model(&1).await.unwrap();
+                assert_eq!(document.name, "a1");
+            })
+            .await;
+
+            TenantContext::scope_superuser(async {
+                let query = Query::new(Map::new());
+                let documents = Document::find_as::<Document>(&query).await.unwrap();
+                assert_eq!(documents.len(), 2);
+            })
+            .await;
+        });
+    }
+}
@@ -4,13 +4,17 @@ use super::{
 use crate::{
     error::Error,
     extension::JsonValueExt,
-    model::{EncodeColumn, Mutation, Query},
+    model::{EncodeColumn, Mutation, Query, QueryArguments},
     BoxFuture, Map,
 };
 use std::fmt::Display;
 
+#[cfg(feature = "orm-sqlx")]
+use super::{ConnectionPool, DatabaseConnection};
 #[cfg(feature = "orm-sqlx")]
 use sqlx::Acquire;
+#[cfg(feature = "orm-sqlx")]
+use std::ops::{Deref, DerefMut};
 
 /// An in-progress database transaction.
 pub trait Transaction<K, Tx>: Schema<PrimaryKey = K>
@@ -24,6 +28,11 @@ where
     where
         F: for<'t> FnOnce(&'t mut Tx) -> BoxFuture<'t, Result<T, Error>>;
 
+    /// Begins a transaction on the writer of the model and returns a handle,
+    /// which can be used as an executor for the `*_in` methods of [`Schema`].
+    #[cfg(feature = "orm-sqlx")]
+    async fn begin() -> Result<TransactionHandle<'static>, Error>;
+
     /// Executes the queries sequentially inside of a transaction.
     /// If it returns an error, the transaction will be rolled back;
     /// if not, the transaction will be committed.
@@ -57,9 +66,15 @@ where
         let mut transaction = Self::acquire_writer().await?.pool().begin().await?;
         let data = tx(&mut transaction).await?;
         transaction.commit().await?;
+        super::GlobalPool::pin_writer();
         Ok(data)
     }
 
+    #[inline]
+    async fn begin() -> Result<TransactionHandle<'static>, Error> {
+        TransactionHandle::begin(Self::acquire_writer().await?).await
+    }
+
     async fn transactional_execute(queries: &[&str], params: Option<&Map>) -> Result<u64, Error> {
         let mut transaction = Self::acquire_writer().await?.pool().begin().await?;
         let connection = transaction.acquire().await?;
@@ -75,7 +90,7 @@ where
                 .collect::<Vec<_>>();
 
             let rows_affected = connection
-                .execute_with(&sql, &arguments)
+                .execute_with_values(&sql, &arguments)
                 .await?
                 .rows_affected();
             total_rows += rows_affected;
@@ -85,6 +100,7 @@ where
             Self::after_scan(&ctx).await?;
         }
         transaction.commit().await?;
+        super::GlobalPool::pin_writer();
         Ok(total_rows)
     }
 
@@ -99,6 +115,7 @@ where
         let columns = Self::columns();
 
         let mut fields = Vec::with_capacity(columns.len());
+        let mut arguments = QueryArguments::new();
         let values = columns
             .iter()
             .filter_map(|col| {
@@ -107,7 +124,7 @@ where
                 } else {
                     let name = col.name();
                     fields.push(name);
-                    Some(col.encode_value(map.get(name)))
+                    Some(col.encode_value(map.get(name), &mut arguments))
                 }
             })
             .collect::<Vec<_>>()
@@ -117,7 +134,9 @@ where
         let mut ctx = Self::before_scan(&sql).await?;
 
         let mut total_rows = 0;
-        let query_result = connection.execute(&sql).await?;
+        let query_result = connection
+            .execute_with_values(&sql, arguments.values())
+            .await?;
         let (last_insert_id, rows_affected) = Query::parse_query_result(query_result);
         let success = rows_affected == 1;
         if let Some(last_insert_id) = last_insert_id {
@@ -125,39 +144,50 @@ where
         }
         total_rows += rows_affected;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), success);
         Self::after_scan(&ctx).await?;
         Self::after_insert(&ctx, model_data).await?;
 
         // Inserts associations
         let columns = S::columns();
-        let mut values = Vec::with_capacity(models.len());
+        let mut maps = Vec::with_capacity(models.len());
         for mut model in models.into_iter() {
             let _model_data = model.before_insert().await?;
-
-            let map = model.into_map();
-            let entries = columns
-                .iter()
-                .map(|col| col.encode_value(map.get(col.name())))
-                .collect::<Vec<_>>()
-                .join(", ");
-            values.push(format!("({entries})"));
+            maps.push(model.into_map());
         }
 
         let table_name = S::table_name();
         let fields = S::fields().join(", ");
-        let values = values.join(", ");
+        let mut arguments = QueryArguments::new();
+        let values = maps
+            .iter()
+            .map(|map| {
+                let entries = columns
+                    .iter()
+                    .map(|col| col.encode_value(map.get(col.name()), &mut arguments))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("({entries})")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
         let sql = format!("INSERT INTO {table_name} ({fields}) VALUES {values};");
         let mut ctx = S::before_scan(&sql).await?;
 
-        let rows_affected = connection.execute(&sql).await?.rows_affected();
+        let rows_affected = connection
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         total_rows += rows_affected;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         S::after_scan(&ctx).await?;
 
         // Commits the transaction
         transaction.commit().await?;
+        super::GlobalPool::pin_writer();
         Ok(total_rows)
     }
 
@@ -173,15 +203,20 @@ where
         Self::before_mutation(query, mutation).await?;
 
         let table_name = Self::table_name();
-        let filters = query.format_filters::<Self>();
-        let updates = mutation.format_updates::<Self>();
+        let mut arguments = QueryArguments::new();
+        let updates = mutation.format_updates::<Self>(&mut arguments);
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("UPDATE {table_name} SET {updates} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
         let mut total_rows = 0;
-        let rows_affected = connection.execute(&sql).await?.rows_affected();
+        let rows_affected = connection
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         total_rows += rows_affected;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         Self::after_scan(&ctx).await?;
         Self::after_mutation(&ctx).await?;
@@ -191,20 +226,26 @@ where
         S::before_mutation(query, mutation).await?;
 
         let table_name = S::table_name();
-        let filters = query.format_filters::<S>();
-        let updates = mutation.format_updates::<S>();
+        let mut arguments = QueryArguments::new();
+        let updates = mutation.format_updates::<S>(&mut arguments);
+        let filters = query.format_filters::<S>(&mut arguments);
         let sql = format!("UPDATE {table_name} SET {updates} {filters};");
         let mut ctx = S::before_scan(&sql).await?;
 
-        let rows_affected = connection.execute(&sql).await?.rows_affected();
+        let rows_affected = connection
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         total_rows += rows_affected;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         S::after_scan(&ctx).await?;
         S::after_mutation(&ctx).await?;
 
         // Commits the transaction
         transaction.commit().await?;
+        super::GlobalPool::pin_writer();
         Ok(total_rows)
     }
 
@@ -216,14 +257,19 @@ where
         Self::before_query(query).await?;
 
         let table_name = query.format_table_name::<Self>();
-        let filters = query.format_filters::<Self>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<Self>(&mut arguments);
         let sql = format!("DELETE FROM {table_name} {filters};");
         let mut ctx = Self::before_scan(&sql).await?;
 
         let mut total_rows = 0;
-        let rows_affected = connection.execute(&sql).await?.rows_affected();
+        let rows_affected = connection
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         total_rows += rows_affected;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         Self::after_scan(&ctx).await?;
         Self::after_query(&ctx).await?;
@@ -232,19 +278,279 @@ where
         S::before_query(query).await?;
 
         let table_name = query.format_table_name::<S>();
-        let filters = query.format_filters::<S>();
+        let mut arguments = QueryArguments::new();
+        let filters = query.format_filters::<S>(&mut arguments);
         let sql = format!("DELETE FROM {table_name} {filters};");
         let mut ctx = S::before_scan(&sql).await?;
 
-        let rows_affected = connection.execute(&sql).await?.rows_affected();
+        let rows_affected = connection
+            .execute_with_values(&sql, arguments.values())
+            .await?
+            .rows_affected();
         total_rows += rows_affected;
         ctx.set_query(sql);
+        ctx.append_arguments(&mut arguments.format_values());
         ctx.set_query_result(Some(rows_affected), true);
         S::after_scan(&ctx).await?;
         S::after_query(&ctx).await?;
 
         // Commits the transaction
         transaction.commit().await?;
+        super::GlobalPool::pin_writer();
         Ok(total_rows)
     }
 }
+
+/// A handle of an in-progress database transaction.
+///
+/// It can be used as an [`Executor`] for the `*_in` methods of [`Schema`],
+/// such as `insert_in`, `update_one_in` and `find_in`. The model hooks are called
+/// inside of the transaction as usual, so that an error returned by a hook can be used
+/// to roll back the changes. If the handle is dropped without being committed,
+/// the transaction will be rolled back.
+///
+/// Note that the `after_*` hooks fire as soon as each statement has been executed,
+/// which is before the transaction is committed. The hooks with side effects
+/// outside of the database, such as sending notifications, should not assume
+/// that the changes have been persisted.
+///
+/// ```rust,ignore
+/// use zino_core::orm::{Schema, Transaction};
+///
+/// let mut tx = User::begin().await?;
+/// user.insert_in(&mut tx).await?;
+///
+/// let mut savepoint = tx.savepoint().await?;
+/// match Tag::update_many_in(&query, &mut mutation, &mut savepoint).await {
+///     Ok(_) => savepoint.commit().await?,
+///     Err(_) => savepoint.rollback().await?,
+/// }
+///
+/// let users = User::find_in::<Map, _>(&query, &mut tx).await?;
+/// tx.commit().await?;
+/// ```
+#[cfg(feature = "orm-sqlx")]
+pub struct TransactionHandle<'c> {
+    /// Underlying transaction.
+    transaction: sqlx::Transaction<'c, DatabaseDriver>,
+    /// Depth of the nested savepoints.
+    depth: usize,
+}
+
+#[cfg(feature = "orm-sqlx")]
+impl TransactionHandle<'static> {
+    /// Begins a new transaction on the connection pool.
+    #[inline]
+    pub async fn begin(pool: &ConnectionPool) -> Result<Self, Error> {
+        let transaction = pool.pool().begin().await?;
+        Ok(Self {
+            transaction,
+            depth: 0,
+        })
+    }
+}
+
+#[cfg(feature = "orm-sqlx")]
+impl<'c> TransactionHandle<'c> {
+    /// Creates a savepoint nested in the transaction.
+    /// Committing the savepoint releases it, and rolling it back
+    /// only reverts the changes made after the savepoint.
+    #[inline]
+    pub async fn savepoint(&mut self) -> Result<TransactionHandle<'_>, Error> {
+        let transaction = Acquire::begin(&mut self.transaction).await?;
+        Ok(TransactionHandle {
+            transaction,
+            depth: self.depth + 1,
+        })
+    }
+
+    /// Returns the depth of the nested savepoints.
+    /// It is `0` for the outermost transaction.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Commits the transaction or releases the savepoint.
+    #[inline]
+    pub async fn commit(self) -> Result<(), Error> {
+        self.transaction.commit().await?;
+        if self.depth == 0 {
+            super::GlobalPool::pin_writer();
+        }
+        Ok(())
+    }
+
+    /// Rolls back the transaction or the savepoint.
+    #[inline]
+    pub async fn rollback(self) -> Result<(), Error> {
+        self.transaction.rollback().await?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "orm-sqlx")]
+impl<'c> Deref for TransactionHandle<'c> {
+    type Target = DatabaseConnection;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.transaction
+    }
+}
+
+#[cfg(feature = "orm-sqlx")]
+impl<'c> DerefMut for TransactionHandle<'c> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.transaction
+    }
+}
+
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+mod tests {
+    use super::Transaction;
+    use crate::{
+        extension::JsonObjectExt,
+        model::Query,
+        orm::{
+            fixture::{block_on, create_table, test_model},
+            Schema,
+        },
+        Map,
+    };
+
+    test_model!(Project, "test_transaction_project", auto_increment = false);
+    test_model!(Member, "test_transaction_member", auto_increment = false);
+    test_model!(Task, "test_transaction_task", auto_increment = false);
+    test_model!(Note, "test_transaction_note", auto_increment = false);
+
+    #[test]
+    fn it_inserts_a_model_with_associations() {
+        block_on(async {
+            create_table("test_transaction_project", false).await;
+            create_table("test_transaction_member", false).await;
+
+            let project = Project {
+                id: 1,
+                name: "zino".to_owned(),
+                ..Default::default()
+            };
+            let members = ["alice", "bob's"]
+                .into_iter()
+                .enumerate()
+                .map(|(index, name)| Member {
+                    id: index as i64 + 1,
+                    name: name.to_owned(),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+            let rows_affected = project.transactional_insert(members).await.unwrap();
+            assert_eq!(rows_affected, 3);
+
+            let members = Member::find_as::<Member>(&Query::new(Map::new()))
+                .await
+                .unwrap();
+            let names = members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+            assert_eq!(names, ["alice", "bob's"]);
+        });
+    }
+
+    #[test]
+    fn it_reverts_the_changes_made_by_a_rolled_back_transaction() {
+        block_on(async {
+            create_table("test_transaction_task", false).await;
+
+            let mut tx = Task::begin().await.unwrap();
+            let task = Task {
+                id: 1,
+                name: "draft".to_owned(),
+                ..Default::default()
+            };
+            task.insert_in(&mut tx).await.unwrap();
+            let task = Task::try_get_model_in(&1, &mut tx).await.unwrap();
+            assert_eq!(task.name, "draft");
+            tx.rollback().await.unwrap();
+
+            let task = Task::find_by_id::<Map>(&1).await.unwrap();
+            assert!(task.is_none());
+        });
+    }
+
+    #[test]
+    fn it_runs_the_queries_on_the_transaction_handle() {
+        block_on(async {
+            create_table("test_transaction_task", false).await;
+
+            let mut tx = Task::begin().await.unwrap();
+            for (id, name) in [(11, "alpha"), (12, "beta"), (13, "gamma")] {
+                let task = Task {
+                    id,
+                    name: name.to_owned(),
+                    ..Default::default()
+                };
+                task.insert_in(&mut tx).await.unwrap();
+            }
+            Task::delete_by_id_in(&13, &mut tx).await.unwrap();
+
+            let task = Task::find_by_id_in::<Map, _>(&12, &mut tx).await.unwrap();
+            assert_eq!(task.unwrap().get_str("name"), Some("beta"));
+
+            let sql = "UPDATE test_transaction_task SET name = 'delta' WHERE id = 12;";
+            let ctx = Task::execute_in(sql, None, &mut tx).await.unwrap();
+            assert_eq!(ctx.rows_affected(), Some(1));
+
+            let sql = "SELECT * FROM test_transaction_task WHERE id > 10 ORDER BY id;";
+            let tasks = Task::query_in::<Map, _>(sql, None, &mut tx).await.unwrap();
+            let names = tasks
+                .iter()
+                .filter_map(|t| t.get_str("name"))
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["alpha", "delta"]);
+            tx.commit().await.unwrap();
+
+            let task = Task::try_get_model(&12).await.unwrap();
+            assert_eq!(task.name, "delta");
+            assert!(Task::find_by_id::<Map>(&13).await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn it_pins_the_writer_only_after_a_successful_write() {
+        use crate::orm::{GlobalPool, WRITER_PINNED};
+        use std::cell::Cell;
+
+        block_on(WRITER_PINNED.scope(Cell::new(false), async {
+            create_table("test_transaction_note", false).await;
+            assert!(!GlobalPool::is_writer_pinned());
+
+            let sql = "INSERT INTO test_transaction_missing (id) VALUES (1);";
+            assert!(Note::execute(sql, None).await.is_err());
+            assert!(!GlobalPool::is_writer_pinned());
+
+            let mut tx = Note::begin().await.unwrap();
+            let note = Note {
+                id: 1,
+                ..Default::default()
+            };
+            note.insert_in(&mut tx).await.unwrap();
+            tx.rollback().await.unwrap();
+            assert!(!GlobalPool::is_writer_pinned());
+
+            let note = Note {
+                id: 2,
+                ..Default::default()
+            };
+            note.insert().await.unwrap();
+            assert!(GlobalPool::is_writer_pinned());
+        }));
+    }
+}
@@ -3,8 +3,8 @@
 use crate::{
     application::http_client,
     auth::{
-        self, AccessKeyId, Authentication, JwtClaims, ParseSecurityTokenError, SecurityToken,
-        SessionId,
+        self, AccessKeyId, Authentication, JwtClaims, JwtKeySet, ParseSecurityTokenError,
+        PolicyEngine, SecurityToken, Session, SessionId, UserSession,
     },
     channel::{CloudEvent, Subscription},
     datetime::DateTime,
@@ -20,11 +20,12 @@ use crate::{
 };
 use cookie::{Cookie, SameSite};
 use fluent::FluentArgs;
-use jwt_simple::algorithms::MACLike;
+use jwt_simple::{algorithms::MACLike, token::Token};
 use multer::Multipart;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     borrow::Cow,
+    collections::HashSet,
     net::IpAddr,
     str::FromStr,
     time::{Duration, Instant},
@@ -32,8 +33,19 @@ use std::{
 use unic_langid::LanguageIdentifier;
 
 mod context;
+mod rate_limiter;
+
+#[cfg(feature = "orm")]
+mod rate_limit_store;
 
 pub use context::Context;
+pub use rate_limiter::{
+    MemoryRateLimitStore, RateLimitAlgorithm, RateLimitKey, RateLimitRule, RateLimitStatus,
+    RateLimitStore, RateLimiter,
+};
+
+#[cfg(feature = "orm")]
+pub use rate_limit_store::DatabaseRateLimitStore;
 
 /// The URI component of a request.
 pub type Uri = http::Uri;
@@ -225,6 +237,13 @@ pub trait RequestContext {
             .and_then(|ctx| ctx.session_id().map(|s| s.to_owned()))
     }
 
+    /// Returns the server-side session loaded by the session middleware.
+    /// The changes of the session data will be saved after the request is handled.
+    #[inline]
+    fn session(&self) -> Option<Session> {
+        self.get_data::<Session>()
+    }
+
     /// Returns the locale.
     #[inline]
     fn locale(&self) -> Option<LanguageIdentifier> {
@@ -559,8 +578,29 @@ pub trait RequestContext {
 
     /// Attempts to construct an instance of `JwtClaims` from an HTTP request.
     /// The value is extracted from the query parameter `access_token` or
-    /// the `authorization` header.
+    /// the `authorization` header. Tokens signed by an asymmetric algorithm are verified
+    /// against the key in [`JwtKeySet`] selected by the `kid` header, and the HMAC tokens
+    /// are rejected unless they are enabled by the key set.
+    ///
+    /// The tokens with an audience, such as the MFA challenge tokens, are rejected.
+    /// They should be parsed by [`parse_jwt_claims_with_audience()`](Self::parse_jwt_claims_with_audience).
+    #[inline]
     fn parse_jwt_claims<T, K>(&self, key: &K) -> Result<JwtClaims<T>, Rejection>
+    where
+        T: Default + Serialize + DeserializeOwned,
+        K: MACLike,
+    {
+        self.parse_jwt_claims_with_audience(key, None)
+    }
+
+    /// Attempts to construct an instance of `JwtClaims` from an HTTP request,
+    /// and requires the token to be issued for the audience.
+    /// If the audience is `None`, the tokens with an audience are rejected.
+    fn parse_jwt_claims_with_audience<T, K>(
+        &self,
+        key: &K,
+        audience: Option<&str>,
+    ) -> Result<JwtClaims<T>, Rejection>
     where
         T: Default + Serialize + DeserializeOwned,
         K: MACLike,
@@ -586,9 +626,36 @@ pub trait RequestContext {
             .and_then(|s| s.parse().ok())
             .map(|i| Duration::from_secs(i).into());
         options.required_nonce = self.get_query("nonce").map(|s| s.to_owned());
+        options.allowed_audiences = audience.map(|s| HashSet::from([s.to_owned()]));
 
-        match key.verify_token(token, Some(options)) {
-            Ok(claims) => Ok(JwtClaims(claims)),
+        // Selects the asymmetric key by the `kid` header if the algorithm is not HMAC.
+        let key_set = JwtKeySet::shared();
+        let jwt_key = if key_set.is_empty() {
+            None
+        } else {
+            Token::decode_metadata(token).ok().and_then(|metadata| {
+                let algorithm = metadata.algorithm();
+                (algorithm != K::jwt_alg_name())
+                    .then(|| key_set.verification_key(algorithm, metadata.key_id()))
+                    .flatten()
+            })
+        };
+        let result = if let Some(jwt_key) = jwt_key {
+            jwt_key.verify_token(token, Some(options))
+        } else if !key_set.hmac_enabled() {
+            Err(warn!("the JWT token should be signed by an asymmetric key"))
+        } else {
+            key.verify_token(token, Some(options))
+                .map_err(|err| Error::new(err.to_string()))
+        };
+        match result {
+            Ok(claims) => {
+                if audience.is_none() && claims.audiences.is_some() {
+                    let message = "401 Unauthorized: the JWT token is issued for an audience";
+                    return Err(Rejection::with_message(message).context(self));
+                }
+                Ok(JwtClaims(claims))
+            }
             Err(err) => {
                 let message = format!("401 Unauthorized: {err}");
                 Err(Rejection::with_message(message).context(self))
@@ -596,6 +663,19 @@ pub trait RequestContext {
         }
     }
 
+    /// Authorizes the user session to access the matched route with the request method
+    /// by the shared [`PolicyEngine`]. An anonymous user is represented by `None`.
+    fn authorize<U, T: ToString>(
+        &self,
+        session: Option<&UserSession<U, String, T>>,
+    ) -> Result<(), Rejection> {
+        let resource = self.matched_route();
+        let action = self.request_method().as_ref();
+        PolicyEngine::shared()
+            .authorize(session, &resource, action)
+            .map_err(|err| Rejection::forbidden(err).context(self))
+    }
+
     /// Returns a `Response` or `Rejection` from a model query validation.
     /// The data is extracted from [`parse_query()`](RequestContext::parse_query).
     fn query_validation<S>(&self, query: &mut Query) -> Result<Response<S>, Rejection>
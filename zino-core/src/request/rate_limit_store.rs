@@ -0,0 +1,222 @@
+use super::{RateLimitRule, RateLimitStatus, RateLimitStore};
+use crate::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::DecodeRow,
+    orm::{self, ConnectionPool, Executor, GlobalPool},
+    BoxFuture, Map,
+};
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+    time::Duration,
+};
+
+/// A store which keeps the state of rate limits in the database,
+/// so that the limits hold across the instances in a cluster.
+///
+/// It counts the requests in fixed windows and estimates the number of requests
+/// in the sliding window by weighting the count of the previous window,
+/// which is used for both algorithms. The stale windows of a key are deleted
+/// when the key is acquired, and the expired rows of the other keys are swept periodically.
+#[derive(Debug)]
+pub struct DatabaseRateLimitStore {
+    /// Connection pool.
+    pool: &'static ConnectionPool,
+    /// Table name.
+    table_name: String,
+    /// A flag to indicate whether the table has been created.
+    table_created: AtomicBool,
+    /// Number of acquisitions.
+    acquisitions: AtomicUsize,
+}
+
+impl DatabaseRateLimitStore {
+    /// Creates a new instance for the database service.
+    pub fn new(service: &str) -> Result<Self, Error> {
+        let Some(pool) = GlobalPool::get(service) else {
+            bail!(
+                "connection to the database service `{}` is unavailable",
+                service
+            );
+        };
+        Ok(Self::with_pool(pool))
+    }
+
+    /// Creates a new instance with the connection pool.
+    #[inline]
+    pub fn with_pool(pool: &'static ConnectionPool) -> Self {
+        Self {
+            pool,
+            table_name: "zino_rate_limits".to_owned(),
+            table_created: AtomicBool::new(false),
+            acquisitions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets the table name.
+    #[inline]
+    pub fn set_table_name(&mut self, table_name: impl ToString) {
+        self.table_name = table_name.to_string();
+    }
+
+    /// Creates the table if it does not exist.
+    async fn create_table(&self) -> Result<(), Error> {
+        if self.table_created.load(Relaxed) {
+            return Ok(());
+        }
+
+        let table_name = &self.table_name;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (\n  \
+                bucket_key VARCHAR(255) NOT NULL,\n  \
+                window_start BIGINT NOT NULL,\n  \
+                hits BIGINT NOT NULL,\n  \
+                expires_at BIGINT NOT NULL,\n  \
+                PRIMARY KEY (bucket_key, window_start)\n\
+            );"
+        );
+        self.pool.pool().execute(&sql).await?;
+        self.table_created.store(true, Relaxed);
+        Ok(())
+    }
+}
+
+impl RateLimitStore for DatabaseRateLimitStore {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        rule: &'a RateLimitRule,
+    ) -> BoxFuture<'a, Result<RateLimitStatus, Error>> {
+        Box::pin(async move {
+            self.create_table().await?;
+
+            let pool = self.pool.pool();
+            let table_name = &self.table_name;
+            let limit = rule.limit();
+            let window = i64::try_from(rule.period().as_millis())
+                .unwrap_or(i64::MAX)
+                .max(1);
+            let now = DateTime::current_timestamp_millis();
+            let window_start = now - now % window;
+            let previous_window_start = window_start - window;
+            let expires_at = window_start.saturating_add(window.saturating_mul(2));
+            let upsert = if cfg!(any(
+                feature = "orm-mariadb",
+                feature = "orm-mysql",
+                feature = "orm-tidb"
+            )) {
+                "ON DUPLICATE KEY UPDATE hits = hits + 1".to_owned()
+            } else {
+                format!(
+                    "ON CONFLICT (bucket_key, window_start) \
+                        DO UPDATE SET hits = {table_name}.hits + 1"
+                )
+            };
+            let sql = format!(
+                "INSERT INTO {table_name} (bucket_key, window_start, hits, expires_at) \
+                    VALUES ({}, {window_start}, 1, {expires_at}) {upsert};",
+                orm::placeholder(1),
+            );
+            pool.execute_with_values(&sql, &[key]).await?;
+
+            let sql = format!(
+                "SELECT window_start, hits FROM {table_name} \
+                    WHERE bucket_key = {} AND window_start >= {previous_window_start};",
+                orm::placeholder(1),
+            );
+            let rows = pool.fetch_with_values(&sql, &[key]).await?;
+            let mut current_hits = 0;
+            let mut previous_hits = 0;
+            for row in rows {
+                let data = Map::decode_row(&row)?;
+                let hits = data
+                    .parse_i64("hits")
+                    .and_then(|result| result.ok())
+                    .unwrap_or_default();
+                match data
+                    .parse_i64("window_start")
+                    .and_then(|result| result.ok())
+                {
+                    Some(start) if start == window_start => current_hits = hits,
+                    Some(start) if start == previous_window_start => previous_hits = hits,
+                    _ => (),
+                }
+            }
+
+            let sql = format!(
+                "DELETE FROM {table_name} \
+                    WHERE bucket_key = {} AND window_start < {previous_window_start};",
+                orm::placeholder(1),
+            );
+            pool.execute_with_values(&sql, &[key]).await?;
+            if self.acquisitions.fetch_add(1, Relaxed) % 100 == 0 {
+                let sql = format!("DELETE FROM {table_name} WHERE expires_at < {now};");
+                pool.execute(&sql).await?;
+            }
+
+            let elapsed = now - window_start;
+            let weight = (window - elapsed) as f64 / window as f64;
+            let estimated_hits = (previous_hits as f64 * weight).floor() as i64 + current_hits;
+            let remaining = i64::from(limit) - estimated_hits;
+            let reset = Duration::from_millis((window - elapsed).unsigned_abs());
+            if remaining >= 0 {
+                let remaining = u32::try_from(remaining).unwrap_or_default();
+                Ok(RateLimitStatus::allowed(limit, remaining, reset))
+            } else {
+                Ok(RateLimitStatus::rejected(limit, reset, reset))
+            }
+        })
+    }
+}
+
+#[cfg(all(
+    test,
+    not(any(
+        feature = "orm-mariadb",
+        feature = "orm-mysql",
+        feature = "orm-postgres",
+        feature = "orm-tidb"
+    ))
+))]
+mod tests {
+    use super::DatabaseRateLimitStore;
+    use crate::{
+        orm::{
+            fixture::{block_on, TEST_POOL},
+            Executor,
+        },
+        request::{RateLimitRule, RateLimitStore},
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn it_deletes_stale_windows_of_the_key() {
+        block_on(async {
+            let mut store = DatabaseRateLimitStore::with_pool(&TEST_POOL);
+            store.set_table_name("test_rate_limits");
+            store.create_table().await.unwrap();
+
+            let pool = TEST_POOL.pool();
+            let sql = "INSERT INTO test_rate_limits (bucket_key, window_start, hits, expires_at) \
+                VALUES ('a', 0, 1, 9223372036854775807), ('b', 0, 1, 9223372036854775807);";
+            pool.execute(sql).await.unwrap();
+
+            let rule = RateLimitRule::new(2, Duration::from_secs(3600));
+            assert!(store.acquire("a", &rule).await.unwrap().is_allowed());
+            assert!(store.acquire("a", &rule).await.unwrap().is_allowed());
+            assert!(!store.acquire("a", &rule).await.unwrap().is_allowed());
+            assert!(store.acquire("b", &rule).await.unwrap().is_allowed());
+
+            let sql = "SELECT bucket_key FROM test_rate_limits WHERE window_start = 0;";
+            let rows = pool.fetch(sql).await.unwrap();
+            assert!(rows.is_empty());
+        });
+    }
+}
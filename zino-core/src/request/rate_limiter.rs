@@ -0,0 +1,633 @@
+use super::RequestContext;
+use crate::{
+    auth::{self, JwtClaims, SecretAccessKey},
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    response::Rejection,
+    warn, BoxFuture, Map,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    time::{Duration, Instant},
+};
+use toml::Table;
+
+/// Algorithm of the rate limiting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitAlgorithm {
+    /// Token bucket which allows bursts up to the limit.
+    #[default]
+    TokenBucket,
+    /// Sliding window which allows at most `limit` requests in any period.
+    SlidingWindow,
+}
+
+impl FromStr for RateLimitAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "token-bucket" => Ok(Self::TokenBucket),
+            "sliding-window" => Ok(Self::SlidingWindow),
+            _ => Err(warn!("unsupported rate limit algorithm `{}`", s)),
+        }
+    }
+}
+
+/// Key to identify the client for the rate limiting.
+///
+/// Only the authenticated identities are used as the keys, so the client IP is used
+/// if the identity can not be verified.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// Client IP.
+    #[default]
+    ClientIp,
+    /// Session ID in the verified JWT claims or the server-side session.
+    SessionId,
+    /// Access key ID of the verified security token.
+    AccessKeyId,
+    /// Subject of the verified JWT token.
+    Subject,
+}
+
+impl FromStr for RateLimitKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client-ip" => Ok(Self::ClientIp),
+            "session-id" => Ok(Self::SessionId),
+            "access-key-id" => Ok(Self::AccessKeyId),
+            "subject" => Ok(Self::Subject),
+            _ => Err(warn!("unsupported rate limit key `{}`", s)),
+        }
+    }
+}
+
+/// A rule of the rate limiting.
+#[derive(Debug, Clone)]
+pub struct RateLimitRule {
+    /// Route pattern in which `*` matches any sequence of characters.
+    route: Option<String>,
+    /// Algorithm.
+    algorithm: RateLimitAlgorithm,
+    /// Key to identify the client.
+    key: RateLimitKey,
+    /// Max number of requests in a period.
+    limit: u32,
+    /// Period.
+    period: Duration,
+}
+
+impl RateLimitRule {
+    /// Creates a new instance which allows `limit` requests in a period.
+    #[inline]
+    pub fn new(limit: u32, period: Duration) -> Self {
+        Self {
+            route: None,
+            algorithm: RateLimitAlgorithm::default(),
+            key: RateLimitKey::default(),
+            limit: limit.max(1),
+            period,
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let limit = config
+            .get_u32("limit")
+            .ok_or_else(|| warn!("the `limit` field should be specified"))?;
+        let period = config
+            .get_duration("period")
+            .unwrap_or_else(|| Duration::from_secs(60));
+        let mut rule = Self::new(limit, period);
+        if let Some(route) = config.get_str("route") {
+            rule.set_route(route);
+        }
+        if let Some(algorithm) = config.get_str("algorithm") {
+            rule.set_algorithm(algorithm.parse()?);
+        }
+        if let Some(key) = config.get_str("key") {
+            rule.set_key(key.parse()?);
+        }
+        Ok(rule)
+    }
+
+    /// Sets the route pattern.
+    #[inline]
+    pub fn set_route(&mut self, route: impl ToString) {
+        self.route = Some(route.to_string());
+    }
+
+    /// Sets the algorithm.
+    #[inline]
+    pub fn set_algorithm(&mut self, algorithm: RateLimitAlgorithm) {
+        self.algorithm = algorithm;
+    }
+
+    /// Sets the key to identify the client.
+    #[inline]
+    pub fn set_key(&mut self, key: RateLimitKey) {
+        self.key = key;
+    }
+
+    /// Returns the route pattern.
+    #[inline]
+    pub fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    /// Returns the algorithm.
+    #[inline]
+    pub fn algorithm(&self) -> RateLimitAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the key to identify the client.
+    #[inline]
+    pub fn key(&self) -> RateLimitKey {
+        self.key
+    }
+
+    /// Returns the max number of requests in a period.
+    #[inline]
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Returns the period.
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns `true` if the rule applies to the route.
+    #[inline]
+    pub fn matches(&self, route: &str) -> bool {
+        self.route
+            .as_deref()
+            .map_or(true, |pattern| auth::match_pattern(pattern, route, false))
+    }
+}
+
+/// Status of the rate limiting for a request.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus {
+    /// Max number of requests in a period.
+    limit: u32,
+    /// Remaining number of requests.
+    remaining: u32,
+    /// Duration until the quota is fully restored.
+    reset: Duration,
+    /// Duration to wait before retrying if the request is rejected.
+    retry_after: Option<Duration>,
+}
+
+impl RateLimitStatus {
+    /// Creates a new instance for an allowed request.
+    #[inline]
+    pub fn allowed(limit: u32, remaining: u32, reset: Duration) -> Self {
+        Self {
+            limit,
+            remaining,
+            reset,
+            retry_after: None,
+        }
+    }
+
+    /// Creates a new instance for a rejected request.
+    #[inline]
+    pub fn rejected(limit: u32, reset: Duration, retry_after: Duration) -> Self {
+        Self {
+            limit,
+            remaining: 0,
+            reset,
+            retry_after: Some(retry_after),
+        }
+    }
+
+    /// Returns `true` if the request is allowed.
+    #[inline]
+    pub fn is_allowed(&self) -> bool {
+        self.retry_after.is_none()
+    }
+
+    /// Returns the max number of requests in a period.
+    #[inline]
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Returns the remaining number of requests.
+    #[inline]
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    /// Returns the duration until the quota is fully restored.
+    #[inline]
+    pub fn reset(&self) -> Duration {
+        self.reset
+    }
+
+    /// Returns the duration to wait before retrying if the request is rejected.
+    #[inline]
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    /// Returns the `RateLimit-*` and `Retry-After` headers.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            ("ratelimit-limit", self.limit.to_string()),
+            ("ratelimit-remaining", self.remaining.to_string()),
+            ("ratelimit-reset", ceil_secs(self.reset).to_string()),
+        ];
+        if let Some(retry_after) = self.retry_after {
+            headers.push(("retry-after", ceil_secs(retry_after).to_string()));
+        }
+        headers
+    }
+}
+
+/// A store which keeps the state of rate limits.
+pub trait RateLimitStore: Send + Sync {
+    /// Returns the store name.
+    fn name(&self) -> &'static str;
+
+    /// Acquires a permit for the key with the rule.
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        rule: &'a RateLimitRule,
+    ) -> BoxFuture<'a, Result<RateLimitStatus, Error>>;
+}
+
+/// State of a rate limit in the memory.
+#[derive(Debug)]
+enum MemoryState {
+    /// Token bucket.
+    TokenBucket {
+        /// Number of tokens available.
+        tokens: f64,
+        /// Last time when the bucket was refilled.
+        refilled_at: Instant,
+    },
+    /// Timestamps of the requests in the sliding window.
+    SlidingWindow(VecDeque<Instant>),
+}
+
+impl MemoryState {
+    /// Returns the last time when the state was used.
+    fn last_used(&self) -> Option<Instant> {
+        match self {
+            Self::TokenBucket { refilled_at, .. } => Some(*refilled_at),
+            Self::SlidingWindow(timestamps) => timestamps.back().copied(),
+        }
+    }
+}
+
+/// A store which keeps the state of rate limits in the process memory.
+///
+/// The expired states are evicted periodically, and the least recently used states
+/// are evicted if the number of keys reaches the capacity.
+#[derive(Debug)]
+pub struct MemoryRateLimitStore {
+    /// States of rate limits.
+    states: Mutex<HashMap<String, (MemoryState, Duration)>>,
+    /// Max number of keys.
+    capacity: usize,
+    /// Number of acquisitions.
+    acquisitions: AtomicUsize,
+}
+
+impl Default for MemoryRateLimitStore {
+    #[inline]
+    fn default() -> Self {
+        Self::with_capacity(100_000)
+    }
+}
+
+impl MemoryRateLimitStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new instance with the max number of keys.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            states: Mutex::new(HashMap::new()),
+            capacity: capacity.max(1),
+            acquisitions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.states.lock().len()
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.states.lock().is_empty()
+    }
+
+    /// Evicts the expired states, and the least recently used states
+    /// if the number of keys still reaches the capacity.
+    fn evict(&self, states: &mut HashMap<String, (MemoryState, Duration)>, now: Instant) {
+        states.retain(|_, (state, period)| {
+            state
+                .last_used()
+                .is_some_and(|time| now.duration_since(time) < *period)
+        });
+        if states.len() >= self.capacity {
+            let mut entries = states
+                .iter()
+                .map(|(key, (state, _))| (state.last_used(), key.clone()))
+                .collect::<Vec<_>>();
+            entries.sort_unstable();
+
+            // Evicts a tenth of the keys at once so that the sorting is amortized.
+            let num_evicted = states.len() + 1 - self.capacity + self.capacity / 10;
+            for (_, key) in entries.into_iter().take(num_evicted) {
+                states.remove(&key);
+            }
+        }
+    }
+
+    /// Acquires a permit synchronously.
+    fn try_acquire(&self, key: &str, rule: &RateLimitRule) -> RateLimitStatus {
+        let now = Instant::now();
+        let limit = rule.limit();
+        let period = rule.period();
+        let mut states = self.states.lock();
+        if self.acquisitions.fetch_add(1, Relaxed) % 1000 == 0
+            || (states.len() >= self.capacity && !states.contains_key(key))
+        {
+            self.evict(&mut states, now);
+        }
+
+        let (state, _) = states.entry(key.to_owned()).or_insert_with(|| {
+            let state = match rule.algorithm() {
+                RateLimitAlgorithm::TokenBucket => MemoryState::TokenBucket {
+                    tokens: f64::from(limit),
+                    refilled_at: now,
+                },
+                RateLimitAlgorithm::SlidingWindow => MemoryState::SlidingWindow(VecDeque::new()),
+            };
+            (state, period)
+        });
+        match state {
+            MemoryState::TokenBucket {
+                tokens,
+                refilled_at,
+            } => {
+                let capacity = f64::from(limit);
+                let rate = capacity / period.as_secs_f64().max(f64::EPSILON);
+                let elapsed = now.duration_since(*refilled_at).as_secs_f64();
+                *tokens = (*tokens + elapsed * rate).min(capacity);
+                *refilled_at = now;
+                if *tokens >= 1.0 {
+                    *tokens -= 1.0;
+                    let reset = Duration::from_secs_f64((capacity - *tokens) / rate);
+                    RateLimitStatus::allowed(limit, *tokens as u32, reset)
+                } else {
+                    let reset = Duration::from_secs_f64((capacity - *tokens) / rate);
+                    let retry_after = Duration::from_secs_f64((1.0 - *tokens) / rate);
+                    RateLimitStatus::rejected(limit, reset, retry_after)
+                }
+            }
+            MemoryState::SlidingWindow(timestamps) => {
+                while timestamps
+                    .front()
+                    .is_some_and(|&time| now.duration_since(time) >= period)
+                {
+                    timestamps.pop_front();
+                }
+
+                let count = u32::try_from(timestamps.len()).unwrap_or(u32::MAX);
+                if count < limit {
+                    timestamps.push_back(now);
+                    let oldest = timestamps.front().copied().unwrap_or(now);
+                    let reset = period.saturating_sub(now.duration_since(oldest));
+                    RateLimitStatus::allowed(limit, limit - count - 1, reset)
+                } else {
+                    let oldest = timestamps.front().copied().unwrap_or(now);
+                    let retry_after = period.saturating_sub(now.duration_since(oldest));
+                    RateLimitStatus::rejected(limit, period, retry_after)
+                }
+            }
+        }
+    }
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    #[inline]
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        rule: &'a RateLimitRule,
+    ) -> BoxFuture<'a, Result<RateLimitStatus, Error>> {
+        let status = self.try_acquire(key, rule);
+        Box::pin(async move { Ok(status) })
+    }
+}
+
+/// A rate limiter for the HTTP requests.
+///
+/// The rules with a route pattern take precedence over the default rule.
+/// Requests are not limited if the store fails.
+pub struct RateLimiter {
+    /// Rules with route patterns.
+    route_rules: Vec<RateLimitRule>,
+    /// Default rule.
+    default_rule: Option<RateLimitRule>,
+    /// Store.
+    store: Box<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    /// Creates a new instance with the default rule and store.
+    #[inline]
+    pub fn new(default_rule: Option<RateLimitRule>, store: Box<dyn RateLimitStore>) -> Self {
+        Self {
+            route_rules: Vec::new(),
+            default_rule,
+            store,
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let default_rule = if config.contains_key("limit") {
+            Some(RateLimitRule::try_from_config(config)?)
+        } else {
+            None
+        };
+        let store: Box<dyn RateLimitStore> = match config.get_str("store").unwrap_or("memory") {
+            "memory" => {
+                let capacity = config.get_usize("max-keys").unwrap_or(100_000);
+                Box::new(MemoryRateLimitStore::with_capacity(capacity))
+            }
+            #[cfg(feature = "orm")]
+            "database" => {
+                let service = config.get_str("database").unwrap_or("main");
+                let mut store = super::DatabaseRateLimitStore::new(service)?;
+                if let Some(table_name) = config.get_str("table") {
+                    store.set_table_name(table_name);
+                }
+                Box::new(store)
+            }
+            store => return Err(warn!("unsupported rate limit store `{}`", store)),
+        };
+        let mut rate_limiter = Self::new(default_rule, store);
+        if let Some(routes) = config.get_array("routes") {
+            for route in routes.iter().filter_map(|v| v.as_table()) {
+                let mut rule = RateLimitRule::try_from_config(route)?;
+                if rule.route().is_none() {
+                    return Err(warn!("the `route` field should be specified"));
+                }
+                if !route.contains_key("algorithm") {
+                    if let Some(default_rule) = &rate_limiter.default_rule {
+                        rule.set_algorithm(default_rule.algorithm());
+                    }
+                }
+                if !route.contains_key("key") {
+                    if let Some(default_rule) = &rate_limiter.default_rule {
+                        rule.set_key(default_rule.key());
+                    }
+                }
+                rate_limiter.add_route_rule(rule);
+            }
+        }
+        Ok(rate_limiter)
+    }
+
+    /// Adds a rule with the route pattern.
+    #[inline]
+    pub fn add_route_rule(&mut self, rule: RateLimitRule) {
+        self.route_rules.push(rule);
+    }
+
+    /// Returns the rule which applies to the route.
+    pub fn find_rule(&self, route: &str) -> Option<&RateLimitRule> {
+        self.route_rules
+            .iter()
+            .find(|rule| rule.matches(route))
+            .or(self.default_rule.as_ref())
+    }
+
+    /// Checks the rate limit for the request.
+    /// It returns `None` if no rule applies or the store fails.
+    pub async fn check<Ctx: RequestContext + ?Sized>(
+        &self,
+        ctx: &Ctx,
+    ) -> Result<Option<RateLimitStatus>, Rejection> {
+        let route = ctx.matched_route();
+        let Some(rule) = self.find_rule(&route) else {
+            return Ok(None);
+        };
+        let client_key = match rule.key() {
+            RateLimitKey::ClientIp => None,
+            RateLimitKey::SessionId => ctx
+                .parse_jwt_claims::<Map, _>(JwtClaims::shared_key())
+                .ok()
+                .and_then(|claims| claims.data().get_str("sid").map(|s| s.to_owned()))
+                .or_else(|| ctx.session().map(|session| session.id())),
+            RateLimitKey::AccessKeyId => ctx.parse_access_key_id().ok().and_then(|access_key_id| {
+                let secret_key = SecretAccessKey::new(&access_key_id);
+                ctx.parse_security_token(secret_key.as_ref())
+                    .ok()
+                    .map(|security_token| security_token.access_key_id().to_string())
+            }),
+            RateLimitKey::Subject => ctx
+                .parse_jwt_claims::<Map, _>(JwtClaims::shared_key())
+                .ok()
+                .and_then(|claims| claims.subject().map(|s| s.to_owned())),
+        }
+        .or_else(|| ctx.client_ip().map(|ip| ip.to_string()))
+        .unwrap_or_else(|| "unknown".to_owned());
+        let key = format!("{}:{}", rule.route().unwrap_or("*"), client_key);
+        match self.store.acquire(&key, rule).await {
+            Ok(status) => {
+                if status.is_allowed() {
+                    Ok(Some(status))
+                } else {
+                    let message = format!("rate limit of `{route}` has been exceeded");
+                    let mut rejection = Rejection::too_many_requests(Error::new(message));
+                    for (name, value) in status.headers() {
+                        rejection.insert_header(name, value);
+                    }
+                    Err(rejection.context(ctx))
+                }
+            }
+            Err(err) => {
+                tracing::error!(store = self.store.name(), "fail to acquire a permit: {err}");
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Returns the number of seconds rounded up.
+#[inline]
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryRateLimitStore, RateLimitAlgorithm, RateLimitRule};
+    use std::time::Duration;
+
+    #[test]
+    fn it_limits_requests_in_memory() {
+        let store = MemoryRateLimitStore::new();
+        let mut rule = RateLimitRule::new(2, Duration::from_secs(60));
+        for algorithm in [
+            RateLimitAlgorithm::TokenBucket,
+            RateLimitAlgorithm::SlidingWindow,
+        ] {
+            rule.set_algorithm(algorithm);
+
+            let key = format!("{algorithm:?}");
+            let status = store.try_acquire(&key, &rule);
+            assert!(status.is_allowed());
+            assert_eq!(status.remaining(), 1);
+            assert!(store.try_acquire(&key, &rule).is_allowed());
+
+            let status = store.try_acquire(&key, &rule);
+            assert!(!status.is_allowed());
+            assert!(status.retry_after().is_some_and(|d| d > Duration::ZERO));
+            assert!(store.try_acquire("other", &rule).is_allowed());
+        }
+    }
+
+    #[test]
+    fn it_evicts_least_recently_used_keys() {
+        let store = MemoryRateLimitStore::with_capacity(10);
+        let rule = RateLimitRule::new(1, Duration::from_secs(60));
+        assert!(store.try_acquire("key-0", &rule).is_allowed());
+        for i in 1..20 {
+            std::thread::sleep(Duration::from_millis(1));
+            assert!(store.try_acquire(&format!("key-{i}"), &rule).is_allowed());
+            assert!(store.len() <= 10);
+        }
+        assert!(store.try_acquire("key-19", &rule).retry_after().is_some());
+        assert!(store.try_acquire("key-0", &rule).is_allowed());
+    }
+}
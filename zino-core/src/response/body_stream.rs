@@ -0,0 +1,222 @@
+use crate::{error::Error, extension::JsonObjectExt, Map};
+use bytes::Bytes;
+use csv::{ByteRecord, Writer};
+use futures::{future, stream::BoxStream, Stream, StreamExt};
+use parking_lot::Mutex;
+use std::{fmt, sync::Arc};
+
+/// A boxed stream of the body chunks.
+pub(super) type ChunkStream = BoxStream<'static, Result<Bytes, Error>>;
+
+/// A stream of the response body chunks.
+///
+/// It is shared by the clones of a response, and it can only be taken once.
+/// If a model in the stream fails, the chunk ends with an error marker
+/// and the stream is terminated, since the response status has been sent.
+#[derive(Clone)]
+pub struct BodyStream(Arc<Mutex<Option<ChunkStream>>>);
+
+impl BodyStream {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(stream: impl Stream<Item = Result<Bytes, Error>> + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Some(stream.boxed()))))
+    }
+
+    /// Encodes a stream of models as the CSV chunks.
+    /// The headers are the fields of the first model,
+    /// and the error marker is a record with the `$error` field and the message.
+    pub fn from_csv(stream: impl Stream<Item = Result<Map, Error>> + Send + 'static) -> Self {
+        let mut headers = None;
+        Self::encode(stream, move |models| encode_csv(&mut headers, models))
+    }
+
+    /// Encodes a stream of models as the JSON Lines chunks.
+    /// The error marker is a line of the object `{"$error": message}`.
+    pub fn from_jsonlines(stream: impl Stream<Item = Result<Map, Error>> + Send + 'static) -> Self {
+        Self::encode(stream, |models| {
+            let mut buffer = Vec::new();
+            for model in models {
+                let result = model.and_then(|model| {
+                    serde_json::to_writer(&mut buffer, &model)?;
+                    Ok(())
+                });
+                if let Err(err) = result {
+                    let marker = Map::from_entry("$error", err.to_string());
+                    serde_json::to_writer(&mut buffer, &marker).ok();
+                    buffer.push(b'\n');
+                    return (buffer, Some(err));
+                }
+                buffer.push(b'\n');
+            }
+            (buffer, None)
+        })
+    }
+
+    /// Encodes a stream of models as the MsgPack chunks.
+    /// Each model is encoded as a separate MsgPack map instead of an element of an array,
+    /// and the error marker is the map `{"$error": message}`.
+    pub fn from_msgpack(stream: impl Stream<Item = Result<Map, Error>> + Send + 'static) -> Self {
+        Self::encode(stream, |models| {
+            let mut buffer = Vec::new();
+            for model in models {
+                let result = model.and_then(|model| {
+                    rmp_serde::encode::write(&mut buffer, &model)?;
+                    Ok(())
+                });
+                if let Err(err) = result {
+                    let marker = Map::from_entry("$error", err.to_string());
+                    rmp_serde::encode::write(&mut buffer, &marker).ok();
+                    return (buffer, Some(err));
+                }
+            }
+            (buffer, None)
+        })
+    }
+
+    /// Encodes a stream of models in chunks with the encoder,
+    /// which returns the encoded bytes and the error terminating the stream.
+    fn encode(
+        stream: impl Stream<Item = Result<Map, Error>> + Send + 'static,
+        mut encoder: impl FnMut(Vec<Result<Map, Error>>) -> (Vec<u8>, Option<Error>) + Send + 'static,
+    ) -> Self {
+        let stream = stream
+            .ready_chunks(MAX_CHUNK_ROWS)
+            .scan(false, move |terminated, models| {
+                if *terminated {
+                    return future::ready(None);
+                }
+
+                let (buffer, error) = encoder(models);
+                if let Some(err) = error {
+                    tracing::error!("fail to encode the body stream: {err}");
+                    *terminated = true;
+                }
+                future::ready(Some(Ok(Bytes::from(buffer))))
+            });
+        Self::new(stream)
+    }
+
+    /// Takes the stream out of `self`. It returns `None` if the stream has been taken.
+    #[inline]
+    pub fn take(&self) -> Option<ChunkStream> {
+        self.0.lock().take()
+    }
+}
+
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyStream").finish_non_exhaustive()
+    }
+}
+
+/// Encodes the models as CSV records. The headers are written before the first record.
+fn encode_csv(
+    headers: &mut Option<Vec<String>>,
+    models: Vec<Result<Map, Error>>,
+) -> (Vec<u8>, Option<Error>) {
+    let mut wtr = Writer::from_writer(Vec::new());
+    let mut error = None;
+    for model in models {
+        if let Err(err) = model.and_then(|model| write_csv_record(&mut wtr, headers, &model)) {
+            wtr.write_record(["$error".to_owned(), err.to_string()])
+                .ok();
+            error = Some(err);
+            break;
+        }
+    }
+    wtr.flush().ok();
+    let buffer = wtr.into_inner().unwrap_or_default();
+    (buffer, error)
+}
+
+/// Writes the model as a CSV record.
+fn write_csv_record(
+    wtr: &mut Writer<Vec<u8>>,
+    headers: &mut Option<Vec<String>>,
+    model: &Map,
+) -> Result<(), Error> {
+    if headers.is_none() {
+        let fields = model.keys().cloned().collect::<Vec<_>>();
+        wtr.write_record(&fields)?;
+        *headers = Some(fields);
+    }
+
+    let fields = headers.as_deref().unwrap_or_default();
+    let num_fields = fields.len();
+    let mut record = ByteRecord::with_capacity(num_fields * 8, num_fields);
+    for field in fields {
+        let value = model.parse_string(field).unwrap_or("".into());
+        record.push_field(value.as_ref().as_bytes());
+    }
+    wtr.write_byte_record(&record)?;
+    Ok(())
+}
+
+/// Max number of rows encoded in a chunk.
+const MAX_CHUNK_ROWS: usize = 256;
+
+#[cfg(test)]
+mod tests {
+    use super::BodyStream;
+    use crate::{error::Error, extension::JsonObjectExt, Map};
+    use futures::{executor::block_on, stream, StreamExt};
+
+    fn collect_body(body: BodyStream) -> String {
+        let chunks = block_on(body.take().unwrap().collect::<Vec<_>>());
+        let buffer = chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.unwrap())
+            .collect::<Vec<_>>();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn models() -> Vec<Result<Map, Error>> {
+        let mut alice = Map::from_entry("id", 1);
+        alice.upsert("name", "alice");
+        let mut bob = Map::from_entry("id", 2);
+        bob.upsert("name", "bob");
+        vec![Ok(alice), Ok(bob)]
+    }
+
+    #[test]
+    fn it_encodes_models_as_chunks() {
+        let body = BodyStream::from_csv(stream::iter(models()));
+        assert_eq!(collect_body(body), "id,name\n1,alice\n2,bob\n");
+
+        let body = BodyStream::from_jsonlines(stream::iter(models()));
+        assert_eq!(
+            collect_body(body),
+            "{\"id\":1,\"name\":\"alice\"}\n{\"id\":2,\"name\":\"bob\"}\n"
+        );
+
+        let body = BodyStream::from_msgpack(stream::iter(models()));
+        let chunks = block_on(body.take().unwrap().collect::<Vec<_>>());
+        let buffer = chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.unwrap())
+            .collect::<Vec<_>>();
+        let mut deserializer = rmp_serde::Deserializer::new(buffer.as_slice());
+        let alice: Map = serde::Deserialize::deserialize(&mut deserializer).unwrap();
+        let bob: Map = serde::Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(alice.get_str("name"), Some("alice"));
+        assert_eq!(bob.get_str("name"), Some("bob"));
+        assert!(body.take().is_none());
+    }
+
+    #[test]
+    fn it_terminates_the_stream_with_an_error_marker() {
+        let mut models = models();
+        models.insert(1, Err(Error::new("connection lost")));
+
+        let body = BodyStream::from_csv(stream::iter(models));
+        assert_eq!(
+            collect_body(body),
+            "id,name\n1,alice\n$error,connection lost\n"
+        );
+
+        let models = vec![Err(Error::new("connection lost"))];
+        let body = BodyStream::from_jsonlines(stream::iter(models));
+        assert_eq!(collect_body(body), "{\"$error\":\"connection lost\"}\n");
+    }
+}
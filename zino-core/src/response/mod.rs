@@ -8,11 +8,12 @@ use crate::{
     request::RequestContext,
     trace::{ServerTiming, TimingMetric, TraceContext},
     validation::Validation,
-    JsonValue, SharedString, Uuid,
+    JsonValue, Map, SharedString, Uuid,
 };
 use bytes::Bytes;
 use cookie::Cookie;
 use etag::EntityTag;
+use futures::Stream;
 use http::header::{self, HeaderName, HeaderValue};
 use http_body::Full;
 use serde::Serialize;
@@ -22,14 +23,18 @@ use std::{
     time::{Duration, Instant},
 };
 
+mod body_stream;
 mod rejection;
 mod response_code;
 mod webhook;
 
+pub use body_stream::BodyStream;
 pub use rejection::{ExtractRejection, Rejection};
 pub use response_code::ResponseCode;
 pub use webhook::WebHook;
 
+use body_stream::ChunkStream;
+
 /// An HTTP status code.
 pub type StatusCode = http::StatusCode;
 
@@ -88,6 +93,9 @@ pub struct Response<S: ResponseCode = StatusCode> {
     /// Transformer of the response data.
     #[serde(skip)]
     data_transformer: Option<DataTransformer>,
+    /// Stream of the body chunks.
+    #[serde(skip)]
+    body_stream: Option<BodyStream>,
     /// Content type.
     #[serde(skip)]
     content_type: Option<SharedString>,
@@ -125,6 +133,7 @@ impl<S: ResponseCode> Response<S> {
             json_data: JsonValue::Null,
             bytes_data: Bytes::new(),
             data_transformer: None,
+            body_stream: None,
             content_type: None,
             trace_context: None,
             server_timing: ServerTiming::new(),
@@ -158,6 +167,7 @@ impl<S: ResponseCode> Response<S> {
             json_data: JsonValue::Null,
             bytes_data: Bytes::new(),
             data_transformer: None,
+            body_stream: None,
             content_type: None,
             trace_context: None,
             server_timing: ServerTiming::new(),
@@ -389,6 +399,46 @@ impl<S: ResponseCode> Response<S> {
         self.set_data_transformer(|data| Ok(data.to_csv(Vec::new())?.into()));
     }
 
+    /// Sets a stream of the body chunks as the response body.
+    /// The response will be sent with the chunked transfer encoding.
+    #[inline]
+    pub fn set_body_stream(
+        &mut self,
+        stream: impl Stream<Item = Result<Bytes, Error>> + Send + 'static,
+    ) {
+        self.body_stream = Some(BodyStream::new(stream));
+    }
+
+    /// Sets a stream of models as the JSON Lines response body.
+    #[inline]
+    pub fn set_jsonlines_stream(
+        &mut self,
+        stream: impl Stream<Item = Result<Map, Error>> + Send + 'static,
+    ) {
+        self.body_stream = Some(BodyStream::from_jsonlines(stream));
+        self.set_content_type("application/jsonlines; charset=utf-8");
+    }
+
+    /// Sets a stream of models as the MsgPack response body.
+    #[inline]
+    pub fn set_msgpack_stream(
+        &mut self,
+        stream: impl Stream<Item = Result<Map, Error>> + Send + 'static,
+    ) {
+        self.body_stream = Some(BodyStream::from_msgpack(stream));
+        self.set_content_type("application/msgpack");
+    }
+
+    /// Sets a stream of models as the CSV response body.
+    #[inline]
+    pub fn set_csv_stream(
+        &mut self,
+        stream: impl Stream<Item = Result<Map, Error>> + Send + 'static,
+    ) {
+        self.body_stream = Some(BodyStream::from_csv(stream));
+        self.set_content_type("text/csv; charset=utf-8");
+    }
+
     /// Sets the plain text as the response body.
     #[inline]
     pub fn set_text_response(&mut self, data: impl Into<String>) {
@@ -548,6 +598,12 @@ impl<S: ResponseCode> Response<S> {
         self.server_timing.to_string()
     }
 
+    /// Takes the stream of the body chunks if it has been set.
+    #[inline]
+    pub fn take_body_stream(&mut self) -> Option<ChunkStream> {
+        self.body_stream.take().and_then(|stream| stream.take())
+    }
+
     /// Reads the response into a byte buffer.
     pub fn read_bytes(&mut self) -> Result<Bytes, Error> {
         let has_bytes_data = !self.bytes_data.is_empty();
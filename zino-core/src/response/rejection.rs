@@ -7,6 +7,7 @@ use crate::{
     validation::Validation,
     warn, SharedString,
 };
+use smallvec::SmallVec;
 
 /// A rejection response type.
 #[derive(Debug)]
@@ -17,6 +18,8 @@ pub struct Rejection {
     context: Option<Context>,
     /// Optional trace context.
     trace_context: Option<TraceContext>,
+    /// Custom headers.
+    headers: SmallVec<[(SharedString, String); 4]>,
 }
 
 /// Rejection kind.
@@ -35,6 +38,8 @@ enum RejectionKind {
     MethodNotAllowed(Error),
     /// 409 Conflict
     Conflict(Error),
+    /// 429 Too Many Requests
+    TooManyRequests(Error),
     /// 500 Internal Server Error
     InternalServerError(Error),
     /// 503 Service Unavailable
@@ -49,6 +54,7 @@ impl Rejection {
             kind: BadRequest(validation),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -59,6 +65,7 @@ impl Rejection {
             kind: Unauthorized(err.into()),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -69,6 +76,7 @@ impl Rejection {
             kind: Forbidden(err.into()),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -79,6 +87,7 @@ impl Rejection {
             kind: NotFound(err.into()),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -89,6 +98,7 @@ impl Rejection {
             kind: MethodNotAllowed(err.into()),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -99,6 +109,18 @@ impl Rejection {
             kind: Conflict(err.into()),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
+        }
+    }
+
+    /// Creates a `429 Too Many Requests` rejection.
+    #[inline]
+    pub fn too_many_requests(err: impl Into<Error>) -> Self {
+        Self {
+            kind: TooManyRequests(err.into()),
+            context: None,
+            trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -109,6 +131,7 @@ impl Rejection {
             kind: InternalServerError(err.into()),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -119,6 +142,7 @@ impl Rejection {
             kind: ServiceUnavailable(err.into()),
             context: None,
             trace_context: None,
+            headers: SmallVec::new(),
         }
     }
 
@@ -143,6 +167,8 @@ impl Rejection {
             Self::method_not_allowed(err)
         } else if message.starts_with("409 Conflict") {
             Self::conflict(err)
+        } else if message.starts_with("429 Too Many Requests") {
+            Self::too_many_requests(err)
         } else if message.starts_with("503 Service Unavailable") {
             Self::service_unavailable(err)
         } else {
@@ -164,6 +190,12 @@ impl Rejection {
         self
     }
 
+    /// Inserts a custom header.
+    #[inline]
+    pub fn insert_header(&mut self, name: impl Into<SharedString>, value: impl ToString) {
+        self.headers.push((name.into(), value.to_string()));
+    }
+
     /// Returns the status code as `u16`.
     #[inline]
     pub fn status_code(&self) -> u16 {
@@ -174,6 +206,7 @@ impl Rejection {
             NotFound(_) => 404,
             MethodNotAllowed(_) => 405,
             Conflict(_) => 409,
+            TooManyRequests(_) => 429,
             InternalServerError(_) => 500,
             ServiceUnavailable(_) => 503,
         }
@@ -213,6 +246,11 @@ impl From<Rejection> for Response<StatusCode> {
                 res.set_error_message(err);
                 res
             }
+            TooManyRequests(err) => {
+                let mut res = Response::new(StatusCode::TOO_MANY_REQUESTS);
+                res.set_error_message(err);
+                res
+            }
             InternalServerError(err) => {
                 let mut res = Response::new(StatusCode::INTERNAL_SERVER_ERROR);
                 res.set_error_message(err);
@@ -230,6 +268,9 @@ impl From<Rejection> for Response<StatusCode> {
             res.set_request_id(ctx.request_id());
         }
         res.set_trace_context(rejection.trace_context);
+        for (name, value) in rejection.headers {
+            res.insert_header(name, value);
+        }
         res
     }
 }
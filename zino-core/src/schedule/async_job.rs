@@ -1,21 +1,76 @@
 //! Scheduler for sync and async cron jobs.
 
 use super::AsyncScheduler;
-use crate::{datetime::DateTime, BoxFuture, Map, Uuid};
+use crate::{datetime::DateTime, error::Error, BoxFuture, Map, SharedString, Uuid};
 use chrono::Local;
 use cron::Schedule;
-use std::{str::FromStr, time::Duration};
+use futures::FutureExt;
+use parking_lot::{Mutex, MutexGuard};
+use std::{
+    any::Any, collections::VecDeque, panic::AssertUnwindSafe, str::FromStr, sync::Arc,
+    time::Duration,
+};
 
 /// A function pointer of the async cron job.
 pub type AsyncCronJob =
     for<'a> fn(id: Uuid, data: &'a mut Map, last_tick: DateTime) -> BoxFuture<'a>;
 
+/// A function pointer of the fallible async cron job.
+pub type AsyncFallibleCronJob = for<'a> fn(
+    id: Uuid,
+    data: &'a mut Map,
+    last_tick: DateTime,
+) -> BoxFuture<'a, Result<(), Error>>;
+
+/// A function pointer of the error handler for async jobs.
+pub type AsyncJobErrorHandler = fn(error: &Error, data: &Map);
+
+/// Policy for a due run of the async job when the previous run is still in progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Skips the due run.
+    Skip,
+    /// Queues the due run until the previous run finishes.
+    /// All the queued runs are executed in order once the job is idle,
+    /// and the due run is skipped if the queue is full.
+    #[default]
+    Queue,
+    /// Allows the due run to be executed concurrently.
+    /// The job data is overwritten by the run which finishes last.
+    Concurrent,
+}
+
+/// Async cron job to run.
+#[derive(Clone, Copy)]
+enum AsyncJobRunner {
+    /// An infallible job.
+    Infallible(AsyncCronJob),
+    /// A fallible job.
+    Fallible(AsyncFallibleCronJob),
+}
+
 /// An async schedulable job.
+///
+/// Each run works on a snapshot of the job data,
+/// and the data modified by the run is written back as soon as it finishes.
+/// The written back data is visible in [`data`](Self::data) after the next tick,
+/// and in [`shared_data`](Self::shared_data) immediately.
+/// Runs which fail, panic or exceed the timeout are reported to the error handler.
+///
+/// With the `runtime-tokio` feature, the runs are spawned as Tokio tasks.
+/// Otherwise, they are executed in order within [`tick`](Self::tick),
+/// and the timeout is not enforced.
 pub struct AsyncJob {
     /// Job ID.
     id: Uuid,
+    /// Job name.
+    name: SharedString,
     /// Job data.
     data: Map,
+    /// Flag to indicate whether the job data has been modified since the last sync.
+    data_modified: bool,
+    /// Job data shared with the runs.
+    shared_data: Arc<Mutex<Map>>,
     /// Flag to indicate whether the job is disabled.
     disabled: bool,
     /// Flag to indicate whether the job is executed immediately.
@@ -23,7 +78,20 @@ pub struct AsyncJob {
     /// Cron expression parser.
     schedule: Schedule,
     /// Cron job to run.
-    run: AsyncCronJob,
+    run: AsyncJobRunner,
+    /// Timeout for each run.
+    timeout: Option<Duration>,
+    /// Policy for overlapping runs.
+    overlap_policy: OverlapPolicy,
+    /// Error handler.
+    on_error: Option<AsyncJobErrorHandler>,
+    /// Runs in progress.
+    #[cfg(feature = "runtime-tokio")]
+    running: Vec<tokio::task::JoinHandle<()>>,
+    /// Queued runs.
+    queued: VecDeque<DateTime>,
+    /// Max number of the queued runs.
+    max_queued_runs: usize,
     /// Last time when running the job.
     last_tick: Option<chrono::DateTime<Local>>,
 }
@@ -32,15 +100,36 @@ impl AsyncJob {
     /// Creates a new instance.
     #[inline]
     pub fn new(cron_expr: &str, exec: AsyncCronJob) -> Self {
+        Self::with_runner(cron_expr, AsyncJobRunner::Infallible(exec))
+    }
+
+    /// Creates a new instance for the fallible job.
+    #[inline]
+    pub fn new_fallible(cron_expr: &str, exec: AsyncFallibleCronJob) -> Self {
+        Self::with_runner(cron_expr, AsyncJobRunner::Fallible(exec))
+    }
+
+    /// Creates a new instance with the job runner.
+    fn with_runner(cron_expr: &str, run: AsyncJobRunner) -> Self {
         let schedule = Schedule::from_str(cron_expr)
             .unwrap_or_else(|err| panic!("invalid cron expression `{cron_expr}`: {err}"));
         Self {
             id: Uuid::now_v7(),
+            name: cron_expr.to_owned().into(),
             data: Map::new(),
+            data_modified: false,
+            shared_data: Arc::new(Mutex::new(Map::new())),
             disabled: false,
             immediate: false,
             schedule,
-            run: exec,
+            run,
+            timeout: None,
+            overlap_policy: OverlapPolicy::default(),
+            on_error: None,
+            #[cfg(feature = "runtime-tokio")]
+            running: Vec::new(),
+            queued: VecDeque::new(),
+            max_queued_runs: 16,
             last_tick: None,
         }
     }
@@ -59,6 +148,46 @@ impl AsyncJob {
         self
     }
 
+    /// Sets the job name used in the logs and metrics.
+    /// The default value is the cron expression.
+    #[inline]
+    pub fn name(mut self, name: impl Into<SharedString>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the timeout for each run.
+    ///
+    /// The timeout is only enforced with the `runtime-tokio` feature,
+    /// and it is ignored otherwise.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the policy for a due run when the previous run is still in progress.
+    #[inline]
+    pub fn overlap_policy(mut self, policy: OverlapPolicy) -> Self {
+        self.overlap_policy = policy;
+        self
+    }
+
+    /// Sets the max number of the queued runs for the [`OverlapPolicy::Queue`].
+    /// The default value is 16.
+    #[inline]
+    pub fn max_queued_runs(mut self, max_queued_runs: usize) -> Self {
+        self.max_queued_runs = max_queued_runs;
+        self
+    }
+
+    /// Sets the error handler which receives the error and the job data.
+    #[inline]
+    pub fn on_error(mut self, handler: AsyncJobErrorHandler) -> Self {
+        self.on_error = Some(handler);
+        self
+    }
+
     /// Returns the job ID.
     #[inline]
     pub fn id(&self) -> Uuid {
@@ -72,11 +201,19 @@ impl AsyncJob {
     }
 
     /// Returns a mutable reference to the job data.
+    /// The modification is passed to the runs from the next tick.
     #[inline]
     pub fn data_mut(&mut self) -> &mut Map {
+        self.data_modified = true;
         &mut self.data
     }
 
+    /// Returns a guard of the job data shared with the runs in progress.
+    #[inline]
+    pub fn shared_data(&self) -> MutexGuard<'_, Map> {
+        self.shared_data.lock()
+    }
+
     /// Returns `true` if the job is disabled.
     #[inline]
     pub fn is_disabled(&self) -> bool {
@@ -89,6 +226,24 @@ impl AsyncJob {
         self.immediate
     }
 
+    /// Returns `true` if the job has runs in progress or queued.
+    #[inline]
+    pub fn is_busy(&self) -> bool {
+        self.is_running() || !self.queued.is_empty()
+    }
+
+    /// Returns `true` if the job has runs in progress.
+    #[inline]
+    fn is_running(&self) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "runtime-tokio")] {
+                !self.running.is_empty()
+            } else {
+                false
+            }
+        }
+    }
+
     /// Pauses the job by setting the `disabled` flag to `true`.
     #[inline]
     pub fn pause(&mut self) {
@@ -108,32 +263,227 @@ impl AsyncJob {
     }
 
     /// Executes the missed runs asynchronously.
+    ///
+    /// With the `runtime-tokio` feature, the runs are spawned onto the Tokio runtime,
+    /// so it will not wait for them to finish.
     pub async fn tick(&mut self) {
+        #[cfg(feature = "runtime-tokio")]
+        self.collect_finished_runs();
+        self.sync_data();
+
         let now = Local::now();
         let disabled = self.disabled;
-        let run = self.run;
         if let Some(last_tick) = self.last_tick {
-            for event in self.schedule.after(&last_tick) {
-                if event > now {
-                    break;
-                }
-                if !disabled {
-                    run(self.id, &mut self.data, last_tick.into()).await;
+            let events = self
+                .schedule
+                .after(&last_tick)
+                .take_while(|event| event <= &now)
+                .count();
+            if !disabled {
+                for _ in 0..events {
+                    self.schedule_run(last_tick.into()).await;
                 }
             }
         } else if !disabled && self.immediate {
-            run(self.id, &mut self.data, now.into()).await;
+            self.schedule_run(now.into()).await;
+        }
+        if !disabled && !self.is_running() && !self.queued.is_empty() {
+            let ticks = self.queued.drain(..).collect();
+            self.start_runs(ticks).await;
         }
+        self.sync_data();
         self.last_tick = Some(now);
     }
 
-    /// Executes the job manually.
+    /// Executes the job manually and waits for it to finish.
     pub async fn execute(&mut self) {
         let now = Local::now();
-        let run = self.run;
-        run(self.id, &mut self.data, now.into()).await;
+        self.sync_data();
+        Self::execute_runs(
+            self.id,
+            self.name.clone(),
+            self.run,
+            self.shared_data.clone(),
+            vec![now.into()],
+            self.timeout,
+            self.on_error,
+        )
+        .await;
+        self.sync_data();
         self.last_tick = Some(now);
     }
+
+    /// Passes the modified job data to the runs,
+    /// or updates the job data with the data written back by the runs.
+    fn sync_data(&mut self) {
+        if self.data_modified {
+            *self.shared_data.lock() = self.data.clone();
+            self.data_modified = false;
+        } else {
+            self.data.clone_from(&self.shared_data.lock());
+        }
+    }
+
+    /// Schedules a due run according to the overlap policy.
+    async fn schedule_run(&mut self, last_tick: DateTime) {
+        match self.overlap_policy {
+            OverlapPolicy::Skip if self.is_running() => {
+                tracing::warn!(
+                    job_id = %self.id,
+                    job_name = %self.name,
+                    "skip the run since the previous one is in progress",
+                );
+                self.record_skipped_run();
+            }
+            OverlapPolicy::Queue if self.queued.len() >= self.max_queued_runs => {
+                tracing::warn!(
+                    job_id = %self.id,
+                    job_name = %self.name,
+                    "skip the run since the queue is full",
+                );
+                self.record_skipped_run();
+            }
+            OverlapPolicy::Queue => self.queued.push_back(last_tick),
+            _ => self.start_runs(vec![last_tick]).await,
+        }
+    }
+
+    /// Records a skipped run in the metrics.
+    #[inline]
+    fn record_skipped_run(&self) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!(
+            "zino_job_runs_total",
+            "job_name" => self.name.clone(),
+            "status" => "skipped",
+        )
+        .increment(1);
+    }
+
+    /// Starts the runs which are executed in order.
+    async fn start_runs(&mut self, ticks: Vec<DateTime>) {
+        let future = Self::execute_runs(
+            self.id,
+            self.name.clone(),
+            self.run,
+            self.shared_data.clone(),
+            ticks,
+            self.timeout,
+            self.on_error,
+        );
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "runtime-tokio")] {
+                self.running.push(tokio::spawn(future));
+            } else {
+                future.await;
+            }
+        }
+    }
+
+    /// Collects the finished runs.
+    #[cfg(feature = "runtime-tokio")]
+    fn collect_finished_runs(&mut self) {
+        let job_id = self.id;
+        self.running.retain_mut(|handle| {
+            if !handle.is_finished() {
+                return true;
+            }
+            if let Some(Err(err)) = handle.now_or_never() {
+                tracing::error!(job_id = %job_id, "fail to join the run: {err}");
+            }
+            false
+        });
+    }
+
+    /// Executes the runs in order, and writes back the job data once each run finishes.
+    async fn execute_runs(
+        id: Uuid,
+        name: SharedString,
+        run: AsyncJobRunner,
+        data: Arc<Mutex<Map>>,
+        ticks: Vec<DateTime>,
+        timeout: Option<Duration>,
+        on_error: Option<AsyncJobErrorHandler>,
+    ) {
+        for last_tick in ticks {
+            let mut snapshot = data.lock().clone();
+            Self::run_once(id, &name, run, &mut snapshot, last_tick, timeout, on_error).await;
+            *data.lock() = snapshot;
+        }
+    }
+
+    /// Runs the job once with the timeout, and reports the error to the handler.
+    async fn run_once(
+        id: Uuid,
+        name: &SharedString,
+        run: AsyncJobRunner,
+        data: &mut Map,
+        last_tick: DateTime,
+        timeout: Option<Duration>,
+        on_error: Option<AsyncJobErrorHandler>,
+    ) {
+        #[cfg(feature = "metrics")]
+        let start_time = std::time::Instant::now();
+
+        let future = AssertUnwindSafe(async {
+            match run {
+                AsyncJobRunner::Infallible(exec) => {
+                    exec(id, data, last_tick).await;
+                    Ok(())
+                }
+                AsyncJobRunner::Fallible(exec) => exec(id, data, last_tick).await,
+            }
+        })
+        .catch_unwind();
+        #[cfg(feature = "runtime-tokio")]
+        let result = if let Some(timeout) = timeout {
+            match tokio::time::timeout(timeout, future).await {
+                Ok(result) => result,
+                Err(_) => Ok(Err(Error::new(format!(
+                    "async job `{name}` timed out after {timeout:?}"
+                )))),
+            }
+        } else {
+            future.await
+        };
+        #[cfg(not(feature = "runtime-tokio"))]
+        let result = {
+            let _ = timeout;
+            future.await
+        };
+        let result = result.unwrap_or_else(|payload| {
+            let message = panic_message(payload.as_ref());
+            Err(Error::new(format!(
+                "async job `{name}` panicked: {message}"
+            )))
+        });
+
+        #[cfg(feature = "metrics")]
+        {
+            let status = if result.is_ok() {
+                "succeeded"
+            } else {
+                "failed"
+            };
+            metrics::counter!(
+                "zino_job_runs_total",
+                "job_name" => name.clone(),
+                "status" => status,
+            )
+            .increment(1);
+            metrics::histogram!(
+                "zino_job_run_duration_seconds",
+                "job_name" => name.clone(),
+            )
+            .record(start_time.elapsed().as_secs_f64());
+        }
+        if let Err(err) = result {
+            tracing::error!(job_id = %id, job_name = %name, "fail to run the async job: {err}");
+            if let Some(handler) = on_error {
+                handler(&err, data);
+            }
+        }
+    }
 }
 
 /// A type contains and executes the async scheduled jobs.
@@ -195,9 +545,15 @@ impl AsyncJobScheduler {
                     }
                 }
             }
-            duration
+            let duration = duration
                 .to_std()
-                .unwrap_or_else(|_| Duration::from_millis(500))
+                .unwrap_or_else(|_| Duration::from_millis(500));
+            if self.jobs.iter().any(|job| job.is_busy()) {
+                // Polls the runs in progress periodically.
+                duration.min(Duration::from_millis(500))
+            } else {
+                duration
+            }
         }
     }
 
@@ -210,7 +566,7 @@ impl AsyncJobScheduler {
         }
     }
 
-    /// Executes all the job manually.
+    /// Executes all the jobs manually.
     pub async fn execute(&mut self) {
         for job in &mut self.jobs {
             job.execute().await;
@@ -234,3 +590,14 @@ impl AsyncScheduler for AsyncJobScheduler {
         self.tick().await;
     }
 }
+
+/// Extracts the message from the payload of a panic.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
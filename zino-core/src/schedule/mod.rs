@@ -1,13 +1,26 @@
 //! Scheduler for sync and async cron jobs.
 
-use std::{future::Future, time::Duration};
+use crate::{datetime::DateTime, error::Error};
+use chrono::Local;
+use cron::Schedule;
+use std::{future::Future, str::FromStr, time::Duration};
 
 mod async_job;
 mod job;
 
-pub use async_job::{AsyncCronJob, AsyncJob, AsyncJobScheduler};
+pub use async_job::{
+    AsyncCronJob, AsyncFallibleCronJob, AsyncJob, AsyncJobErrorHandler, AsyncJobScheduler,
+    OverlapPolicy,
+};
 pub use job::{CronJob, Job, JobScheduler};
 
+/// Returns the upcoming time after the datetime for the cron expression.
+pub fn upcoming_time(cron_expr: &str, after: DateTime) -> Result<Option<DateTime>, Error> {
+    let schedule = Schedule::from_str(cron_expr)?;
+    let after: chrono::DateTime<Local> = after.into();
+    Ok(schedule.after(&after).next().map(DateTime::from))
+}
+
 /// An interface for scheduling sync jobs.
 pub trait Scheduler {
     /// Returns `true` if the scheduler is ready to run.
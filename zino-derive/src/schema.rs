@@ -36,9 +36,12 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
     let mut writer_name = String::from("main");
     let mut table_name = None;
     let mut model_comment = None;
+    let mut audit_enabled = false;
     for attr in input.attrs.iter() {
         for (key, value) in parser::parse_schema_attr(attr).into_iter() {
-            if let Some(value) = value {
+            if key == "audit" {
+                audit_enabled = true;
+            } else if let Some(value) = value {
                 match key.as_str() {
                     "model_name" => {
                         model_name = value;
@@ -66,6 +69,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
     let mut primary_key_name = String::from("id");
     let mut primary_key_value = None;
     let mut primary_key_column = None;
+    let mut tenant_key = None;
     let mut columns = Vec::new();
     let mut column_fields = Vec::new();
     let mut read_only_fields = Vec::new();
@@ -154,6 +158,9 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                 "primary_key" => {
                                     primary_key_name = name.clone();
                                 }
+                                "tenant_key" => {
+                                    tenant_key = Some(name.clone());
+                                }
                                 "read_only" => {
                                     read_only_fields.push(quote! { #name });
                                 }
@@ -267,6 +274,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
     let num_read_only_fields = read_only_fields.len();
     let num_write_only_fields = write_only_fields.len();
     let quote_table_name = parser::quote_option_string(table_name);
+    let quote_tenant_key = parser::quote_option_string(tenant_key);
     let quote_model_comment = parser::quote_option_string(model_comment);
     quote! {
         use zino_core::{
@@ -317,6 +325,8 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
             const READER_NAME: &'static str = #reader_name;
             const WRITER_NAME: &'static str = #writer_name;
             const TABLE_NAME: Option<&'static str> = #quote_table_name;
+            const TENANT_KEY: Option<&'static str> = #quote_tenant_key;
+            const AUDIT_ENABLED: bool = #audit_enabled;
 
             #[inline]
             fn primary_key(&self) -> &Self::PrimaryKey {
@@ -359,19 +369,17 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
             }
 
             async fn acquire_reader() -> Result<&'static ConnectionPool, ZinoError> {
-                use zino_core::{bail, orm::PoolManager, warn};
+                use zino_core::{bail, orm::GlobalPool, warn};
 
+                if Self::READER_NAME != Self::WRITER_NAME && GlobalPool::is_writer_pinned() {
+                    return Self::acquire_writer().await;
+                }
                 if let Some(reader) = #schema_reader.get() {
-                    if reader.is_available()
-                        || reader.is_retryable() && reader.check_availability().await
-                    {
-                        Ok(*reader)
-                    } else if let Ok(connection_pool) = Self::init_reader() {
+                    let reader = Self::init_reader().unwrap_or(*reader);
+                    if !reader.is_available() {
                         reader.increment_missed_count();
-                        Ok(connection_pool)
-                    } else {
-                        Ok(*reader)
                     }
+                    Ok(reader)
                 } else {
                     let model_name = Self::MODEL_NAME;
                     let connection_pool = Self::init_reader()?;
@@ -410,19 +418,14 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
             }
 
             async fn acquire_writer() -> Result<&'static ConnectionPool, ZinoError> {
-                use zino_core::{bail, orm::PoolManager, warn};
+                use zino_core::{bail, warn};
 
                 if let Some(writer) = #schema_writer.get() {
-                    if writer.is_available()
-                        || writer.is_retryable() && writer.check_availability().await
-                    {
-                        Ok(*writer)
-                    } else if let Ok(connection_pool) = Self::init_writer() {
+                    let writer = Self::init_writer().unwrap_or(*writer);
+                    if !writer.is_available() {
                         writer.increment_missed_count();
-                        Ok(connection_pool)
-                    } else {
-                        Ok(*writer)
                     }
+                    Ok(writer)
                 } else {
                     let model_name = Self::MODEL_NAME;
                     let connection_pool = Self::init_writer()?;
@@ -38,7 +38,7 @@ pub use collection::Collection;
 pub use dataset::Dataset;
 pub use project::Project;
 pub use source::Source;
-pub use task::Task;
+pub use task::{Task, TaskRun};
 
 pub use log::Log;
 pub use record::Record;
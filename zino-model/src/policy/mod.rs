@@ -1,14 +1,18 @@
 //! The `policy` model and related services.
 
 use crate::group::Group;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 use zino_core::{
+    auth::{AccessPolicy, PolicyEffect, PolicyEngine},
     datetime::DateTime,
     error::Error,
     extension::JsonObjectExt,
+    json,
     model::{Model, ModelHooks},
     validation::Validation,
-    Map, Uuid,
+    LazyLock, Map, Uuid,
 };
 use zino_derive::{DecodeRow, ModelAccessor, Schema};
 
@@ -46,9 +50,12 @@ pub struct Policy {
     #[schema(not_null)]
     resource: String,
     actions: Vec<String>,
+    #[schema(default_value = "Allow")]
     effect: String,
-    valid_from: DateTime,
-    expires_at: DateTime,
+    #[schema(index_type = "gin")]
+    roles: Vec<String>,
+    valid_from: Option<DateTime>,
+    expires_at: Option<DateTime>,
     #[cfg(feature = "tags")]
     #[schema(reference = "Tag", index_type = "gin")]
     tags: Vec<Uuid>, // tag.id, tag.namespace = "*:policy"
@@ -77,6 +84,7 @@ impl Model for Policy {
     fn new() -> Self {
         Self {
             id: Uuid::now_v7(),
+            effect: "Allow".to_owned(),
             ..Self::default()
         }
     }
@@ -95,6 +103,42 @@ impl Model for Policy {
         if let Some(description) = data.parse_string("description") {
             self.description = description.into_owned();
         }
+        if let Some(result) = data.parse_uuid("tenant_id") {
+            match result {
+                Ok(tenant_id) => self.tenant_id = tenant_id,
+                Err(err) => validation.record_fail("tenant_id", err),
+            }
+        }
+        if let Some(resource) = data.parse_string("resource") {
+            self.resource = resource.into_owned();
+        }
+        if self.resource.is_empty() {
+            validation.record("resource", "should be nonempty");
+        }
+        if let Some(actions) = data.parse_str_array("actions") {
+            self.actions = actions.into_iter().map(|s| s.to_owned()).collect();
+        }
+        if let Some(effect) = data.parse_string("effect") {
+            match effect.parse::<PolicyEffect>() {
+                Ok(effect) => self.effect = effect.to_string(),
+                Err(err) => validation.record_fail("effect", err),
+            }
+        }
+        if let Some(roles) = data.parse_str_array("roles") {
+            self.roles = roles.into_iter().map(|s| s.to_owned()).collect();
+        }
+        if let Some(result) = data.parse_datetime("valid_from") {
+            match result {
+                Ok(valid_from) => self.valid_from = Some(valid_from),
+                Err(err) => validation.record_fail("valid_from", err),
+            }
+        }
+        if let Some(result) = data.parse_datetime("expires_at") {
+            match result {
+                Ok(expires_at) => self.expires_at = Some(expires_at),
+                Err(err) => validation.record_fail("expires_at", err),
+            }
+        }
         #[cfg(feature = "tags")]
         if let Some(result) = data.parse_array("tags") {
             match result {
@@ -120,6 +164,78 @@ impl Model for Policy {
     }
 }
 
+impl Policy {
+    /// Returns `true` if the policy denies the actions.
+    #[inline]
+    pub fn is_deny(&self) -> bool {
+        self.effect.eq_ignore_ascii_case("deny")
+    }
+
+    /// Converts into an access policy which can be evaluated by the policy engine.
+    pub fn to_access_policy(&self) -> AccessPolicy {
+        let effect = if self.is_deny() {
+            PolicyEffect::Deny
+        } else {
+            PolicyEffect::Allow
+        };
+        let mut policy = AccessPolicy::new(self.name.clone(), &self.resource, effect);
+        policy.set_actions(self.actions.clone());
+        policy.set_roles(self.roles.clone());
+        if !self.tenant_id.is_nil() {
+            policy.set_tenant_id(self.tenant_id);
+        }
+        policy.set_valid_period(self.valid_from, self.expires_at);
+        policy
+    }
+
+    /// Loads the active policies into the shared policy engine,
+    /// and returns the number of policies loaded.
+    ///
+    /// The policies are fetched in batches by the keyset pagination on the `id`.
+    pub async fn load_policies() -> Result<usize, Error> {
+        let mut query = Query::new(json!({
+            "status": "Active",
+        }));
+        query.order_asc("id");
+        query.set_limit(POLICY_BATCH_SIZE);
+
+        let mut access_policies = Vec::new();
+        loop {
+            let policies = Self::find::<Self>(&query).await?;
+            access_policies.extend(policies.iter().map(|policy| policy.to_access_policy()));
+            match policies.last() {
+                Some(policy) if policies.len() == POLICY_BATCH_SIZE => {
+                    let mut cursor = Map::new();
+                    cursor.upsert("id", policy.id.to_string());
+                    query.seek_after(cursor);
+                }
+                _ => break,
+            }
+        }
+
+        let num_policies = access_policies.len();
+        PolicyEngine::set_shared(PolicyEngine::with_policies(access_policies));
+        *POLICIES_LOADED_AT.write() = Some(Instant::now());
+        Ok(num_policies)
+    }
+
+    /// Reloads the policies if they have not been loaded within the TTL,
+    /// and returns the number of policies loaded.
+    ///
+    /// It is intended to be called periodically, such as in an async cron job,
+    /// so that the changes of the policies are picked up by all the instances.
+    pub async fn reload_policies(ttl: Duration) -> Result<Option<usize>, Error> {
+        let is_stale = !POLICIES_LOADED_AT
+            .read()
+            .is_some_and(|loaded_at| loaded_at.elapsed() < ttl);
+        if is_stale {
+            Self::load_policies().await.map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
 impl ModelHooks for Policy {
     type Data = ();
     #[cfg(feature = "maintainer-id")]
@@ -146,3 +262,9 @@ impl ModelHooks for Policy {
         Ok(())
     }
 }
+
+/// Number of policies fetched in a batch.
+const POLICY_BATCH_SIZE: usize = 1000;
+
+/// Time when the policies were loaded at.
+static POLICIES_LOADED_AT: LazyLock<RwLock<Option<Instant>>> = LazyLock::new(|| RwLock::new(None));
@@ -0,0 +1,96 @@
+use super::Record;
+use zino_core::{
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, Query},
+    orm::{AuditEntry, AuditRecorder, ReborrowedExecutor, Schema},
+    BoxFuture, JsonValue, Map,
+};
+
+/// An audit recorder which writes the audit entries into the `record` table.
+///
+/// The name of a record is `{model_name}:{entity_id}`, the description is the action,
+/// and the entry is stored in the `extra` field with a checksum as the `integrity`.
+/// The entries of the changes in a transaction are written in the same transaction,
+/// which requires the `record` table to be in the same database as the audited models.
+/// It can be registered by
+/// `AuditTrail::register_recorder(RecordAuditRecorder::new())`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordAuditRecorder;
+
+impl RecordAuditRecorder {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl AuditRecorder for RecordAuditRecorder {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "record"
+    }
+
+    #[inline]
+    fn record<'a>(&'a self, entry: &'a AuditEntry) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            new_record(entry).insert().await?;
+            Ok(())
+        })
+    }
+
+    fn record_in<'a>(
+        &'a self,
+        entry: &'a AuditEntry,
+        executor: ReborrowedExecutor<'a>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let record = new_record(entry);
+            match executor {
+                // The auto-committed change is recorded with the pool of the `record` table.
+                ReborrowedExecutor::Pool(_) => record.insert().await?,
+                ReborrowedExecutor::Connection(conn) => record.insert_in(conn).await?,
+            };
+            Ok(())
+        })
+    }
+
+    fn history<'a>(
+        &'a self,
+        model_name: &'a str,
+        entity_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<AuditEntry>, Error>> {
+        Box::pin(async move {
+            let name = format!("{model_name}:{entity_id}");
+            let mut query = Query::new(Map::from_entry("name", name));
+            query.allow_fields(&["extra", "recorded_at"]);
+            query.order_asc("recorded_at");
+            let records = Record::find::<Map>(&query).await?;
+            records
+                .into_iter()
+                .filter_map(|mut record| record.remove("extra"))
+                .filter_map(|extra| match extra {
+                    JsonValue::Object(map) => Some(AuditEntry::try_from_map(map)),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+}
+
+/// Creates a new record for the audit entry.
+fn new_record(entry: &AuditEntry) -> Record {
+    let mut record = Record::new();
+    record.name = format!("{}:{}", entry.model_name(), entry.entity_id());
+    record.status = "Active".to_owned();
+    record.description = entry.action().to_owned();
+    record.integrity = entry.checksum();
+    record.recorded_at = entry.recorded_at();
+    #[cfg(feature = "maintainer-id")]
+    {
+        record.maintainer_id = entry.user_id().and_then(|s| s.parse().ok());
+    }
+    record.extra = entry.clone().into_map();
+    record
+}
@@ -11,6 +11,10 @@ use zino_core::{
 };
 use zino_derive::{DecodeRow, ModelAccessor, Schema};
 
+mod audit_recorder;
+
+pub use audit_recorder::RecordAuditRecorder;
+
 #[cfg(any(feature = "owner-id", feature = "maintainer-id"))]
 use crate::user::User;
 
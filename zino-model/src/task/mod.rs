@@ -7,11 +7,18 @@ use zino_core::{
     error::Error,
     extension::JsonObjectExt,
     model::{Model, ModelHooks},
+    schedule,
     validation::Validation,
     Map, Uuid,
 };
 use zino_derive::{DecodeRow, ModelAccessor, Schema};
 
+mod run;
+mod scheduler;
+
+pub use run::TaskRun;
+pub use scheduler::{TaskHandler, TaskScheduler};
+
 #[cfg(feature = "tags")]
 use crate::tag::Tag;
 
@@ -98,9 +105,39 @@ impl Model for Task {
         if let Some(name) = data.parse_string("name") {
             self.name = name.into_owned();
         }
+        if let Some(status) = data.parse_string("status") {
+            self.status = status.into_owned();
+        }
         if let Some(description) = data.parse_string("description") {
             self.description = description.into_owned();
         }
+        if let Some(cron_expr) = data.parse_string("schedule") {
+            match schedule::upcoming_time(&cron_expr, DateTime::now()) {
+                Ok(Some(next_time)) => {
+                    self.schedule = cron_expr.into_owned();
+                    self.next_time = next_time;
+                }
+                Ok(None) => validation.record("schedule", "there is no upcoming time"),
+                Err(err) => validation.record_fail("schedule", err),
+            }
+        } else if self.status == "Active" && !self.schedule.is_empty() {
+            // Skips the ticks missed during the pause.
+            let now = DateTime::now();
+            if self.next_time < now {
+                if let Ok(Some(next_time)) = schedule::upcoming_time(&self.schedule, now) {
+                    self.next_time = next_time;
+                }
+            }
+        }
+        if let Some(result) = data.parse_u16("priority") {
+            match result {
+                Ok(priority) => self.priority = priority,
+                Err(err) => validation.record_fail("priority", err),
+            }
+        }
+        if let Some(extra) = data.parse_object("extra") {
+            self.extra = extra.clone();
+        }
         #[cfg(feature = "tags")]
         if let Some(result) = data.parse_array("tags") {
             match result {
@@ -133,6 +170,14 @@ impl ModelHooks for Task {
     #[cfg(not(feature = "maintainer-id"))]
     type Extension = ();
 
+    #[inline]
+    async fn after_validation(&mut self, data: &mut Map) -> Result<(), Error> {
+        if !self.schedule.is_empty() {
+            data.upsert("next_time", self.next_time);
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "maintainer-id")]
     #[inline]
     async fn after_extract(&mut self, session: Self::Extension) -> Result<(), Error> {
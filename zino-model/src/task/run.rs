@@ -0,0 +1,119 @@
+use super::Task;
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    validation::Validation,
+    Map, Uuid,
+};
+use zino_derive::{DecodeRow, ModelAccessor, Schema};
+
+/// The `task_run` model for the run history of scheduled tasks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Schema, ModelAccessor)]
+#[serde(default)]
+pub struct TaskRun {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null)]
+    name: String,
+    #[cfg(feature = "namespace")]
+    #[schema(default_value = "TaskRun::model_namespace", index_type = "hash")]
+    namespace: String,
+    #[schema(default_value = "Running", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(reference = "Task", index_type = "hash")]
+    task_id: Uuid, // task.id
+    attempt: u32,
+    instance_id: Uuid,
+    #[schema(index_type = "btree")]
+    started_at: DateTime,
+    finished_at: Option<DateTime>,
+    message: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl TaskRun {
+    /// Creates a new run for the task.
+    pub(super) fn start(task_id: Uuid, name: &str, attempt: u32, instance_id: Uuid) -> Self {
+        Self {
+            name: name.to_owned(),
+            task_id,
+            attempt,
+            instance_id,
+            started_at: DateTime::now(),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the task ID.
+    #[inline]
+    pub fn task_id(&self) -> Uuid {
+        self.task_id
+    }
+
+    /// Returns the attempt number.
+    #[inline]
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Returns the time when the run was started.
+    #[inline]
+    pub fn started_at(&self) -> DateTime {
+        self.started_at
+    }
+
+    /// Returns the time when the run was finished.
+    #[inline]
+    pub fn finished_at(&self) -> Option<DateTime> {
+        self.finished_at
+    }
+}
+
+impl Model for TaskRun {
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            status: "Running".to_owned(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for TaskRun {
+    type Data = ();
+    type Extension = ();
+}
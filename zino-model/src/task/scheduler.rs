@@ -0,0 +1,436 @@
+use super::{Task, TaskRun};
+use futures::stream::{self, StreamExt};
+use std::{collections::HashMap, time::Duration};
+use zino_core::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    json,
+    model::{Model, Mutation, Query},
+    orm::{ModelAccessor, Schema},
+    schedule::{self, AsyncScheduler},
+    warn, BoxFuture, Map, Uuid,
+};
+
+/// A function pointer of the task handler.
+pub type TaskHandler = for<'a> fn(
+    id: Uuid,
+    data: &'a mut Map,
+    last_tick: DateTime,
+) -> BoxFuture<'a, Result<(), Error>>;
+
+/// A persistent scheduler for the tasks stored in the database.
+///
+/// Each active task with a cron expression in the `schedule` field is executed by the handler
+/// registered with the task name, and the `data` object in the `extra` field is passed to it.
+/// Before running a task, the scheduler claims the tick with the optimistic lock on `version`
+/// and takes a lease by moving `next_time` to the lease deadline, so that only one instance
+/// in the cluster runs each tick. If an instance fails to finish a run before the deadline,
+/// the lease expires and the task is reclaimed by another instance.
+/// Failed runs are retried with an exponential backoff, and every run is recorded as a [`TaskRun`].
+///
+/// The tasks can be added, paused and resumed at runtime by the `new` and `update` actions
+/// of the default controller for [`Task`], such as `{ "status": "Paused" }`.
+#[derive(Debug)]
+pub struct TaskScheduler {
+    /// Instance ID.
+    instance_id: Uuid,
+    /// Task handlers.
+    handlers: HashMap<&'static str, TaskHandler>,
+    /// Interval for polling the due tasks.
+    poll_interval: Duration,
+    /// Max number of retries for a failed run.
+    max_retries: u32,
+    /// Initial backoff for retrying a failed run.
+    retry_backoff: Duration,
+    /// Duration of the lease for a run.
+    lease_timeout: Duration,
+    /// Max number of the runs executed concurrently in a tick.
+    max_concurrency: usize,
+}
+
+impl TaskScheduler {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            instance_id: Uuid::now_v7(),
+            handlers: HashMap::new(),
+            poll_interval: Duration::from_secs(1),
+            max_retries: 3,
+            retry_backoff: Duration::from_secs(10),
+            lease_timeout: Duration::from_secs(300),
+            max_concurrency: 16,
+        }
+    }
+
+    /// Registers a handler for the tasks with the name.
+    #[inline]
+    pub fn register(&mut self, name: &'static str, handler: TaskHandler) {
+        self.handlers.insert(name, handler);
+    }
+
+    /// Sets the interval for polling the due tasks.
+    #[inline]
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Sets the max number of retries for a failed run.
+    #[inline]
+    pub fn set_max_retries(&mut self, max_retries: u32) {
+        self.max_retries = max_retries;
+    }
+
+    /// Sets the initial backoff for retrying a failed run.
+    #[inline]
+    pub fn set_retry_backoff(&mut self, backoff: Duration) {
+        self.retry_backoff = backoff;
+    }
+
+    /// Sets the duration of the lease for a run.
+    /// It should be longer than the time for running any of the tasks.
+    #[inline]
+    pub fn set_lease_timeout(&mut self, timeout: Duration) {
+        self.lease_timeout = timeout;
+    }
+
+    /// Sets the max number of the runs executed concurrently in a tick.
+    #[inline]
+    pub fn set_max_concurrency(&mut self, max_concurrency: usize) {
+        self.max_concurrency = max_concurrency.max(1);
+    }
+
+    /// Adds a scheduled task with the JSON data, and returns the task ID.
+    pub async fn add_task(data: &Map) -> Result<Uuid, Error> {
+        let mut task = Task::new();
+        let validation = task.read_map(data);
+        if !validation.is_success() {
+            bail!("invalid task data: {:?}", validation.into_map());
+        }
+        if task.schedule.is_empty() {
+            bail!("the `schedule` field should be a cron expression");
+        }
+
+        let task_id = task.id;
+        task.insert().await?;
+        Ok(task_id)
+    }
+
+    /// Pauses the scheduled task.
+    #[inline]
+    pub async fn pause_task(id: &Uuid) -> Result<(), Error> {
+        Self::set_task_status(id, "Paused").await
+    }
+
+    /// Resumes the scheduled task.
+    /// The ticks missed during the pause are skipped.
+    #[inline]
+    pub async fn resume_task(id: &Uuid) -> Result<(), Error> {
+        Self::set_task_status(id, "Active").await
+    }
+
+    /// Sets the status of a scheduled task.
+    async fn set_task_status(id: &Uuid, status: &str) -> Result<(), Error> {
+        let mut data = Map::from_entry("status", status);
+        let (validation, _) = Task::update_by_id(id, &mut data, None).await?;
+        if !validation.is_success() {
+            bail!("invalid task status: {:?}", validation.into_map());
+        }
+        Ok(())
+    }
+
+    /// Returns the backoff for retrying a run which has been retried for the times.
+    fn backoff(&self, retries: u32) -> Duration {
+        self.retry_backoff.saturating_mul(1 << retries.min(16))
+    }
+
+    /// Claims the due tasks and runs them concurrently.
+    async fn run_due_tasks(&self) -> Result<(), Error> {
+        let names = self.handlers.keys().copied().collect::<Vec<_>>();
+        let mut query = Query::new(json!({
+            "name": { "$in": names },
+            "status": "Active",
+            "schedule": { "$ne": "" },
+            "next_time": { "$le": DateTime::now() },
+        }));
+        query.order_desc("priority");
+        query.order_asc("next_time");
+        query.set_limit(100);
+
+        let tasks = Task::find::<Map>(&query).await?;
+        stream::iter(tasks)
+            .for_each_concurrent(self.max_concurrency, |task| async move {
+                if let Err(err) = self.run_task(task).await {
+                    tracing::error!("fail to run the scheduled task: {err}");
+                }
+            })
+            .await;
+        Ok(())
+    }
+
+    /// Claims the tick of a task and runs it.
+    async fn run_task(&self, task: Map) -> Result<(), Error> {
+        let Some(Ok(task_id)) = task.parse_uuid("id") else {
+            bail!("the task should have a valid `id`");
+        };
+        let name = task.get_str("name").unwrap_or_default();
+        let Some(&handler) = self.handlers.get(name) else {
+            return Ok(());
+        };
+        let schedule = task.get_str("schedule").unwrap_or_default();
+        let version = task.get_u64("version").unwrap_or_default();
+        let now = DateTime::now();
+        let next_time = schedule::upcoming_time(schedule, now)?
+            .ok_or_else(|| warn!("there is no upcoming time for the task `{}`", task_id))?;
+
+        // A lease left in the task means that the last run has not been finished
+        // before the deadline, so it is counted as a failed attempt.
+        let mut extra = task.get_object("extra").cloned().unwrap_or_default();
+        let mut retries = extra.get_u32("retries").unwrap_or_default();
+        let expired_run_id = lease_run_id(&extra);
+        if expired_run_id.is_some() {
+            retries = if retries < self.max_retries {
+                retries + 1
+            } else {
+                0
+            };
+        }
+
+        // Claims the tick with the optimistic lock, and holds the lease until the deadline.
+        let run = TaskRun::start(task_id, name, retries + 1, self.instance_id);
+        let run_id = *run.primary_key();
+        let lease_deadline = now + self.lease_timeout;
+        extra.upsert(
+            "lease",
+            json!({
+                "run_id": run_id,
+                "instance_id": self.instance_id,
+                "expires_at": lease_deadline,
+            }),
+        );
+        let query = Query::new(json!({
+            "id": task_id.to_string(),
+            "version": version,
+        }));
+        let mut mutation = Mutation::new(json!({
+            "last_time": now,
+            "next_time": lease_deadline,
+            "extra": extra,
+            "$inc": { "version": 1 },
+        }));
+        let ctx = Task::update_one(&query, &mut mutation).await?;
+        if ctx.rows_affected() != Some(1) {
+            return Ok(());
+        }
+
+        if let Some(expired_run_id) = expired_run_id {
+            tracing::warn!(task_id = %task_id, name, "the lease of the run `{expired_run_id}` has expired");
+            let query = Query::new(json!({
+                "id": expired_run_id.to_string(),
+                "status": "Running",
+            }));
+            let mut mutation = Mutation::new(json!({
+                "status": "Expired",
+                "message": "the lease has expired",
+                "finished_at": now,
+                "updated_at": now,
+            }));
+            TaskRun::update_one(&query, &mut mutation).await?;
+        }
+        run.insert().await?;
+
+        let mut data = extra.get_object("data").cloned().unwrap_or_default();
+        let last_tick = task
+            .parse_datetime("last_time")
+            .and_then(|result| result.ok())
+            .unwrap_or(now);
+        let result = handler(task_id, &mut data, last_tick).await;
+        let mut run_mutation = Mutation::new(json!({
+            "finished_at": DateTime::now(),
+            "updated_at": DateTime::now(),
+        }));
+        let mut retry_at = None;
+        match result {
+            Ok(()) => {
+                run_mutation.add_update("status", "Succeeded");
+                retries = 0;
+            }
+            Err(err) => {
+                let message = err.to_string();
+                tracing::error!(task_id = %task_id, name, "fail to run the task: {message}");
+                run_mutation.add_update("status", "Failed");
+                run_mutation.add_update("message", message);
+                if retries < self.max_retries {
+                    retry_at = Some(now + self.backoff(retries));
+                    retries += 1;
+                } else {
+                    retries = 0;
+                }
+            }
+        }
+
+        let outcome = RunOutcome {
+            run_id,
+            data,
+            next_time,
+            retries,
+            retry_at,
+        };
+        if !Self::release_lease(task_id, version + 1, extra, outcome).await? {
+            tracing::warn!(task_id = %task_id, name, "the lease of the run `{run_id}` has been reclaimed");
+        }
+
+        let query = Query::new(json!({
+            "id": run_id.to_string(),
+            "status": "Running",
+        }));
+        TaskRun::update_one(&query, &mut run_mutation).await?;
+        Ok(())
+    }
+
+    /// Releases the lease of a run and updates the task with the optimistic lock.
+    /// If the task has been modified during the run, it is reloaded to check that
+    /// the lease is still held. It returns `false` if the lease has been reclaimed.
+    async fn release_lease(
+        task_id: Uuid,
+        mut version: u64,
+        mut extra: Map,
+        outcome: RunOutcome,
+    ) -> Result<bool, Error> {
+        let RunOutcome {
+            run_id,
+            data,
+            next_time,
+            retries,
+            retry_at,
+        } = outcome;
+        let mut schedule = None;
+        for _ in 0..3 {
+            let mut next_time = match schedule.as_deref() {
+                Some(cron_expr) => schedule::upcoming_time(cron_expr, DateTime::now())?,
+                None => Some(next_time),
+            };
+            if let Some(retry_at) = retry_at {
+                next_time = Some(next_time.map_or(retry_at, |t| t.min(retry_at)));
+            }
+
+            extra.remove("lease");
+            extra.upsert("data", data.clone());
+            if retries > 0 {
+                extra.upsert("retries", retries);
+            } else {
+                extra.remove("retries");
+            }
+
+            let query = Query::new(json!({
+                "id": task_id.to_string(),
+                "version": version,
+            }));
+            let mut mutation = Mutation::new(json!({
+                "extra": extra,
+                "updated_at": DateTime::now(),
+                "$inc": { "version": 1 },
+            }));
+            if let Some(next_time) = next_time {
+                mutation.add_update("next_time", next_time);
+            }
+            let ctx = Task::update_one(&query, &mut mutation).await?;
+            if ctx.rows_affected() == Some(1) {
+                return Ok(true);
+            }
+
+            let Some(task) = Task::find_by_id::<Map>(&task_id).await? else {
+                return Ok(false);
+            };
+            extra = task.get_object("extra").cloned().unwrap_or_default();
+            if lease_run_id(&extra) != Some(run_id) {
+                return Ok(false);
+            }
+            version = task.get_u64("version").unwrap_or_default();
+            schedule = task.get_str("schedule").map(|s| s.to_owned());
+        }
+        bail!(
+            "409 Conflict: fail to release the lease of the task `{}`",
+            task_id
+        );
+    }
+}
+
+impl Default for TaskScheduler {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncScheduler for TaskScheduler {
+    #[inline]
+    fn is_ready(&self) -> bool {
+        !self.handlers.is_empty()
+    }
+
+    #[inline]
+    fn time_till_next_job(&self) -> Duration {
+        self.poll_interval
+    }
+
+    async fn tick(&mut self) {
+        if let Err(err) = self.run_due_tasks().await {
+            tracing::error!("fail to poll the scheduled tasks: {err}");
+        }
+    }
+}
+
+/// Outcome of a run to be written back to the task.
+struct RunOutcome {
+    /// Run ID.
+    run_id: Uuid,
+    /// Task data.
+    data: Map,
+    /// Upcoming time of the schedule.
+    next_time: DateTime,
+    /// Number of retries.
+    retries: u32,
+    /// Time for retrying the failed run.
+    retry_at: Option<DateTime>,
+}
+
+/// Returns the run ID of the lease in the `extra` object of a task.
+fn lease_run_id(extra: &Map) -> Option<Uuid> {
+    extra
+        .get_object("lease")?
+        .parse_uuid("run_id")
+        .and_then(|result| result.ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lease_run_id, TaskScheduler};
+    use std::time::Duration;
+    use zino_core::{extension::JsonObjectExt, json, Map, Uuid};
+
+    #[test]
+    fn it_doubles_the_retry_backoff() {
+        let mut scheduler = TaskScheduler::new();
+        scheduler.set_retry_backoff(Duration::from_secs(10));
+        assert_eq!(scheduler.backoff(0), Duration::from_secs(10));
+        assert_eq!(scheduler.backoff(1), Duration::from_secs(20));
+        assert_eq!(scheduler.backoff(3), Duration::from_secs(80));
+        assert_eq!(scheduler.backoff(64), scheduler.backoff(16));
+    }
+
+    #[test]
+    fn it_parses_the_run_id_of_a_lease() {
+        let run_id = Uuid::now_v7();
+        let mut extra = Map::new();
+        assert_eq!(lease_run_id(&extra), None);
+
+        extra.upsert("lease", json!({ "run_id": run_id.to_string() }));
+        assert_eq!(lease_run_id(&extra), Some(run_id));
+
+        extra.upsert("lease", json!({ "run_id": "invalid" }));
+        assert_eq!(lease_run_id(&extra), None);
+    }
+}
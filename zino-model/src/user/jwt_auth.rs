@@ -1,15 +1,29 @@
-use std::{fmt::Display, str::FromStr};
+use super::{LoginAttempt, LoginGuard, RefreshToken};
+use std::{fmt::Display, net::IpAddr, str::FromStr};
 use zino_core::{
-    auth::JwtClaims,
+    auth::{AccessKeyId, JwtClaims, SecretAccessKey, UserSession},
     bail,
+    channel::CloudEvent,
     datetime::DateTime,
     error::Error,
     extension::JsonObjectExt,
-    model::Query,
-    orm::{ModelAccessor, ModelHelper},
-    warn, Map, Uuid,
+    json,
+    model::{Mutation, Query},
+    orm::{ModelAccessor, ModelHelper, Schema},
+    warn, JsonValue, Map, Uuid,
 };
 
+#[cfg(feature = "mfa")]
+use std::time::Duration;
+#[cfg(feature = "mfa")]
+use zino_core::{
+    extension::{JsonValueExt, TomlTableExt},
+    state::State,
+};
+
+#[cfg(feature = "oidc")]
+use zino_core::auth::{OidcIdentity, OidcProvider};
+
 /// JWT authentication service.
 pub trait JwtAuthService<K = Uuid>
 where
@@ -29,6 +43,29 @@ where
     const LOGIN_AT_FIELD: Option<&'static str> = None;
     /// Login-IP field name.
     const LOGIN_IP_FIELD: Option<&'static str> = None;
+    /// Failed-login-count field name.
+    const FAILED_LOGIN_COUNT_FIELD: Option<&'static str> = None;
+    /// Locked-until field name, which enables the temporary lockout of an account.
+    const LOCKED_UNTIL_FIELD: Option<&'static str> = None;
+    /// A flag to indicate whether the refresh tokens are persisted and rotated on each use.
+    /// It requires the table of the [`RefreshToken`] model, so it is disabled by default
+    /// and enabled for the [`User`](super::User) model.
+    const REFRESH_TOKEN_ROTATION: bool = false;
+    /// Access-key-ID field name, which is used for deriving the signing key.
+    const ACCESS_KEY_ID_FIELD: &'static str = "access_key_id";
+    /// MFA field name, which stores the enrollment state, the encrypted TOTP secret,
+    /// the last accepted time step, hashed recovery codes and the pending challenge.
+    /// The model should have a `version` field, which is used as an optimistic lock
+    /// when the MFA data is updated.
+    #[cfg(feature = "mfa")]
+    const MFA_FIELD: Option<&'static str> = None;
+    /// Audience of the MFA challenge token, which is rejected as an access token.
+    /// The token should be parsed by `parse_jwt_claims_with_audience()` for this audience.
+    #[cfg(feature = "mfa")]
+    const MFA_AUDIENCE: &'static str = "mfa";
+    /// Union-ID field name, which links the user to the identities of the providers.
+    #[cfg(feature = "oidc")]
+    const UNION_ID_FIELD: Option<&'static str> = None;
 
     /// Returns the standard claims parsed from the `content` field.
     /// See [the spec](https://openid.net/specs/openid-connect-core-1_0.html#StandardClaims).
@@ -83,71 +120,328 @@ where
     }
 
     /// Generates the access token and refresh token.
+    /// If the user has enrolled in MFA, a challenge token will be returned instead,
+    /// which should be exchanged for the tokens by [`verify_mfa()`](Self::verify_mfa).
+    #[inline]
     async fn generate_token(body: Map) -> Result<(K, Map), Error> {
+        Self::generate_token_with_ip(body, None).await
+    }
+
+    /// Generates the access token and refresh token for the login from the client IP.
+    ///
+    /// Failed attempts are tracked per client IP and per account with the client IP
+    /// by the [`LoginGuard`]. The account is also throttled with an exponential back-off
+    /// by the persisted failed login count, and an active account gets the `Locked` status
+    /// temporarily after too many failed attempts. The status is restored when it logs in
+    /// successfully. The back-off and the lockout of the account apply to all the clients,
+    /// except the client IP of the last successful login recorded by the
+    /// [`LOGIN_IP_FIELD`](Self::LOGIN_IP_FIELD), so that an attacker can not lock the owner out.
+    async fn generate_token_with_ip(
+        body: Map,
+        client_ip: Option<IpAddr>,
+    ) -> Result<(K, Map), Error> {
         let account = body
             .get_str("account")
             .ok_or_else(|| warn!("401 Unauthorized: the user `account` should be specified"))?;
         let passowrd = body
             .get_str("password")
             .ok_or_else(|| warn!("401 Unauthorized: the user `password` should be specified"))?;
+
+        let guard = LoginGuard::shared();
+        let ip_attempt = client_ip
+            .map(|ip| guard.check(&format!("ip:{ip}"), guard.ip_max_attempts()))
+            .transpose()?;
+        let account_key = if let Some(ip) = client_ip {
+            format!("account:{account}@{ip}")
+        } else {
+            format!("account:{account}")
+        };
+        let account_attempt = guard.check(&account_key, guard.max_attempts())?;
+
         let mut query = Query::default();
-        let mut fields = vec![Self::PRIMARY_KEY_NAME, Self::PASSWORD_FIELD];
-        if let Some(role_field) = Self::ROLE_FIELD {
-            fields.push(role_field);
+        let mut fields = Self::token_fields();
+        fields.extend(["status", Self::PASSWORD_FIELD]);
+        if let Some(failed_login_count_field) = Self::FAILED_LOGIN_COUNT_FIELD {
+            fields.push(failed_login_count_field);
         }
-        if let Some(tenant_id_field) = Self::TENANT_ID_FIELD {
-            fields.push(tenant_id_field);
+        if let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD {
+            fields.push(locked_until_field);
         }
-        if let Some(login_at_field) = Self::LOGIN_AT_FIELD {
-            fields.push(login_at_field);
-        }
-        if let Some(login_ip_field) = Self::LOGIN_IP_FIELD {
-            fields.push(login_ip_field);
+        #[cfg(feature = "mfa")]
+        if let Some(mfa_field) = Self::MFA_FIELD {
+            fields.extend([mfa_field, "version"]);
         }
         query.allow_fields(&fields);
-        query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+        if Self::LOCKED_UNTIL_FIELD.is_some() {
+            query.add_filter("status", Map::from_entry("$ne", "Deleted"));
+        } else {
+            query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+        }
         query.add_filter(Self::ACCOUNT_FIELD, account);
 
-        let mut user: Map = Self::find_one(&query)
-            .await?
-            .ok_or_else(|| warn!("404 Not Found: invalid user account or password"))?;
+        let Some(mut user) = Self::find_one::<Map>(&query).await? else {
+            account_attempt.fail();
+            if let Some(attempt) = ip_attempt {
+                Self::record_ip_failure(attempt, client_ip);
+            }
+            bail!("404 Not Found: invalid user account or password");
+        };
+        let status = user.get_str("status").unwrap_or_default().to_owned();
+        let is_locked = status == "Locked";
+        let locked_until = Self::LOCKED_UNTIL_FIELD
+            .and_then(|field| user.parse_datetime(field).and_then(|result| result.ok()));
+        let is_trusted = client_ip.is_some_and(|ip| {
+            Self::LOGIN_IP_FIELD
+                .and_then(|field| user.get_str(field))
+                .is_some_and(|login_ip| login_ip == ip.to_string())
+        });
+        if let Some(locked_until) = locked_until {
+            // The trusted client is still throttled by the login guard.
+            if let Some(retry_after) = locked_until.span_after_now().filter(|_| !is_trusted) {
+                if is_locked {
+                    bail!(
+                        "403 Forbidden: the account is locked until `{}`",
+                        locked_until
+                    );
+                }
+                bail!(
+                    "429 Too Many Requests: too many failed login attempts, retry after {}s",
+                    retry_after.as_secs().max(1)
+                );
+            }
+        } else if is_locked {
+            bail!("404 Not Found: invalid user account or password");
+        }
+
+        let failed_login_count = Self::FAILED_LOGIN_COUNT_FIELD
+            .and_then(|field| user.get_u32(field))
+            .unwrap_or_default();
+        Self::reserve_login_attempt(&user, failed_login_count).await?;
+
         let encrypted_password = user
             .get_str(Self::PASSWORD_FIELD)
             .ok_or_else(|| warn!("404 Not Found: the user password is absent"))?;
         if Self::verify_password(passowrd, encrypted_password)? {
-            // Cann't use `get_str` because the primary key may be an integer
-            let user_id = user
-                .parse_string(Self::PRIMARY_KEY_NAME)
-                .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?;
-            let mut claims = JwtClaims::new(user_id.as_ref());
-
-            let user_id = user_id.parse()?;
-            if let Some(role_field) = Self::ROLE_FIELD.filter(|&field| user.contains_key(field)) {
-                claims.add_data_entry("roles", user.parse_str_array(role_field));
+            account_attempt.succeed();
+
+            if Self::FAILED_LOGIN_COUNT_FIELD.is_some() || is_locked {
+                let user_id = user
+                    .parse_string(Self::PRIMARY_KEY_NAME)
+                    .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?;
+                let query = Query::from_entry(Self::PRIMARY_KEY_NAME, user_id.as_ref());
+                let mut mutation = Mutation::default();
+                if let Some(failed_login_count_field) = Self::FAILED_LOGIN_COUNT_FIELD {
+                    mutation.add_update(failed_login_count_field, 0);
+                }
+                if let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD {
+                    mutation.add_update(locked_until_field, JsonValue::Null);
+                }
+                if is_locked {
+                    // Only an active account is locked, so the previous status is `Active`.
+                    mutation.add_update("status", "Active");
+                }
+                Self::update_one(&query, &mut mutation).await?;
+            }
+            for field in [Self::FAILED_LOGIN_COUNT_FIELD, Self::LOCKED_UNTIL_FIELD]
+                .into_iter()
+                .flatten()
+            {
+                user.remove(field);
             }
-            if let Some(tenant_id_field) = Self::TENANT_ID_FIELD {
-                if let Some(tenant_id) = user.remove(tenant_id_field) {
-                    claims.add_data_entry("tenant_id", tenant_id);
+            user.remove("status");
+
+            let device_id = body.get_str("device_id");
+            #[cfg(feature = "mfa")]
+            if let Some(mfa_field) = Self::MFA_FIELD {
+                let mfa_enabled = user
+                    .get_object(mfa_field)
+                    .and_then(|mfa| mfa.get_bool("enabled"))
+                    .unwrap_or_default();
+                if mfa_enabled {
+                    return Self::generate_mfa_challenge(&user, device_id).await;
                 }
             }
+            Self::issue_tokens(user, device_id).await
+        } else {
+            account_attempt.fail();
+            if let Some(attempt) = ip_attempt {
+                Self::record_ip_failure(attempt, client_ip);
+            }
+            let lockable = is_locked || status == "Active";
+            Self::record_account_failure(
+                &user,
+                account,
+                failed_login_count + 1,
+                lockable,
+                client_ip,
+            )
+            .await?;
+            Err(warn!("fail to generate access token"))
+        }
+    }
+
+    /// Reserves a login attempt for the account by increasing the failed login count
+    /// and throttling the account with the back-off before the password is verified.
+    /// The update is conditional on the failed login count, so that the concurrent attempts
+    /// across the instances are rejected.
+    async fn reserve_login_attempt(user: &Map, failed_login_count: u32) -> Result<(), Error> {
+        let Some(failed_login_count_field) = Self::FAILED_LOGIN_COUNT_FIELD else {
+            return Ok(());
+        };
+        let user_id = user
+            .parse_string(Self::PRIMARY_KEY_NAME)
+            .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?;
+        let mut query = Query::from_entry(Self::PRIMARY_KEY_NAME, user_id.as_ref());
+        query.add_filter(failed_login_count_field, failed_login_count);
+
+        let count = failed_login_count + 1;
+        let mut mutation = Mutation::from_entry(failed_login_count_field, count);
+        if let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD {
+            let backoff = LoginGuard::shared().backoff_for(count);
+            mutation.add_update(locked_until_field, DateTime::now() + backoff);
+        }
+        let ctx = Self::update_one(&query, &mut mutation).await?;
+        if ctx.rows_affected() == Some(0) {
+            bail!("429 Too Many Requests: another login attempt of the account is in progress");
+        }
+        Ok(())
+    }
+
+    /// Records a failed login attempt for the account. The failed login count has been
+    /// increased by the reservation, and the account will be locked for the back-off
+    /// if the number of failed attempts reaches the limit.
+    async fn record_account_failure(
+        user: &Map,
+        account: &str,
+        failed_login_count: u32,
+        lockable: bool,
+        client_ip: Option<IpAddr>,
+    ) -> Result<(), Error> {
+        let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD else {
+            return Ok(());
+        };
+        let guard = LoginGuard::shared();
+        let max_attempts = guard.max_attempts();
+        if !lockable || max_attempts == 0 || failed_login_count < max_attempts {
+            return Ok(());
+        }
+
+        let user_id = user
+            .parse_string(Self::PRIMARY_KEY_NAME)
+            .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?;
+        let query = Query::from_entry(Self::PRIMARY_KEY_NAME, user_id.as_ref());
+        let locked_until = DateTime::now() + guard.backoff_for(failed_login_count);
+        let mut mutation = Mutation::from_entry("status", "Locked");
+        mutation.add_update(locked_until_field, locked_until);
+        Self::update_one(&query, &mut mutation).await?;
 
+        let mut data = Map::new();
+        data.upsert("user_id", user_id.as_ref());
+        data.upsert("account", account);
+        data.upsert("failed_attempts", failed_login_count);
+        data.upsert("locked_until", locked_until);
+        data.upsert("client_ip", client_ip.map(|ip| ip.to_string()));
+
+        let mut event = CloudEvent::new(Uuid::now_v7(), Self::model_name(), "user.locked");
+        event.set_subject(account.to_owned());
+        event.set_data(data);
+        Self::emit_event(event);
+        Ok(())
+    }
+
+    /// Records a failed login attempt for the client IP,
+    /// and emits an event if the client IP is blocked.
+    fn record_ip_failure(attempt: LoginAttempt<'_>, client_ip: Option<IpAddr>) {
+        let guard = LoginGuard::shared();
+        let max_attempts = guard.ip_max_attempts();
+        let failed_attempts = attempt.fail();
+        if max_attempts > 0 && failed_attempts == max_attempts {
+            let client_ip = client_ip.map(|ip| ip.to_string()).unwrap_or_default();
             let mut data = Map::new();
-            data.upsert("expires_in", claims.expires_in().as_secs());
-            data.upsert("refresh_token", claims.refresh_token()?);
-            data.upsert("access_token", claims.access_token()?);
-            if let Some(login_at_field) = Self::LOGIN_AT_FIELD {
-                data.upsert(login_at_field, user.remove(login_at_field));
-            }
-            if let Some(login_ip_field) = Self::LOGIN_IP_FIELD {
-                data.upsert(login_ip_field, user.remove(login_ip_field));
+            data.upsert("client_ip", client_ip.as_str());
+            data.upsert("failed_attempts", failed_attempts);
+            data.upsert("blocked_until", DateTime::now() + guard.lock_duration());
+
+            let mut event = CloudEvent::new(Uuid::now_v7(), Self::model_name(), "login.blocked");
+            event.set_subject(client_ip);
+            event.set_data(data);
+            Self::emit_event(event);
+        }
+    }
+
+    /// Emits a cloud event for the security incidents such as the account lockout.
+    /// By default, the event is only logged. It can be overridden to send the event
+    /// to a message channel so that it can be alerted on.
+    #[inline]
+    fn emit_event(event: CloudEvent) {
+        tracing::warn!(
+            event_type = event.event_type(),
+            subject = event.subject(),
+            "{}",
+            event.stringify_data()
+        );
+    }
+
+    /// Returns the fields required for issuing the tokens.
+    fn token_fields() -> Vec<&'static str> {
+        let mut fields = vec![Self::PRIMARY_KEY_NAME];
+        if let Some(role_field) = Self::ROLE_FIELD {
+            fields.push(role_field);
+        }
+        if let Some(tenant_id_field) = Self::TENANT_ID_FIELD {
+            fields.push(tenant_id_field);
+        }
+        if let Some(login_at_field) = Self::LOGIN_AT_FIELD {
+            fields.push(login_at_field);
+        }
+        if let Some(login_ip_field) = Self::LOGIN_IP_FIELD {
+            fields.push(login_ip_field);
+        }
+        fields
+    }
+
+    /// Issues the access token and refresh token for the user.
+    async fn issue_tokens(mut user: Map, device_id: Option<&str>) -> Result<(K, Map), Error> {
+        // Cann't use `get_str` because the primary key may be an integer
+        let user_id = user
+            .parse_string(Self::PRIMARY_KEY_NAME)
+            .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?
+            .into_owned();
+        let mut claims = JwtClaims::new(&user_id);
+        if let Some(role_field) = Self::ROLE_FIELD.filter(|&field| user.contains_key(field)) {
+            claims.add_data_entry("roles", user.parse_str_array(role_field));
+        }
+        if let Some(tenant_id_field) = Self::TENANT_ID_FIELD {
+            if let Some(tenant_id) = user.remove(tenant_id_field) {
+                claims.add_data_entry("tenant_id", tenant_id);
             }
-            Ok((user_id, data))
+        }
+
+        let mut data = Map::new();
+        data.upsert("expires_in", claims.expires_in().as_secs());
+        if Self::REFRESH_TOKEN_ROTATION {
+            let session_id = Uuid::now_v7();
+            let expires_at = DateTime::now() + JwtClaims::refresh_interval();
+            let token_id = RefreshToken::issue(&user_id, session_id, device_id, expires_at).await?;
+            claims.add_data_entry("sid", session_id.to_string());
+            data.upsert("session_id", session_id.to_string());
+            data.upsert("refresh_token", claims.refresh_token_with_id(token_id)?);
         } else {
-            Err(warn!("fail to generate access token"))
+            data.upsert("refresh_token", claims.refresh_token()?);
         }
+        data.upsert("access_token", claims.access_token()?);
+        if let Some(login_at_field) = Self::LOGIN_AT_FIELD {
+            data.upsert(login_at_field, user.remove(login_at_field));
+        }
+        if let Some(login_ip_field) = Self::LOGIN_IP_FIELD {
+            data.upsert(login_ip_field, user.remove(login_ip_field));
+        }
+        Ok((user_id.parse()?, data))
     }
 
     /// Refreshes the access token.
+    /// If the refresh token rotation is enabled, a new refresh token will be issued,
+    /// and the reuse of the refresh token will revoke all the tokens in the same session.
     async fn refresh_token(claims: &JwtClaims) -> Result<Map, Error> {
         if !claims.data().is_empty() {
             bail!("401 Unauthorized: the JWT token is not a refresh token");
@@ -175,6 +469,17 @@ where
         let mut user: Map = Self::find_one(&query)
             .await?
             .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
+
+        // The token is consumed only if the user is allowed to refresh it.
+        let refresh_token = if Self::REFRESH_TOKEN_ROTATION {
+            let Some(token_id) = claims.jwt_id() else {
+                bail!("401 Unauthorized: the refresh token does not have a JWT ID");
+            };
+            Some(RefreshToken::rotate(token_id, user_id).await?)
+        } else {
+            None
+        };
+
         let mut claims = JwtClaims::new(user_id);
         if let Some(role_field) = Self::ROLE_FIELD.filter(|&field| user.contains_key(field)) {
             claims.add_data_entry("roles", user.parse_str_array(role_field));
@@ -187,6 +492,15 @@ where
 
         let mut data = Map::new();
         data.upsert("expires_in", claims.expires_in().as_secs());
+        if let Some(token) = refresh_token {
+            let session_id = token.family_id();
+            let expires_at = DateTime::now() + JwtClaims::refresh_interval();
+            let token_id =
+                RefreshToken::issue(user_id, session_id, token.device_id(), expires_at).await?;
+            claims.add_data_entry("sid", session_id.to_string());
+            data.upsert("session_id", session_id.to_string());
+            data.upsert("refresh_token", claims.refresh_token_with_id(token_id)?);
+        }
         data.upsert("access_token", claims.access_token()?);
         Ok(data)
     }
@@ -219,6 +533,9 @@ where
             .await?
             .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
         let data = claims.data();
+        if claims.audience().is_some() || data.contains_key("mfa") {
+            bail!("401 Unauthorized: the MFA challenge token is not an access token");
+        }
         if let Some(role_field) = Self::ROLE_FIELD {
             if let Some(roles) = data.get("roles") {
                 if user.get(role_field) != Some(roles) {
@@ -248,9 +565,102 @@ where
                 }
             }
         }
+        if Self::REFRESH_TOKEN_ROTATION {
+            if let Some(result) = data.parse_uuid("sid") {
+                let session_id = result?;
+                if !RefreshToken::is_family_active(&session_id).await? {
+                    bail!("401 Unauthorized: the session `{}` is revoked", session_id);
+                }
+            }
+        }
         Ok(true)
     }
 
+    /// Resolves the secret access key and the user session for the access key ID,
+    /// which is used for verifying the request signatures.
+    /// The secret access key is derived by [`SecretAccessKey::new()`].
+    async fn resolve_access_key(
+        access_key_id: &AccessKeyId,
+    ) -> Result<Option<(SecretAccessKey, UserSession<String>)>, Error> {
+        let mut query = Query::default();
+        let mut fields = vec![Self::PRIMARY_KEY_NAME];
+        if let Some(role_field) = Self::ROLE_FIELD {
+            fields.push(role_field);
+        }
+        if let Some(tenant_id_field) = Self::TENANT_ID_FIELD {
+            fields.push(tenant_id_field);
+        }
+        query.allow_fields(&fields);
+        query.add_filter(Self::ACCESS_KEY_ID_FIELD, access_key_id.as_str());
+        query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+
+        let Some(user) = Self::find_one::<Map>(&query).await? else {
+            return Ok(None);
+        };
+        let user_id = user
+            .parse_string(Self::PRIMARY_KEY_NAME)
+            .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?;
+        let mut session = UserSession::new(user_id.into_owned(), None);
+        if let Some(roles) = Self::ROLE_FIELD.and_then(|field| user.parse_str_array(field)) {
+            session.set_roles(roles.into_iter().map(|s| s.to_owned()).collect::<Vec<_>>());
+        }
+        if let Some(tenant_id) = Self::TENANT_ID_FIELD.and_then(|field| user.parse_string(field)) {
+            session.set_tenant_id(tenant_id.into_owned());
+        }
+        Ok(Some((SecretAccessKey::new(access_key_id), session)))
+    }
+
+    /// Signs out the sessions of the user, and returns the number of revoked tokens.
+    ///
+    /// The `scope` field in the body can be `session` (default), `device` or `all`.
+    /// The current session in the JWT claims is revoked unless a `session_id` is specified,
+    /// and the `device_id` field should be specified for the `device` scope.
+    /// No tokens are revoked if the refresh token rotation is disabled.
+    async fn sign_out(claims: &JwtClaims, body: &Map) -> Result<Map, Error> {
+        let Some(user_id) = claims.subject() else {
+            bail!("401 Unauthorized: the JWT token does not have a subject");
+        };
+        if !Self::REFRESH_TOKEN_ROTATION {
+            return Ok(Map::from_entry("revoked", 0));
+        }
+        let revoked = match body.get_str("scope").unwrap_or("session") {
+            "session" => {
+                let session_id = body
+                    .parse_uuid("session_id")
+                    .or_else(|| claims.data().parse_uuid("sid"))
+                    .ok_or_else(|| {
+                        warn!("400 Bad Request: the `session_id` should be specified")
+                    })??;
+                let query = Query::new(json!({
+                    "family_id": session_id.to_string(),
+                    "user_id": user_id,
+                }));
+                if RefreshToken::count(&query).await? == 0 {
+                    bail!("404 Not Found: cannot find the session `{}`", session_id);
+                }
+                RefreshToken::revoke_family(&session_id).await?
+            }
+            "device" => {
+                let device_id = body
+                    .get_str("device_id")
+                    .ok_or_else(|| warn!("400 Bad Request: the `device_id` should be specified"))?;
+                RefreshToken::revoke_device(user_id, device_id).await?
+            }
+            "all" => RefreshToken::revoke_user(user_id).await?,
+            scope => bail!("400 Bad Request: invalid sign-out scope `{}`", scope),
+        };
+        Ok(Map::from_entry("revoked", revoked))
+    }
+
+    /// Lists the active sessions of the user.
+    /// The sessions are only tracked if the refresh token rotation is enabled.
+    async fn list_sessions(user_id: &K) -> Result<Vec<Map>, Error> {
+        if !Self::REFRESH_TOKEN_ROTATION {
+            return Ok(Vec::new());
+        }
+        RefreshToken::list_sessions(&user_id.to_string()).await
+    }
+
     /// Verifies the user identity.
     async fn verify_identity(user_id: K, body: &Map) -> Result<Map, Error> {
         let mut query = Query::default();
@@ -286,9 +696,601 @@ where
         }
         Ok(data)
     }
+
+    /// Generates a short-lived challenge token for the MFA step.
+    ///
+    /// The challenge is recorded in the MFA data, so that the token can be exchanged
+    /// only once, and the failed attempts of the previous challenge are reset.
+    #[cfg(feature = "mfa")]
+    async fn generate_mfa_challenge(
+        user: &Map,
+        device_id: Option<&str>,
+    ) -> Result<(K, Map), Error> {
+        let Some(mfa_field) = Self::MFA_FIELD else {
+            bail!("403 Forbidden: MFA is not supported for the model");
+        };
+        let user_id = user
+            .parse_string(Self::PRIMARY_KEY_NAME)
+            .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?;
+        let version = user.get_u64("version").unwrap_or_default();
+        let mut mfa = user.get_object(mfa_field).cloned().unwrap_or_default();
+        let challenge_id = Uuid::now_v7().to_string();
+        mfa.upsert("challenge_id", challenge_id.as_str());
+        mfa.upsert("failed_attempts", 0);
+        Self::update_mfa(&user_id, version, mfa_field, mfa).await?;
+
+        let mut claims = JwtClaims::with_max_age(user_id.as_ref(), MFA_CHALLENGE_MAX_AGE);
+        claims.set_audience(Self::MFA_AUDIENCE);
+        claims.add_data_entry("mfa", "totp");
+        claims.add_data_entry("challenge_id", challenge_id);
+        if let Some(device_id) = device_id {
+            claims.add_data_entry("device_id", device_id);
+        }
+
+        let mut data = Map::new();
+        data.upsert("mfa_required", true);
+        data.upsert("expires_in", claims.expires_in().as_secs());
+        data.upsert("mfa_token", claims.access_token()?);
+        Ok((user_id.parse()?, data))
+    }
+
+    /// Exchanges the MFA challenge token and a TOTP code or a recovery code
+    /// for the access token and refresh token.
+    ///
+    /// The claims should be parsed by `parse_jwt_claims_with_audience()`
+    /// for the [`MFA_AUDIENCE`](Self::MFA_AUDIENCE). The challenge is consumed
+    /// when it succeeds. Failed attempts are throttled by the [`LoginGuard`],
+    /// and the challenge is invalidated after too many failed attempts,
+    /// so that the user should log in with the password again.
+    #[cfg(feature = "mfa")]
+    async fn verify_mfa(claims: &JwtClaims, body: &Map) -> Result<(K, Map), Error> {
+        let Some(mfa_field) = Self::MFA_FIELD else {
+            bail!("403 Forbidden: MFA is not supported for the model");
+        };
+        let challenge_id = claims.data().get_str("challenge_id");
+        if claims.audience() != Some(Self::MFA_AUDIENCE)
+            || claims.data().get_str("mfa") != Some("totp")
+            || challenge_id.is_none()
+        {
+            bail!("401 Unauthorized: the JWT token is not an MFA challenge token");
+        }
+
+        let Some(user_id) = claims.subject() else {
+            bail!("401 Unauthorized: the JWT token does not have a subject");
+        };
+        let guard = LoginGuard::shared();
+        let attempt = guard.check(&format!("mfa:{user_id}"), guard.max_attempts())?;
+
+        let mut query = Query::default();
+        let mut fields = Self::token_fields();
+        fields.extend([Self::ACCOUNT_FIELD, mfa_field, "version"]);
+        query.allow_fields(&fields);
+        query.add_filter(Self::PRIMARY_KEY_NAME, user_id);
+        query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+
+        let mut user: Map = Self::find_one(&query)
+            .await?
+            .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
+        let version = user
+            .remove("version")
+            .and_then(|v| v.as_u64())
+            .unwrap_or_default();
+        let mut mfa = user
+            .remove(mfa_field)
+            .and_then(|v| v.into_map_opt())
+            .unwrap_or_default();
+        if mfa.get_bool("enabled") != Some(true) {
+            bail!(
+                "403 Forbidden: the user `{}` has not enrolled in MFA",
+                user_id
+            );
+        }
+        if mfa.get_str("challenge_id") != challenge_id {
+            bail!("401 Unauthorized: the MFA challenge token has been used or invalidated");
+        }
+
+        let result = if let Some(code) = body.get_str("code") {
+            let account = user.get_str(Self::ACCOUNT_FIELD).unwrap_or_default();
+            Self::check_totp_code(&mut mfa, account, code)
+        } else if let Some(recovery_code) = body.get_str("recovery_code") {
+            Self::check_recovery_code(&mut mfa, recovery_code)
+        } else {
+            bail!("401 Unauthorized: the TOTP `code` or `recovery_code` should be specified");
+        };
+        if let Err(err) = result {
+            attempt.fail();
+
+            let failed_attempts = mfa.get_u32("failed_attempts").unwrap_or_default() + 1;
+            let max_attempts = guard.max_attempts();
+            if max_attempts > 0 && failed_attempts >= max_attempts {
+                mfa.remove("challenge_id");
+            }
+            mfa.upsert("failed_attempts", failed_attempts);
+            Self::update_mfa(user_id, version, mfa_field, mfa).await?;
+            return Err(err);
+        }
+        attempt.succeed();
+
+        // Persists the last accepted time step or the remaining recovery codes,
+        // and consumes the challenge.
+        mfa.remove("challenge_id");
+        mfa.remove("failed_attempts");
+        Self::update_mfa(user_id, version, mfa_field, mfa).await?;
+        Self::issue_tokens(user, claims.data().get_str("device_id")).await
+    }
+
+    /// Enrolls the user in MFA, and returns the provisioning URI, QR code and recovery codes.
+    /// MFA will not be enabled until it is activated with a TOTP code.
+    #[cfg(feature = "mfa")]
+    async fn enroll_mfa(user_id: &K) -> Result<Map, Error> {
+        let Some(mfa_field) = Self::MFA_FIELD else {
+            bail!("403 Forbidden: MFA is not supported for the model");
+        };
+
+        let mut query = Query::default();
+        query.allow_fields(&[Self::ACCOUNT_FIELD, mfa_field]);
+        query.add_filter(Self::PRIMARY_KEY_NAME, user_id.to_string());
+        query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+
+        let user: Map = Self::find_one(&query)
+            .await?
+            .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
+        let mfa_enabled = user
+            .get_object(mfa_field)
+            .and_then(|mfa| mfa.get_bool("enabled"))
+            .unwrap_or_default();
+        if mfa_enabled {
+            bail!(
+                "409 Conflict: the user `{}` has already enrolled in MFA",
+                user_id
+            );
+        }
+
+        // The TOTP secret is generated randomly for each enrollment.
+        let secret = SecretAccessKey::new(&AccessKeyId::new());
+        let encrypted_secret = Self::encrypt_secret(&secret.to_string())?;
+        let account = user.get_str(Self::ACCOUNT_FIELD).unwrap_or_default();
+        let totp = secret.generate_totp(totp_issuer(), account.to_owned());
+        let recovery_codes = (0..MFA_RECOVERY_CODES)
+            .map(|_| {
+                let code = AccessKeyId::new().as_str().to_ascii_lowercase();
+                format!("{}-{}", &code[..5], &code[5..10])
+            })
+            .collect::<Vec<_>>();
+        let encrypted_codes = recovery_codes
+            .iter()
+            .map(|code| Self::encrypt_password(code))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut mfa = Map::new();
+        mfa.upsert("enabled", false);
+        mfa.upsert("secret", encrypted_secret);
+        mfa.upsert("recovery_codes", encrypted_codes);
+
+        let query = Query::from_entry(Self::PRIMARY_KEY_NAME, user_id.to_string());
+        let mut mutation = Mutation::from_entry(mfa_field, mfa);
+        Self::update_one(&query, &mut mutation).await?;
+
+        let mut data = Map::new();
+        data.upsert("secret", totp.get_secret_base32());
+        data.upsert("provisioning_uri", totp.get_url());
+        if let Ok(qr_code) = totp.get_qr_base64() {
+            data.upsert("qr_code", format!("data:image/png;base64,{qr_code}"));
+        }
+        data.upsert("recovery_codes", recovery_codes);
+        Ok(data)
+    }
+
+    /// Activates MFA for the enrolled user with a TOTP code.
+    #[cfg(feature = "mfa")]
+    async fn activate_mfa(user_id: &K, code: &str) -> Result<(), Error> {
+        let Some(mfa_field) = Self::MFA_FIELD else {
+            bail!("403 Forbidden: MFA is not supported for the model");
+        };
+
+        let (account, mut mfa, version) = Self::fetch_mfa(user_id, mfa_field).await?;
+        Self::check_totp_code(&mut mfa, &account, code)?;
+        mfa.upsert("enabled", true);
+        mfa.upsert("enrolled_at", DateTime::now());
+        Self::update_mfa(&user_id.to_string(), version, mfa_field, mfa).await
+    }
+
+    /// Disables MFA for the user with a TOTP code.
+    #[cfg(feature = "mfa")]
+    async fn disable_mfa(user_id: &K, code: &str) -> Result<(), Error> {
+        let Some(mfa_field) = Self::MFA_FIELD else {
+            bail!("403 Forbidden: MFA is not supported for the model");
+        };
+
+        let (account, mut mfa, version) = Self::fetch_mfa(user_id, mfa_field).await?;
+        Self::check_totp_code(&mut mfa, &account, code)?;
+        Self::update_mfa(&user_id.to_string(), version, mfa_field, Map::new()).await
+    }
+
+    /// Fetches the account, the MFA data and the version of the enrolled user.
+    #[cfg(feature = "mfa")]
+    async fn fetch_mfa(user_id: &K, mfa_field: &str) -> Result<(String, Map, u64), Error> {
+        let mut query = Query::default();
+        query.allow_fields(&[Self::ACCOUNT_FIELD, mfa_field, "version"]);
+        query.add_filter(Self::PRIMARY_KEY_NAME, user_id.to_string());
+        query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+
+        let mut user: Map = Self::find_one(&query)
+            .await?
+            .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
+        let Some(mfa) = user
+            .remove(mfa_field)
+            .and_then(|v| v.into_map_opt())
+            .filter(|mfa| mfa.contains_key("secret"))
+        else {
+            bail!(
+                "403 Forbidden: the user `{}` has not enrolled in MFA",
+                user_id
+            );
+        };
+        let account = user
+            .get_str(Self::ACCOUNT_FIELD)
+            .unwrap_or_default()
+            .to_owned();
+        let version = user.get_u64("version").unwrap_or_default();
+        Ok((account, mfa, version))
+    }
+
+    /// Updates the MFA data of the user if the version has not been changed,
+    /// so that a TOTP code, a recovery code or a challenge can not be used concurrently.
+    #[cfg(feature = "mfa")]
+    async fn update_mfa(
+        user_id: &str,
+        version: u64,
+        mfa_field: &str,
+        mfa: Map,
+    ) -> Result<(), Error> {
+        let mut query = Query::from_entry(Self::PRIMARY_KEY_NAME, user_id);
+        query.add_filter("version", version);
+
+        let mut mutation = Mutation::from_entry(mfa_field, mfa);
+        mutation.add_update("version", version + 1);
+        let ctx = Self::update_one(&query, &mut mutation).await?;
+        if ctx.rows_affected() != Some(1) {
+            bail!(
+                "409 Conflict: the MFA data of the user `{}` has been modified concurrently",
+                user_id
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the TOTP code with the secret in the MFA data,
+    /// and records the accepted time step so that the code can not be replayed.
+    #[cfg(feature = "mfa")]
+    fn check_totp_code(mfa: &mut Map, account: &str, code: &str) -> Result<(), Error> {
+        let encrypted_secret = mfa
+            .get_str("secret")
+            .ok_or_else(|| warn!("403 Forbidden: the TOTP secret is absent"))?;
+        let secret = SecretAccessKey::from_base64(&Self::decrypt_secret(encrypted_secret)?)?;
+        let last_time_step = mfa.get_u64("last_time_step");
+        let current_time = u64::try_from(DateTime::current_timestamp()).unwrap_or_default();
+        let Some(time_step) = verify_totp_code(secret, account, code, current_time, last_time_step)
+        else {
+            bail!("401 Unauthorized: invalid TOTP code");
+        };
+        mfa.upsert("last_time_step", time_step);
+        Ok(())
+    }
+
+    /// Checks the recovery code with the hashed ones in the MFA data,
+    /// and removes the matched one so that it can be used only once.
+    #[cfg(feature = "mfa")]
+    fn check_recovery_code(mfa: &mut Map, recovery_code: &str) -> Result<(), Error> {
+        let mut recovery_codes = mfa
+            .parse_str_array("recovery_codes")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|code| code.to_owned())
+            .collect::<Vec<_>>();
+        let mut matched_index = None;
+        for (index, encrypted_code) in recovery_codes.iter().enumerate() {
+            if Self::verify_password(recovery_code, encrypted_code)? {
+                matched_index = Some(index);
+                break;
+            }
+        }
+        let Some(index) = matched_index else {
+            bail!("401 Unauthorized: invalid recovery code");
+        };
+        recovery_codes.remove(index);
+        mfa.upsert("recovery_codes", recovery_codes);
+        Ok(())
+    }
+
+    /// Returns the URL of the authorization endpoint for the provider configured by `[[oidc]]`,
+    /// which the user agent should be redirected to, and the value of the `set-cookie` header
+    /// which binds the authorization state to the user agent.
+    #[cfg(feature = "oidc")]
+    async fn oidc_authorize(
+        provider: &str,
+        redirect_to: Option<&str>,
+    ) -> Result<(String, String), Error> {
+        let provider = OidcProvider::get(provider)
+            .ok_or_else(|| warn!("404 Not Found: the provider `{}` is unavailable", provider))?;
+        let (url, cookie) = provider.authorize(redirect_to).await?;
+        Ok((url.into(), cookie.to_string()))
+    }
+
+    /// Handles the callback from the provider with the value of the state cookie,
+    /// and issues the access token and refresh token for the user linked by the union ID.
+    /// If there is no such user, a new user will be created with the claims
+    /// when the sign-up is allowed by the provider.
+    #[cfg(feature = "oidc")]
+    async fn oidc_login(
+        provider: &str,
+        params: &Map,
+        state_cookie: Option<&str>,
+        device_id: Option<&str>,
+    ) -> Result<(K, Map), Error> {
+        let Some(union_id_field) = Self::UNION_ID_FIELD else {
+            bail!("403 Forbidden: the OIDC login is not supported for the model");
+        };
+        let provider = OidcProvider::get(provider)
+            .ok_or_else(|| warn!("404 Not Found: the provider `{}` is unavailable", provider))?;
+        let identity = provider.callback(params, state_cookie).await?;
+        let union_id = identity.union_id();
+
+        let mut query = Query::default();
+        let mut fields = Self::token_fields();
+        fields.push("status");
+        if let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD {
+            fields.push(locked_until_field);
+        }
+        #[cfg(feature = "mfa")]
+        if let Some(mfa_field) = Self::MFA_FIELD {
+            fields.extend([mfa_field, "version"]);
+        }
+        query.allow_fields(&fields);
+        query.add_filter("status", Map::from_entry("$ne", "Deleted"));
+        query.add_filter(union_id_field, union_id.as_str());
+
+        let mut user = if let Some(user) = Self::find_one::<Map>(&query).await? {
+            user
+        } else if provider.sign_up_allowed() {
+            let data = Self::map_oidc_claims(&identity);
+            let mut model = Self::new();
+            let validation = model.read_map(&data);
+            if !validation.is_success() {
+                bail!(
+                    "400 Bad Request: invalid user data for the identity: {}",
+                    validation.invalid_params().join(", ")
+                );
+            }
+            model.insert().await?;
+            Self::find_one::<Map>(&query)
+                .await?
+                .ok_or_else(|| warn!("404 Not Found: fail to create the user"))?
+        } else {
+            bail!(
+                "403 Forbidden: the identity is not linked to any user for the provider `{}`",
+                provider.name()
+            );
+        };
+        if user.get_str("status") == Some("Locked") {
+            let locked_until = Self::LOCKED_UNTIL_FIELD
+                .and_then(|field| user.parse_datetime(field).and_then(|result| result.ok()));
+            match locked_until {
+                Some(locked_until) if locked_until > DateTime::now() => {
+                    bail!(
+                        "403 Forbidden: the account is locked until `{}`",
+                        locked_until
+                    );
+                }
+                Some(_) => {
+                    let user_id = user
+                        .parse_string(Self::PRIMARY_KEY_NAME)
+                        .ok_or_else(|| warn!("404 Not Found: the user id is absent"))?;
+                    let query = Query::from_entry(Self::PRIMARY_KEY_NAME, user_id.as_ref());
+                    let mut mutation = Mutation::from_entry("status", "Active");
+                    if let Some(failed_login_count_field) = Self::FAILED_LOGIN_COUNT_FIELD {
+                        mutation.add_update(failed_login_count_field, 0);
+                    }
+                    if let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD {
+                        mutation.add_update(locked_until_field, JsonValue::Null);
+                    }
+                    Self::update_one(&query, &mut mutation).await?;
+                }
+                None => bail!("403 Forbidden: the account is locked"),
+            }
+        }
+        if let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD {
+            user.remove(locked_until_field);
+        }
+        user.remove("status");
+
+        #[cfg(feature = "mfa")]
+        if let Some(mfa_field) = Self::MFA_FIELD {
+            let mfa_enabled = user
+                .get_object(mfa_field)
+                .and_then(|mfa| mfa.get_bool("enabled"))
+                .unwrap_or_default();
+            if mfa_enabled {
+                let (user_id, mut data) = Self::generate_mfa_challenge(&user, device_id).await?;
+                if let Some(redirect_to) = identity.redirect_to() {
+                    data.upsert("redirect_to", redirect_to);
+                }
+                return Ok((user_id, data));
+            }
+        }
+
+        let (user_id, mut data) = Self::issue_tokens(user, device_id).await?;
+        if let Some(redirect_to) = identity.redirect_to() {
+            data.upsert("redirect_to", redirect_to);
+        }
+        Ok((user_id, data))
+    }
+
+    /// Handles the callback from the provider, and links the identity to the user.
+    /// The user identity should be verified by the caller before calling this method.
+    #[cfg(feature = "oidc")]
+    async fn link_oidc_identity(
+        user_id: &K,
+        provider: &str,
+        params: &Map,
+        state_cookie: Option<&str>,
+    ) -> Result<OidcIdentity, Error> {
+        let Some(union_id_field) = Self::UNION_ID_FIELD else {
+            bail!("403 Forbidden: the OIDC login is not supported for the model");
+        };
+        let provider = OidcProvider::get(provider)
+            .ok_or_else(|| warn!("404 Not Found: the provider `{}` is unavailable", provider))?;
+        let identity = provider.callback(params, state_cookie).await?;
+        let union_id = identity.union_id();
+
+        let user_id = user_id.to_string();
+        let mut query = Query::from_entry(union_id_field, union_id.as_str());
+        query.add_filter(
+            Self::PRIMARY_KEY_NAME,
+            Map::from_entry("$ne", user_id.as_str()),
+        );
+        if Self::count(&query).await? > 0 {
+            bail!("409 Conflict: the identity has been linked to another user");
+        }
+
+        let query = Query::from_entry(Self::PRIMARY_KEY_NAME, user_id);
+        let mut mutation = Mutation::from_entry(union_id_field, union_id);
+        Self::update_one(&query, &mut mutation).await?;
+        Ok(identity)
+    }
+
+    /// Maps the claims of the identity to the data for creating a new user.
+    #[cfg(feature = "oidc")]
+    fn map_oidc_claims(identity: &OidcIdentity) -> Map {
+        let claims = identity.claims();
+        let union_id = identity.union_id();
+        let name = claims
+            .get_str("name")
+            .or_else(|| claims.get_str("preferred_username"))
+            .unwrap_or_else(|| identity.subject());
+
+        let mut data = Map::new();
+        data.upsert("name", name);
+        data.upsert(Self::ACCOUNT_FIELD, union_id.as_str());
+        data.upsert(Self::PASSWORD_FIELD, Uuid::new_v4().simple().to_string());
+        if let Some(union_id_field) = Self::UNION_ID_FIELD {
+            data.upsert(union_id_field, union_id);
+        }
+        data
+    }
+}
+
+/// Max age of the MFA challenge token.
+#[cfg(feature = "mfa")]
+const MFA_CHALLENGE_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// Number of the MFA recovery codes.
+#[cfg(feature = "mfa")]
+const MFA_RECOVERY_CODES: usize = 10;
+
+/// Returns the issuer for the TOTP.
+#[cfg(feature = "mfa")]
+fn totp_issuer() -> Option<String> {
+    State::shared()
+        .config()
+        .get_str("name")
+        .map(|name| name.to_owned())
+}
+
+/// Verifies the TOTP code at the time, and returns the matched time step.
+/// The time steps not later than the last accepted one are rejected.
+#[cfg(feature = "mfa")]
+fn verify_totp_code(
+    secret: SecretAccessKey,
+    account: &str,
+    code: &str,
+    time: u64,
+    last_time_step: Option<u64>,
+) -> Option<u64> {
+    let totp = secret.generate_totp(totp_issuer(), account.to_owned());
+    let step = totp.step;
+    let skew = u64::from(totp.skew);
+    let current_time_step = time / step;
+    (current_time_step.saturating_sub(skew)..=current_time_step + skew)
+        .filter(|&time_step| last_time_step.map_or(true, |last| time_step > last))
+        .find(|&time_step| {
+            let expected_code = totp.generate(time_step * step);
+            expected_code.len() == code.len()
+                && expected_code
+                    .bytes()
+                    .zip(code.bytes())
+                    .fold(0, |acc, (a, b)| acc | (a ^ b))
+                    == 0
+        })
 }
 
 impl JwtAuthService<Uuid> for super::User {
     const LOGIN_AT_FIELD: Option<&'static str> = Some("current_login_at");
     const LOGIN_IP_FIELD: Option<&'static str> = Some("current_login_ip");
+    const FAILED_LOGIN_COUNT_FIELD: Option<&'static str> = Some("failed_login_count");
+    const LOCKED_UNTIL_FIELD: Option<&'static str> = Some("locked_until");
+    const REFRESH_TOKEN_ROTATION: bool = true;
+    #[cfg(feature = "mfa")]
+    const MFA_FIELD: Option<&'static str> = Some("mfa");
+    #[cfg(feature = "oidc")]
+    const UNION_ID_FIELD: Option<&'static str> = Some("union_id");
+
+    #[cfg(feature = "oidc")]
+    fn map_oidc_claims(identity: &OidcIdentity) -> Map {
+        let claims = identity.claims();
+        let union_id = identity.union_id();
+        let name = claims
+            .get_str("name")
+            .or_else(|| claims.get_str("preferred_username"))
+            .unwrap_or_else(|| identity.subject());
+
+        let mut data = Map::new();
+        data.upsert("name", name);
+        data.upsert("union_id", union_id.as_str());
+        data.upsert("account", union_id);
+        data.upsert("password", Uuid::new_v4().simple().to_string());
+        data.upsert("roles", vec!["user"]);
+        for (field, claim) in [
+            ("nickname", "nickname"),
+            ("avatar", "picture"),
+            ("website", "website"),
+            ("locale", "locale"),
+        ] {
+            if let Some(value) = claims.get_str(claim) {
+                data.upsert(field, value);
+            }
+        }
+        if claims.get_bool("email_verified") == Some(true) {
+            if let Some(email) = claims.get_str("email") {
+                data.upsert("email", email);
+            }
+        }
+        data
+    }
+}
+
+#[cfg(all(test, feature = "mfa"))]
+mod tests {
+    use super::verify_totp_code;
+    use zino_core::auth::{AccessKeyId, SecretAccessKey};
+
+    #[test]
+    fn it_rejects_replayed_totp_codes() {
+        let secret = SecretAccessKey::new(&AccessKeyId::new());
+        let totp = secret
+            .clone()
+            .generate_totp(super::totp_issuer(), "alice".to_owned());
+        let time = 1_700_000_000;
+        let code = totp.generate(time);
+        let time_step = verify_totp_code(secret.clone(), "alice", &code, time, None);
+        assert_eq!(time_step, Some(time / totp.step));
+        assert_eq!(
+            verify_totp_code(secret.clone(), "alice", &code, time, time_step),
+            None
+        );
+        assert_eq!(
+            verify_totp_code(secret, "alice", "000000x", time, None),
+            None
+        );
+    }
 }
@@ -0,0 +1,46 @@
+use zino_core::{
+    auth::{AccessKeyId, KeyProvider, SecretAccessKey, UserSession},
+    error::Error,
+    BoxFuture,
+};
+
+/// A function pointer to resolve the secret access key and the user session.
+pub type AccessKeyResolver =
+    for<'a> fn(
+        access_key_id: &'a AccessKeyId,
+    ) -> BoxFuture<'a, Result<Option<(SecretAccessKey, UserSession<String>)>, Error>>;
+
+/// A key provider which resolves the access keys of the users.
+///
+/// The resolver is usually a wrapper of
+/// [`JwtAuthService::resolve_access_key()`](super::JwtAuthService::resolve_access_key)
+/// for the concrete user model, such as
+/// `|access_key_id| Box::pin(User::resolve_access_key(access_key_id))`.
+#[derive(Debug, Clone, Copy)]
+pub struct UserKeyProvider {
+    /// Resolver.
+    resolver: AccessKeyResolver,
+}
+
+impl UserKeyProvider {
+    /// Creates a new instance with the resolver.
+    #[inline]
+    pub fn new(resolver: AccessKeyResolver) -> Self {
+        Self { resolver }
+    }
+}
+
+impl KeyProvider for UserKeyProvider {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "user"
+    }
+
+    #[inline]
+    fn resolve<'a>(
+        &'a self,
+        access_key_id: &'a AccessKeyId,
+    ) -> BoxFuture<'a, Result<Option<(SecretAccessKey, UserSession<String>)>, Error>> {
+        (self.resolver)(access_key_id)
+    }
+}
@@ -0,0 +1,298 @@
+use parking_lot::Mutex;
+use std::{collections::HashMap, time::Duration};
+use toml::Table;
+use zino_core::{
+    bail, datetime::DateTime, error::Error, extension::TomlTableExt, state::State, LazyLock,
+};
+
+/// A guard against the brute-force attacks on the password login.
+///
+/// The failed attempts are tracked per key, such as the user account or the client IP.
+/// After a failed attempt, the next attempt with the same key should wait for a back-off
+/// which grows exponentially, and the key will be blocked for the lock duration
+/// once the number of failed attempts reaches the limit. An attempt is reserved
+/// when it is checked, so the concurrent attempts with the same key are counted as well.
+///
+/// The state is kept in memory, so it is not shared across the instances in a cluster.
+/// The failed attempts of an account are also persisted on the user model
+/// by [`JwtAuthService`], which throttles the account across the instances.
+///
+/// [`JwtAuthService`]: super::JwtAuthService
+#[derive(Debug)]
+pub struct LoginGuard {
+    /// Max number of failed attempts for an account before it is locked.
+    max_attempts: u32,
+    /// Max number of failed attempts for a client IP before it is blocked.
+    ip_max_attempts: u32,
+    /// Initial back-off after a failed attempt.
+    backoff: Duration,
+    /// Max back-off after a failed attempt.
+    max_backoff: Duration,
+    /// Duration of the lockout.
+    lock_duration: Duration,
+    /// Failed attempts.
+    attempts: Mutex<HashMap<String, FailedAttempts>>,
+}
+
+/// Failed attempts for a key.
+#[derive(Debug, Clone, Copy)]
+struct FailedAttempts {
+    /// Number of failed attempts.
+    count: u32,
+    /// Number of attempts in progress.
+    pending: u32,
+    /// Time of the last attempt.
+    last_attempt_at: DateTime,
+}
+
+impl LoginGuard {
+    /// Creates a new instance with the default settings.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 5,
+            ip_max_attempts: 20,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            lock_duration: Duration::from_secs(15 * 60),
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a new instance with the configuration.
+    pub fn with_config(config: &Table) -> Self {
+        let mut guard = Self::new();
+        if let Some(max_attempts) = config.get_u32("max-attempts") {
+            guard.max_attempts = max_attempts;
+        }
+        if let Some(ip_max_attempts) = config.get_u32("ip-max-attempts") {
+            guard.ip_max_attempts = ip_max_attempts;
+        }
+        if let Some(backoff) = config.get_duration("backoff") {
+            guard.backoff = backoff;
+        }
+        if let Some(max_backoff) = config.get_duration("max-backoff") {
+            guard.max_backoff = max_backoff;
+        }
+        if let Some(lock_duration) = config.get_duration("lock-duration") {
+            guard.lock_duration = lock_duration;
+        }
+        guard
+    }
+
+    /// Returns the max number of failed attempts for an account before it is locked.
+    /// A value of `0` disables the lockout.
+    #[inline]
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the max number of failed attempts for a client IP before it is blocked.
+    /// A value of `0` disables the blocking.
+    #[inline]
+    pub fn ip_max_attempts(&self) -> u32 {
+        self.ip_max_attempts
+    }
+
+    /// Returns the duration of the lockout.
+    #[inline]
+    pub fn lock_duration(&self) -> Duration {
+        self.lock_duration
+    }
+
+    /// Checks whether an attempt with the key is allowed now, and reserves it if so.
+    ///
+    /// The attempts in progress are counted as failed ones until they are resolved
+    /// by [`LoginAttempt::succeed()`] or [`LoginAttempt::fail()`].
+    pub fn check(&self, key: &str, max_attempts: u32) -> Result<LoginAttempt<'_>, Error> {
+        let now = DateTime::now();
+        let mut attempts = self.attempts.lock();
+        if attempts.len() >= 10_000 {
+            let max_wait = self.lock_duration.max(self.max_backoff);
+            attempts.retain(|_, attempts| {
+                attempts.pending > 0
+                    || attempts
+                        .last_attempt_at
+                        .span_before_now()
+                        .is_some_and(|elapsed| elapsed < max_wait)
+            });
+        }
+
+        let entry = attempts.entry(key.to_owned()).or_insert(FailedAttempts {
+            count: 0,
+            pending: 0,
+            last_attempt_at: now,
+        });
+        let elapsed = entry.last_attempt_at.span_before_now().unwrap_or_default();
+        if entry.pending == 0 && elapsed >= self.lock_duration {
+            entry.count = 0;
+        }
+
+        let count = entry.count + entry.pending;
+        let wait = if max_attempts > 0 && count >= max_attempts {
+            self.lock_duration
+        } else {
+            self.backoff_for(count)
+        };
+        if let Some(retry_after) = wait.checked_sub(elapsed).filter(|d| !d.is_zero()) {
+            bail!(
+                "429 Too Many Requests: too many failed login attempts, retry after {}s",
+                retry_after.as_secs().max(1)
+            );
+        }
+        entry.pending += 1;
+        entry.last_attempt_at = now;
+        Ok(LoginAttempt {
+            guard: self,
+            key: key.to_owned(),
+            resolved: false,
+        })
+    }
+
+    /// Resets the failed attempts with the key.
+    #[inline]
+    pub fn reset(&self, key: &str) {
+        self.attempts.lock().remove(key);
+    }
+
+    /// Returns the back-off for the number of failed attempts.
+    pub(crate) fn backoff_for(&self, count: u32) -> Duration {
+        if count == 0 {
+            return Duration::ZERO;
+        }
+        self.backoff
+            .saturating_mul(1 << (count - 1).min(16))
+            .min(self.max_backoff)
+    }
+
+    /// Resolves an attempt in progress with the key, and returns the number of failed attempts.
+    fn resolve(&self, key: &str, failed: bool) -> u32 {
+        let mut attempts = self.attempts.lock();
+        let Some(entry) = attempts.get_mut(key) else {
+            return 0;
+        };
+        entry.pending = entry.pending.saturating_sub(1);
+        if failed {
+            entry.count += 1;
+            entry.last_attempt_at = DateTime::now();
+        }
+        entry.count
+    }
+
+    /// Returns the shared login guard configured by `[jwt.login-guard]`.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        LazyLock::force(&SHARED_LOGIN_GUARD)
+    }
+}
+
+impl Default for LoginGuard {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A login attempt reserved by [`LoginGuard::check()`].
+///
+/// It is released without being counted as a failed attempt if it is dropped unresolved.
+#[derive(Debug)]
+pub struct LoginAttempt<'a> {
+    /// Login guard.
+    guard: &'a LoginGuard,
+    /// Key of the attempt.
+    key: String,
+    /// Flag to indicate whether the attempt has been resolved.
+    resolved: bool,
+}
+
+impl<'a> LoginAttempt<'a> {
+    /// Resolves the attempt as a successful one, and resets the failed attempts with the key.
+    #[inline]
+    pub fn succeed(mut self) {
+        self.resolved = true;
+        self.guard.reset(&self.key);
+    }
+
+    /// Resolves the attempt as a failed one, and returns the number of failed attempts.
+    #[inline]
+    pub fn fail(mut self) -> u32 {
+        self.resolved = true;
+        self.guard.resolve(&self.key, true)
+    }
+}
+
+impl<'a> Drop for LoginAttempt<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        if !self.resolved {
+            self.guard.resolve(&self.key, false);
+        }
+    }
+}
+
+/// Shared login guard.
+static SHARED_LOGIN_GUARD: LazyLock<LoginGuard> = LazyLock::new(|| {
+    State::shared()
+        .get_config("jwt")
+        .and_then(|config| config.get_table("login-guard"))
+        .map(LoginGuard::with_config)
+        .unwrap_or_default()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::LoginGuard;
+    use std::time::Duration;
+
+    #[test]
+    fn it_grows_the_backoff_exponentially() {
+        let guard = LoginGuard::new();
+        assert_eq!(guard.backoff_for(0), Duration::ZERO);
+        assert_eq!(guard.backoff_for(1), Duration::from_secs(1));
+        assert_eq!(guard.backoff_for(3), Duration::from_secs(4));
+        assert_eq!(guard.backoff_for(7), Duration::from_secs(60));
+        assert_eq!(guard.backoff_for(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn it_reserves_the_attempts() {
+        let mut guard = LoginGuard::new();
+        guard.backoff = Duration::ZERO;
+
+        let attempt = guard.check("account:alice", 2).unwrap();
+        let concurrent_attempt = guard.check("account:alice", 2).unwrap();
+        assert!(guard.check("account:alice", 2).is_err());
+        drop(concurrent_attempt);
+
+        assert_eq!(attempt.fail(), 1);
+        let attempt = guard.check("account:alice", 2).unwrap();
+        attempt.succeed();
+        assert!(guard.check("account:alice", 2).is_ok());
+    }
+
+    #[test]
+    fn it_locks_the_key_after_failed_attempts() {
+        let mut guard = LoginGuard::new();
+        guard.backoff = Duration::ZERO;
+
+        for count in 1..=2 {
+            let attempt = guard.check("ip:127.0.0.1", 2).unwrap();
+            assert_eq!(attempt.fail(), count);
+        }
+        let err = guard.check("ip:127.0.0.1", 2).unwrap_err();
+        assert!(err.to_string().starts_with("429 Too Many Requests"));
+        assert!(guard.check("ip:127.0.0.2", 2).is_ok());
+
+        guard.lock_duration = Duration::ZERO;
+        assert!(guard.check("ip:127.0.0.1", 2).is_ok());
+    }
+
+    #[test]
+    fn it_backs_off_after_a_failed_attempt() {
+        let guard = LoginGuard::new();
+        let attempt = guard.check("account:bob", 0).unwrap();
+        attempt.fail();
+        assert!(guard.check("account:bob", 0).is_err());
+    }
+}
@@ -19,9 +19,15 @@ use zino_derive::{DecodeRow, ModelAccessor, Schema};
 use crate::tag::Tag;
 
 mod jwt_auth;
+mod key_provider;
+mod login_guard;
+mod refresh_token;
 mod status;
 
 pub use jwt_auth::JwtAuthService;
+pub use key_provider::{AccessKeyResolver, UserKeyProvider};
+pub use login_guard::{LoginAttempt, LoginGuard};
+pub use refresh_token::RefreshToken;
 pub use status::UserStatus;
 
 #[cfg(feature = "visibility")]
@@ -88,6 +94,10 @@ pub struct User {
     current_login_ip: String,
     login_count: u32,
     failed_login_count: u8,
+    locked_until: Option<DateTime>,
+    #[cfg(feature = "mfa")]
+    #[schema(write_only)]
+    mfa: Map,
 
     // Extensions.
     extra: Map,
@@ -135,6 +145,21 @@ impl Model for User {
         if let Some(account) = data.parse_string("account") {
             self.account = account.into_owned();
         }
+        if let Some(nickname) = data.parse_string("nickname") {
+            self.nickname = nickname.into_owned();
+        }
+        if let Some(avatar) = data.parse_string("avatar") {
+            self.avatar = avatar.into_owned();
+        }
+        if let Some(website) = data.parse_string("website") {
+            self.website = website.into_owned();
+        }
+        if let Some(email) = data.parse_string("email") {
+            self.email = email.into_owned();
+        }
+        if let Some(locale) = data.parse_string("locale") {
+            self.locale = locale.into_owned();
+        }
         if let Some(password) = data.parse_string("password") {
             match User::encrypt_password(&password) {
                 Ok(password) => self.password = password,
@@ -0,0 +1,345 @@
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    time::{Duration, Instant},
+};
+use zino_core::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    json,
+    model::{Model, ModelHooks},
+    validation::Validation,
+    LazyLock, Map, Uuid,
+};
+use zino_derive::{DecodeRow, ModelAccessor, Schema};
+
+/// The `refresh_token` model for the rotation and revocation of refresh tokens.
+///
+/// Each refresh token is identified by the `jti` claim and belongs to a token family,
+/// which represents a login session. A refresh token can be used only once:
+/// it is rotated to a new one in the same family, and the reuse of a rotated token
+/// revokes the whole family. The expired tokens are deleted periodically.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Schema, ModelAccessor)]
+#[serde(default)]
+pub struct RefreshToken {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null)]
+    name: String,
+    #[cfg(feature = "namespace")]
+    #[schema(default_value = "RefreshToken::model_namespace", index_type = "hash")]
+    namespace: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, index_type = "hash")]
+    user_id: String,
+    #[schema(index_type = "hash")]
+    family_id: Uuid,
+    #[schema(index_type = "hash")]
+    device_id: String,
+    #[schema(index_type = "btree")]
+    expires_at: DateTime,
+    rotated_at: Option<DateTime>,
+    revoked_at: Option<DateTime>,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl RefreshToken {
+    /// Issues a new refresh token in the token family, and returns the token ID.
+    pub async fn issue(
+        user_id: &str,
+        family_id: Uuid,
+        device_id: Option<&str>,
+        expires_at: DateTime,
+    ) -> Result<Uuid, Error> {
+        let device_id = device_id.unwrap_or_default();
+        let token = Self {
+            name: if device_id.is_empty() {
+                "Unknown".to_owned()
+            } else {
+                device_id.to_owned()
+            },
+            user_id: user_id.to_owned(),
+            family_id,
+            device_id: device_id.to_owned(),
+            expires_at,
+            ..Self::new()
+        };
+        let token_id = token.id;
+        token.insert().await?;
+        if ISSUED_TOKENS.fetch_add(1, Relaxed) % 100 == 0 {
+            if let Err(err) = Self::delete_expired().await {
+                tracing::error!("fail to delete the expired refresh tokens: {err}");
+            }
+        }
+        Ok(token_id)
+    }
+
+    /// Deletes the expired refresh tokens, including the rotated and revoked ones.
+    /// The reuse of an expired token can not be detected, but the token is rejected
+    /// since the JWT token has also expired.
+    pub async fn delete_expired() -> Result<u64, Error> {
+        let query = Query::from_entry("expires_at", Map::from_entry("$lt", DateTime::now()));
+        let ctx = Self::delete_many(&query).await?;
+        Ok(ctx.rows_affected().unwrap_or_default())
+    }
+
+    /// Rotates the refresh token for the user, and returns the token itself.
+    /// If the token has already been rotated or revoked, the whole family will be revoked.
+    pub async fn rotate(token_id: &str, user_id: &str) -> Result<Self, Error> {
+        let query = Query::from_entry("id", token_id);
+        let Some(token) = Self::find_one::<Self>(&query).await? else {
+            bail!(
+                "401 Unauthorized: the refresh token `{}` is unknown",
+                token_id
+            );
+        };
+        if token.user_id != user_id {
+            bail!("401 Unauthorized: the refresh token does not belong to the user");
+        }
+        if token.status != "Active" {
+            Self::revoke_family(&token.family_id).await?;
+            tracing::warn!(
+                user_id,
+                family_id = %token.family_id,
+                "the reuse of the refresh token `{token_id}` is detected"
+            );
+            bail!("401 Unauthorized: the refresh token has been used or revoked");
+        }
+
+        // Claims the token with the optimistic lock.
+        let query = Query::new(json!({
+            "id": token_id,
+            "status": "Active",
+            "version": token.version,
+        }));
+        let now = DateTime::now();
+        let mut mutation = Mutation::new(json!({
+            "status": "Rotated",
+            "rotated_at": now,
+            "updated_at": now,
+            "$inc": { "version": 1 },
+        }));
+        let ctx = Self::update_one(&query, &mut mutation).await?;
+        if ctx.rows_affected() != Some(1) {
+            Self::revoke_family(&token.family_id).await?;
+            bail!("401 Unauthorized: the refresh token has been used concurrently");
+        }
+        Ok(token)
+    }
+
+    /// Revokes all the active refresh tokens in the token family.
+    #[inline]
+    pub async fn revoke_family(family_id: &Uuid) -> Result<u64, Error> {
+        let query = Query::from_entry("family_id", family_id.to_string());
+        let rows_affected = Self::revoke_tokens(query).await?;
+        FAMILY_STATES
+            .write()
+            .insert(*family_id, (false, Instant::now()));
+        Ok(rows_affected)
+    }
+
+    /// Revokes all the active refresh tokens of the device for the user.
+    #[inline]
+    pub async fn revoke_device(user_id: &str, device_id: &str) -> Result<u64, Error> {
+        let query = Query::new(json!({
+            "user_id": user_id,
+            "device_id": device_id,
+        }));
+        Self::revoke_tokens(query).await
+    }
+
+    /// Revokes all the active refresh tokens for the user.
+    #[inline]
+    pub async fn revoke_user(user_id: &str) -> Result<u64, Error> {
+        let query = Query::from_entry("user_id", user_id);
+        Self::revoke_tokens(query).await
+    }
+
+    /// Revokes the active refresh tokens selected by the query.
+    async fn revoke_tokens(mut query: Query) -> Result<u64, Error> {
+        query.add_filter("status", "Active");
+
+        let now = DateTime::now();
+        let mut mutation = Mutation::new(json!({
+            "status": "Revoked",
+            "revoked_at": now,
+            "updated_at": now,
+            "$inc": { "version": 1 },
+        }));
+        let ctx = Self::update_many(&query, &mut mutation).await?;
+        let rows_affected = ctx.rows_affected().unwrap_or_default();
+        if rows_affected > 0 {
+            FAMILY_STATES.write().clear();
+        }
+        Ok(rows_affected)
+    }
+
+    /// Returns `true` if the token family has an active refresh token which is unexpired.
+    ///
+    /// The result is cached for a short period, so the revocation by another instance
+    /// in a cluster may take effect after the period.
+    pub async fn is_family_active(family_id: &Uuid) -> Result<bool, Error> {
+        if let Some((active, checked_at)) = FAMILY_STATES.read().get(family_id) {
+            if checked_at.elapsed() < FAMILY_STATE_TTL {
+                return Ok(*active);
+            }
+        }
+
+        let query = Query::new(json!({
+            "family_id": family_id.to_string(),
+            "status": "Active",
+            "expires_at": { "$gt": DateTime::now() },
+        }));
+        let active = Self::count(&query).await? > 0;
+        let mut states = FAMILY_STATES.write();
+        if states.len() >= MAX_FAMILY_STATES {
+            states.retain(|_, (_, checked_at)| checked_at.elapsed() < FAMILY_STATE_TTL);
+            if states.len() >= MAX_FAMILY_STATES {
+                states.clear();
+            }
+        }
+        states.insert(*family_id, (active, Instant::now()));
+        Ok(active)
+    }
+
+    /// Lists the active sessions for the user.
+    pub async fn list_sessions(user_id: &str) -> Result<Vec<Map>, Error> {
+        let mut query = Query::new(json!({
+            "user_id": user_id,
+            "status": "Active",
+            "expires_at": { "$gt": DateTime::now() },
+        }));
+        query.allow_fields(&["family_id", "device_id", "expires_at", "created_at"]);
+        query.order_desc("created_at");
+        Self::find::<Map>(&query).await
+    }
+
+    /// Returns the user ID.
+    #[inline]
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Returns the token family ID.
+    #[inline]
+    pub fn family_id(&self) -> Uuid {
+        self.family_id
+    }
+
+    /// Returns the device ID.
+    #[inline]
+    pub fn device_id(&self) -> Option<&str> {
+        Some(self.device_id.as_str()).filter(|s| !s.is_empty())
+    }
+
+    /// Returns the time when the token expires at.
+    #[inline]
+    pub fn expires_at(&self) -> DateTime {
+        self.expires_at
+    }
+}
+
+impl Model for RefreshToken {
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            status: "Active".to_owned(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for RefreshToken {
+    type Data = ();
+    type Extension = ();
+}
+
+/// Time-to-live for the cached states of token families.
+const FAMILY_STATE_TTL: Duration = Duration::from_secs(30);
+
+/// Max number of the cached states of token families.
+const MAX_FAMILY_STATES: usize = 100_000;
+
+/// Cached states of token families.
+static FAMILY_STATES: LazyLock<RwLock<HashMap<Uuid, (bool, Instant)>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Number of issued tokens.
+static ISSUED_TOKENS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(test)]
+mod tests {
+    use super::RefreshToken;
+    use std::time::Duration;
+    use zino_core::{datetime::DateTime, orm::Schema, Uuid};
+
+    #[test]
+    fn it_revokes_the_family_of_a_reused_token() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let data_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/local/data");
+            std::fs::create_dir_all(data_dir).unwrap();
+            RefreshToken::create_table().await.unwrap();
+
+            let family_id = Uuid::now_v7();
+            let expires_at = DateTime::now() + Duration::from_secs(60);
+            let token_id = RefreshToken::issue("alice", family_id, None, expires_at)
+                .await
+                .unwrap()
+                .to_string();
+            let token = RefreshToken::rotate(&token_id, "alice").await.unwrap();
+            assert_eq!(token.family_id(), family_id);
+
+            let next_token_id = RefreshToken::issue("alice", family_id, None, expires_at)
+                .await
+                .unwrap()
+                .to_string();
+            assert!(RefreshToken::is_family_active(&family_id).await.unwrap());
+
+            // The reuse of the rotated token revokes the tokens issued after it.
+            let err = RefreshToken::rotate(&token_id, "alice").await.unwrap_err();
+            assert!(err.to_string().starts_with("401 Unauthorized"));
+            assert!(!RefreshToken::is_family_active(&family_id).await.unwrap());
+            assert!(RefreshToken::rotate(&next_token_id, "alice").await.is_err());
+        });
+    }
+}
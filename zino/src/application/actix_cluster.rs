@@ -1,4 +1,4 @@
-use crate::{middleware, ActixResponse, Request, RouterConfigure};
+use crate::{endpoint, middleware, ActixResponse, Request, RouterConfigure};
 use actix_files::{Files, NamedFile};
 use actix_web::{
     dev::{fn_service, ServiceRequest, ServiceResponse},
@@ -12,6 +12,7 @@ use std::{fs, path::PathBuf, time::Duration};
 use utoipa_rapidoc::RapiDoc;
 use zino_core::{
     application::{Application, ServerTag},
+    auth::JwtKeySet,
     extension::TomlTableExt,
     response::Response,
     schedule::AsyncScheduler,
@@ -52,6 +53,11 @@ impl Application for ActixCluster {
             });
         }
 
+        runtime.spawn(crate::channel::message_channel::relay_events());
+
+        #[cfg(feature = "orm")]
+        runtime.spawn(zino_core::orm::GlobalPool::run_health_checks());
+
         runtime.block_on(async {
             let default_routes = self.default_routes.leak() as &'static [_];
             let tagged_routes = self.tagged_routes.leak() as &'static [_];
@@ -78,6 +84,7 @@ impl Application for ActixCluster {
                 let default_public_dir = project_dir.join("public");
                 let mut public_route_prefix = "/public";
                 let mut public_dir = PathBuf::new();
+                let mut sse_route = None;
                 let mut backlog = 2048; // Maximum number of pending connections
                 let mut max_connections = 25000; // Maximum number of concurrent connections
                 let mut body_limit = 128 * 1024 * 1024; // 128MB
@@ -94,6 +101,9 @@ impl Application for ActixCluster {
                     if let Some(route_prefix) = config.get_str("public-route-prefix") {
                         public_route_prefix = route_prefix;
                     }
+                    if let Some(path) = config.get_str("sse-route") {
+                        sse_route = Some(path);
+                    }
                     if let Some(value) = config.get_u32("backlog") {
                         backlog = value;
                     }
@@ -133,6 +143,16 @@ impl Application for ActixCluster {
                             let res = Response::new(StatusCode::NOT_FOUND);
                             ActixResponse::from(res).respond_to(&req.into())
                         }));
+                    if let Some(path) = sse_route {
+                        app = app.route(path, web::get().to(endpoint::sse_handler));
+                    }
+                    if !JwtKeySet::shared().is_empty() {
+                        let path = app_state
+                            .get_config("jwt")
+                            .and_then(|config| config.get_str("jwks-route"))
+                            .unwrap_or("/.well-known/jwks.json");
+                        app = app.route(path, web::get().to(endpoint::jwks_handler));
+                    }
                     for route in default_routes {
                         app = app.configure(route);
                     }
@@ -185,6 +205,9 @@ impl Application for ActixCluster {
                         .app_data(JsonConfig::default().limit(body_limit))
                         .app_data(PayloadConfig::default().limit(body_limit))
                         .wrap(Compress::default())
+                        .wrap(middleware::SessionInitializer)
+                        .wrap(middleware::SignatureVerification)
+                        .wrap(middleware::RateLimitEnforcer)
                         .wrap(middleware::RequestContextInitializer)
                         .wrap(middleware::tracing_middleware())
                         .wrap(middleware::cors_middleware())
@@ -23,6 +23,7 @@ use tower_http::{
 use utoipa_rapidoc::RapiDoc;
 use zino_core::{
     application::{Application, ServerTag},
+    auth::JwtKeySet,
     extension::TomlTableExt,
     response::{FullResponse, Response},
     schedule::AsyncScheduler,
@@ -69,6 +70,10 @@ impl Application for AxumCluster {
                 }
             });
         }
+        runtime.spawn(crate::channel::message_channel::relay_events());
+
+        #[cfg(feature = "orm")]
+        runtime.spawn(zino_core::orm::GlobalPool::run_health_checks());
 
         runtime.block_on(async {
             let default_routes = self.default_routes;
@@ -161,6 +166,13 @@ impl Application for AxumCluster {
                 if let Some(path) = websocket_route {
                     app = app.route(path, routing::get(endpoint::websocket_handler));
                 }
+                if !JwtKeySet::shared().is_empty() {
+                    let path = app_state
+                        .get_config("jwt")
+                        .and_then(|config| config.get_str("jwks-route"))
+                        .unwrap_or("/.well-known/jwks.json");
+                    app = app.route(path, routing::get(endpoint::jwks_handler));
+                }
                 for route in &default_routes {
                     app = app.merge(route.clone());
                 }
@@ -229,6 +241,9 @@ impl Application for AxumCluster {
                             .layer(LazyLock::force(&middleware::TRACING_MIDDLEWARE))
                             .layer(LazyLock::force(&middleware::CORS_MIDDLEWARE))
                             .layer(from_fn(middleware::request_context))
+                            .layer(from_fn(middleware::rate_limit))
+                            .layer(from_fn(middleware::verify_signature))
+                            .layer(from_fn(middleware::session))
                             .layer(from_fn(middleware::extract_etag))
                             .layer(HandleErrorLayer::new(|err: BoxError| async move {
                                 let status_code = if err.is::<Elapsed>() {
@@ -56,6 +56,10 @@ where
                 }
             });
         }
+
+        #[cfg(feature = "orm")]
+        runtime.spawn(zino_core::orm::GlobalPool::run_health_checks());
+
         runtime.block_on(async {
             Self::load().await;
         });
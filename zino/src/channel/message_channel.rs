@@ -0,0 +1,402 @@
+use parking_lot::RwLock;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        OnceLock,
+    },
+    time::Duration,
+};
+use tokio::{
+    runtime::Handle,
+    sync::mpsc::{self, error::TrySendError, Receiver, Sender},
+};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use zino_core::{
+    application::Application,
+    auth::JwtClaims,
+    channel::{
+        ChannelAuthorizer, ChannelBackend, CloudEvent, EventHistory, MemoryBackend,
+        PolicyAuthorizer, Subscription,
+    },
+    error::Error,
+    extension::TomlTableExt,
+    json, BoxFuture, LazyLock, Uuid,
+};
+
+/// A emitter is a sender of cloud events.
+type Emitter = Sender<CloudEvent>;
+
+/// A listener is a receiver of cloud events.
+type Listener = Receiver<CloudEvent>;
+
+/// A verifier of the JWT claims, such as the user status and the revoked sessions.
+type JwtVerifier = for<'a> fn(&'a JwtClaims) -> BoxFuture<'a, Result<bool, Error>>;
+
+/// A subscriber of cloud events.
+#[derive(Debug, Clone)]
+struct Subscriber {
+    /// Emitter.
+    emitter: Emitter,
+    /// Filter.
+    filter: Option<Subscription>,
+}
+
+impl Subscriber {
+    /// Creates a new instance.
+    #[inline]
+    fn new(emitter: Emitter, filter: Option<Subscription>) -> Self {
+        Self { emitter, filter }
+    }
+
+    /// Returns a reference to the emitter.
+    #[inline]
+    fn emitter(&self) -> &Emitter {
+        &self.emitter
+    }
+
+    /// Returns a reference to the filter.
+    #[inline]
+    fn filter(&self) -> Option<&Subscription> {
+        self.filter.as_ref()
+    }
+}
+
+/// Message channel for sending and receiving cloud events.
+///
+/// Events are relayed across instances if a shared backend is configured
+/// by `backend = "database"` in the `[channel]` table.
+#[derive(Debug)]
+pub struct MessageChannel {
+    /// Sender ID.
+    sender_id: Uuid,
+    /// Receiver.
+    receiver: Option<Listener>,
+}
+
+impl MessageChannel {
+    /// Creates a new `MessageChannel`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_filter(None)
+    }
+
+    /// Creates a new `MessageChannel` which only receives the events matching the subscription.
+    #[inline]
+    pub fn with_subscription(subscription: Subscription) -> Self {
+        Self::with_filter(Some(subscription))
+    }
+
+    /// Creates a new `MessageChannel` with an optional filter.
+    fn with_filter(filter: Option<Subscription>) -> Self {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY.load(Relaxed));
+        let sender_id = Uuid::now_v7();
+        let subscriber = Subscriber::new(sender, filter);
+        let mut senders = CHANNEL_SUBSCRIBERS.write();
+        senders.retain(|_, subscriber| !subscriber.emitter().is_closed());
+        senders.insert(sender_id, subscriber);
+        Self {
+            sender_id,
+            receiver: Some(receiver),
+        }
+    }
+
+    /// Returns a reference to the shared `MessageChannel`.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        LazyLock::force(&SHARED_CHANNEL)
+    }
+
+    /// Attempts to send a message to all receivers in the channel except this one.
+    /// The message is delivered to the other receivers even if one of them fails,
+    /// and the first error is returned.
+    /// It is also published to the other instances if a shared backend is configured.
+    pub fn try_send(&self, message: impl Into<CloudEvent>) -> Result<(), TrySendError<CloudEvent>> {
+        let event = message.into();
+        let backend = &*CHANNEL_BACKEND;
+        let history = &*CHANNEL_HISTORY;
+        if backend.name() != "memory" || history.has_store() {
+            if let Ok(handle) = Handle::try_current() {
+                let event = event.clone();
+                handle.spawn(async move {
+                    if history.has_store() {
+                        if let Err(err) = history.persist(event.clone()).await {
+                            tracing::error!("fail to persist the cloud event: {err}");
+                        }
+                    }
+                    if backend.name() != "memory" {
+                        if let Err(err) = backend.publish(event).await {
+                            tracing::error!("fail to publish the cloud event: {err}");
+                        }
+                    }
+                });
+            } else {
+                tracing::warn!("cloud events can only be published inside of a Tokio runtime");
+            }
+        }
+        broadcast(Some(&self.sender_id), event)
+    }
+
+    /// Returns the recent events after the event with the ID which match the subscription.
+    /// It is used for replaying the events missed by a client during the reconnection.
+    /// It returns `None` if the event has been evicted from the history.
+    #[inline]
+    pub async fn replay(
+        subscription: &Subscription,
+        last_event_id: &str,
+    ) -> Result<Option<Vec<CloudEvent>>, Error> {
+        CHANNEL_HISTORY.replay(subscription, last_event_id).await
+    }
+
+    /// Sets the authorizer for the subscriptions and publications of the SSE
+    /// and WebSocket endpoints. It should be called before the server is started.
+    pub fn set_authorizer(authorizer: impl ChannelAuthorizer + 'static) {
+        if CHANNEL_AUTHORIZER.set(Some(Box::new(authorizer))).is_err() {
+            tracing::warn!("the authorizer for the channel has already been set");
+        }
+    }
+
+    /// Returns the authorizer for the subscriptions and publications.
+    /// If it is `None`, all the subscriptions and publications are denied.
+    #[inline]
+    pub(crate) fn authorizer() -> Option<&'static dyn ChannelAuthorizer> {
+        CHANNEL_AUTHORIZER
+            .get_or_init(|| {
+                let authorizer = crate::Cluster::config()
+                    .get_table("channel")
+                    .and_then(|config| config.get_str("authorizer"))?;
+                let authorizer: Box<dyn ChannelAuthorizer> = match authorizer {
+                    "policy" => Box::new(PolicyAuthorizer::new()),
+                    _ => {
+                        tracing::error!("unsupported channel authorizer `{authorizer}`");
+                        return None;
+                    }
+                };
+                Some(authorizer)
+            })
+            .as_deref()
+    }
+
+    /// Sets the verifier of the JWT claims for the SSE and WebSocket endpoints,
+    /// which checks the user status and the revoked sessions.
+    /// It should be called before the server is started.
+    pub fn set_jwt_verifier(verifier: JwtVerifier) {
+        if JWT_VERIFIER.set(verifier).is_err() {
+            tracing::warn!("the JWT verifier for the channel has already been set");
+        }
+    }
+
+    /// Returns the verifier of the JWT claims.
+    /// If it is `None`, the JWT tokens are rejected.
+    #[inline]
+    pub(crate) fn jwt_verifier() -> Option<JwtVerifier> {
+        JWT_VERIFIER.get().copied()
+    }
+
+    /// Receives the next message sent by the other senders in the channel.
+    /// It returns `None` if the channel has been closed.
+    #[inline]
+    pub async fn recv(&mut self) -> Option<CloudEvent> {
+        self.receiver.as_mut()?.recv().await
+    }
+
+    /// Consumes `Self` and returns a message stream of `CloudEvent`.
+    /// The subscriber is removed when the stream is dropped.
+    pub fn into_stream(mut self) -> impl Stream<Item = CloudEvent> {
+        let receiver = self.receiver.take().unwrap_or_else(|| mpsc::channel(1).1);
+        let stream = ReceiverStream::new(receiver);
+        // Keeps the channel alive until the stream is dropped.
+        stream.map(move |event| {
+            let _guard = &self;
+            event
+        })
+    }
+
+    /// Creates a message stream for the subscription, which replays the recent events
+    /// after the last event ID before receiving the new events.
+    ///
+    /// If the missed events can not be replayed, an event of the type `channel.gap`
+    /// is sent first so that the client can resynchronize its state.
+    pub async fn replay_stream(
+        subscription: Subscription,
+        last_event_id: Option<String>,
+    ) -> impl Stream<Item = CloudEvent> {
+        // Subscribes before the replay so that no events are missed in between.
+        let channel = Self::with_subscription(subscription.clone());
+        let events = if let Some(last_event_id) = last_event_id {
+            match Self::replay(&subscription, &last_event_id).await {
+                Ok(Some(events)) => events,
+                Ok(None) => vec![gap_event(last_event_id)],
+                Err(err) => {
+                    tracing::error!("fail to replay the cloud events: {err}");
+                    vec![gap_event(last_event_id)]
+                }
+            }
+        } else {
+            Vec::new()
+        };
+        let event_ids = events
+            .iter()
+            .map(|event| event.id().to_owned())
+            .collect::<HashSet<_>>();
+        let stream = channel
+            .into_stream()
+            .filter(move |event| !event_ids.contains(event.id()));
+        tokio_stream::iter(events).chain(stream)
+    }
+}
+
+impl Default for MessageChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MessageChannel {
+    fn drop(&mut self) {
+        CHANNEL_SUBSCRIBERS.write().remove(&self.sender_id);
+    }
+}
+
+/// Creates an event to indicate that the events after the last one can not be replayed.
+fn gap_event(last_event_id: String) -> CloudEvent {
+    let mut event = CloudEvent::new(&last_event_id, crate::Cluster::name(), "channel.gap");
+    event.set_data(json!({ "last_event_id": last_event_id }));
+    event
+}
+
+/// Sends the event to the subscribers except the sender.
+fn broadcast(sender_id: Option<&Uuid>, event: CloudEvent) -> Result<(), TrySendError<CloudEvent>> {
+    if !CHANNEL_HISTORY.is_disabled() {
+        CHANNEL_HISTORY.push(event.clone());
+    }
+
+    let subscribers = CHANNEL_SUBSCRIBERS.read();
+    let mut result = Ok(());
+    for (key, subscriber) in subscribers.iter() {
+        let emitter = subscriber.emitter();
+        if Some(key) != sender_id && !emitter.is_closed() {
+            let is_subscribed = subscriber
+                .filter()
+                .map_or(true, |subscription| subscription.matches(&event));
+            if is_subscribed {
+                if let Err(err) = emitter.try_send(event.clone()) {
+                    tracing::warn!(subscriber_id = %key, "fail to send a message: {err}");
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Relays the events published by the other instances to the subscribers in this process.
+pub(crate) async fn relay_events() {
+    let backend = &*CHANNEL_BACKEND;
+    if backend.name() == "memory" {
+        return;
+    }
+
+    let poll_interval = crate::Cluster::config()
+        .get_table("channel")
+        .and_then(|config| config.get_duration("poll-interval"))
+        .unwrap_or_else(|| Duration::from_secs(1));
+    loop {
+        match backend.receive().await {
+            Ok(events) => {
+                for event in events {
+                    if let Err(err) = broadcast(None, event) {
+                        tracing::error!("fail to relay the cloud event: {err}");
+                    }
+                }
+            }
+            Err(err) => tracing::error!("fail to receive cloud events: {err}"),
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Channel capacity.
+static CHANNEL_CAPACITY: AtomicUsize = AtomicUsize::new(10000);
+
+/// Channel subscribers.
+static CHANNEL_SUBSCRIBERS: LazyLock<RwLock<HashMap<Uuid, Subscriber>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Shared channel.
+pub(crate) static SHARED_CHANNEL: LazyLock<MessageChannel> = LazyLock::new(|| {
+    let capacity = if let Some(channel) = crate::Cluster::config().get("channel") {
+        channel
+            .as_table()
+            .expect("the `channel` field should be a table")
+            .get_usize("capacity")
+            .expect("the `channel.capacity` field should be a positive integer")
+    } else {
+        10000
+    };
+    CHANNEL_CAPACITY.store(capacity, Relaxed);
+    MessageChannel::new()
+});
+
+/// History of recent events configured by the `[channel.history]` table.
+static CHANNEL_HISTORY: LazyLock<EventHistory> = LazyLock::new(|| {
+    let Some(config) = crate::Cluster::config()
+        .get_table("channel")
+        .and_then(|config| config.get_table("history"))
+    else {
+        return EventHistory::default();
+    };
+    EventHistory::try_from_config(config).unwrap_or_else(|err| {
+        tracing::error!("fail to create the event history for the channel: {err}");
+        EventHistory::default()
+    })
+});
+
+/// Channel authorizer configured by `authorizer` in the `[channel]` table.
+static CHANNEL_AUTHORIZER: OnceLock<Option<Box<dyn ChannelAuthorizer>>> = OnceLock::new();
+
+/// Verifier of the JWT claims for the channel.
+static JWT_VERIFIER: OnceLock<JwtVerifier> = OnceLock::new();
+
+/// Channel backend.
+static CHANNEL_BACKEND: LazyLock<Box<dyn ChannelBackend>> = LazyLock::new(|| {
+    let Some(config) = crate::Cluster::config().get_table("channel") else {
+        return Box::new(MemoryBackend);
+    };
+    let backend = config.get_str("backend").unwrap_or("memory");
+    match backend {
+        "memory" => Box::new(MemoryBackend),
+        #[cfg(feature = "orm")]
+        "database" => {
+            use zino_core::channel::DatabaseBackend;
+
+            let service = config.get_str("database").unwrap_or("main");
+            let mut backend = match DatabaseBackend::new(service) {
+                Ok(backend) => backend,
+                Err(err) => {
+                    tracing::error!("fail to create the database backend for the channel: {err}");
+                    return Box::new(MemoryBackend);
+                }
+            };
+            if let Some(table_name) = config.get_str("table") {
+                backend.set_table_name(table_name);
+            }
+            if let Some(retention) = config.get_duration("retention") {
+                backend.set_retention(retention);
+            }
+            if let Some(lookback) = config.get_duration("lookback") {
+                backend.set_lookback(lookback);
+            }
+            if let Some(batch_size) = config.get_usize("batch-size") {
+                backend.set_batch_size(batch_size);
+            }
+            Box::new(backend)
+        }
+        _ => {
+            tracing::error!("unsupported channel backend `{backend}`");
+            Box::new(MemoryBackend)
+        }
+    }
+});
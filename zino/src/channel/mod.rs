@@ -1,5 +1,5 @@
 cfg_if::cfg_if! {
-    if #[cfg(feature = "axum")] {
-        pub(crate) mod axum_channel;
+    if #[cfg(any(feature = "actix", feature = "axum"))] {
+        pub(crate) mod message_channel;
     }
 }
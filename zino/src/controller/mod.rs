@@ -19,6 +19,9 @@ pub trait DefaultController<K, U> {
     async fn view(req: Self::Request) -> Self::Result;
 
     /// Lists models.
+    ///
+    /// The `cursor` and `next_cursor` in the response can be used as the `before` and `after`
+    /// query parameters to request the previous and next pages by keyset pagination.
     async fn list(req: Self::Request) -> Self::Result;
 
     /// Logically deletes a model.
@@ -37,6 +40,11 @@ pub trait DefaultController<K, U> {
     async fn import(req: Self::Request) -> Self::Result;
 
     /// Exports model data.
+    ///
+    /// The `csv`, `jsonlines` and `msgpack` formats are streamed in chunks
+    /// without the `max-rows` limit, and the
+    /// [`before_export`](zino_core::model::ModelHooks::before_export) hook
+    /// is called after `after_decode` and `before_respond` for each model.
     async fn export(req: Self::Request) -> Self::Result;
 
     /// Gets the tree hierarchy data.
@@ -52,12 +60,17 @@ pub trait DefaultController<K, U> {
     async fn mock(req: Self::Request) -> Self::Result;
 }
 
+#[cfg(any(feature = "actix", feature = "axum"))]
+#[cfg(feature = "orm")]
+use futures::stream::{BoxStream, Stream, StreamExt};
+
 #[cfg(any(feature = "actix", feature = "axum"))]
 #[cfg(feature = "orm")]
 use zino_core::{
+    error::Error,
     extension::JsonObjectExt,
     model::{ModelHooks, Mutation, Query},
-    orm::{ModelAccessor, ModelHelper},
+    orm::{ModelAccessor, ModelHelper, Schema},
     request::RequestContext,
     response::{ExtractRejection, Rejection, Response, StatusCode},
     JsonValue, Map,
@@ -154,8 +167,31 @@ where
             .await
             .extract(&req)?;
 
+        // The primary key is used as a tiebreaker for keyset pagination,
+        // which is disabled if the offset or the current page is specified.
+        let keyset_enabled = query.cursor().is_some()
+            || ["offset", "skip", "current_page"]
+                .into_iter()
+                .all(|key| req.get_query(key).is_none());
+        let primary_key_name = Self::PRIMARY_KEY_NAME;
+        let sort_order = query.sort_order();
+        if keyset_enabled
+            && !sort_order.is_empty()
+            && !sort_order
+                .iter()
+                .any(|(field, _)| field == primary_key_name)
+        {
+            let descending = sort_order.last().is_some_and(|&(_, descending)| descending);
+            query.order_by(primary_key_name, descending);
+        }
+
+        let mut cursors = None;
         let models = if query.populate_enabled() {
             let mut models = Self::fetch(&query).await.extract(&req)?;
+            if keyset_enabled && !query.sort_order().is_empty() {
+                // The populated values are added as new fields, so the sort keys are kept.
+                cursors = Some(encode_cursors(&query, &models).extract(&req)?);
+            }
             for model in models.iter_mut() {
                 Self::before_respond(model, extension.as_ref())
                     .await
@@ -164,6 +200,9 @@ where
             models
         } else {
             let mut models = Self::find(&query).await.extract(&req)?;
+            if keyset_enabled && !query.sort_order().is_empty() {
+                cursors = Some(encode_cursors(&query, &models).extract(&req)?);
+            }
             let translate_enabled = query.translate_enabled();
             for model in models.iter_mut() {
                 Self::after_decode(model).await.extract(&req)?;
@@ -176,8 +215,14 @@ where
         };
 
         let mut data = Map::data_entries(models);
+        if let Some((cursor, next_cursor)) = cursors {
+            data.upsert("cursor", cursor);
+            data.upsert("next_cursor", next_cursor);
+        }
         if let Some(page_size) = req.get_query("page_size").and_then(|s| s.parse().ok()) {
             if req.get_query("total_rows").is_none() {
+                query.clear_cursor();
+
                 let total_rows = Self::count(&query).await.extract(&req)?;
                 let page_count = total_rows.div_ceil(page_size);
                 data.upsert("total_rows", total_rows);
@@ -379,8 +424,20 @@ where
             .await
             .extract(&req)?;
 
-        let mut models = Self::find(&query).await.extract(&req)?;
         let translate_enabled = query.translate_enabled();
+        let format = req.get_query("format").unwrap_or("json");
+        if matches!(format, "csv" | "jsonlines" | "msgpack") {
+            let stream = Self::find_stream::<Map>(&query).await.extract(&req)?;
+            let stream = export_stream::<Self>(stream, translate_enabled, extension);
+            match format {
+                "csv" => res.set_csv_stream(stream),
+                "jsonlines" => res.set_jsonlines_stream(stream),
+                _ => res.set_msgpack_stream(stream),
+            }
+            return Ok(res.into());
+        }
+
+        let mut models = Self::find(&query).await.extract(&req)?;
         for model in models.iter_mut() {
             Self::after_decode(model).await.extract(&req)?;
             translate_enabled.then(|| Self::translate_model(model));
@@ -388,14 +445,7 @@ where
                 .await
                 .extract(&req)?;
         }
-
-        let format = req.get_query("format").unwrap_or("json");
-        match format {
-            "csv" => res.set_csv_response(models),
-            "jsonlines" => res.set_jsonlines_response(models),
-            "msgpack" => res.set_msgpack_response(models),
-            _ => res.set_json_response(models),
-        }
+        res.set_json_response(models);
         Ok(res.into())
     }
 
@@ -538,3 +588,46 @@ where
         Ok(res.into())
     }
 }
+
+/// Prepares the models in the stream for exporting.
+#[cfg(any(feature = "actix", feature = "axum"))]
+#[cfg(feature = "orm")]
+fn export_stream<M: Schema>(
+    stream: BoxStream<'static, Result<Map, Error>>,
+    translate_enabled: bool,
+    extension: Option<<M as ModelHooks>::Extension>,
+) -> impl Stream<Item = Result<Map, Error>> + Send + 'static {
+    stream.then(move |model| {
+        let extension = extension.clone();
+        async move {
+            let mut model = model?;
+            M::after_decode(&mut model).await?;
+            if translate_enabled {
+                M::translate_model(&mut model);
+            }
+            M::before_respond(&mut model, extension.as_ref()).await?;
+            M::before_export(&mut model, extension.as_ref()).await?;
+            Ok(model)
+        }
+    })
+}
+
+/// Encodes the cursors of the first and last models for keyset pagination.
+/// The cursor of the last model is omitted if there are no more models.
+#[cfg(any(feature = "actix", feature = "axum"))]
+#[cfg(feature = "orm")]
+fn encode_cursors(
+    query: &Query,
+    models: &[Map],
+) -> Result<(Option<String>, Option<String>), Error> {
+    let cursor = models
+        .first()
+        .map(|model| query.encode_cursor(model))
+        .transpose()?;
+    let next_cursor = models
+        .last()
+        .filter(|_| models.len() == query.limit())
+        .map(|model| query.encode_cursor(model))
+        .transpose()?;
+    Ok((cursor, next_cursor))
+}
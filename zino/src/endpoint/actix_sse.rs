@@ -0,0 +1,47 @@
+use super::{channel_auth, SSE_RETRY};
+use actix_web::{
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    rt::time,
+    web::Bytes,
+    HttpResponse,
+};
+use std::{convert::Infallible, time::Duration};
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+use zino_core::{channel::CloudEvent, request::RequestContext};
+
+/// SSE endpoint handler.
+///
+/// The client is authenticated in the same way as the normal routes,
+/// and the subscription is checked by the channel authorizer.
+/// The events missed by a reconnecting client are replayed
+/// according to the `Last-Event-ID` header.
+pub(crate) async fn sse_handler(req: crate::Request) -> crate::Result<HttpResponse> {
+    let (_, subscription) = channel_auth::authorize_subscription(&req).await?;
+    let last_event_id = req
+        .get_header("last-event-id")
+        .map(|value| value.to_owned());
+    let events = crate::MessageChannel::replay_stream(subscription, last_event_id)
+        .await
+        .map(|event| Ok::<_, Infallible>(Bytes::from(encode_event(&event))));
+    let keep_alive = IntervalStream::new(time::interval(Duration::from_secs(15)))
+        .map(|_| Ok(Bytes::from_static(b":\n\n")));
+    let retry = SSE_RETRY.map(|retry| Ok(Bytes::from(format!("retry: {}\n\n", retry.as_millis()))));
+    let stream = tokio_stream::iter(retry).chain(events.merge(keep_alive));
+    let res = HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(stream);
+    Ok(res)
+}
+
+/// Encodes the cloud event as an SSE message.
+fn encode_event(event: &CloudEvent) -> String {
+    let mut message = format!("id: {}\nevent: {}\n", event.id(), event.event_type());
+    for line in event.stringify_data().split('\n') {
+        message.push_str("data: ");
+        message.push_str(line.trim_end_matches('\r'));
+        message.push('\n');
+    }
+    message.push('\n');
+    message
+}
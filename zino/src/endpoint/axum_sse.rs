@@ -1,39 +1,35 @@
-use axum::{
-    extract::Query,
-    response::sse::{Event, KeepAlive, Sse},
-};
+use super::{channel_auth, SSE_RETRY};
+use axum::response::sse::{Event, KeepAlive, Sse};
 use std::convert::Infallible;
 use tokio_stream::{Stream, StreamExt};
-use zino_core::channel::Subscription;
+use zino_core::request::RequestContext;
 
 /// SSE endpoint handler.
+///
+/// The client is authenticated in the same way as the normal routes,
+/// and the subscription is checked by the channel authorizer.
+/// The events missed by a reconnecting client are replayed
+/// according to the `Last-Event-ID` header.
 pub(crate) async fn sse_handler(
-    query: Query<Subscription>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let subscription = query.0;
-    let session_id = subscription.session_id().map(|s| s.to_owned());
-    let source = subscription.source().map(|s| s.to_owned());
-    let topic = subscription.topic().map(|t| t.to_owned());
-    let channel = crate::MessageChannel::new();
-    let stream = channel.into_stream().filter_map(move |event| {
-        let mut sse_event_filter = None;
-        let event_session_id = event.session_id();
-        if session_id.is_none() || session_id.as_deref() != event_session_id {
-            let event_source = event.source();
-            if source.as_ref().filter(|&s| event_source != s).is_none() {
-                let event_type = event.event_type();
-                if topic.as_ref().filter(|&t| event_type != t).is_none() {
-                    let event_id = event.id();
-                    let event_data = event.stringify_data();
-                    let sse_event = Event::default()
-                        .event(event_type)
-                        .data(event_data)
-                        .id(event_id);
-                    sse_event_filter = Some(Ok(sse_event));
-                }
-            }
-        }
-        sse_event_filter
-    });
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    req: crate::Request,
+) -> crate::Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let (_, subscription) = channel_auth::authorize_subscription(&req).await?;
+    let last_event_id = req
+        .get_header("last-event-id")
+        .map(|value| value.to_owned());
+    let stream = crate::MessageChannel::replay_stream(subscription, last_event_id)
+        .await
+        .map(|event| {
+            let event_id = event.id();
+            let event_type = event.event_type();
+            let event_data = event.stringify_data();
+            let sse_event = Event::default()
+                .event(event_type)
+                .data(event_data)
+                .id(event_id);
+            Ok(sse_event)
+        });
+    let retry = SSE_RETRY.map(|retry| Ok(Event::default().retry(retry)));
+    let stream = tokio_stream::iter(retry).chain(stream);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
@@ -1,40 +1,75 @@
+use super::channel_auth;
 use axum::{
-    extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        Query,
-    },
-    response::IntoResponse,
+    extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    response::Response,
 };
-use zino_core::channel::{CloudEvent, Subscription};
+use std::borrow::Cow;
+use zino_core::channel::CloudEvent;
 
 /// WebSocket endpoint handler.
+///
+/// The client is authenticated in the same way as the normal routes,
+/// and the subscription is checked by the channel authorizer before the upgrade.
+/// Events received from the socket are sent to the other receivers in the message channel,
+/// and events matching the subscription are sent back to the socket.
+/// The socket is closed with a policy violation if the client publishes an unauthorized event.
 pub(crate) async fn websocket_handler(
     ws: WebSocketUpgrade,
-    query: Query<Subscription>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|mut socket: WebSocket| async move {
-        let subscription = query.0;
-        let session_id = subscription.session_id();
-        let source = subscription.source();
-        let topic = subscription.topic();
-        while let Some(Ok(Message::Text(message))) = socket.recv().await {
-            match serde_json::from_str::<CloudEvent>(&message) {
-                Ok(event) => {
-                    let event_session_id = event.session_id();
-                    if session_id.is_none() || session_id != event_session_id {
-                        let event_source = event.source();
-                        if source.filter(|&s| event_source != s).is_none() {
-                            let event_type = event.event_type();
-                            if topic.filter(|&t| event_type != t).is_none() {
-                                if let Err(err) = socket.send(Message::Text(message)).await {
+    req: crate::Request,
+) -> crate::Result<Response> {
+    let (session, subscription) = channel_auth::authorize_subscription(&req).await?;
+    let res = ws.on_upgrade(|mut socket: WebSocket| async move {
+        let mut channel = crate::MessageChannel::with_subscription(subscription);
+        loop {
+            tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Text(message))) => {
+                        match serde_json::from_str::<CloudEvent>(&message) {
+                            Ok(mut event) => {
+                                let result =
+                                    channel_auth::authorize_publication(session.as_ref(), &mut event)
+                                        .await;
+                                if let Err(err) = result {
+                                    tracing::warn!("fail to publish the cloud event: {err}");
+                                    let frame = CloseFrame {
+                                        code: close_code::POLICY,
+                                        reason: Cow::Borrowed("unauthorized publication"),
+                                    };
+                                    if let Err(err) = socket.send(Message::Close(Some(frame))).await {
+                                        tracing::error!("{err}");
+                                    }
+                                    break;
+                                }
+                                if let Err(err) = channel.try_send(event) {
                                     tracing::error!("{err}");
                                 }
                             }
+                            Err(err) => tracing::error!("{err}"),
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => (),
+                    Some(Err(err)) => {
+                        tracing::error!("{err}");
+                        break;
+                    }
+                },
+                event = channel.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    match serde_json::to_string(&event) {
+                        Ok(message) => {
+                            if let Err(err) = socket.send(Message::Text(message)).await {
+                                tracing::error!("{err}");
+                                break;
+                            }
                         }
+                        Err(err) => tracing::error!("{err}"),
                     }
                 }
-                Err(err) => tracing::error!("{err}"),
             }
         }
-    })
+    });
+    Ok(res)
 }
@@ -0,0 +1,123 @@
+use zino_core::{
+    application::Application,
+    auth::{JwtClaims, SecretAccessKey, UserSession},
+    channel::Subscription,
+    error::Error,
+    extension::TomlTableExt,
+    request::RequestContext,
+    response::Rejection,
+    warn, LazyLock,
+};
+
+#[cfg(all(feature = "axum", not(feature = "actix")))]
+use zino_core::{bail, channel::CloudEvent};
+
+/// Authenticates the client of an SSE or WebSocket connection and authorizes
+/// the subscription parsed from the query.
+///
+/// The session ID of the subscription is replaced with the one of the principal,
+/// so that a client can not impersonate the other sessions.
+/// The subscription is denied if there is no channel authorizer.
+pub(crate) async fn authorize_subscription(
+    req: &crate::Request,
+) -> Result<(Option<UserSession<String>>, Subscription), Rejection> {
+    let session = authenticate_client(req).await?;
+    let mut subscription = req.parse_query::<Subscription>()?;
+    let session_id = session
+        .as_ref()
+        .and_then(|session| session.session_id())
+        .map(|session_id| session_id.to_string());
+    subscription.set_session_id(session_id);
+    let Some(authorizer) = crate::MessageChannel::authorizer() else {
+        let err = warn!("there is no authorizer for the channel");
+        return Err(Rejection::forbidden(err).context(req));
+    };
+    authorizer
+        .authorize_subscription(session.as_ref(), &subscription)
+        .await
+        .map_err(|err| Rejection::forbidden(err).context(req))?;
+    Ok((session, subscription))
+}
+
+/// Authorizes the principal to publish the event.
+/// The session ID of the event is set to the one of the principal.
+/// The publication is denied if there is no channel authorizer.
+#[cfg(all(feature = "axum", not(feature = "actix")))]
+pub(crate) async fn authorize_publication(
+    session: Option<&UserSession<String>>,
+    event: &mut CloudEvent,
+) -> Result<(), Error> {
+    match session.and_then(|session| session.session_id()) {
+        Some(session_id) => event.set_session_id(session_id),
+        None => {
+            if let Some(session_id) = event.session_id() {
+                bail!("the session ID `{}` is not owned by the client", session_id);
+            }
+        }
+    }
+    let Some(authorizer) = crate::MessageChannel::authorizer() else {
+        bail!("there is no authorizer for the channel");
+    };
+    authorizer.authorize_publication(session, event).await
+}
+
+/// Authenticates the client by the JWT token or the security token.
+/// An anonymous client is represented by `None`.
+///
+/// The JWT claims are checked by the verifier of the message channel,
+/// and the session ID is derived from the verified claims or the access key ID.
+async fn authenticate_client(
+    req: &crate::Request,
+) -> Result<Option<UserSession<String>>, Rejection> {
+    let has_bearer_token = req
+        .get_header("authorization")
+        .is_some_and(|authorization| authorization.starts_with("Bearer "));
+    let has_security_token =
+        req.get_header("x-security-token").is_some() || req.get_query("security_token").is_some();
+    let (mut session, access_key_id) =
+        if has_bearer_token || req.get_query("access_token").is_some() {
+            let claims = req.parse_jwt_claims(JwtClaims::shared_key())?;
+            let Some(verifier) = crate::MessageChannel::jwt_verifier() else {
+                let err = warn!("there is no JWT verifier for the channel");
+                return Err(Rejection::unauthorized(err).context(req));
+            };
+            match verifier(&claims).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    let err = warn!("invalid JWT claims");
+                    return Err(Rejection::unauthorized(err).context(req));
+                }
+                Err(err) => return Err(Rejection::unauthorized(err).context(req)),
+            }
+            let session = UserSession::<String>::try_from_jwt_claims(claims)
+                .map_err(|err| Rejection::unauthorized(err).context(req))?;
+            (session, None)
+        } else if has_security_token {
+            let access_key_id = req.parse_access_key_id()?;
+            let secret_key = SecretAccessKey::new(&access_key_id);
+            let security_token = req.parse_security_token(secret_key.as_ref()).map_err(|_| {
+                Rejection::unauthorized(warn!("invalid security token")).context(req)
+            })?;
+            let access_key_id = security_token.access_key_id().clone();
+            let session = UserSession::new(access_key_id.as_str().to_owned(), None);
+            (session, Some(access_key_id))
+        } else if *ALLOW_ANONYMOUS {
+            return Ok(None);
+        } else {
+            let err = warn!("credentials are required to connect to the channel");
+            return Err(Rejection::unauthorized(err).context(req));
+        };
+    if let Some(access_key_id) = access_key_id {
+        session.set_access_key_id(access_key_id);
+    }
+    Ok(Some(session))
+}
+
+/// A flag to indicate whether the anonymous clients are allowed
+/// to connect to the SSE and WebSocket endpoints.
+static ALLOW_ANONYMOUS: LazyLock<bool> = LazyLock::new(|| {
+    crate::Cluster::config()
+        .get_table("channel")
+        .and_then(|config| config.get_bool("allow-anonymous"))
+        .unwrap_or(false)
+});
@@ -0,0 +1,12 @@
+use zino_core::{
+    auth::JwtKeySet,
+    response::{Response, StatusCode},
+};
+
+/// JWKS endpoint handler.
+pub(crate) async fn jwks_handler(req: crate::Request) -> crate::Result {
+    let mut res = Response::new(StatusCode::OK).context(&req);
+    res.set_json_response(JwtKeySet::shared().jwks());
+    res.insert_header("cache-control", "public, max-age=300");
+    Ok(res.into())
+}
@@ -1,9 +1,31 @@
 cfg_if::cfg_if! {
-    if #[cfg(feature = "axum")] {
+    if #[cfg(feature = "actix")] {
+        mod actix_sse;
+        mod jwks;
+
+        pub(crate) use self::actix_sse::sse_handler;
+        pub(crate) use self::jwks::jwks_handler;
+    } else if #[cfg(feature = "axum")] {
         mod axum_sse;
         mod axum_websocket;
+        mod jwks;
 
         pub(crate) use self::axum_sse::sse_handler;
         pub(crate) use self::axum_websocket::websocket_handler;
+        pub(crate) use self::jwks::jwks_handler;
     }
 }
+
+#[cfg(any(feature = "actix", feature = "axum"))]
+mod channel_auth;
+
+/// Retry hint for the SSE clients configured by `sse-retry` in the `[server]` table.
+#[cfg(any(feature = "actix", feature = "axum"))]
+static SSE_RETRY: zino_core::LazyLock<Option<std::time::Duration>> =
+    zino_core::LazyLock::new(|| {
+        use zino_core::{application::Application, extension::TomlTableExt};
+
+        crate::Cluster::config()
+            .get_table("server")
+            .and_then(|config| config.get_duration("sse-retry"))
+    });
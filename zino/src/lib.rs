@@ -24,6 +24,9 @@ cfg_if::cfg_if! {
         use crate::response::actix_response::{ActixRejection, ActixResponse};
         use zino_core::response::StatusCode;
 
+        pub use channel::message_channel::MessageChannel;
+        pub use middleware::AccessControl;
+
         /// HTTP server cluster for `actix-web`.
         pub type Cluster = ActixCluster;
 
@@ -44,7 +47,8 @@ cfg_if::cfg_if! {
         use crate::response::axum_response::{AxumRejection, AxumResponse};
         use zino_core::response::StatusCode;
 
-        pub use channel::axum_channel::MessageChannel;
+        pub use channel::message_channel::MessageChannel;
+        pub use middleware::access_control;
 
         /// HTTP server cluster for `axum`.
         pub type Cluster = AxumCluster;
@@ -0,0 +1,116 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use std::{
+    future::{ready, Future, Ready},
+    marker::PhantomData,
+    pin::Pin,
+};
+use zino_core::{auth::UserSession, request::RequestContext};
+
+/// Authorizes the request by the shared policy engine,
+/// with the user session of `UserSession<U, String, T>` in the request data.
+/// The tenant and the user of the user session are set for the rest of the request.
+pub struct AccessControl<U, T = U> {
+    /// Phantom type of the user session.
+    phantom: PhantomData<fn() -> (U, T)>,
+}
+
+impl<U, T> AccessControl<U, T> {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<U, T> Default for AccessControl<U, T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, B, U, T> Transform<S, ServiceRequest> for AccessControl<U, T>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    U: Clone + ToString + Send + Sync + 'static,
+    T: Clone + ToString + Send + Sync + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AccessControlMiddleware<S, U, T>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AccessControlMiddleware {
+            service,
+            phantom: PhantomData,
+        }))
+    }
+}
+
+pub struct AccessControlMiddleware<S, U, T> {
+    service: S,
+    phantom: PhantomData<fn() -> (U, T)>,
+}
+
+impl<S, B, U, T> Service<ServiceRequest> for AccessControlMiddleware<S, U, T>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    U: Clone + ToString + Send + Sync + 'static,
+    T: Clone + ToString + Send + Sync + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let req = crate::Request::from(req);
+        let session = req.get_data::<UserSession<U, String, T>>();
+        if let Err(rejection) = req.authorize(session.as_ref()) {
+            return Box::pin(async move {
+                let result: crate::Result<Self::Response> = Err(rejection.into());
+                result.map_err(|err| err.into())
+            });
+        }
+
+        #[cfg(feature = "orm")]
+        let tenant_id = session
+            .as_ref()
+            .and_then(|session| session.tenant_id())
+            .map(|tenant_id| tenant_id.to_string());
+        #[cfg(feature = "orm")]
+        let user_id = session
+            .as_ref()
+            .map(|session| session.user_id().to_string());
+
+        let fut = self.service.call(ServiceRequest::from(req));
+        Box::pin(async move {
+            // The tenant is set when the future is polled in the scope of the request.
+            #[cfg(feature = "orm")]
+            if let Some(tenant_id) = tenant_id {
+                if let Err(err) = zino_core::orm::TenantContext::set_tenant_id(tenant_id) {
+                    let rejection = zino_core::response::Rejection::forbidden(err);
+                    let result: crate::Result<Self::Response> = Err(rejection.into());
+                    return result.map_err(|err| err.into());
+                }
+            }
+            #[cfg(feature = "orm")]
+            if let Some(user_id) = user_id {
+                zino_core::orm::AuditContext::set_user_id(user_id);
+            }
+
+            let res = fut.await?;
+            Ok(res)
+        })
+    }
+}
@@ -46,12 +46,33 @@ where
         let req = crate::Request::from(req);
         let new_context = req.get_context().is_none().then(|| req.new_context());
 
+        #[cfg(feature = "orm")]
+        let request_id = new_context
+            .as_ref()
+            .map(|ctx| ctx.request_id())
+            .or_else(|| req.get_context().map(|ctx| ctx.request_id()));
+
         let req = ServiceRequest::from(req);
         if let Some(ctx) = new_context {
             req.extensions_mut().insert(ctx);
         }
 
         let fut = self.service.call(req);
+
+        #[cfg(feature = "orm")]
+        let fut = {
+            use zino_core::orm::{AuditContext, GlobalPool, TenantContext};
+
+            let fut = GlobalPool::scope_read_your_writes(fut);
+            let fut = AuditContext::scope(async move {
+                if let Some(request_id) = request_id {
+                    AuditContext::set_request_id(request_id);
+                }
+                fut.await
+            });
+            TenantContext::scope(fut)
+        };
+
         Box::pin(async move {
             let res = fut.await?;
             Ok(res)
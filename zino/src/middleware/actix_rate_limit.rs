@@ -0,0 +1,81 @@
+use super::RATE_LIMITER;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    Error,
+};
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+};
+
+#[derive(Default)]
+pub struct RateLimitEnforcer;
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitEnforcer
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let Some(rate_limiter) = RATE_LIMITER.as_ref() else {
+            let fut = self.service.call(req);
+            return Box::pin(async move {
+                let res = fut.await?;
+                Ok(res)
+            });
+        };
+
+        let service = self.service.clone();
+        Box::pin(async move {
+            let req = crate::Request::from(req);
+            let status = match rate_limiter.check(&req).await {
+                Ok(status) => status,
+                Err(rejection) => {
+                    let result: crate::Result<Self::Response> = Err(rejection.into());
+                    return result.map_err(|err| err.into());
+                }
+            };
+
+            let mut res = service.call(ServiceRequest::from(req)).await?;
+            if let Some(status) = status {
+                let headers = res.headers_mut();
+                for (name, value) in status.headers() {
+                    if let Ok(value) = HeaderValue::try_from(value) {
+                        headers.insert(HeaderName::from_static(name), value);
+                    }
+                }
+            }
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use super::SESSION_MANAGER;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderValue, SET_COOKIE},
+    Error,
+};
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+};
+use zino_core::request::RequestContext;
+
+#[derive(Default)]
+pub struct SessionInitializer;
+
+impl<S, B> Transform<S, ServiceRequest> for SessionInitializer
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SessionMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SessionMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct SessionMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for SessionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let Some(manager) = SESSION_MANAGER.as_ref() else {
+            let fut = self.service.call(req);
+            return Box::pin(async move {
+                let res = fut.await?;
+                Ok(res)
+            });
+        };
+
+        let service = self.service.clone();
+        Box::pin(async move {
+            let mut req = crate::Request::from(req);
+            let session_id = req
+                .get_cookie(manager.cookie_name())
+                .map(|cookie| cookie.value().to_owned());
+            let session = manager.load_session(session_id.as_deref()).await;
+            req.set_data(session.clone());
+
+            let mut res = service.call(ServiceRequest::from(req)).await?;
+            match manager.commit_session(&session).await {
+                Ok(Some(cookie)) => {
+                    if let Ok(value) = HeaderValue::try_from(cookie.to_string()) {
+                        res.headers_mut().append(SET_COOKIE, value);
+                    }
+                }
+                Ok(None) => (),
+                Err(err) => tracing::error!("fail to commit the session: {err}"),
+            }
+            Ok(res)
+        })
+    }
+}
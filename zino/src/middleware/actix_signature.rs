@@ -0,0 +1,76 @@
+use super::SIGNATURE_VERIFIER;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+};
+use zino_core::request::RequestContext;
+
+#[derive(Default)]
+pub struct SignatureVerification;
+
+impl<S, B> Transform<S, ServiceRequest> for SignatureVerification
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SignatureMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SignatureMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct SignatureMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for SignatureMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let Some(verifier) = SIGNATURE_VERIFIER.as_ref() else {
+            let fut = self.service.call(req);
+            return Box::pin(async move {
+                let res = fut.await?;
+                Ok(res)
+            });
+        };
+
+        let service = self.service.clone();
+        Box::pin(async move {
+            let mut req = crate::Request::from(req);
+            match verifier.verify(&req).await {
+                Ok(Some(session)) => {
+                    req.set_data(session);
+                }
+                Ok(None) => (),
+                Err(rejection) => {
+                    let result: crate::Result<Self::Response> = Err(rejection.into());
+                    return result.map_err(|err| err.into());
+                }
+            }
+
+            let res = service.call(ServiceRequest::from(req)).await?;
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,24 @@
+use axum::{body::Body, middleware::Next, response::Response};
+use zino_core::{auth::UserSession, request::RequestContext};
+
+/// Authorizes the request by the shared policy engine,
+/// with the user session of `UserSession<U, String, T>` in the request data.
+/// The tenant and the user of the user session are set for the rest of the request.
+pub async fn access_control<U, T>(req: crate::Request, next: Next<Body>) -> crate::Result<Response>
+where
+    U: Clone + ToString + Send + Sync + 'static,
+    T: Clone + ToString + Send + Sync + 'static,
+{
+    let session = req.get_data::<UserSession<U, String, T>>();
+    req.authorize(session.as_ref())?;
+
+    #[cfg(feature = "orm")]
+    if let Some(session) = session.as_ref() {
+        if let Some(tenant_id) = session.tenant_id() {
+            zino_core::orm::TenantContext::set_tenant_id(tenant_id.to_string())
+                .map_err(|err| zino_core::response::Rejection::forbidden(err).context(&req))?;
+        }
+        zino_core::orm::AuditContext::set_user_id(session.user_id().to_string());
+    }
+    Ok(next.run(req.into()).await)
+}
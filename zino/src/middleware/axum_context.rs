@@ -4,9 +4,32 @@ use zino_core::request::RequestContext;
 pub(crate) async fn request_context(req: crate::Request, next: Next<Body>) -> Response {
     let new_context = req.get_context().is_none().then(|| req.new_context());
 
+    #[cfg(feature = "orm")]
+    let request_id = new_context
+        .as_ref()
+        .map(|ctx| ctx.request_id())
+        .or_else(|| req.get_context().map(|ctx| ctx.request_id()));
+
     let mut req = http::Request::from(req);
     if let Some(ctx) = new_context {
         req.extensions_mut().insert(ctx);
     }
-    next.run(req).await
+
+    #[cfg(feature = "orm")]
+    {
+        use zino_core::orm::{AuditContext, GlobalPool, TenantContext};
+
+        let fut = GlobalPool::scope_read_your_writes(next.run(req));
+        let fut = AuditContext::scope(async move {
+            if let Some(request_id) = request_id {
+                AuditContext::set_request_id(request_id);
+            }
+            fut.await
+        });
+        TenantContext::scope(fut).await
+    }
+    #[cfg(not(feature = "orm"))]
+    {
+        next.run(req).await
+    }
 }
@@ -0,0 +1,25 @@
+use super::RATE_LIMITER;
+use axum::{
+    body::Body,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+pub(crate) async fn rate_limit(req: crate::Request, next: Next<Body>) -> crate::Result<Response> {
+    let Some(rate_limiter) = RATE_LIMITER.as_ref() else {
+        return Ok(next.run(req.into()).await);
+    };
+
+    let status = rate_limiter.check(&req).await?;
+    let mut res = next.run(req.into()).await;
+    if let Some(status) = status {
+        let headers = res.headers_mut();
+        for (name, value) in status.headers() {
+            if let Ok(value) = HeaderValue::try_from(value) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        }
+    }
+    Ok(res)
+}
@@ -0,0 +1,32 @@
+use super::SESSION_MANAGER;
+use axum::{
+    body::Body,
+    http::{header::SET_COOKIE, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use zino_core::request::RequestContext;
+
+pub(crate) async fn session(mut req: crate::Request, next: Next<Body>) -> crate::Result<Response> {
+    let Some(manager) = SESSION_MANAGER.as_ref() else {
+        return Ok(next.run(req.into()).await);
+    };
+
+    let session_id = req
+        .get_cookie(manager.cookie_name())
+        .map(|cookie| cookie.value().to_owned());
+    let session = manager.load_session(session_id.as_deref()).await;
+    req.set_data(session.clone());
+
+    let mut res = next.run(req.into()).await;
+    match manager.commit_session(&session).await {
+        Ok(Some(cookie)) => {
+            if let Ok(value) = HeaderValue::try_from(cookie.to_string()) {
+                res.headers_mut().append(SET_COOKIE, value);
+            }
+        }
+        Ok(None) => (),
+        Err(err) => tracing::error!("fail to commit the session: {err}"),
+    }
+    Ok(res)
+}
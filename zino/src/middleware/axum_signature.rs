@@ -0,0 +1,17 @@
+use super::SIGNATURE_VERIFIER;
+use axum::{body::Body, middleware::Next, response::Response};
+use zino_core::request::RequestContext;
+
+pub(crate) async fn verify_signature(
+    mut req: crate::Request,
+    next: Next<Body>,
+) -> crate::Result<Response> {
+    let Some(verifier) = SIGNATURE_VERIFIER.as_ref() else {
+        return Ok(next.run(req.into()).await);
+    };
+
+    if let Some(session) = verifier.verify(&req).await? {
+        req.set_data(session);
+    }
+    Ok(next.run(req.into()).await)
+}
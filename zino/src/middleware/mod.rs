@@ -1,25 +1,84 @@
 cfg_if::cfg_if! {
     if #[cfg(feature = "actix")] {
+        mod actix_access;
         mod actix_context;
         mod actix_cors;
         mod actix_etag;
+        mod actix_rate_limit;
+        mod actix_session;
+        mod actix_signature;
         mod actix_tracing;
 
+        pub use self::actix_access::AccessControl;
+
         pub(crate) use self::actix_context::RequestContextInitializer;
         pub(crate) use self::actix_cors::cors_middleware;
         pub(crate) use self::actix_etag::ETagFinalizer;
+        pub(crate) use self::actix_rate_limit::RateLimitEnforcer;
+        pub(crate) use self::actix_session::SessionInitializer;
+        pub(crate) use self::actix_signature::SignatureVerification;
         pub(crate) use self::actix_tracing::tracing_middleware;
     } else if #[cfg(feature = "axum")] {
+        mod axum_access;
         mod axum_context;
         mod axum_etag;
+        mod axum_rate_limit;
+        mod axum_session;
+        mod axum_signature;
         mod axum_static_pages;
         mod tower_cors;
         mod tower_tracing;
 
+        pub use self::axum_access::access_control;
+
         pub(crate) use self::axum_context::request_context;
         pub(crate) use self::axum_etag::extract_etag;
+        pub(crate) use self::axum_rate_limit::rate_limit;
+        pub(crate) use self::axum_session::session;
+        pub(crate) use self::axum_signature::verify_signature;
         pub(crate) use self::axum_static_pages::serve_static_pages;
         pub(crate) use self::tower_cors::CORS_MIDDLEWARE;
         pub(crate) use self::tower_tracing::TRACING_MIDDLEWARE;
     }
 }
+
+/// Rate limiter configured by the `[server.rate-limit]` table.
+#[cfg(any(feature = "actix", feature = "axum"))]
+static RATE_LIMITER: zino_core::LazyLock<Option<zino_core::request::RateLimiter>> =
+    zino_core::LazyLock::new(|| {
+        use zino_core::{application::Application, extension::TomlTableExt};
+
+        let config = crate::Cluster::config()
+            .get_table("server")
+            .and_then(|config| config.get_table("rate-limit"))?;
+        match zino_core::request::RateLimiter::try_from_config(config) {
+            Ok(rate_limiter) => Some(rate_limiter),
+            Err(err) => panic!("fail to create the rate limiter: {err}"),
+        }
+    });
+
+/// Session manager configured by the `[session]` table.
+#[cfg(any(feature = "actix", feature = "axum"))]
+static SESSION_MANAGER: zino_core::LazyLock<Option<zino_core::auth::SessionManager>> =
+    zino_core::LazyLock::new(|| {
+        use zino_core::{application::Application, extension::TomlTableExt};
+
+        let config = crate::Cluster::config().get_table("session")?;
+        match zino_core::auth::SessionManager::try_from_config(config) {
+            Ok(session_manager) => Some(session_manager),
+            Err(err) => panic!("fail to create the session manager: {err}"),
+        }
+    });
+
+/// Signature verifier configured by the `[signature]` table.
+#[cfg(any(feature = "actix", feature = "axum"))]
+static SIGNATURE_VERIFIER: zino_core::LazyLock<Option<zino_core::auth::SignatureVerifier>> =
+    zino_core::LazyLock::new(|| {
+        use zino_core::{application::Application, extension::TomlTableExt};
+
+        let config = crate::Cluster::config().get_table("signature")?;
+        match zino_core::auth::SignatureVerifier::try_from_config(config) {
+            Ok(verifier) => Some(verifier),
+            Err(err) => panic!("fail to create the signature verifier: {err}"),
+        }
+    });
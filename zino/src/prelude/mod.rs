@@ -18,7 +18,10 @@ pub use zino_core::{
     reject,
     request::RequestContext,
     response::{ExtractRejection, Rejection, StatusCode, WebHook},
-    schedule::{AsyncCronJob, AsyncJob, AsyncJobScheduler, CronJob, Job, JobScheduler},
+    schedule::{
+        AsyncCronJob, AsyncFallibleCronJob, AsyncJob, AsyncJobScheduler, CronJob, Job,
+        JobScheduler, OverlapPolicy,
+    },
     state::State,
     validation::Validation,
     warn, BoxFuture, Decimal, LazyLock, Map, Record, Uuid,
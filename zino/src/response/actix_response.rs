@@ -3,7 +3,8 @@ use actix_web::{
     http::header::{self, HeaderName, HeaderValue},
     HttpRequest, HttpResponse, Responder, ResponseError,
 };
-use std::fmt;
+use futures::TryStreamExt;
+use std::{fmt, io};
 use zino_core::{
     response::{Rejection, Response, ResponseCode, StatusCode},
     trace::TimingMetric,
@@ -116,6 +117,19 @@ impl ResponseError for ActixRejection {
 
 /// Build http response from `zino_core::response::Response`.
 fn build_http_response(response: &mut Response<StatusCode>) -> HttpResponse<BoxBody> {
+    if let Some(stream) = response.take_body_stream() {
+        let status_code = response
+            .status_code()
+            .try_into()
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let stream = stream.map_err(|err| io::Error::other(err.to_string()));
+        let mut res = HttpResponse::build(status_code).streaming(stream);
+        if let Ok(header_value) = HeaderValue::try_from(response.content_type()) {
+            res.headers_mut().insert(header::CONTENT_TYPE, header_value);
+        }
+        return res;
+    }
+
     match response.read_bytes() {
         Ok(data) => {
             let status_code = response
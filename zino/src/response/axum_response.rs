@@ -1,4 +1,13 @@
-use axum::response::IntoResponse;
+use axum::{
+    body::StreamBody,
+    http::{
+        header::{self, HeaderName, HeaderValue},
+        StatusCode,
+    },
+    response::IntoResponse,
+};
+use futures::TryStreamExt;
+use std::io;
 use zino_core::response::{FullResponse, Rejection, Response, ResponseCode};
 
 /// An HTTP response for `axum`.
@@ -12,9 +21,27 @@ impl<S: ResponseCode> From<Response<S>> for AxumResponse<S> {
 }
 
 impl<S: ResponseCode> IntoResponse for AxumResponse<S> {
-    #[inline]
     fn into_response(self) -> axum::response::Response {
-        FullResponse::from(self.0).into_response()
+        let mut response = self.0;
+        let Some(stream) = response.take_body_stream() else {
+            return FullResponse::from(response).into_response();
+        };
+
+        let stream = stream.map_err(|err| io::Error::other(err.to_string()));
+        let mut res = StreamBody::new(stream).into_response();
+        *res.status_mut() = StatusCode::from_u16(response.status_code())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        if let Ok(header_value) = HeaderValue::try_from(response.content_type()) {
+            res.headers_mut().insert(header::CONTENT_TYPE, header_value);
+        }
+        for (key, value) in response.finalize() {
+            if let Ok(header_name) = HeaderName::try_from(key.as_ref()) {
+                if let Ok(header_value) = HeaderValue::try_from(value) {
+                    res.headers_mut().insert(header_name, header_value);
+                }
+            }
+        }
+        res
     }
 }
 